@@ -0,0 +1,53 @@
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{Backend, BackendConfig, BulkString};
+
+const WRITERS: usize = 8;
+const OPS_PER_WRITER: usize = 10_000;
+
+// 多个线程并发写入，对比单 shard（等价于原先的全局 map）与多 shard 的吞吐
+fn concurrent_set(backend: &Backend) {
+    thread::scope(|s| {
+        for w in 0..WRITERS {
+            let backend = backend.clone();
+            s.spawn(move || {
+                for i in 0..OPS_PER_WRITER {
+                    backend.set(format!("key:{}:{}", w, i), BulkString::from("value").into());
+                    backend.hset(
+                        format!("hash:{}", i % 64),
+                        format!("field:{}:{}", w, i),
+                        BulkString::from("value").into(),
+                    );
+                }
+            });
+        }
+    });
+}
+
+fn bench_concurrent_writes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_writes");
+    group.throughput(Throughput::Elements((WRITERS * OPS_PER_WRITER * 2) as u64));
+    group.sample_size(20);
+
+    let default_shards = BackendConfig::default().shards;
+    for shards in [1, default_shards] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(shards),
+            &shards,
+            |b, &shards| {
+                b.iter(|| {
+                    let backend = Backend::with_config(BackendConfig {
+                        shards,
+                        ..Default::default()
+                    });
+                    concurrent_set(&backend);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_writes);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use bytes::BytesMut;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use simple_redis::{RespDecode, RespFrame, SimpleError, SimpleString, SmallString};
+
+const SHORT: &str = "OK";
+const ERROR: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+// 短字符串内联存放之后，创建和 clone 不再分配堆内存；超过内联长度的字符串与 String 相同
+fn bench_small_string(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_string");
+    for (name, s) in [("short", SHORT), ("long", ERROR)] {
+        group.bench_function(format!("string_new/{}", name), |b| {
+            b.iter(|| String::from(black_box(s)))
+        });
+        group.bench_function(format!("small_new/{}", name), |b| {
+            b.iter(|| SmallString::from(black_box(s)))
+        });
+
+        let string = s.to_string();
+        group.bench_function(format!("string_clone/{}", name), |b| {
+            b.iter(|| black_box(&string).clone())
+        });
+        let small = SmallString::from(s);
+        group.bench_function(format!("small_clone/{}", name), |b| {
+            b.iter(|| black_box(&small).clone())
+        });
+    }
+    group.finish();
+}
+
+// 命令最常见的回复：构造 +OK 和短错误，以及从缓冲区解码
+fn bench_replies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("replies");
+    group.bench_function("simple_string_ok", |b| {
+        b.iter(|| RespFrame::from(SimpleString::new(black_box(SHORT))))
+    });
+    group.bench_function("simple_error_nokey", |b| {
+        b.iter(|| RespFrame::from(SimpleError::new(black_box("ERR no such key"))))
+    });
+    group.bench_function("decode_ok", |b| {
+        b.iter_batched(
+            || BytesMut::from("+OK\r\n"),
+            |mut buf| RespFrame::decode(&mut buf).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_small_string, bench_replies);
+criterion_main!(benches);
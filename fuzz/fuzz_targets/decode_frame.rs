@@ -0,0 +1,32 @@
+#![no_main]
+// 任意字节分别交给 RespFrame::decode 和服务器读取请求时使用的 RespCodec
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{RespCodec, RespDecode, RespFrame, RespLimits};
+use tokio_util::codec::Decoder;
+
+// 比默认值小得多的限制，超过限制的长度前缀不会导致大块的内存分配
+const LIMITS: RespLimits = RespLimits {
+    max_bulk_len: 1 << 20,
+    max_multibulk_len: 1 << 16,
+    max_depth: 128,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    let _ = RespFrame::expect_length(&buf);
+    let _ = RespFrame::decode(&mut buf);
+
+    let mut codec = RespCodec::new(LIMITS);
+    let mut buf = BytesMut::from(data);
+    loop {
+        let before = buf.len();
+        match codec.decode(&mut buf) {
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            // 格式错误的帧被丢弃后继续解码剩下的数据，超过限制时缓冲区不变，服务器会关闭连接
+            Err(_) if buf.len() < before => {}
+            Err(_) => break,
+        }
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+// 能解码出帧的输入继续解析为命令，覆盖各个命令的参数解析
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{cmd::Command, RespLimits};
+
+fuzz_target!(|data: &[u8]| {
+    let limits = RespLimits {
+        max_bulk_len: 1 << 20,
+        max_multibulk_len: 1 << 16,
+        max_depth: 128,
+    };
+    let mut buf = BytesMut::from(data);
+    while let Ok(frame) = limits.decode(&mut buf) {
+        let _ = Command::try_from(frame);
+    }
+});
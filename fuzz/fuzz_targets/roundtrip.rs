@@ -0,0 +1,26 @@
+#![no_main]
+// 解码出的帧重新编码后再解码，应该得到编码完全相同的帧。
+// 比较编码而不是帧本身：流式的聚合类型重新编码后不再是流式的，double 的 NaN 也不等于自身
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{RespEncode, RespLimits};
+
+fuzz_target!(|data: &[u8]| {
+    let limits = RespLimits {
+        max_bulk_len: 1 << 20,
+        max_multibulk_len: 1 << 16,
+        max_depth: 128,
+    };
+    let mut buf = BytesMut::from(data);
+    let Ok(frame) = limits.decode(&mut buf) else {
+        return;
+    };
+
+    let encoded = frame.encode();
+    let mut buf = BytesMut::from(&encoded[..]);
+    let decoded = limits
+        .decode(&mut buf)
+        .expect("encoded frame should decode");
+    assert!(buf.is_empty(), "trailing bytes after re-decoding");
+    assert_eq!(decoded.encode(), encoded);
+});
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::RespFrame;
+
+use super::{Backend, KeyOp};
+
+// MGET / MSET / DEL 等多 key 命令的批量接口：key 先按 shard 分组，每个 shard 只加一次锁，
+// 而不是每个 key 都重新计算 shard 并加锁
+impl Backend {
+    // 与 GET 一样只返回字符串的值，key 不存在、已过期或不是字符串时为 None
+    pub fn mget(&self, keys: &[&str]) -> Vec<Option<Arc<RespFrame>>> {
+        let mut values = vec![None; keys.len()];
+        let now = Instant::now();
+        let clock = self.tier.clock();
+        for (index, positions) in self.group_by_shard(keys) {
+            let mut expired = Vec::new();
+            let mut cold = Vec::new();
+            {
+                let shard = self.shards[index].read();
+                for i in positions {
+                    if shard.is_expired(keys[i], now) {
+                        expired.push(keys[i]);
+                    } else if shard.is_cold(keys[i]) {
+                        // 值在磁盘上，释放读锁后读回内存再读取
+                        cold.push(i);
+                        continue;
+                    } else {
+                        values[i] = shard.map.get(keys[i]).map(|e| {
+                            e.touch(clock);
+                            e.value.clone()
+                        });
+                    }
+                    self.stats.record_lookup(values[i].is_some());
+                }
+            }
+            self.purge_batch(index, &expired);
+            if !cold.is_empty() {
+                for &i in &cold {
+                    self.page_in(keys[i]);
+                }
+                let shard = self.shards[index].read();
+                for i in cold {
+                    values[i] = shard.map.get(keys[i]).map(|e| e.value.clone());
+                    self.stats.record_lookup(values[i].is_some());
+                }
+            }
+        }
+        values
+    }
+
+    // 所有 key 所在的 shard 同时加写锁后再写入，与 redis 的 MSET 一样其他请求看不到只写入了一部分的状态。
+    // 与 SET 一样会清除原来的过期时间
+    pub fn mset(&self, pairs: Vec<(String, RespFrame)>) {
+        let indexes = pairs
+            .iter()
+            .map(|(key, _)| self.shard_index(key))
+            .collect::<Vec<_>>();
+        let mut locked = indexes.clone();
+        locked.sort_unstable();
+        locked.dedup();
+
+        // 按 shard 下标顺序加锁，与 lock_keys 一致，不会死锁
+        let mut shards = locked
+            .iter()
+            .map(|&index| self.shards[index].write())
+            .collect::<Vec<_>>();
+        for ((key, value), index) in pairs.into_iter().zip(indexes) {
+            let Ok(pos) = locked.binary_search(&index) else {
+                unreachable!("shard of every key is locked");
+            };
+            let shard = &mut shards[pos];
+            shard.expires.swap_remove(&key);
+            self.put_string(shard, key, value);
+        }
+    }
+
+    // 删除任意类型的 key，返回实际删除的个数。同一个 key 出现多次时只计算一次
+    pub fn del_many(&self, keys: &[&str]) -> usize {
+        let mut count = 0;
+        for (index, positions) in self.group_by_shard(keys) {
+            let mut shard = self.shards[index].write();
+            for i in positions {
+                let key = keys[i];
+                if self.purge_expired(&mut shard, key) {
+                    continue;
+                }
+                if shard.remove_key(key) {
+                    self.notify(key, KeyOp::Del);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // 存在的 key 的个数，与 redis 的 EXISTS 一样同一个 key 出现多次时重复计算
+    pub fn exists_many(&self, keys: &[&str]) -> usize {
+        let mut count = 0;
+        let now = Instant::now();
+        for (index, positions) in self.group_by_shard(keys) {
+            let mut expired = Vec::new();
+            {
+                let shard = self.shards[index].read();
+                for i in positions {
+                    if shard.is_expired(keys[i], now) {
+                        expired.push(keys[i]);
+                    } else if shard.contains_key(keys[i]) {
+                        count += 1;
+                    }
+                }
+            }
+            self.purge_batch(index, &expired);
+        }
+        count
+    }
+
+    // 按 shard 下标分组，返回 (shard 下标, key 在 keys 中的位置)，组内保持 key 原来的顺序
+    fn group_by_shard(&self, keys: &[&str]) -> Vec<(usize, Vec<usize>)> {
+        let mut indexes = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (self.shard_index(key), i))
+            .collect::<Vec<_>>();
+        indexes.sort_unstable();
+
+        let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+        for (index, i) in indexes {
+            match groups.last_mut() {
+                Some((last, positions)) if *last == index => positions.push(i),
+                _ => groups.push((index, vec![i])),
+            }
+        }
+        groups
+    }
+
+    // 读锁下发现的过期 key 释放读锁后再统一加一次写锁删除
+    fn purge_batch(&self, index: usize, expired: &[&str]) {
+        if expired.is_empty() {
+            return;
+        }
+        let mut shard = self.shards[index].write();
+        for key in expired {
+            self.purge_expired(&mut shard, key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{backend::BackendConfig, BulkString};
+
+    fn backend() -> Backend {
+        Backend::with_config(BackendConfig {
+            shards: 4,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_mget_keeps_request_order() {
+        let backend = backend();
+        for i in 0..20 {
+            backend.set_string(format!("k{}", i), format!("v{}", i));
+        }
+        backend.hash("h").set("f", "v");
+        backend.set_with_ttl("expired", "v", Duration::ZERO);
+
+        let keys = ["k3", "missing", "k17", "h", "k3", "expired", "k0"];
+        let values = backend
+            .mget(&keys)
+            .into_iter()
+            .map(|v| v.map(|v| (*v).clone()))
+            .collect::<Vec<_>>();
+        let bulk = |s: &str| Some(RespFrame::from(BulkString::from(s)));
+        assert_eq!(
+            values,
+            vec![
+                bulk("v3"),
+                None,
+                bulk("v17"),
+                None,
+                bulk("v3"),
+                None,
+                bulk("v0")
+            ]
+        );
+        // 过期的 key 已经被删除
+        assert!(!backend
+            .shards
+            .iter()
+            .any(|s| s.read().contains_key("expired")));
+    }
+
+    #[test]
+    fn test_mset_overwrites_and_clears_ttl() {
+        let backend = backend();
+        backend.set_with_ttl("a", "old", Duration::from_secs(60));
+        backend.mset(
+            (0..20)
+                .map(|i| (format!("k{}", i), BulkString::from(i.to_string()).into()))
+                .chain([("a".to_string(), BulkString::from("new").into())])
+                .collect(),
+        );
+
+        assert_eq!(backend.get_string("a").as_deref(), Some("new"));
+        assert_eq!(backend.ttl("a"), None);
+        assert!((0..20).all(|i| backend.get_string(&format!("k{}", i)) == Some(i.to_string())));
+    }
+
+    #[test]
+    fn test_del_many_and_exists_many() {
+        let backend = backend();
+        backend.set_string("s", "v");
+        backend.hash("h").set("f", "v");
+        backend.set_of("set").add("m");
+        backend.set_with_ttl("expired", "v", Duration::ZERO);
+
+        assert_eq!(
+            backend.exists_many(&["s", "s", "h", "missing", "expired"]),
+            3
+        );
+        assert_eq!(backend.del_many(&["s", "h", "s", "missing", "expired"]), 2);
+        assert_eq!(backend.exists_many(&["s", "h", "set"]), 1);
+        assert_eq!(
+            backend.memory_stats().total(),
+            backend.memory_usage("set").unwrap()
+        );
+    }
+}
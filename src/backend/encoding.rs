@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{RespFrame, ServerConfig};
+
+// 仿照 redis 的编码规则报告 OBJECT ENCODING，
+// 目前底层始终使用哈希表存储，这里只根据配置的阈值给出对应的编码名称
+
+// redis 中 embstr 与 raw 的分界
+const EMBSTR_SIZE_LIMIT: usize = 44;
+
+pub(super) fn string_encoding(value: &RespFrame) -> &'static str {
+    match value {
+        RespFrame::Integer(_) => "int",
+        RespFrame::BulkString(s) if s.len() <= 20 && is_integer(s) => "int",
+        RespFrame::BulkString(s) if s.len() <= EMBSTR_SIZE_LIMIT => "embstr",
+        _ => "raw",
+    }
+}
+
+pub(super) fn hash_encoding(
+    hash: &HashMap<String, Arc<RespFrame>>,
+    config: &ServerConfig,
+) -> &'static str {
+    let small = hash.len() <= config.hash_max_listpack_entries
+        && hash.iter().all(|(field, value)| {
+            field.len() <= config.hash_max_listpack_value
+                && match value.as_ref() {
+                    RespFrame::BulkString(s) => s.len() <= config.hash_max_listpack_value,
+                    _ => true,
+                }
+        });
+
+    if small {
+        "listpack"
+    } else {
+        "hashtable"
+    }
+}
+
+pub(super) fn set_encoding(set: &HashSet<String>, config: &ServerConfig) -> &'static str {
+    if set.len() <= config.set_max_intset_entries && set.iter().all(|m| is_integer(m.as_bytes())) {
+        "intset"
+    } else if set.len() <= config.set_max_listpack_entries
+        && set.iter().all(|m| m.len() <= config.set_max_listpack_value)
+    {
+        "listpack"
+    } else {
+        "hashtable"
+    }
+}
+
+fn is_integer(s: &[u8]) -> bool {
+    std::str::from_utf8(s).is_ok_and(|s| s.parse::<i64>().is_ok_and(|n| n.to_string() == s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_string_encoding() {
+        assert_eq!(string_encoding(&BulkString::from("123").into()), "int");
+        assert_eq!(string_encoding(&BulkString::from("0123").into()), "embstr");
+        assert_eq!(string_encoding(&BulkString::from("hello").into()), "embstr");
+        assert_eq!(
+            string_encoding(&BulkString::from("a".repeat(45)).into()),
+            "raw"
+        );
+    }
+
+    #[test]
+    fn test_collection_encoding_follows_thresholds() {
+        let mut config = ServerConfig::default();
+
+        let set = (0..3).map(|i| i.to_string()).collect::<HashSet<_>>();
+        assert_eq!(set_encoding(&set, &config), "intset");
+        config.set_max_intset_entries = 2;
+        assert_eq!(set_encoding(&set, &config), "listpack");
+        config.set_max_listpack_entries = 2;
+        assert_eq!(set_encoding(&set, &config), "hashtable");
+
+        let hash = HashMap::from([("f".to_string(), Arc::new(BulkString::from("v").into()))]);
+        assert_eq!(hash_encoding(&hash, &config), "listpack");
+        config.hash_max_listpack_value = 0;
+        assert_eq!(hash_encoding(&hash, &config), "hashtable");
+    }
+}
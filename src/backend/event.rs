@@ -0,0 +1,39 @@
+// 所有写操作都会通过 broadcast 通道广播一个 KeyEvent，
+// keyspace 通知、WATCH、复制以及嵌入方都可以订阅同一个事件流
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: String,
+    pub op: KeyOp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyOp {
+    Set,
+    Del,
+    HSet,
+    HDel,
+    SAdd,
+    SRem,
+    // 过期被删除，与 Del 区分以便 keyspace 通知发出 expired 事件
+    Expired,
+    // 设置或者清除过期时间，值本身不变
+    Expire,
+    Persist,
+}
+
+impl KeyOp {
+    // 与 redis keyspace 通知中的事件名保持一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyOp::Set => "set",
+            KeyOp::Del => "del",
+            KeyOp::HSet => "hset",
+            KeyOp::HDel => "hdel",
+            KeyOp::SAdd => "sadd",
+            KeyOp::SRem => "srem",
+            KeyOp::Expired => "expired",
+            KeyOp::Expire => "expire",
+            KeyOp::Persist => "persist",
+        }
+    }
+}
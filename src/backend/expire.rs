@@ -0,0 +1,157 @@
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use tokio::{runtime::Handle, task::AbortHandle};
+
+use super::{Backend, BackendInner};
+
+/*
+    与 redis 的主动过期类似：每隔 expire-interval 依次处理每个 shard，从设置了过期时间的 key 中
+    随机抽取 expire-samples 个，删除其中已过期的；过期的比例超过 1/4 时说明还有很多过期的 key，
+    释放锁后继续抽样，每个 shard 每轮最多重复 MAX_ROUNDS 次，避免长时间持有写锁。
+    后台任务只持有 Weak，Backend 释放后任务在下一次唤醒时退出，ActiveExpire 释放时也会直接结束任务。
+    创建 Backend 时不在 tokio runtime 中则不启动任务，可以自己定期调用 active_expire_cycle
+*/
+const MAX_ROUNDS: usize = 16;
+
+#[derive(Debug, Default)]
+pub(super) struct ActiveExpire {
+    task: Mutex<Option<AbortHandle>>,
+}
+
+impl Drop for ActiveExpire {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.get_mut().take() {
+            task.abort();
+        }
+    }
+}
+
+impl Backend {
+    pub(super) fn start_active_expire(&self, interval: Duration, samples: usize) {
+        if interval.is_zero() || samples == 0 {
+            return;
+        }
+        let Ok(handle) = Handle::try_current() else {
+            return;
+        };
+        let inner = Arc::downgrade(&self.0);
+        let task = handle.spawn(active_expire_loop(inner, interval, samples));
+        *self.active_expire.task.lock() = Some(task.abort_handle());
+    }
+
+    // 对所有 shard 执行一轮主动过期，返回删除的 key 个数
+    pub fn active_expire_cycle(&self, samples: usize) -> usize {
+        let mut rng = Rng::new();
+        let now = Instant::now();
+        let mut removed = 0;
+        for lock in self.shards.iter() {
+            for _ in 0..MAX_ROUNDS {
+                let mut shard = lock.write();
+                let len = shard.expires.len();
+                if len == 0 {
+                    break;
+                }
+                let count = samples.min(len);
+                let expired = (0..count)
+                    .filter_map(|_| {
+                        let (key, at) = shard.expires.get_index(rng.below(len))?;
+                        (*at <= now).then(|| key.clone())
+                    })
+                    .collect::<Vec<_>>();
+                let mut round = 0;
+                for key in expired {
+                    // 同一个 key 可能被抽中多次，只有第一次会删除
+                    if self.purge_expired(&mut shard, &key) {
+                        round += 1;
+                    }
+                }
+                removed += round;
+                if round * 4 <= count {
+                    break;
+                }
+            }
+        }
+        removed
+    }
+}
+
+async fn active_expire_loop(inner: Weak<BackendInner>, interval: Duration, samples: usize) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        Backend(inner).active_expire_cycle(samples);
+    }
+}
+
+// xorshift64，抽样不需要密码学强度的随机数，种子取自 RandomState 的随机种子
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        Self(RandomState::new().hash_one(Instant::now()) | 1)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendConfig;
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let backend = Backend::with_config(BackendConfig {
+            shards: 1,
+            ..Default::default()
+        });
+        for i in 0..100 {
+            backend.set_with_ttl(format!("old{}", i), "v", Duration::ZERO);
+        }
+        for i in 0..10 {
+            backend.set_with_ttl(format!("new{}", i), "v", Duration::from_secs(100));
+        }
+        backend.set_string("plain", "v");
+
+        // 过期的 key 占多数时一轮内会反复抽样；剩下少数过期的 key 时由之后的几轮删除
+        let mut removed = backend.active_expire_cycle(20);
+        assert!(removed >= 20);
+        for _ in 0..1000 {
+            if removed == 100 {
+                break;
+            }
+            removed += backend.active_expire_cycle(20);
+        }
+        assert_eq!(removed, 100);
+        assert_eq!(backend.keyspace_stats().keys, 11);
+        assert_eq!(backend.keyspace_stats().expires, 10);
+    }
+
+    #[tokio::test]
+    async fn test_background_task_removes_expired_keys() {
+        let backend = Backend::with_config(BackendConfig {
+            expire_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        backend.set_with_ttl("k", "v", Duration::from_millis(5));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(backend.keyspace_stats().keys, 0);
+
+        // Backend 释放后任务退出
+        let inner = Arc::downgrade(&backend.0);
+        drop(backend);
+        assert!(inner.upgrade().is_none());
+    }
+}
@@ -0,0 +1,198 @@
+/*
+    供嵌入方备份和恢复整个数据集，需要开启 serde feature。
+
+    Dump 本身实现了 Serialize / Deserialize，可以用任意 serde 格式保存；
+    export / import 使用 to_frame 把 Dump 转成一个 RESP 帧后编码，不需要额外的依赖：
+
+    - version: 格式版本，目前是 1
+    - entries: 每个 key 一项
+      - key: key 名
+      - value: 外部标签的 enum，{"String": 值} / {"Hash": {字段: 值}} / {"Set": [成员]}
+      - expire_at: 过期时间的 unix 毫秒时间戳，没有设置过期时间时为 null
+*/
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
+
+use crate::{from_frame, to_frame, RespDecode, RespEncode, RespFrame};
+
+use super::{snapshot::unix_millis, Backend, KeyOp, Value};
+
+pub const DUMP_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dump {
+    pub version: u32,
+    pub entries: Vec<DumpEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DumpEntry {
+    pub key: String,
+    pub value: DumpValue,
+    #[serde(default)]
+    pub expire_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DumpValue {
+    String(RespFrame),
+    Hash(BTreeMap<String, RespFrame>),
+    Set(BTreeSet<String>),
+}
+
+impl Backend {
+    // 基于 snapshot，得到的是某一时刻的一致数据
+    pub fn dump(&self) -> Dump {
+        let snapshot = self.snapshot();
+        let (now, unix_now) = (Instant::now(), unix_millis());
+        let entries = snapshot
+            .iter()
+            .map(|(key, value)| DumpEntry {
+                key: key.to_string(),
+                value: match value {
+                    Value::String(frame) => DumpValue::String((**frame).clone()),
+                    Value::Hash(hash) => DumpValue::Hash(
+                        hash.iter()
+                            .map(|(f, v)| (f.clone(), (**v).clone()))
+                            .collect(),
+                    ),
+                    Value::Set(set) => DumpValue::Set(set.iter().cloned().collect()),
+                },
+                expire_at: snapshot
+                    .expire_at(key)
+                    .map(|at| unix_now + at.saturating_duration_since(now).as_millis() as u64),
+            })
+            .collect();
+        Dump {
+            version: DUMP_VERSION,
+            entries,
+        }
+    }
+
+    // dump 中的 key 替换同名的 key，其余的 key 保持不变；已经过期的项会被跳过。返回写入的 key 个数
+    pub fn restore(&self, dump: Dump) -> io::Result<usize> {
+        if dump.version != DUMP_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported dump version {}", dump.version),
+            ));
+        }
+
+        let (now, unix_now) = (Instant::now(), unix_millis());
+        let mut restored = 0;
+        for entry in dump.entries {
+            if entry.expire_at.is_some_and(|at| at <= unix_now) {
+                continue;
+            }
+            let key = entry.key;
+            let mut shard = self.shard(&key).write();
+            if shard.remove_key(&key) {
+                self.notify(&key, KeyOp::Del);
+            }
+            match entry.value {
+                DumpValue::String(value) => self.put_string(&mut shard, key.clone(), value),
+                DumpValue::Hash(hash) => {
+                    for (field, value) in hash {
+                        self.put_hash_field(&mut shard, key.clone(), field, value);
+                    }
+                }
+                DumpValue::Set(set) => {
+                    self.add_set_members(&mut shard, key.clone(), set);
+                }
+            }
+            if let Some(at) = entry.expire_at {
+                let ttl = Duration::from_millis(at - unix_now);
+                shard.expires.insert(key, now + ttl);
+            }
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    pub fn export(&self, mut writer: impl Write) -> io::Result<()> {
+        let frame = to_frame(&self.dump()).map_err(invalid_data)?;
+        let mut buf = BytesMut::new();
+        frame.encode_into(&mut buf);
+        writer.write_all(&buf)?;
+        writer.flush()
+    }
+
+    pub fn import(&self, mut reader: impl Read) -> io::Result<usize> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let frame = RespFrame::decode(&mut BytesMut::from(&data[..])).map_err(invalid_data)?;
+        self.restore(from_frame(frame).map_err(invalid_data)?)
+    }
+}
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let backend = Backend::new();
+        backend.set_string("string", "value");
+        backend.set_with_ttl("volatile", "value", Duration::from_secs(100));
+        backend.hset("hash".to_string(), "field".to_string(), 1.into());
+        backend.sadd("set".to_string(), ["a".to_string(), "b".to_string()]);
+
+        let mut data = Vec::new();
+        backend.export(&mut data).unwrap();
+
+        let restored = Backend::new();
+        restored.set_string("string", "old");
+        restored.set_string("untouched", "value");
+        assert_eq!(restored.import(&data[..]).unwrap(), 4);
+        assert_eq!(restored.get_string("string"), Some("value".to_string()));
+        assert_eq!(restored.get_string("untouched"), Some("value".to_string()));
+        assert_eq!(
+            restored.hget("hash", "field").as_deref(),
+            Some(&RespFrame::Integer(1))
+        );
+        assert!(restored.sismember("set", "b"));
+        let ttl = restored.ttl("volatile").unwrap();
+        assert!(ttl > Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_restore_skips_expired_entries_and_replaces_type() {
+        let backend = Backend::new();
+        backend.set_string("key", "value");
+        let dump = Dump {
+            version: DUMP_VERSION,
+            entries: vec![
+                DumpEntry {
+                    key: "key".to_string(),
+                    value: DumpValue::Set(["a".to_string()].into()),
+                    expire_at: None,
+                },
+                DumpEntry {
+                    key: "expired".to_string(),
+                    value: DumpValue::String(BulkString::from("value").into()),
+                    expire_at: Some(1000),
+                },
+            ],
+        };
+
+        assert_eq!(backend.restore(dump).unwrap(), 1);
+        assert_eq!(backend.get("key"), None);
+        assert!(backend.sismember("key", "a"));
+        assert!(!backend.exists("expired"));
+
+        let future = Dump {
+            version: DUMP_VERSION + 1,
+            entries: Vec::new(),
+        };
+        assert!(backend.restore(future).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{sync::Arc, time::Instant};
+
+use parking_lot::RwLockWriteGuard;
+
+use crate::{RespFrame, ValueKind};
+
+use super::{BackendInner, Shard};
+
+// 持有一个或多个 key 所在 shard 的写锁，用于 SMOVE、RENAME 这类需要原子地读写多个结构的命令。
+// 只能操作加锁时传入的 key，操作其他 key 会 panic
+pub struct KeyGuard<'a> {
+    backend: &'a BackendInner,
+    shards: Vec<(usize, RwLockWriteGuard<'a, Shard>)>,
+}
+
+impl<'a> KeyGuard<'a> {
+    pub(super) fn new(
+        backend: &'a BackendInner,
+        shards: Vec<(usize, RwLockWriteGuard<'a, Shard>)>,
+    ) -> Self {
+        Self { backend, shards }
+    }
+
+    fn shard(&self, key: &str) -> &Shard {
+        let index = self.backend.shard_index(key);
+        self.shards
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, shard)| &**shard)
+            .unwrap_or_else(|| panic!("key {:?} is not locked by this guard", key))
+    }
+
+    // 返回 backend 与 shard 两个引用，写操作需要同时用到
+    fn shard_mut(&mut self, key: &str) -> (&'a BackendInner, &mut Shard) {
+        let index = self.backend.shard_index(key);
+        let shard = self
+            .shards
+            .iter_mut()
+            .find(|(i, _)| *i == index)
+            .map(|(_, shard)| &mut **shard)
+            .unwrap_or_else(|| panic!("key {:?} is not locked by this guard", key));
+        (self.backend, shard)
+    }
+
+    // 加锁时已经删除了过期的 key 并读回了冷数据
+    pub fn key_type(&self, key: &str) -> Option<ValueKind> {
+        self.shard(key).kind(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<RespFrame>> {
+        self.shard(key).map.get(key).map(|e| e.value.clone())
+    }
+
+    pub fn set(&mut self, key: &str, value: RespFrame) {
+        let (backend, shard) = self.shard_mut(key);
+        backend.put_string(shard, key.to_string(), value);
+    }
+
+    // 设置过期时间点，at 为 None 时清除过期时间
+    pub fn set_expiry(&mut self, key: &str, at: Option<Instant>) {
+        let (_, shard) = self.shard_mut(key);
+        match at {
+            Some(at) => {
+                shard.expires.insert(key.to_string(), at);
+            }
+            None => {
+                shard.expires.swap_remove(key);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<RespFrame> {
+        let (backend, shard) = self.shard_mut(key);
+        backend.remove_string(shard, key)
+    }
+
+    pub fn hget(&self, key: &str, field: &str) -> Option<Arc<RespFrame>> {
+        self.shard(key).hmap.get(key)?.get(field).cloned()
+    }
+
+    pub fn hset(&mut self, key: &str, field: &str, value: RespFrame) {
+        let (backend, shard) = self.shard_mut(key);
+        backend.put_hash_field(shard, key.to_string(), field.to_string(), value);
+    }
+
+    pub fn hdel(&mut self, key: &str, field: &str) -> Option<Arc<RespFrame>> {
+        let (backend, shard) = self.shard_mut(key);
+        backend.remove_hash_field(shard, key, field)
+    }
+
+    pub fn sismember(&self, key: &str, member: &str) -> bool {
+        self.shard(key)
+            .smap
+            .get(key)
+            .is_some_and(|set| set.contains(member))
+    }
+
+    pub fn sadd(&mut self, key: &str, member: &str) -> bool {
+        let (backend, shard) = self.shard_mut(key);
+        backend.add_set_members(shard, key.to_string(), [member]) > 0
+    }
+
+    // 返回新增的成员个数
+    pub fn sadd_members<I, T>(&mut self, key: &str, members: I) -> i64
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let (backend, shard) = self.shard_mut(key);
+        backend.add_set_members(shard, key.to_string(), members)
+    }
+
+    pub fn srem(&mut self, key: &str, member: &str) -> bool {
+        let (backend, shard) = self.shard_mut(key);
+        backend.remove_set_members(shard, key, [member]) > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, BackendConfig, BulkString};
+
+    #[test]
+    fn test_guard_moves_member_between_sets() {
+        let backend = Backend::new();
+        backend.sadd("src", ["a", "b"]);
+
+        {
+            let mut guard = backend.lock_keys(&["src", "dst"]);
+            assert!(guard.srem("src", "a"));
+            assert!(guard.sadd("dst", "a"));
+            assert!(guard.sismember("dst", "a"));
+        }
+
+        assert!(!backend.sismember("src", "a"));
+        assert!(backend.sismember("dst", "a"));
+    }
+
+    #[test]
+    fn test_guard_rename_string() {
+        let backend = Backend::new();
+        backend.set("old".to_string(), BulkString::from("v").into());
+
+        let mut guard = backend.lock_keys(&["old", "new"]);
+        let value = guard.remove("old").unwrap();
+        guard.set("new", value);
+        drop(guard);
+
+        assert_eq!(backend.get("old"), None);
+        assert_eq!(
+            backend.get("new").as_deref(),
+            Some(&BulkString::from("v").into())
+        );
+    }
+
+    #[test]
+    fn test_concurrent_moves_keep_members() {
+        // shard 数较少时更容易出现两个 key 落在同一 shard 或交叉加锁的情况
+        let backend = Backend::with_config(BackendConfig {
+            shards: 2,
+            ..Default::default()
+        });
+        let members = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+        backend.sadd("s1".to_string(), members.clone());
+
+        std::thread::scope(|s| {
+            for (from, to) in [("s1", "s2"), ("s2", "s1"), ("s1", "s3"), ("s3", "s2")] {
+                let backend = backend.clone();
+                let members = &members;
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        for member in members {
+                            let mut guard = backend.lock_keys(&[from, to]);
+                            if guard.srem(from, member) {
+                                guard.sadd(to, member);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let guard = backend.lock_keys(&["s1", "s2", "s3"]);
+        for member in &members {
+            let count = ["s1", "s2", "s3"]
+                .iter()
+                .filter(|key| guard.sismember(key, member))
+                .count();
+            assert_eq!(count, 1);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+use super::Backend;
+
+/*
+    用 Space-Saving 算法近似统计访问最多的 key，只保留 hotkeys-capacity 个计数器：
+    - key 已经在表中时计数加一
+    - 表没满时加入，计数为 1
+    - 表满时替换计数最小的 key，新 key 的计数为最小计数加一，error 记录继承的最小计数
+    真实访问次数在 [count - error, count] 之间，访问次数超过总访问数 / capacity 的 key 一定在表中。
+    hotkeys-capacity 为 0 表示不统计，修改容量后重新开始统计
+*/
+#[derive(Debug, Default)]
+pub(super) struct HotKeys {
+    counters: Mutex<HashMap<String, HotKey>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotKey {
+    pub key: String,
+    pub count: u64,
+    pub error: u64,
+}
+
+impl HotKeys {
+    fn record(&self, keys: &[String], capacity: usize) {
+        let mut counters = self.counters.lock();
+        if counters.len() > capacity {
+            counters.clear();
+        }
+        for key in keys {
+            if let Some(entry) = counters.get_mut(key) {
+                entry.count += 1;
+                continue;
+            }
+            let mut error = 0;
+            if counters.len() >= capacity {
+                let Some(min) = counters
+                    .values()
+                    .min_by_key(|entry| entry.count)
+                    .map(|entry| entry.key.clone())
+                else {
+                    return;
+                };
+                error = counters.remove(&min).map_or(0, |entry| entry.count);
+            }
+            counters.insert(
+                key.clone(),
+                HotKey {
+                    key: key.clone(),
+                    count: error + 1,
+                    error,
+                },
+            );
+        }
+    }
+}
+
+impl Backend {
+    pub fn hotkeys_enabled(&self) -> bool {
+        self.config.read().hotkeys_capacity > 0
+    }
+
+    // 命令访问的 key，由命令分发按命令表中的 key 位置提取
+    pub fn record_hot_keys(&self, keys: &[String]) {
+        let capacity = self.config.read().hotkeys_capacity;
+        if capacity > 0 {
+            self.hotkeys.record(keys, capacity);
+        }
+    }
+
+    // 按计数从大到小返回最多 count 个 key
+    pub fn hot_keys(&self, count: usize) -> Vec<HotKey> {
+        let mut keys = self
+            .hotkeys
+            .counters
+            .lock()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.sort_unstable_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+        keys.truncate(count);
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(backend: &Backend, key: &str, times: usize) {
+        for _ in 0..times {
+            backend.record_hot_keys(&[key.to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_hot_keys() {
+        let backend = Backend::new();
+        record(&backend, "a", 10);
+        assert!(backend.hot_keys(10).is_empty());
+
+        backend.set_config("hotkeys-capacity", "2").unwrap();
+        record(&backend, "hot", 10);
+        record(&backend, "warm", 5);
+        // 表满后冷 key 替换计数最小的 warm
+        record(&backend, "cold", 1);
+        assert_eq!(
+            backend.hot_keys(10),
+            vec![
+                HotKey {
+                    key: "hot".to_string(),
+                    count: 10,
+                    error: 0
+                },
+                HotKey {
+                    key: "cold".to_string(),
+                    count: 6,
+                    error: 5
+                },
+            ]
+        );
+        assert_eq!(backend.hot_keys(1).len(), 1);
+
+        backend.set_config("hotkeys-capacity", "1").unwrap();
+        record(&backend, "new", 1);
+        assert_eq!(backend.hot_keys(10)[0].key, "new");
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+
+// 与 redis 的 LATENCY HISTORY 一样最多保留 160 个采样点，每秒一个，记录该秒内的最大延迟
+const HISTORY_LEN: usize = 160;
+const BUCKETS: usize = 64;
+
+// 按 2 的幂（微秒）划分桶的延迟直方图，百分位数取所在桶的上界
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKETS],
+    pub calls: u64,
+    pub max_us: u64,
+    pub latest_us: u64,
+    // 最近一次执行的 unix 时间（秒）
+    pub latest_at: u64,
+    history: VecDeque<(u64, u64)>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            calls: 0,
+            max_us: 0,
+            latest_us: 0,
+            latest_at: 0,
+            history: VecDeque::new(),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, us: u64, now: u64) {
+        let index = (u64::BITS - us.leading_zeros()) as usize;
+        self.buckets[index.min(BUCKETS - 1)] += 1;
+        self.calls += 1;
+        self.max_us = self.max_us.max(us);
+        self.latest_us = us;
+        self.latest_at = now;
+
+        match self.history.back_mut() {
+            Some((at, max)) if *at == now => *max = (*max).max(us),
+            _ => {
+                if self.history.len() == HISTORY_LEN {
+                    self.history.pop_front();
+                }
+                self.history.push_back((now, us));
+            }
+        }
+    }
+
+    // p 为 0 ~ 100 的百分数
+    pub fn percentile(&self, p: f64) -> u64 {
+        let target = ((self.calls as f64) * p / 100.0).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                let upper = if index == 0 { 0 } else { (1u64 << index) - 1 };
+                return upper.min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    // (unix 时间, 该秒内的最大延迟)，按时间从早到晚
+    pub fn history(&self) -> impl Iterator<Item = &(u64, u64)> {
+        self.history.iter()
+    }
+}
+
+// 每个命令一个直方图，由网络层在命令执行完后记录
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    commands: Mutex<HashMap<String, LatencyHistogram>>,
+}
+
+impl LatencyStats {
+    pub fn record(&self, command: &str, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut commands = self.commands.lock();
+        match commands.get_mut(command) {
+            Some(histogram) => histogram.record(us, now),
+            None => {
+                let mut histogram = LatencyHistogram::default();
+                histogram.record(us, now);
+                commands.insert(command.to_string(), histogram);
+            }
+        }
+    }
+
+    pub fn get(&self, command: &str) -> Option<LatencyHistogram> {
+        self.commands.lock().get(command).cloned()
+    }
+
+    // 按命令名排序
+    pub fn all(&self) -> Vec<(String, LatencyHistogram)> {
+        let mut all = self
+            .commands
+            .lock()
+            .iter()
+            .map(|(name, histogram)| (name.clone(), histogram.clone()))
+            .collect::<Vec<_>>();
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+        all
+    }
+
+    // commands 为空时清空所有命令，返回清空的命令个数
+    pub fn reset(&self, commands: &[String]) -> usize {
+        let mut all = self.commands.lock();
+        if commands.is_empty() {
+            let count = all.len();
+            all.clear();
+            return count;
+        }
+        commands
+            .iter()
+            .filter(|name| all.remove(name.as_str()).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        for us in [1, 2, 3, 5, 100] {
+            histogram.record(us, 10);
+        }
+        histogram.record(1000, 11);
+
+        assert_eq!(histogram.calls, 6);
+        assert_eq!(histogram.max_us, 1000);
+        assert_eq!(histogram.latest_us, 1000);
+        assert_eq!(histogram.percentile(50.0), 3);
+        assert_eq!(histogram.percentile(99.0), 1000);
+        assert_eq!(
+            histogram.history().copied().collect::<Vec<_>>(),
+            vec![(10, 100), (11, 1000)]
+        );
+    }
+
+    #[test]
+    fn test_latency_stats_reset() {
+        let stats = LatencyStats::default();
+        stats.record("get", Duration::from_micros(10));
+        stats.record("set", Duration::from_micros(20));
+        assert_eq!(stats.get("get").unwrap().calls, 1);
+
+        assert_eq!(stats.reset(&["get".to_string(), "hget".to_string()]), 1);
+        assert!(stats.get("get").is_none());
+        assert_eq!(stats.reset(&[]), 1);
+        assert!(stats.all().is_empty());
+    }
+}
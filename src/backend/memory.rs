@@ -0,0 +1,110 @@
+use std::mem::size_of;
+use std::sync::Arc;
+
+use crate::RespFrame;
+
+use super::{shared, ValueKind};
+
+// 每个哈希表条目的固定开销估算（bucket、hash 值、控制字节等）
+const ENTRY_OVERHEAD: usize = 16;
+
+// 按类型统计的近似内存占用（字节），用于 MEMORY USAGE、INFO memory 以及 maxmemory 淘汰
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub strings: usize,
+    pub hashes: usize,
+    pub sets: usize,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> usize {
+        self.strings + self.hashes + self.sets
+    }
+
+    pub fn get(&self, kind: ValueKind) -> usize {
+        match kind {
+            ValueKind::String => self.strings,
+            ValueKind::Hash => self.hashes,
+            ValueKind::Set => self.sets,
+        }
+    }
+
+    pub(super) fn add(&mut self, kind: ValueKind, n: usize) {
+        *self.slot(kind) += n;
+    }
+
+    pub(super) fn sub(&mut self, kind: ValueKind, n: usize) {
+        let slot = self.slot(kind);
+        *slot = slot.saturating_sub(n);
+    }
+
+    pub(super) fn merge(&mut self, other: &MemoryStats) {
+        self.strings += other.strings;
+        self.hashes += other.hashes;
+        self.sets += other.sets;
+    }
+
+    fn slot(&mut self, kind: ValueKind) -> &mut usize {
+        match kind {
+            ValueKind::String => &mut self.strings,
+            ValueKind::Hash => &mut self.hashes,
+            ValueKind::Set => &mut self.sets,
+        }
+    }
+}
+
+pub(super) fn key_size(key: &str) -> usize {
+    size_of::<String>() + key.len() + ENTRY_OVERHEAD
+}
+
+pub(super) fn string_size(key: &str, value: &Arc<RespFrame>) -> usize {
+    key_size(key) + stored_size(value)
+}
+
+// 共享的整数对象只计算指针本身
+pub(super) fn stored_size(value: &Arc<RespFrame>) -> usize {
+    if shared::is_shared(value) {
+        size_of::<Arc<RespFrame>>()
+    } else {
+        size_of::<Arc<RespFrame>>() + frame_size(value)
+    }
+}
+
+// hash / set 本身（key 与内部哈希表头）的开销，不含字段
+pub(super) fn collection_size(key: &str) -> usize {
+    key_size(key) + size_of::<std::collections::HashMap<String, RespFrame>>()
+}
+
+pub(super) fn hash_field_size(field: &str, value: &Arc<RespFrame>) -> usize {
+    key_size(field) + stored_size(value)
+}
+
+pub(super) fn set_member_size(member: &str) -> usize {
+    key_size(member)
+}
+
+pub(super) fn frame_size(frame: &RespFrame) -> usize {
+    let heap = match frame {
+        RespFrame::SimpleString(s) => s.0.len(),
+        RespFrame::Error(e) => e.0.len(),
+        RespFrame::BulkString(b) => b.0.len(),
+        RespFrame::Array(a) => a.0.iter().map(frame_size).sum(),
+        RespFrame::Set(s) => s.0.iter().map(frame_size).sum(),
+        RespFrame::Push(p) => p.0.iter().map(frame_size).sum(),
+        RespFrame::Map(m) => m.0.iter().map(|(k, v)| key_size(k) + frame_size(v)).sum(),
+        RespFrame::Attribute(a) => {
+            a.attributes
+                .iter()
+                .map(|(k, v)| key_size(k) + frame_size(v))
+                .sum::<usize>()
+                + frame_size(&a.frame)
+        }
+        RespFrame::Integer(_)
+        | RespFrame::Null(_)
+        | RespFrame::NullBulkString(_)
+        | RespFrame::NullArray(_)
+        | RespFrame::Boolean(_)
+        | RespFrame::Double(_) => 0,
+    };
+    size_of::<RespFrame>() + heap
+}
@@ -1,17 +1,540 @@
-use crate::cmd::{RESP_INT_0, RESP_INT_1};
-use crate::RespFrame;
-use dashmap::{DashMap, DashSet};
+mod batch;
+mod encoding;
+mod event;
+mod expire;
+#[cfg(feature = "serde")]
+mod export;
+mod guard;
+mod hotkeys;
+mod latency;
+mod memory;
+mod oplog;
+mod persist;
+mod pubsub;
+mod scan;
+mod shared;
+mod snapshot;
+mod stats;
+mod tier;
+mod typed;
+mod value;
+
+use crate::{ConfigError, RespFrame, ServerConfig};
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, RandomState};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "serde")]
+pub use self::export::{Dump, DumpEntry, DumpValue, DUMP_VERSION};
+pub use self::{
+    event::{KeyEvent, KeyOp},
+    guard::KeyGuard,
+    hotkeys::HotKey,
+    latency::{LatencyHistogram, LatencyStats},
+    memory::MemoryStats,
+    oplog::OpEntry,
+    persist::SaveState,
+    pubsub::{message_frame, OverflowPolicy, SubscriberLag, SubscriberQueue},
+    shared::SHARED_INTEGERS,
+    snapshot::{Snapshot, SnapshotSummary},
+    stats::{KeyspaceStats, ServerStats},
+    typed::{ExpireCondition, HashRef, SetRef},
+    value::{Value, ValueKind},
+};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
-#[derive(Debug, Default)]
+// 按 key 的 hash 把 keyspace 切分到 N 个 shard，每个 shard 各自持有一把读写锁，
+// 写入不同 shard 的请求互不阻塞
+#[derive(Debug)]
 pub struct BackendInner {
-    map: DashMap<String, RespFrame>,
-    hmap: DashMap<String, DashMap<String, RespFrame>>,
-    smap: DashMap<String, DashSet<String>>,
+    shards: Box<[RwLock<Shard>]>,
+    hasher: RandomState,
+    events: broadcast::Sender<KeyEvent>,
+    // 全局单调递增的版本号，key 被删除后重建也不会复用旧版本号，避免 ABA 问题
+    version: AtomicU64,
+    config: RwLock<ServerConfig>,
+    latency: LatencyStats,
+    stats: ServerStats,
+    save_state: SaveState,
+    oplog: oplog::Oplog,
+    tier: tier::Tier,
+    pubsub: pubsub::PubSub,
+    hotkeys: hotkeys::HotKeys,
+    active_expire: expire::ActiveExpire,
+}
+
+#[derive(Debug, Default)]
+struct Shard {
+    map: HashMap<String, StringEntry>,
+    // hash 和 set 放在 Arc 中：快照只增加引用计数，之后的写入通过 Arc::make_mut 写时复制，
+    // 没有快照持有时 make_mut 直接原地修改
+    hmap: HashMap<String, Arc<HashMap<String, Arc<RespFrame>>>>,
+    smap: HashMap<String, Arc<HashSet<String>>>,
+    // 设置了过期时间的 key，与类型无关。读写 key 之前先检查并删除已过期的 key；
+    // 使用 IndexMap 以便主动过期按下标随机抽样
+    expires: IndexMap<String, Instant>,
+    // 值已经写到磁盘的字符串，key 和过期时间仍在内存中，访问之前先读回 map
+    cold: HashMap<String, tier::ColdEntry>,
+    memory: MemoryStats,
+}
+
+#[derive(Debug)]
+struct StringEntry {
+    value: Arc<RespFrame>,
+    version: u64,
+    // 最近一次读写的时间，用于判断是否可以写到磁盘
+    last_access: AtomicU32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    // shard 数量，会向上取整为 2 的幂
+    pub shards: usize,
+    // 事件通道和操作日志通道的容量，订阅者落后超过该数量时会收到 RecvError::Lagged
+    pub event_capacity: usize,
+    // 主动过期的间隔和每次抽样的 key 个数，间隔为 0 表示只在访问时删除过期的 key
+    pub expire_interval: Duration,
+    pub expire_samples: usize,
+    // 运行时可修改的配置的初始值
+    pub server: ServerConfig,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            shards: cpus * 4,
+            event_capacity: 1024,
+            expire_interval: Duration::from_millis(100),
+            expire_samples: 20,
+            server: ServerConfig::default(),
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::with_config(BackendConfig::default())
+    }
+}
+
+impl BackendInner {
+    fn new(config: BackendConfig) -> Self {
+        let shards = config.shards.max(1).next_power_of_two();
+        let (events, _) = broadcast::channel(config.event_capacity.max(1));
+        Self {
+            shards: (0..shards).map(|_| RwLock::default()).collect(),
+            hasher: RandomState::new(),
+            events,
+            version: AtomicU64::new(0),
+            config: RwLock::new(config.server),
+            latency: LatencyStats::default(),
+            stats: ServerStats::default(),
+            save_state: SaveState::default(),
+            oplog: oplog::Oplog::new(config.event_capacity),
+            tier: tier::Tier::default(),
+            pubsub: pubsub::PubSub::default(),
+            hotkeys: hotkeys::HotKeys::default(),
+            active_expire: expire::ActiveExpire::default(),
+        }
+    }
+
+    fn shard_index(&self, key: &str) -> usize {
+        let hash = self.hasher.hash_one(key) as usize;
+        hash & (self.shards.len() - 1)
+    }
+
+    fn shard(&self, key: &str) -> &RwLock<Shard> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    fn next_version(&self) -> u64 {
+        self.version.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // 在持有 shard 写锁时调用，保证同一个 key 的事件顺序与写入顺序一致
+    fn notify(&self, key: &str, op: KeyOp) {
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(KeyEvent {
+                key: key.to_string(),
+                op,
+            });
+        }
+    }
+
+    // 以下写操作都要求调用方已经持有 key 所在 shard 的写锁，
+    // Backend 的方法与 KeyGuard 共用这些实现
+    fn put_string(&self, shard: &mut Shard, key: String, value: RespFrame) {
+        self.notify(&key, KeyOp::Set);
+        let version = self.next_version();
+        let value = shared::share(value);
+        let key_size = memory::key_size(&key);
+        // 与 redis 一样 SET 会替换任意类型的旧值
+        shard.remove_other_kinds(&key, ValueKind::String);
+        // 覆盖冷数据时不需要读回旧值，冷数据只占用 key 的内存
+        if shard.drop_cold(&key) {
+            shard.memory.sub(ValueKind::String, key_size);
+        }
+        shard
+            .memory
+            .add(ValueKind::String, key_size + memory::stored_size(&value));
+        let entry = StringEntry {
+            value,
+            version,
+            last_access: AtomicU32::new(self.tier.clock()),
+        };
+        if let Some(old) = shard.map.insert(key, entry) {
+            shard.memory.sub(
+                ValueKind::String,
+                key_size + memory::stored_size(&old.value),
+            );
+        }
+    }
+
+    fn remove_string(&self, shard: &mut Shard, key: &str) -> Option<RespFrame> {
+        let value = shard.take_string(key)?;
+        shard.expires.swap_remove(key);
+        self.notify(key, KeyOp::Del);
+        Some(value)
+    }
+
+    // 只在读锁下检查，过期时才加写锁，没有设置过期时间的 key 不需要写锁。
+    // 值在磁盘上时读回内存，之后的读取都可以直接访问 map
+    fn expire_if_needed(&self, key: &str) {
+        let lock = self.shard(key);
+        let (expired, cold) = {
+            let shard = lock.read();
+            (shard.is_expired(key, Instant::now()), shard.is_cold(key))
+        };
+        if expired {
+            self.purge_expired(&mut lock.write(), key);
+        } else if cold {
+            self.page_in(key);
+        }
+    }
+
+    // 已经持有写锁时使用，key 已过期时删除所有类型的值，返回是否删除。
+    // 冷数据不在这里读回，需要读取值的调用方在加锁之前调用 page_in
+    fn purge_expired(&self, shard: &mut Shard, key: &str) -> bool {
+        if !shard.is_expired(key, Instant::now()) {
+            return false;
+        }
+        shard.remove_key(key);
+        ServerStats::incr(&self.stats.expired_keys, 1);
+        self.notify(key, KeyOp::Expired);
+        true
+    }
+
+    fn put_hash_field(&self, shard: &mut Shard, key: String, field: String, value: RespFrame) {
+        self.notify(&key, KeyOp::HSet);
+        let value = shared::share(value);
+        shard.remove_other_kinds(&key, ValueKind::Hash);
+        let Shard { hmap, memory, .. } = shard;
+        if !hmap.contains_key(&key) {
+            memory.add(ValueKind::Hash, memory::collection_size(&key));
+        }
+
+        memory.add(ValueKind::Hash, memory::hash_field_size(&field, &value));
+        let hash = Arc::make_mut(hmap.entry(key).or_default());
+        if let Some(old) = hash.get(&field) {
+            memory.sub(ValueKind::Hash, memory::hash_field_size(&field, old));
+        }
+        hash.insert(field, value);
+    }
+
+    // 字段删空之后同时删除整个 hash
+    fn remove_hash_field(
+        &self,
+        shard: &mut Shard,
+        key: &str,
+        field: &str,
+    ) -> Option<Arc<RespFrame>> {
+        let Shard {
+            hmap,
+            memory,
+            expires,
+            ..
+        } = shard;
+        let hash = Arc::make_mut(hmap.get_mut(key)?);
+        let value = hash.remove(field)?;
+        memory.sub(ValueKind::Hash, memory::hash_field_size(field, &value));
+        if hash.is_empty() {
+            hmap.remove(key);
+            memory.sub(ValueKind::Hash, memory::collection_size(key));
+            expires.swap_remove(key);
+        }
+        self.notify(key, KeyOp::HDel);
+        Some(value)
+    }
+
+    fn add_set_members<I, T>(&self, shard: &mut Shard, key: String, members: I) -> i64
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        shard.remove_other_kinds(&key, ValueKind::Set);
+        let Shard { smap, memory, .. } = shard;
+        if !smap.contains_key(&key) {
+            memory.add(ValueKind::Set, memory::collection_size(&key));
+        }
+        let set = Arc::make_mut(smap.entry(key.clone()).or_default());
+
+        let mut count = 0;
+        for member in members {
+            let member = member.into();
+            let size = memory::set_member_size(&member);
+            if set.insert(member) {
+                memory.add(ValueKind::Set, size);
+                count += 1;
+            }
+        }
+        // 没有新增成员时不保留空集合
+        if set.is_empty() {
+            smap.remove(&key);
+            memory.sub(ValueKind::Set, memory::collection_size(&key));
+        }
+        if count > 0 {
+            self.notify(&key, KeyOp::SAdd);
+        }
+        count
+    }
+
+    // 成员删空之后同时删除整个 set
+    fn remove_set_members<I, T>(&self, shard: &mut Shard, key: &str, members: I) -> i64
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let Shard {
+            smap,
+            memory,
+            expires,
+            ..
+        } = shard;
+        let Some(set) = smap.get_mut(key) else {
+            return 0;
+        };
+        let set = Arc::make_mut(set);
+
+        let mut count = 0;
+        for member in members {
+            let member = member.as_ref();
+            if set.remove(member) {
+                memory.sub(ValueKind::Set, memory::set_member_size(member));
+                count += 1;
+            }
+        }
+        if set.is_empty() {
+            smap.remove(key);
+            memory.sub(ValueKind::Set, memory::collection_size(key));
+            expires.swap_remove(key);
+        }
+        if count > 0 {
+            self.notify(key, KeyOp::SRem);
+        }
+        count
+    }
+}
+
+impl Shard {
+    fn is_expired(&self, key: &str, now: Instant) -> bool {
+        !self.expires.is_empty() && self.expires.get(key).is_some_and(|at| *at <= now)
+    }
+
+    fn value(&self, key: &str) -> Option<Value> {
+        if let Some(entry) = self.map.get(key) {
+            return Some(Value::String(entry.value.clone()));
+        }
+        if let Some(hash) = self.hmap.get(key) {
+            return Some(Value::Hash(hash.clone()));
+        }
+        self.smap.get(key).map(|set| Value::Set(set.clone()))
+    }
+
+    // 任意类型的 key 是否存在，不检查是否过期
+    fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+            || self.hmap.contains_key(key)
+            || self.smap.contains_key(key)
+            || self.is_cold(key)
+    }
+
+    // key 当前的类型，冷数据也是字符串
+    fn kind(&self, key: &str) -> Option<ValueKind> {
+        if self.map.contains_key(key) || self.is_cold(key) {
+            Some(ValueKind::String)
+        } else if self.hmap.contains_key(key) {
+            Some(ValueKind::Hash)
+        } else if self.smap.contains_key(key) {
+            Some(ValueKind::Set)
+        } else {
+            None
+        }
+    }
+
+    // 删除 key 的所有类型的值以及过期时间，不触发事件，返回 key 是否存在
+    fn remove_key(&mut self, key: &str) -> bool {
+        self.expires.swap_remove(key);
+        let string = self.remove_other_kinds(key, ValueKind::Set);
+        self.remove_set(key) || string
+    }
+
+    // 写入 keep 类型的值之前删除 key 其他类型的值并扣除占用的内存，一个 key 只保留一种类型。
+    // 返回是否删除了
+    fn remove_other_kinds(&mut self, key: &str, keep: ValueKind) -> bool {
+        let mut removed = false;
+        if keep != ValueKind::String {
+            removed |= self.take_string(key).is_some();
+            if self.drop_cold(key) {
+                self.memory.sub(ValueKind::String, memory::key_size(key));
+                removed = true;
+            }
+        }
+        if keep != ValueKind::Hash {
+            removed |= self.remove_hash(key);
+        }
+        if keep != ValueKind::Set {
+            removed |= self.remove_set(key);
+        }
+        removed
+    }
+
+    fn remove_hash(&mut self, key: &str) -> bool {
+        let Some(hash) = self.hmap.remove(key) else {
+            return false;
+        };
+        let size = memory::collection_size(key)
+            + hash
+                .iter()
+                .map(|(f, v)| memory::hash_field_size(f, v))
+                .sum::<usize>();
+        self.memory.sub(ValueKind::Hash, size);
+        true
+    }
+
+    fn remove_set(&mut self, key: &str) -> bool {
+        let Some(set) = self.smap.remove(key) else {
+            return false;
+        };
+        let size = memory::collection_size(key)
+            + set
+                .iter()
+                .map(|m| memory::set_member_size(m))
+                .sum::<usize>();
+        self.memory.sub(ValueKind::Set, size);
+        true
+    }
+
+    // 不触发事件的删除，供 update 等需要自行决定事件类型的场景使用
+    fn take_string(&mut self, key: &str) -> Option<RespFrame> {
+        let (key, entry) = self.map.remove_entry(key)?;
+        self.memory
+            .sub(ValueKind::String, memory::string_size(&key, &entry.value));
+        Some(Arc::unwrap_or_clone(entry.value))
+    }
+
+    fn object_encoding(&self, key: &str, config: &ServerConfig) -> Option<&'static str> {
+        if let Some(entry) = self.map.get(key) {
+            return Some(encoding::string_encoding(&entry.value));
+        }
+        if let Some(hash) = self.hmap.get(key) {
+            return Some(encoding::hash_encoding(hash, config));
+        }
+        self.smap
+            .get(key)
+            .map(|set| encoding::set_encoding(set, config))
+    }
+
+    fn memory_usage(&self, key: &str) -> Option<usize> {
+        let string = self
+            .map
+            .get(key)
+            .map(|e| memory::string_size(key, &e.value));
+        let hash = self.hmap.get(key).map(|h| {
+            memory::collection_size(key)
+                + h.iter()
+                    .map(|(f, v)| memory::hash_field_size(f, v))
+                    .sum::<usize>()
+        });
+        let set = self.smap.get(key).map(|s| {
+            memory::collection_size(key)
+                + s.iter().map(|m| memory::set_member_size(m)).sum::<usize>()
+        });
+
+        [string, hash, set]
+            .into_iter()
+            .flatten()
+            .reduce(|a, b| a + b)
+    }
+
+    // 冷数据只收集文件，调用方释放锁之后再用 tier::read_cold 读取
+    fn collect_entries<F>(
+        &self,
+        kind: Option<ValueKind>,
+        matches: &mut F,
+        out: &mut Vec<(String, Value)>,
+        cold: &mut Vec<(String, Arc<tier::ColdFile>)>,
+    ) where
+        F: FnMut(&str) -> bool,
+    {
+        let wanted = |k: ValueKind| kind.is_none_or(|kind| kind == k);
+
+        if wanted(ValueKind::String) {
+            out.extend(
+                self.map
+                    .iter()
+                    .filter(|(k, _)| matches(k))
+                    .map(|(k, v)| (k.clone(), Value::String(v.value.clone()))),
+            );
+            cold.extend(
+                self.cold_files()
+                    .filter(|(k, _)| matches(k))
+                    .map(|(k, file)| (k.clone(), file.clone())),
+            );
+        }
+        if wanted(ValueKind::Hash) {
+            out.extend(
+                self.hmap
+                    .iter()
+                    .filter(|(k, _)| matches(k))
+                    .map(|(k, v)| (k.clone(), Value::Hash(v.clone()))),
+            );
+        }
+        if wanted(ValueKind::Set) {
+            out.extend(
+                self.smap
+                    .iter()
+                    .filter(|(k, _)| matches(k))
+                    .map(|(k, v)| (k.clone(), Value::Set(v.clone()))),
+            );
+        }
+    }
+
+    fn collect_keys<F>(&self, kind: Option<ValueKind>, matches: &mut F, out: &mut Vec<String>)
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let wanted = |k: ValueKind| kind.is_none_or(|kind| kind == k);
+
+        if wanted(ValueKind::String) {
+            out.extend(self.map.keys().filter(|k| matches(k)).cloned());
+            out.extend(self.cold_keys().filter(|k| matches(k)).cloned());
+        }
+        if wanted(ValueKind::Hash) {
+            out.extend(self.hmap.keys().filter(|k| matches(k)).cloned());
+        }
+        if wanted(ValueKind::Set) {
+            out.extend(self.smap.keys().filter(|k| matches(k)).cloned());
+        }
+    }
 }
 
 impl Deref for Backend {
@@ -24,73 +547,735 @@ impl Deref for Backend {
 
 impl Backend {
     pub fn new() -> Self {
-        Self(Arc::new(BackendInner::default()))
+        Self::default()
+    }
+
+    pub fn with_config(config: BackendConfig) -> Self {
+        let (interval, samples) = (config.expire_interval, config.expire_samples);
+        let backend = Self(Arc::new(BackendInner::new(config)));
+        backend.start_active_expire(interval, samples);
+        backend
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<KeyEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn config(&self) -> ServerConfig {
+        self.config.read().clone()
+    }
+
+    pub fn set_config(&self, name: &str, value: &str) -> Result<(), ConfigError> {
+        self.config.write().set(name, value)
+    }
+
+    pub fn latency(&self) -> &LatencyStats {
+        &self.latency
+    }
+
+    pub fn stats(&self) -> &ServerStats {
+        &self.stats
+    }
+
+    // 根据当前配置的阈值给出 key 的编码名称，key 不存在时返回 None
+    pub fn object_encoding(&self, key: &str) -> Option<&'static str> {
+        self.expire_if_needed(key);
+        let config = self.config.read();
+        self.shard(key).read().object_encoding(key, &config)
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    // 按类型汇总的近似内存占用
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        for shard in self.shards.iter() {
+            stats.merge(&shard.read().memory);
+        }
+        stats
+    }
+
+    // 设置了 maxmemory 且近似内存占用已经超过时返回 true
+    pub fn over_maxmemory(&self) -> bool {
+        let maxmemory = self.config.read().maxmemory;
+        maxmemory > 0 && self.memory_stats().total() > maxmemory
+    }
+
+    // 单个 key 的近似内存占用，key 不存在时返回 None
+    pub fn memory_usage(&self, key: &str) -> Option<usize> {
+        self.expire_if_needed(key);
+        self.shard(key).read().memory_usage(key)
+    }
+
+    // 按 shard 依次遍历 key，kind 为 None 时返回所有类型，matches 用于按模式过滤，已过期的 key 会被跳过。
+    // 每个 shard 只在遍历自身时加读锁，因此结果不是某一时刻的一致视图，需要一致性时使用 snapshot
+    pub fn keys<F>(&self, kind: Option<ValueKind>, mut matches: F) -> Vec<String>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let now = Instant::now();
+        let mut keys = Vec::new();
+        for shard in self.shards.iter() {
+            let shard = shard.read();
+            let mut live = |k: &str| !shard.is_expired(k, now) && matches(k);
+            shard.collect_keys(kind, &mut live, &mut keys);
+        }
+        keys
+    }
+
+    pub fn entries<F>(&self, kind: Option<ValueKind>, mut matches: F) -> Vec<(String, Value)>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let now = Instant::now();
+        let (mut entries, mut cold) = (Vec::new(), Vec::new());
+        for shard in self.shards.iter() {
+            let shard = shard.read();
+            let mut live = |k: &str| !shard.is_expired(k, now) && matches(k);
+            shard.collect_entries(kind, &mut live, &mut entries, &mut cold);
+        }
+        entries.extend(tier::read_cold(cold).map(|(k, v)| (k, Value::String(v))));
+        entries
+    }
+
+    // 按 shard 顺序同时持有所有 shard 的读锁后再拷贝数据，得到某一时刻的一致快照
+    pub fn snapshot(&self) -> Snapshot {
+        let guards = self.shards.iter().map(|s| s.read()).collect::<Vec<_>>();
+
+        let now = Instant::now();
+        let (mut entries, mut cold) = (Vec::new(), Vec::new());
+        let mut expires = HashMap::new();
+        for shard in guards.iter() {
+            let mut live = |k: &str| !shard.is_expired(k, now);
+            shard.collect_entries(None, &mut live, &mut entries, &mut cold);
+            expires.extend(
+                shard
+                    .expires
+                    .iter()
+                    .filter(|(_, at)| **at > now)
+                    .map(|(k, at)| (k.clone(), *at)),
+            );
+        }
+        // 读冷数据的文件较慢，释放锁之后再读，文件在引用释放之前不会被删除
+        drop(guards);
+        entries.extend(tier::read_cold(cold).map(|(k, v)| (k, Value::String(v))));
+        Snapshot::new(entries, expires)
+    }
+
+    // key 对应的值的类型，key 不存在时返回 None
+    pub fn key_type(&self, key: &str) -> Option<ValueKind> {
+        self.expire_if_needed(key);
+        self.shard(key).read().kind(key)
+    }
+
+    // hash 或 set 的元素个数，key 不存在或是字符串时返回 None
+    pub fn collection_len(&self, key: &str) -> Option<usize> {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        shard
+            .hmap
+            .get(key)
+            .map(|h| h.len())
+            .or_else(|| shard.smap.get(key).map(|s| s.len()))
+    }
+
+    // 返回共享的只读句柄，读取大 value 时不需要拷贝
+    pub fn get(&self, key: &str) -> Option<Arc<RespFrame>> {
+        self.expire_if_needed(key);
+        let value = self.shard(key).read().map.get(key).map(|e| {
+            e.touch(self.tier.clock());
+            e.value.clone()
+        });
+        self.stats.record_lookup(value.is_some());
+        value
+    }
+
+    // 返回值及其版本号，版本号可用于 compare_and_swap
+    pub fn get_with_version(&self, key: &str) -> Option<(Arc<RespFrame>, u64)> {
+        self.expire_if_needed(key);
+        self.shard(key).read().map.get(key).map(|e| {
+            e.touch(self.tier.clock());
+            (e.value.clone(), e.version)
+        })
     }
 
+    // 与 SET 命令一样会清除原来的过期时间
     pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+        let mut shard = self.shard(&key).write();
+        shard.expires.swap_remove(&key);
+        self.put_string(&mut shard, key, value);
     }
 
-    pub fn sadd<I, T>(&self, key: T, values: I) -> RespFrame
+    // 锁住 key 所在的 shard，在 guard 存活期间可以对该 key 做多步读写而不被其他请求打断
+    pub fn lock_key(&self, key: &str) -> KeyGuard<'_> {
+        self.lock_keys(&[key])
+    }
+
+    // 按 shard 下标顺序加锁，多个 key 落在同一个 shard 时只加一次锁，因此不会死锁
+    pub fn lock_keys(&self, keys: &[&str]) -> KeyGuard<'_> {
+        let mut indexes = keys
+            .iter()
+            .map(|key| self.shard_index(key))
+            .collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        for key in keys {
+            self.page_in(key);
+        }
+        let mut shards = indexes
+            .into_iter()
+            .map(|index| (index, self.shards[index].write()))
+            .collect::<Vec<_>>();
+        for key in keys {
+            let index = self.shard_index(key);
+            if let Some((_, shard)) = shards.iter_mut().find(|(i, _)| *i == index) {
+                if !self.purge_expired(shard, key) {
+                    self.page_in_locked(shard, key);
+                }
+            }
+        }
+        KeyGuard::new(self, shards)
+    }
+
+    // 在 shard 写锁内完成 读-改-写，f 接收旧值并返回新值，返回 None 表示删除该 key。
+    // INCR、APPEND 这类命令可以基于它实现而不会出现并发丢失更新。
+    // key 是 hash 或 set 时不调用 f 也不修改，返回 false，调用方据此回复 WRONGTYPE
+    pub fn update<F>(&self, key: &str, f: F) -> bool
     where
-        I: IntoIterator<Item = T>,
-        T: Into<String>,
+        F: FnOnce(Option<RespFrame>) -> Option<RespFrame>,
     {
-        let mut count = 0;
-        let set = self.smap.entry(key.into()).or_default();
+        self.page_in(key);
+        let mut shard = self.shard(key).write();
+        if !self.purge_expired(&mut shard, key) {
+            self.page_in_locked(&mut shard, key);
+        }
+        if shard
+            .kind(key)
+            .is_some_and(|kind| kind != ValueKind::String)
+        {
+            return false;
+        }
+        let old = shard.take_string(key);
+        let existed = old.is_some();
 
-        for value in values {
-            if set.insert(value.into()) {
-                count += 1;
+        // 与 INCR、APPEND 一样保留原来的过期时间
+        match f(old) {
+            Some(value) => self.put_string(&mut shard, key.to_string(), value),
+            None if existed => {
+                shard.expires.swap_remove(key);
+                self.notify(key, KeyOp::Del)
             }
+            None => {}
         }
+        true
+    }
 
-        RespFrame::Integer(count.into())
+    // 仅当 key 当前的版本号等于 expected 时才写入，expected 为 None 表示期望 key 不存在；
+    // value 为 None 表示删除。返回是否写入成功
+    pub fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<u64>,
+        value: Option<RespFrame>,
+    ) -> bool {
+        self.page_in(key);
+        let mut shard = self.shard(key).write();
+        if !self.purge_expired(&mut shard, key) {
+            self.page_in_locked(&mut shard, key);
+        }
+        // hash 和 set 没有版本号，不能当作不存在的 key 覆盖
+        if shard.map.get(key).map(|e| e.version) != expected
+            || shard
+                .kind(key)
+                .is_some_and(|kind| kind != ValueKind::String)
+        {
+            return false;
+        }
+
+        match value {
+            Some(value) => self.put_string(&mut shard, key.to_string(), value),
+            None => {
+                self.remove_string(&mut shard, key);
+            }
+        }
+        true
     }
-    pub fn sismember(&self, key: &str, value: &str) -> RespFrame {
-        self.smap
+
+    pub fn sadd<I, T>(&self, key: T, values: I) -> RespFrame
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let key = key.into();
+        let mut shard = self.shard(&key).write();
+        self.purge_expired(&mut shard, &key);
+        RespFrame::Integer(self.add_set_members(&mut shard, key, values))
+    }
+
+    pub fn sismember(&self, key: &str, value: &str) -> bool {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        let set = shard.smap.get(key);
+        self.stats.record_lookup(set.is_some());
+        set.is_some_and(|v| v.contains(value))
+    }
+
+    pub fn smembers(&self, key: &str) -> Option<HashSet<String>> {
+        self.expire_if_needed(key);
+        let members = self
+            .shard(key)
+            .read()
+            .smap
             .get(key)
-            .and_then(|v| v.get(value).map(|_| RESP_INT_1.clone()))
-            .unwrap_or_else(|| RESP_INT_0.clone())
+            .map(|set| (**set).clone());
+        self.stats.record_lookup(members.is_some());
+        members
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
+    // 与 redis 一样按 key 是否存在统计命中，field 不存在也算命中
+    pub fn hget(&self, key: &str, field: &str) -> Option<Arc<RespFrame>> {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        let hash = shard.hmap.get(key);
+        self.stats.record_lookup(hash.is_some());
         // and_then 如何 key 不存在时返回 None，否则就执行对应的方法
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+        hash.and_then(|v| v.get(field).cloned())
     }
 
     pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+        let mut shard = self.shard(&key).write();
+        self.purge_expired(&mut shard, &key);
+        self.put_hash_field(&mut shard, key, field, value);
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    // 在同一把读锁内判断类型并拷贝整个值，HGETALL、SMEMBERS 等聚合读取用它得到某一时刻完整的集合，
+    // 不会与类型检查之间插入其他写入，也不会看到另一个请求只修改了一部分的状态
+    pub fn get_value(&self, key: &str) -> Option<Value> {
+        self.expire_if_needed(key);
+        let value = self.shard(key).read().value(key);
+        self.stats.record_lookup(value.is_some());
+        value
     }
 
-    pub fn hmget<I, T>(&self, key: &str, fields: I) -> Option<DashMap<String, RespFrame>>
+    pub fn hgetall(&self, key: &str) -> Option<HashMap<String, Arc<RespFrame>>> {
+        self.expire_if_needed(key);
+        let hash = self
+            .shard(key)
+            .read()
+            .hmap
+            .get(key)
+            .map(|hash| (**hash).clone());
+        self.stats.record_lookup(hash.is_some());
+        hash
+    }
+
+    // 按 fields 的顺序返回对应的值，重复的 field 返回多次，不存在的 field 为 None。
+    // key 不存在时返回 None
+    pub fn hmget<I, T>(&self, key: &str, fields: I) -> Option<Vec<Option<Arc<RespFrame>>>>
     where
         I: IntoIterator<Item = T>,
-        T: Into<String>,
+        T: AsRef<str>,
     {
-        let field_set: DashSet<String> = fields.into_iter().map(Into::into).collect();
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        let hash = shard.hmap.get(key);
+        self.stats.record_lookup(hash.is_some());
+        hash.map(|hash| {
+            fields
+                .into_iter()
+                .map(|field| hash.get(field.as_ref()).cloned())
+                .collect()
+        })
+    }
+}
 
-        self.hmap.get(key).map(|value| {
-            let result = DashMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
 
-            value.iter().for_each(|entry| {
-                let key = entry.key();
-                if field_set.contains(key) {
-                    result.insert(key.clone(), entry.value().clone());
-                }
-            });
-            result
-        })
+    #[test]
+    fn test_shard_count_rounds_to_power_of_two() {
+        let backend = Backend::with_config(BackendConfig {
+            shards: 5,
+            ..Default::default()
+        });
+        assert_eq!(backend.shard_count(), 8);
+
+        let backend = Backend::with_config(BackendConfig {
+            shards: 0,
+            ..Default::default()
+        });
+        assert_eq!(backend.shard_count(), 1);
+    }
+
+    #[test]
+    fn test_keys_spread_across_shards() {
+        let backend = Backend::with_config(BackendConfig {
+            shards: 16,
+            ..Default::default()
+        });
+        for i in 0..1000 {
+            backend.set(format!("key:{}", i), BulkString::from("v").into());
+        }
+
+        let used = backend
+            .shards
+            .iter()
+            .filter(|shard| !shard.read().map.is_empty())
+            .count();
+        assert!(used > 1);
+
+        for i in 0..1000 {
+            assert!(backend.get(&format!("key:{}", i)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_keys_filtered_by_kind_and_pattern() {
+        let backend = Backend::new();
+        backend.set("user:1".to_string(), BulkString::from("a").into());
+        backend.set("order:1".to_string(), BulkString::from("b").into());
+        backend.hset(
+            "user:2".to_string(),
+            "name".to_string(),
+            BulkString::from("c").into(),
+        );
+        backend.sadd("tags", ["x"]);
+
+        let mut keys = backend.keys(None, |_| true);
+        keys.sort();
+        assert_eq!(keys, vec!["order:1", "tags", "user:1", "user:2"]);
+
+        let mut keys = backend.keys(None, |k| k.starts_with("user:"));
+        keys.sort();
+        assert_eq!(keys, vec!["user:1", "user:2"]);
+
+        let keys = backend.keys(Some(ValueKind::Hash), |_| true);
+        assert_eq!(keys, vec!["user:2"]);
+
+        let entries = backend.entries(Some(ValueKind::Set), |_| true);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, "tags");
+        assert_eq!(entries[0].1.kind(), ValueKind::Set);
+    }
+
+    #[test]
+    fn test_snapshot_is_detached_from_backend() {
+        let backend = Backend::new();
+        backend.set("k1".to_string(), BulkString::from("v1").into());
+        backend.hset(
+            "h1".to_string(),
+            "f1".to_string(),
+            BulkString::from("v1").into(),
+        );
+
+        let snapshot = backend.snapshot();
+        backend.set("k1".to_string(), BulkString::from("v2").into());
+        backend.set("k2".to_string(), BulkString::from("v2").into());
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(
+            snapshot.get("k1", ValueKind::String),
+            Some(&Value::String(Arc::new(BulkString::from("v1").into())))
+        );
+        assert!(snapshot.get("k2", ValueKind::String).is_none());
+        assert!(matches!(
+            snapshot.get("h1", ValueKind::Hash),
+            Some(Value::Hash(h)) if h.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_summary() {
+        let backend = Backend::new();
+        backend.set_string("k1", "v");
+        backend.set_string("k2", "longer");
+        backend.hash("h1").set("f1", "v1");
+        backend.set_of("s1").add("a");
+        backend.set_of("s2").add("a");
+        backend.set_of("s2").add("b");
+        backend.expire_at("k1", Instant::now() + Duration::from_secs(10));
+        backend.expire_at("h1", Instant::now() + Duration::from_secs(7200));
+
+        let summary = backend.snapshot().summary();
+        assert_eq!(summary.counts[&ValueKind::String], 2);
+        assert_eq!(summary.counts[&ValueKind::Hash], 1);
+        assert_eq!(summary.counts[&ValueKind::Set], 2);
+        assert_eq!(summary.biggest[&ValueKind::String], ("k2".to_string(), 6));
+        assert_eq!(summary.biggest[&ValueKind::Set], ("s2".to_string(), 2));
+        assert_eq!(
+            summary.ttls,
+            vec![
+                ("no expiry", 3),
+                ("< 1m", 1),
+                ("< 1h", 0),
+                ("< 1d", 1),
+                (">= 1d", 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mutations_emit_key_events() {
+        let backend = Backend::new();
+        let mut events = backend.subscribe_events();
+
+        backend.set("k1".to_string(), BulkString::from("v1").into());
+        backend.hset(
+            "h1".to_string(),
+            "f1".to_string(),
+            BulkString::from("v1").into(),
+        );
+        backend.sadd("s1", ["a"]);
+        // 重复添加不会改变集合，因此不会产生事件
+        backend.sadd("s1", ["a"]);
+
+        let expected = [("k1", KeyOp::Set), ("h1", KeyOp::HSet), ("s1", KeyOp::SAdd)];
+        for (key, op) in expected {
+            let event = events.try_recv().unwrap();
+            assert_eq!(event.key, key);
+            assert_eq!(event.op, op);
+        }
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_update_is_atomic() {
+        let backend = Backend::new();
+
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        backend.update("counter", |old| {
+                            let n = match old {
+                                Some(RespFrame::Integer(n)) => n,
+                                _ => 0,
+                            };
+                            Some(RespFrame::Integer(n + 1))
+                        });
+                    }
+                });
+            }
+        });
+
+        assert_eq!(
+            backend.get("counter").as_deref(),
+            Some(&RespFrame::Integer(8000))
+        );
+
+        backend.update("counter", |_| None);
+        assert_eq!(backend.get("counter"), None);
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let backend = Backend::new();
+
+        // 期望 key 不存在
+        assert!(backend.compare_and_swap("k1", None, Some(RespFrame::Integer(1))));
+        assert!(!backend.compare_and_swap("k1", None, Some(RespFrame::Integer(2))));
+
+        let (value, version) = backend.get_with_version("k1").unwrap();
+        assert_eq!(*value, RespFrame::Integer(1));
+
+        assert!(backend.compare_and_swap("k1", Some(version), Some(RespFrame::Integer(2))));
+        // 旧版本号已经失效
+        assert!(!backend.compare_and_swap("k1", Some(version), Some(RespFrame::Integer(3))));
+        assert_eq!(backend.get("k1").as_deref(), Some(&RespFrame::Integer(2)));
+
+        // 删除后重建的 key 不会复用旧版本号
+        let (_, version) = backend.get_with_version("k1").unwrap();
+        assert!(backend.compare_and_swap("k1", Some(version), None));
+        assert_eq!(backend.get("k1"), None);
+        backend.set("k1".to_string(), RespFrame::Integer(4));
+        assert!(!backend.compare_and_swap("k1", Some(version), None));
+    }
+
+    #[test]
+    fn test_set_replaces_hash_and_set() {
+        let backend = Backend::new();
+        backend.hset(
+            "k".to_string(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        );
+        backend.set("k".to_string(), BulkString::from("x").into());
+        assert_eq!(backend.key_type("k"), Some(ValueKind::String));
+        assert_eq!(backend.hget("k", "f"), None);
+        assert_eq!(backend.memory_stats().hashes, 0);
+
+        backend.sadd("s", ["a", "b"]);
+        backend.lock_key("s").set("s", BulkString::from("x").into());
+        assert_eq!(backend.key_type("s"), Some(ValueKind::String));
+        assert!(!backend.sismember("s", "a"));
+        assert_eq!(backend.memory_stats().sets, 0);
+
+        // 快照中每个 key 只有一个值
+        let snapshot = backend.snapshot();
+        assert_eq!(snapshot.len(), 2);
+    }
+
+    #[test]
+    fn test_key_holds_one_type() {
+        let backend = Backend::new();
+        backend.set_string("k", "v");
+        backend.hset("k".to_string(), "f".to_string(), 1.into());
+        assert_eq!(backend.key_type("k"), Some(ValueKind::Hash));
+        backend.sadd("k", ["a"]);
+        assert_eq!(backend.key_type("k"), Some(ValueKind::Set));
+        assert_eq!(backend.hget("k", "f"), None);
+        assert_eq!(backend.keyspace_stats().keys, 1);
+        let stats = backend.memory_stats();
+        assert_eq!((stats.strings, stats.hashes), (0, 0));
+
+        // 写锁内检查类型
+        let mut guard = backend.lock_key("k");
+        assert_eq!(guard.key_type("k"), Some(ValueKind::Set));
+        assert_eq!(guard.sadd_members("k", ["a", "b"]), 1);
+    }
+
+    #[test]
+    fn test_update_keeps_other_types() {
+        let backend = Backend::new();
+        backend.hset("h".to_string(), "f".to_string(), 1.into());
+        backend.sadd("s", ["a"]);
+        for key in ["h", "s"] {
+            let mut called = false;
+            assert!(!backend.update(key, |_| {
+                called = true;
+                Some(1.into())
+            }));
+            assert!(!called);
+            assert!(!backend.compare_and_swap(key, None, Some(1.into())));
+        }
+        assert_eq!(backend.key_type("h"), Some(ValueKind::Hash));
+        assert!(backend.sismember("s", "a"));
+
+        assert!(backend.update("c", |_| Some(1.into())));
+        assert_eq!(backend.key_type("c"), Some(ValueKind::String));
+    }
+
+    #[test]
+    fn test_memory_stats_track_writes_and_removes() {
+        let backend = Backend::new();
+        assert_eq!(backend.memory_stats().total(), 0);
+
+        backend.set("k1".to_string(), BulkString::from("v1").into());
+        let stats = backend.memory_stats();
+        assert!(stats.strings > 0);
+        assert_eq!(stats.total(), stats.strings);
+        assert_eq!(backend.memory_usage("k1"), Some(stats.strings));
+
+        // 覆盖为更大的值，占用随之增加
+        backend.set("k1".to_string(), BulkString::from("a".repeat(100)).into());
+        assert!(backend.memory_stats().strings > stats.strings);
+
+        backend.hset(
+            "h1".to_string(),
+            "f1".to_string(),
+            BulkString::from("v1").into(),
+        );
+        backend.sadd("s1", ["a", "b"]);
+        let stats = backend.memory_stats();
+        assert!(stats.hashes > 0);
+        assert!(stats.sets > 0);
+        assert_eq!(backend.memory_usage("h1"), Some(stats.hashes));
+        assert_eq!(backend.memory_usage("s1"), Some(stats.sets));
+        assert_eq!(backend.memory_usage("missing"), None);
+
+        backend.update("k1", |_| None);
+        let mut guard = backend.lock_keys(&["h1", "s1"]);
+        guard.hdel("h1", "f1");
+        guard.srem("s1", "a");
+        guard.srem("s1", "b");
+        drop(guard);
+
+        assert_eq!(backend.memory_stats(), MemoryStats::default());
+    }
+
+    #[test]
+    fn test_object_encoding_uses_config() {
+        let backend = Backend::new();
+        backend.sadd("s1", ["1", "2", "3"]);
+        assert_eq!(backend.object_encoding("s1"), Some("intset"));
+        assert_eq!(backend.object_encoding("missing"), None);
+
+        backend.set_config("set-max-intset-entries", "2").unwrap();
+        assert_eq!(backend.config().set_max_intset_entries, 2);
+        assert_eq!(backend.object_encoding("s1"), Some("listpack"));
+    }
+
+    #[test]
+    fn test_small_integer_values_are_shared() {
+        let backend = Backend::new();
+        backend.set("c1".to_string(), BulkString::from("7").into());
+        backend.set("c2".to_string(), BulkString::from("7").into());
+        backend.set("c3".to_string(), BulkString::from("12345").into());
+
+        let value = |key: &str| backend.shard(key).read().map[key].value.clone();
+        assert!(Arc::ptr_eq(&value("c1"), &value("c2")));
+        assert!(!Arc::ptr_eq(&value("c1"), &value("c3")));
+        assert_eq!(
+            backend.get("c1").as_deref(),
+            Some(&BulkString::from("7").into())
+        );
+
+        // 共享对象不计入每个 key 的占用
+        assert!(backend.memory_usage("c1") < backend.memory_usage("c3"));
+
+        backend.update("c1", |_| None);
+        backend.update("c2", |_| None);
+        backend.update("c3", |_| None);
+        assert_eq!(backend.memory_stats(), MemoryStats::default());
+    }
+
+    #[test]
+    fn test_reads_return_shared_handles() {
+        let backend = Backend::new();
+        let large = BulkString::from("x".repeat(1 << 20));
+        backend.set("big".to_string(), large.clone().into());
+        backend.hset("h".to_string(), "f".to_string(), large.into());
+
+        let (a, b) = (backend.get("big").unwrap(), backend.get("big").unwrap());
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let (a, b) = (
+            backend.hget("h", "f").unwrap(),
+            backend.hget("h", "f").unwrap(),
+        );
+        assert!(Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn test_hmget_keeps_field_order() {
+        let backend = Backend::new();
+        for field in ["a", "b", "c"] {
+            backend.hset(
+                "h".to_string(),
+                field.to_string(),
+                BulkString::from(field).into(),
+            );
+        }
+
+        let values = backend.hmget("h", ["c", "x", "a", "c"]).unwrap();
+        let values = values
+            .iter()
+            .map(|value| value.as_deref().cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Some(BulkString::from("c").into()),
+                None,
+                Some(BulkString::from("a").into()),
+                Some(BulkString::from("c").into()),
+            ]
+        );
+        assert_eq!(backend.hmget("missing", ["a"]), None);
     }
 }
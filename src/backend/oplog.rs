@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use crate::RespArray;
+
+use super::Backend;
+
+/*
+    执行成功的写命令（命令表中带 write flag）按顺序广播的操作日志，嵌入方可以据此实现自己的复制、
+    缓存失效或索引，而不需要完整的复制功能。
+
+    与 KeyEvent 不同，这里记录的是完整的命令参数，重放这些命令即可得到相同的数据：
+    改过名的命令记录原来的名称，相对的过期时间记录为 PEXPIREAT / PXAT 的时间戳。
+    只记录经过命令分发执行的命令，直接调用 Backend 方法的写入只能通过 subscribe_events 观察。
+    不同连接并发写同一个 key 时，日志中的顺序是命令完成的顺序
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpEntry {
+    // 从 1 开始连续递增，订阅者可以据此发现 RecvError::Lagged 时丢失了哪些操作
+    pub seq: u64,
+    pub command: RespArray,
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(super) struct Oplog {
+    sender: broadcast::Sender<Arc<OpEntry>>,
+    // 分配序号和发送在同一把锁中完成，保证订阅者收到的序号是递增的
+    seq: Mutex<u64>,
+}
+
+impl Oplog {
+    pub(super) fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender,
+            seq: Mutex::new(0),
+        }
+    }
+}
+
+impl Backend {
+    pub fn subscribe_oplog(&self) -> broadcast::Receiver<Arc<OpEntry>> {
+        self.oplog.sender.subscribe()
+    }
+
+    // 没有订阅者时不需要保留命令参数
+    pub fn oplog_enabled(&self) -> bool {
+        self.oplog.sender.receiver_count() > 0
+    }
+
+    // 由命令分发调用，返回分配的序号
+    pub fn append_oplog(&self, command: RespArray, keys: Vec<String>) -> u64 {
+        let mut seq = self.oplog.seq.lock();
+        *seq += 1;
+        let entry = OpEntry {
+            seq: *seq,
+            command,
+            keys,
+        };
+        let _ = self.oplog.sender.send(Arc::new(entry));
+        *seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_oplog_sequence() {
+        let backend = Backend::new();
+        assert!(!backend.oplog_enabled());
+        let mut oplog = backend.subscribe_oplog();
+        assert!(backend.oplog_enabled());
+
+        let command = RespArray::new(vec![
+            BulkString::from("del").into(),
+            BulkString::from("a").into(),
+            BulkString::from("b").into(),
+        ]);
+        let keys = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(backend.append_oplog(command.clone(), keys.clone()), 1);
+        assert_eq!(backend.append_oplog(command.clone(), keys.clone()), 2);
+
+        let entry = oplog.try_recv().unwrap();
+        assert_eq!(
+            *entry,
+            OpEntry {
+                seq: 1,
+                command,
+                keys
+            }
+        );
+        assert_eq!(oplog.try_recv().unwrap().seq, 2);
+    }
+}
@@ -0,0 +1,387 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bytes::BytesMut;
+use tracing::{info, warn};
+
+use crate::{RespArray, RespDecode, RespFrame};
+
+use super::{snapshot::unix_millis, Backend, Snapshot};
+
+// INFO persistence 中的 BGSAVE 状态
+#[derive(Debug)]
+pub struct SaveState {
+    in_progress: AtomicBool,
+    // 上一次成功保存的 unix 时间，单位秒，启动时为启动时间
+    last_save_time: AtomicU64,
+    last_failed: AtomicBool,
+}
+
+impl Default for SaveState {
+    fn default() -> Self {
+        Self {
+            in_progress: AtomicBool::new(false),
+            last_save_time: AtomicU64::new(unix_time()),
+            last_failed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl SaveState {
+    pub fn in_progress(&self) -> bool {
+        self.in_progress.load(Ordering::Acquire)
+    }
+
+    pub fn last_save_time(&self) -> u64 {
+        self.last_save_time.load(Ordering::Relaxed)
+    }
+
+    pub fn last_status(&self) -> &'static str {
+        if self.last_failed.load(Ordering::Relaxed) {
+            "err"
+        } else {
+            "ok"
+        }
+    }
+
+    fn finish(&self, result: &io::Result<()>) {
+        if result.is_ok() {
+            self.last_save_time.store(unix_time(), Ordering::Relaxed);
+        }
+        self.last_failed.store(result.is_err(), Ordering::Relaxed);
+        self.in_progress.store(false, Ordering::Release);
+    }
+}
+
+impl Backend {
+    // 配置中 dir 与 dbfilename 拼成的保存路径
+    pub fn save_path(&self) -> PathBuf {
+        let config = self.config.read();
+        Path::new(&config.dir).join(&config.dbfilename)
+    }
+
+    pub fn save_state(&self) -> &SaveState {
+        &self.save_state
+    }
+
+    /*
+        不 fork 的 BGSAVE：在所有 shard 的读锁下取快照，hash 和 set 只增加 Arc 的引用计数，
+        字符串本来就在 Arc 中，所以阻塞写入的时间与数据量无关，只与 key 的个数有关。
+        之后在后台线程中序列化快照，期间的写入通过 Arc::make_mut 写时复制，不会影响快照。
+        已经有保存在进行时返回 false
+    */
+    pub fn bgsave(&self) -> bool {
+        if self.save_state.in_progress.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let snapshot = self.snapshot();
+        let path = self.save_path();
+        let backend = self.clone();
+        std::thread::spawn(move || {
+            let result = write_snapshot(&snapshot, &path);
+            match &result {
+                Ok(()) => info!(
+                    "Background saving to {} terminated with success",
+                    path.display()
+                ),
+                Err(e) => warn!("Background saving to {} failed: {}", path.display(), e),
+            }
+            backend.save_state.finish(&result);
+        });
+        true
+    }
+
+    // 前台保存，关闭服务器时使用。有 BGSAVE 在进行时等它结束再保存，两者不会同时写同一个临时文件
+    pub fn save(&self) -> io::Result<()> {
+        while self.save_state.in_progress.swap(true, Ordering::AcqRel) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let result = write_snapshot(&self.snapshot(), &self.save_path());
+        self.save_state.finish(&result);
+        result
+    }
+
+    /*
+        启动时载入 BGSAVE 写出的文件，返回载入的 key 个数，文件不存在时返回 0。
+        PEXPIREAT 的时间已经过去的 key 直接删除，不会在重启之后复活。
+        载入时不检查 maxmemory
+    */
+    pub fn load(&self, path: &Path) -> io::Result<usize> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let mut buf = BytesMut::from(&data[..]);
+        let (now, unix_now) = (Instant::now(), unix_millis());
+        let mut loaded = 0usize;
+        while !buf.is_empty() {
+            let command = RespArray::decode(&mut buf).map_err(invalid_data)?;
+            let mut args = command.0.into_iter();
+            let name = next_string(&mut args)?;
+            let key = next_string(&mut args)?;
+            let mut shard = self.shard(&key).write();
+            match name.as_str() {
+                "SET" => {
+                    let value = args.next().ok_or_else(|| invalid_data("missing value"))?;
+                    self.put_string(&mut shard, key, value);
+                    loaded += 1;
+                }
+                // 大的 hash 和 set 拆成了多条命令，只在第一次出现时计数
+                "HSET" => {
+                    if !shard.hmap.contains_key(&key) {
+                        loaded += 1;
+                    }
+                    let mut args = args.peekable();
+                    while args.peek().is_some() {
+                        let field = next_string(&mut args)?;
+                        let value = args.next().ok_or_else(|| invalid_data("missing value"))?;
+                        self.put_hash_field(&mut shard, key.clone(), field, value);
+                    }
+                }
+                "SADD" => {
+                    if !shard.smap.contains_key(&key) {
+                        loaded += 1;
+                    }
+                    let members = args.map(String::try_from).collect::<Result<Vec<_>, _>>();
+                    self.add_set_members(&mut shard, key, members.map_err(invalid_data)?);
+                }
+                "PEXPIREAT" => {
+                    let at = next_string(&mut args)?
+                        .parse::<u64>()
+                        .map_err(invalid_data)?;
+                    if at <= unix_now {
+                        if shard.remove_key(&key) {
+                            loaded = loaded.saturating_sub(1);
+                        }
+                    } else {
+                        shard
+                            .expires
+                            .insert(key, now + Duration::from_millis(at - unix_now));
+                    }
+                }
+                name => return Err(invalid_data(format!("unknown command {}", name))),
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+fn next_string(args: &mut impl Iterator<Item = RespFrame>) -> io::Result<String> {
+    let arg = args
+        .next()
+        .ok_or_else(|| invalid_data("missing argument"))?;
+    String::try_from(arg).map_err(invalid_data)
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// 先写入同一目录下的临时文件再 rename，保存失败或中途退出都不会破坏已有的文件
+pub(crate) fn write_snapshot(snapshot: &Snapshot, path: &Path) -> io::Result<()> {
+    let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+    let result = File::create(&tmp)
+        .and_then(|file| {
+            snapshot.write_to(BufWriter::new(&file))?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::{BulkString, RespArray, RespDecode, RespFrame};
+
+    fn test_backend(name: &str) -> Backend {
+        let dir = std::env::temp_dir().join(format!(
+            "simple-redis-persist-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let backend = Backend::new();
+        backend.set_config("dir", dir.to_str().unwrap()).unwrap();
+        backend
+    }
+
+    fn wait_for_save(backend: &Backend) {
+        for _ in 0..500 {
+            if !backend.save_state().in_progress() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("background save did not finish");
+    }
+
+    fn read_commands(path: &Path) -> Vec<RespArray> {
+        let mut buf = BytesMut::from(&fs::read(path).unwrap()[..]);
+        let mut commands = Vec::new();
+        while !buf.is_empty() {
+            commands.push(RespArray::decode(&mut buf).unwrap());
+        }
+        commands
+    }
+
+    #[test]
+    fn test_bgsave_writes_point_in_time_dataset() {
+        let backend = test_backend("bgsave");
+        backend.set("key".to_string(), RespFrame::BulkString("old".into()));
+        backend.hset("hash".to_string(), "field".to_string(), 1.into());
+        backend.sadd("set".to_string(), ["a".to_string(), "b".to_string()]);
+
+        assert!(backend.bgsave());
+        // 快照之后的写入不影响正在保存的数据
+        backend.set("key".to_string(), RespFrame::BulkString("new".into()));
+        backend.hset("hash".to_string(), "other".to_string(), 2.into());
+        wait_for_save(&backend);
+        assert_eq!(backend.save_state().last_status(), "ok");
+
+        let mut commands = read_commands(&backend.save_path());
+        commands.sort_by(|a, b| a[1].partial_cmp(&b[1]).unwrap());
+        assert_eq!(commands.len(), 3);
+        assert_eq!(
+            commands[0],
+            RespArray::new([
+                BulkString::from("HSET").into(),
+                BulkString::from("hash").into(),
+                BulkString::from("field").into(),
+                1.into(),
+            ])
+        );
+        assert_eq!(
+            commands[1],
+            RespArray::new([
+                BulkString::from("SET").into(),
+                BulkString::from("key").into(),
+                BulkString::from("old").into(),
+            ])
+        );
+        assert_eq!(commands[2].len(), 4);
+        assert_eq!(commands[2][0], BulkString::from("SADD").into());
+    }
+
+    #[test]
+    fn test_load_restores_ttls() {
+        let backend = test_backend("ttl");
+        backend.set_with_ttl("volatile", "value", Duration::from_secs(100));
+        backend.set_string("persistent", "value");
+        backend.hset("hash".to_string(), "field".to_string(), 1.into());
+        backend.set_with_ttl("gone", "value", Duration::from_millis(1));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(backend.bgsave());
+        wait_for_save(&backend);
+
+        let restored = Backend::new();
+        assert_eq!(restored.load(&backend.save_path()).unwrap(), 3);
+        assert_eq!(restored.get_string("persistent"), Some("value".to_string()));
+        assert_eq!(restored.ttl("persistent"), None);
+        assert!(restored.hget("hash", "field").is_some());
+        assert!(!restored.exists("gone"));
+        let ttl = restored.ttl("volatile").unwrap();
+        assert!(ttl > Duration::from_secs(90) && ttl <= Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_load_drops_expired_keys() {
+        let backend = test_backend("expired");
+        let path = backend.save_path();
+        fs::write(
+            &path,
+            concat!(
+                "*3\r\n$3\r\nSET\r\n$3\r\nold\r\n$1\r\nv\r\n",
+                "*3\r\n$4\r\nSADD\r\n$3\r\nset\r\n$1\r\na\r\n",
+                "*3\r\n$9\r\nPEXPIREAT\r\n$3\r\nold\r\n$4\r\n1000\r\n",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(backend.load(&path).unwrap(), 1);
+        assert!(!backend.exists("old"));
+        assert!(backend.sismember("set", "a"));
+        assert_eq!(backend.stats().expired_keys.load(Ordering::Relaxed), 0);
+
+        // 文件不存在时是空的数据集
+        assert_eq!(backend.load(&path.with_extension("missing")).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_load_big_collections() {
+        let backend = test_backend("big");
+        let len = crate::RespLimits::default().max_multibulk_len + 1;
+        let members = (0..len).map(|i| i.to_string()).collect::<Vec<_>>();
+        backend.sadd("set", members.iter().map(String::as_str));
+        for i in 0..100 {
+            backend.hset("hash".to_string(), i.to_string(), i.into());
+        }
+        write_snapshot(&backend.snapshot(), &backend.save_path()).unwrap();
+
+        // 每条命令都没有超过解码的限制
+        let commands = read_commands(&backend.save_path());
+        assert!(commands.iter().all(|c| c.len() <= 2 + 2 * 64));
+
+        let restored = Backend::new();
+        assert_eq!(restored.load(&backend.save_path()).unwrap(), 2);
+        assert_eq!(restored.collection_len("set"), Some(len));
+        assert!(restored.sismember("set", &(len - 1).to_string()));
+        assert_eq!(restored.collection_len("hash"), Some(100));
+        assert_eq!(*restored.hget("hash", "99").unwrap(), 99.into());
+    }
+
+    #[test]
+    fn test_bgsave_rejects_concurrent_save() {
+        let backend = test_backend("concurrent");
+        backend
+            .save_state
+            .in_progress
+            .store(true, Ordering::Release);
+        assert!(!backend.bgsave());
+
+        backend
+            .save_state
+            .in_progress
+            .store(false, Ordering::Release);
+        assert!(backend.bgsave());
+        wait_for_save(&backend);
+    }
+
+    #[test]
+    fn test_save_waits_for_bgsave() {
+        let backend = test_backend("save");
+        backend.set_string("key", "old");
+        assert!(backend.bgsave());
+        backend.set_string("key", "new");
+        backend.save().unwrap();
+        assert!(!backend.save_state().in_progress());
+        assert_eq!(backend.save_state().last_status(), "ok");
+
+        let restored = Backend::new();
+        assert_eq!(restored.load(&backend.save_path()).unwrap(), 1);
+        assert_eq!(restored.get_string("key"), Some("new".to_string()));
+    }
+}
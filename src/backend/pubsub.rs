@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::Notify;
+
+use crate::{BulkString, RespFrame, RespPush};
+
+use super::Backend;
+
+/*
+    每个订阅者一个有界的消息队列，PUBLISH 只把消息放进队列，由订阅者的连接自己写出，
+    一个写不动的订阅者不会阻塞 PUBLISH。队列满时按 pubsub-overflow 处理：
+    - disconnect：与 redis 的 client-output-buffer-limit pubsub 一样断开这个订阅者，丢弃队列中的消息
+    - drop-oldest：丢弃最早的消息，订阅者继续接收，丢弃的个数记在 dropped 中
+    pubsub-queue-limit 为 0 表示不限制
+*/
+#[derive(Debug)]
+pub struct SubscriberQueue {
+    id: u64,
+    messages: Mutex<VecDeque<RespFrame>>,
+    notify: Notify,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+}
+
+// 单个订阅者的积压情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriberLag {
+    pub id: u64,
+    // 已经放入队列还没有写出的消息数
+    pub queued: usize,
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    Disconnect,
+    DropOldest,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct PubSub {
+    // 频道 -> 订阅者 id -> 队列
+    channels: RwLock<HashMap<String, HashMap<u64, Arc<SubscriberQueue>>>>,
+}
+
+impl SubscriberQueue {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            messages: Mutex::default(),
+            notify: Notify::new(),
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    // 取出当前所有的消息，队列为空时等待。订阅者因为积压被断开时返回 None
+    pub async fn recv(&self) -> Option<Vec<RespFrame>> {
+        loop {
+            {
+                let mut messages = self.messages.lock();
+                if self.is_closed() {
+                    return None;
+                }
+                if !messages.is_empty() {
+                    return Some(messages.drain(..).collect());
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    // 订阅确认等回复不受长度限制
+    pub fn push_reply(&self, frame: RespFrame) {
+        self.messages.lock().push_back(frame);
+        self.notify.notify_one();
+    }
+
+    // 返回消息是否放入了队列
+    fn push_message(&self, frame: RespFrame, limit: usize, policy: OverflowPolicy) -> bool {
+        let mut messages = self.messages.lock();
+        if self.is_closed() {
+            return false;
+        }
+        if limit > 0 && messages.len() >= limit {
+            match policy {
+                OverflowPolicy::Disconnect => {
+                    messages.clear();
+                    self.closed.store(true, Ordering::Relaxed);
+                    drop(messages);
+                    self.notify.notify_one();
+                    return false;
+                }
+                OverflowPolicy::DropOldest => {
+                    messages.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        messages.push_back(frame);
+        drop(messages);
+        self.notify.notify_one();
+        true
+    }
+
+    fn lag(&self) -> SubscriberLag {
+        SubscriberLag {
+            id: self.id,
+            queued: self.messages.lock().len(),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl OverflowPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "disconnect" => Some(Self::Disconnect),
+            "drop-oldest" => Some(Self::DropOldest),
+            _ => None,
+        }
+    }
+}
+
+// 与 redis 一样推送 ["message", channel, payload]，RESP2 连接上降级为数组
+pub fn message_frame(channel: &str, payload: RespFrame) -> RespFrame {
+    RespPush::new(vec![
+        BulkString::from("message").into(),
+        BulkString::from(channel).into(),
+        payload,
+    ])
+    .into()
+}
+
+impl Backend {
+    pub fn subscribe(&self, channel: &str, queue: &Arc<SubscriberQueue>) {
+        self.pubsub
+            .channels
+            .write()
+            .entry(channel.to_string())
+            .or_default()
+            .insert(queue.id, queue.clone());
+    }
+
+    pub fn unsubscribe(&self, channel: &str, id: u64) {
+        let mut channels = self.pubsub.channels.write();
+        if let Some(subscribers) = channels.get_mut(channel) {
+            subscribers.remove(&id);
+            if subscribers.is_empty() {
+                channels.remove(channel);
+            }
+        }
+    }
+
+    // 连接关闭时调用，取消这个连接的所有订阅
+    pub fn unsubscribe_all(&self, id: u64) {
+        self.pubsub.channels.write().retain(|_, subscribers| {
+            subscribers.remove(&id);
+            !subscribers.is_empty()
+        });
+    }
+
+    // 返回收到消息的订阅者个数，被断开或已经断开的订阅者不计算在内
+    pub fn publish(&self, channel: &str, payload: RespFrame) -> usize {
+        let (limit, policy) = {
+            let config = self.config.read();
+            let policy = OverflowPolicy::parse(&config.pubsub_overflow)
+                .unwrap_or(OverflowPolicy::Disconnect);
+            (config.pubsub_queue_limit, policy)
+        };
+        let channels = self.pubsub.channels.read();
+        let Some(subscribers) = channels.get(channel) else {
+            return 0;
+        };
+        let frame = message_frame(channel, payload);
+        subscribers
+            .values()
+            .filter(|queue| queue.push_message(frame.clone(), limit, policy))
+            .count()
+    }
+
+    // 所有订阅者的积压情况，按 id 排序
+    pub fn pubsub_lag(&self) -> Vec<SubscriberLag> {
+        let channels = self.pubsub.channels.read();
+        let mut queues = HashMap::new();
+        for subscribers in channels.values() {
+            queues.extend(subscribers.iter().map(|(id, queue)| (*id, queue.clone())));
+        }
+        let mut lag = queues.values().map(|queue| queue.lag()).collect::<Vec<_>>();
+        lag.sort_unstable_by_key(|lag| lag.id);
+        lag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_to_subscribers() {
+        let backend = Backend::new();
+        let queue = Arc::new(SubscriberQueue::new(1));
+        backend.subscribe("news", &queue);
+        assert_eq!(backend.publish("news", BulkString::from("a").into()), 1);
+        assert_eq!(backend.publish("other", BulkString::from("b").into()), 0);
+
+        let messages = queue.recv().await.unwrap();
+        assert_eq!(
+            messages,
+            vec![message_frame("news", BulkString::from("a").into())]
+        );
+
+        backend.unsubscribe_all(1);
+        assert_eq!(backend.publish("news", BulkString::from("c").into()), 0);
+        assert!(backend.pubsub_lag().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_when_full() {
+        let backend = Backend::new();
+        backend.set_config("pubsub-queue-limit", "2").unwrap();
+        backend
+            .set_config("pubsub-overflow", "drop-oldest")
+            .unwrap();
+        let queue = Arc::new(SubscriberQueue::new(1));
+        backend.subscribe("news", &queue);
+        for i in 0..5 {
+            assert_eq!(backend.publish("news", RespFrame::Integer(i)), 1);
+        }
+        assert_eq!(
+            backend.pubsub_lag(),
+            vec![SubscriberLag {
+                id: 1,
+                queued: 2,
+                dropped: 3
+            }]
+        );
+        let messages = queue.recv().await.unwrap();
+        assert_eq!(messages[0], message_frame("news", RespFrame::Integer(3)));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_slow_subscriber() {
+        let backend = Backend::new();
+        backend.set_config("pubsub-queue-limit", "2").unwrap();
+        let slow = Arc::new(SubscriberQueue::new(1));
+        let fast = Arc::new(SubscriberQueue::new(2));
+        backend.subscribe("news", &slow);
+        backend.subscribe("news", &fast);
+        for i in 0..2 {
+            assert_eq!(backend.publish("news", RespFrame::Integer(i)), 2);
+            assert!(fast.recv().await.is_some());
+        }
+        assert_eq!(backend.publish("news", RespFrame::Integer(2)), 1);
+        assert!(slow.is_closed());
+        assert_eq!(slow.recv().await, None);
+        assert_eq!(backend.pubsub_lag()[0].queued, 0);
+    }
+}
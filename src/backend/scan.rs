@@ -0,0 +1,236 @@
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::RespFrame;
+
+use super::Backend;
+
+// SCAN / HSCAN / SSCAN 的游标。每个元素在游标空间中的位置是它的 hash 按位反转后的值，
+// 每次返回位置不小于游标的 count 个元素，并把下一个未返回元素的位置作为新的游标，返回 0 表示遍历结束。
+//
+// key 所在的 shard 由 hash 的低位决定，反转后成为位置的高位，因此同一个 shard 的 key 在游标空间中是连续的一段，
+// 每次调用只需要依次对要遍历的 shard 加读锁。元素的位置只取决于它自身的 hash，shard 个数在运行时也不会变化，
+// 所以在整个遍历过程中一直存在的元素一定会被返回，而且只返回一次；遍历期间新增或删除的元素可能返回也可能不返回。
+// hash 的种子每次启动时随机生成，游标只在同一个进程中有效
+impl Backend {
+    // 返回下一个游标和这一批 key，已过期的 key 会被跳过
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<String>) {
+        let count = count.max(1);
+        let shards = self.shards.len();
+        let bits = shards.trailing_zeros();
+        let first = match bits {
+            0 => 0,
+            bits => (cursor >> (u64::BITS - bits)) as usize,
+        };
+
+        let now = Instant::now();
+        let mut keys = Vec::new();
+        for pos in first..shards {
+            let shard = self.shards[shard_at(pos, bits)].read();
+            let mut items = Vec::new();
+            shard.collect_keys(None, &mut |k| !shard.is_expired(k, now), &mut items);
+            let items = items.into_iter().map(|key| (self.position(&key), key));
+            if let Some(next) = take_batch(items, cursor, count - keys.len(), &mut keys) {
+                return (next, keys);
+            }
+            if keys.len() >= count {
+                let next = match pos + 1 {
+                    pos if pos == shards => 0,
+                    pos => (pos as u64) << (u64::BITS - bits),
+                };
+                return (next, keys);
+            }
+        }
+        (0, keys)
+    }
+
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+    ) -> (u64, Vec<(String, Arc<RespFrame>)>) {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        let mut fields = Vec::new();
+        let Some(hash) = shard.hmap.get(key) else {
+            return (0, fields);
+        };
+        let items = hash
+            .iter()
+            .map(|(field, value)| (self.position(field), (field.clone(), value.clone())));
+        let next = take_batch(items, cursor, count.max(1), &mut fields);
+        (next.unwrap_or(0), fields)
+    }
+
+    pub fn sscan(&self, key: &str, cursor: u64, count: usize) -> (u64, Vec<String>) {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        let mut members = Vec::new();
+        let Some(set) = shard.smap.get(key) else {
+            return (0, members);
+        };
+        let items = set
+            .iter()
+            .map(|member| (self.position(member), member.clone()));
+        let next = take_batch(items, cursor, count.max(1), &mut members);
+        (next.unwrap_or(0), members)
+    }
+
+    fn position(&self, item: &str) -> u64 {
+        self.hasher.hash_one(item).reverse_bits()
+    }
+}
+
+// 游标空间中第 pos 段对应的 shard，即 pos 的低 bits 位按位反转
+fn shard_at(pos: usize, bits: u32) -> usize {
+    match bits {
+        0 => 0,
+        bits => pos.reverse_bits() >> (usize::BITS - bits),
+    }
+}
+
+// 按位置顺序取出位置不小于 cursor 的前 count 个元素，位置相同的元素总是在同一批返回。
+// 还有剩余的元素时返回下一个元素的位置
+fn take_batch<T>(
+    items: impl Iterator<Item = (u64, T)>,
+    cursor: u64,
+    count: usize,
+    out: &mut Vec<T>,
+) -> Option<u64> {
+    let mut items = items.filter(|(pos, _)| *pos >= cursor).collect::<Vec<_>>();
+    items.sort_unstable_by_key(|(pos, _)| *pos);
+
+    let mut end = count.min(items.len());
+    while end > 0 && end < items.len() && items[end].0 == items[end - 1].0 {
+        end += 1;
+    }
+    let next = items.get(end).map(|(pos, _)| *pos);
+    out.extend(items.into_iter().take(end).map(|(_, item)| item));
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{backend::BackendConfig, BulkString};
+
+    fn scan_all(backend: &Backend, count: usize) -> Vec<String> {
+        let (mut cursor, mut keys) = (0, Vec::new());
+        loop {
+            let (next, batch) = backend.scan(cursor, count);
+            keys.extend(batch);
+            if next == 0 {
+                return keys;
+            }
+            assert!(next > cursor);
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_once() {
+        for shards in [1, 4, 16] {
+            let backend = Backend::with_config(BackendConfig {
+                shards,
+                ..Default::default()
+            });
+            for i in 0..200 {
+                backend.set_string(format!("key:{}", i), "v");
+            }
+            backend.hash("h").set("f", "v");
+            backend.set_of("s").add("m");
+
+            for count in [1, 7, 10, 1000] {
+                let keys = scan_all(&backend, count);
+                assert_eq!(keys.len(), 202);
+                assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 202);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_under_concurrent_writes() {
+        let backend = Backend::with_config(BackendConfig {
+            shards: 8,
+            ..Default::default()
+        });
+        for i in 0..500 {
+            backend.set_string(format!("stable:{}", i), "v");
+        }
+
+        // 每批之间插入和删除其他 key，一直存在的 key 都应该被返回
+        let (mut cursor, mut seen, mut round) = (0, Vec::new(), 0);
+        loop {
+            let (next, batch) = backend.scan(cursor, 10);
+            seen.extend(batch);
+            for i in 0..20 {
+                backend.set_string(format!("new:{}:{}", round, i), "v");
+            }
+            if round > 0 {
+                for i in 0..20 {
+                    backend.remove(&format!("new:{}:{}", round - 1, i));
+                }
+            }
+            round += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        let stable = seen
+            .iter()
+            .filter(|k| k.starts_with("stable:"))
+            .collect::<Vec<_>>();
+        assert_eq!(stable.len(), 500);
+        assert_eq!(stable.into_iter().collect::<HashSet<_>>().len(), 500);
+    }
+
+    #[test]
+    fn test_hscan_and_sscan() {
+        let backend = Backend::new();
+        for i in 0..50 {
+            backend.hset(
+                "h".to_string(),
+                format!("f{}", i),
+                BulkString::from("v").into(),
+            );
+            backend.set_of("s").add(format!("m{}", i));
+        }
+
+        let (mut cursor, mut fields) = (0, HashSet::new());
+        loop {
+            let (next, batch) = backend.hscan("h", cursor, 7);
+            assert!(batch.len() <= 8);
+            fields.extend(batch.into_iter().map(|(field, _)| field));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(fields.len(), 50);
+
+        let (next, members) = backend.sscan("s", 0, 100);
+        assert_eq!(next, 0);
+        assert_eq!(members.len(), 50);
+
+        assert_eq!(backend.sscan("missing", 0, 10), (0, vec![]));
+    }
+
+    #[test]
+    fn test_take_batch_keeps_collisions_together() {
+        let items = [(5, "c"), (1, "a"), (3, "b1"), (3, "b2"), (9, "d")];
+        let mut out = Vec::new();
+        assert_eq!(take_batch(items.into_iter(), 2, 1, &mut out), Some(5));
+        out.sort();
+        assert_eq!(out, vec!["b1", "b2"]);
+
+        let mut out = Vec::new();
+        assert_eq!(take_batch(items.into_iter(), 6, 10, &mut out), None);
+        assert_eq!(out, vec!["d"]);
+    }
+}
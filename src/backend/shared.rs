@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+
+use crate::{BulkString, RespFrame};
+
+// 与 redis 的 OBJ_SHARED_INTEGERS 保持一致，[0, 10000) 范围内的整数值全局共享同一份对象
+pub const SHARED_INTEGERS: usize = 10000;
+
+lazy_static! {
+    static ref SHARED: Vec<Arc<RespFrame>> = (0..SHARED_INTEGERS)
+        .map(|i| Arc::new(BulkString::from(i.to_string()).into()))
+        .collect();
+}
+
+// 大量计数器通常只存放很小的整数，复用共享对象可以避免为每个 key 单独分配 BulkString
+pub(super) fn share(value: RespFrame) -> Arc<RespFrame> {
+    match shared_index(&value) {
+        Some(i) => SHARED[i].clone(),
+        None => Arc::new(value),
+    }
+}
+
+pub(super) fn is_shared(value: &Arc<RespFrame>) -> bool {
+    shared_index(value).is_some_and(|i| Arc::ptr_eq(&SHARED[i], value))
+}
+
+// 只处理规范形式的十进制整数，"007" 这类带前导零的值需要原样保留
+fn shared_index(value: &RespFrame) -> Option<usize> {
+    let RespFrame::BulkString(s) = value else {
+        return None;
+    };
+    if s.is_empty() || s.len() > 4 || (s.len() > 1 && s[0] == b'0') {
+        return None;
+    }
+    if !s.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let n = s.iter().fold(0, |n, b| n * 10 + (b - b'0') as usize);
+    (n < SHARED_INTEGERS).then_some(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_small_integers() {
+        let a = share(BulkString::from("42").into());
+        let b = share(BulkString::from("42").into());
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(is_shared(&a));
+        assert_eq!(*a, BulkString::from("42").into());
+
+        for value in ["042", "10000", "-1", "abc", ""] {
+            let v = share(BulkString::from(value).into());
+            assert!(!is_shared(&v), "{} should not be shared", value);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bytes::BytesMut;
+
+use crate::{RespEncode, RespFrame};
+
+use super::{Value, ValueKind};
+
+// 某一时刻整个 keyspace 的一致性拷贝，生成之后与 backend 不再有关联
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    entries: Vec<(String, Value)>,
+    // 快照时还没有过期的 key 的过期时间
+    expires: HashMap<String, Instant>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(entries: Vec<(String, Value)>, expires: HashMap<String, Instant>) -> Self {
+        Self { entries, expires }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str, kind: ValueKind) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, v)| k == key && v.kind() == kind)
+            .map(|(_, v)| v)
+    }
+
+    pub fn expire_at(&self, key: &str) -> Option<Instant> {
+        self.expires.get(key).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    // 类似 redis-cli --bigkeys：每种类型的 key 个数、最大的 key 以及剩余生存时间的分布
+    pub fn summary(&self) -> SnapshotSummary {
+        let mut summary = SnapshotSummary {
+            ttls: TTL_BUCKETS.iter().map(|(name, _)| (*name, 0)).collect(),
+            ..Default::default()
+        };
+        let now = Instant::now();
+        for (key, value) in self.iter() {
+            let bucket = match self.expire_at(key) {
+                Some(at) => {
+                    let ttl = at.saturating_duration_since(now);
+                    TTL_BUCKETS
+                        .iter()
+                        .position(|(_, limit)| limit.is_some_and(|limit| ttl < limit))
+                        .unwrap_or(TTL_BUCKETS.len() - 1)
+                }
+                None => 0,
+            };
+            summary.ttls[bucket].1 += 1;
+
+            let kind = value.kind();
+            let size = value_size(value);
+            *summary.counts.entry(kind).or_default() += 1;
+            let biggest = summary
+                .biggest
+                .entry(kind)
+                .or_insert((key.to_string(), size));
+            if size > biggest.1 {
+                *biggest = (key.to_string(), size);
+            }
+        }
+        summary
+    }
+
+    // 以 RESP 命令序列的形式写出整个快照：字符串写成 SET，hash 写成 HSET，set 写成 SADD，
+    // 最后每个设置了过期时间的 key 一条 PEXPIREAT。依次执行这些命令即可重建数据集。
+    // 与 redis 重写 AOF 一样，hash 和 set 每条命令最多写 ITEMS_PER_COMMAND 个元素，
+    // 大的 key 拆成多条命令，载入时不会超过 proto-max-multibulk-len 的限制。
+    // 过期时间写成 unix 毫秒时间戳，重启之后仍然在原来的时刻过期
+    pub fn write_to(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let mut buf = BytesMut::with_capacity(WRITE_BUFFER_SIZE);
+        for (key, value) in self.iter() {
+            match value {
+                Value::String(frame) => {
+                    write_command(&mut buf, "SET", key, 1);
+                    frame.encode_into(&mut buf);
+                }
+                Value::Hash(hash) => {
+                    let fields = hash.iter().collect::<Vec<_>>();
+                    for chunk in fields.chunks(ITEMS_PER_COMMAND) {
+                        write_command(&mut buf, "HSET", key, chunk.len() * 2);
+                        for (field, frame) in chunk {
+                            write_bulk(&mut buf, field);
+                            frame.encode_into(&mut buf);
+                        }
+                    }
+                }
+                Value::Set(set) => {
+                    let members = set.iter().collect::<Vec<_>>();
+                    for chunk in members.chunks(ITEMS_PER_COMMAND) {
+                        write_command(&mut buf, "SADD", key, chunk.len());
+                        chunk.iter().for_each(|member| write_bulk(&mut buf, member));
+                    }
+                }
+            }
+            if buf.len() >= WRITE_BUFFER_SIZE {
+                writer.write_all(&buf)?;
+                buf.clear();
+            }
+        }
+
+        let (now, unix_now) = (Instant::now(), unix_millis());
+        for (key, at) in self.expires.iter() {
+            let at = unix_now + at.saturating_duration_since(now).as_millis() as u64;
+            write_command(&mut buf, "PEXPIREAT", key, 1);
+            write_bulk(&mut buf, &at.to_string());
+            if buf.len() >= WRITE_BUFFER_SIZE {
+                writer.write_all(&buf)?;
+                buf.clear();
+            }
+        }
+        writer.write_all(&buf)?;
+        writer.flush()
+    }
+}
+
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+// 剩余生存时间的分组及其上限，第一组是没有设置过期时间的 key，最后一组没有上限
+const TTL_BUCKETS: [(&str, Option<Duration>); 5] = [
+    ("no expiry", None),
+    ("< 1m", Some(Duration::from_secs(60))),
+    ("< 1h", Some(Duration::from_secs(3600))),
+    ("< 1d", Some(Duration::from_secs(86400))),
+    (">= 1d", None),
+];
+// 对应 redis 的 AOF_REWRITE_ITEMS_PER_CMD
+const ITEMS_PER_COMMAND: usize = 64;
+
+// 写入数组头、命令名和 key，之后还有 args 个参数由调用方写入
+fn write_command(buf: &mut BytesMut, name: &str, key: &str, args: usize) {
+    let _ = write!(buf, "*{}\r\n", args + 2);
+    write_bulk(buf, name);
+    write_bulk(buf, key);
+}
+
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+// 与 BulkString 的编码相同，省去拷贝成 Bytes 的一次分配
+fn write_bulk(buf: &mut BytesMut, s: &str) {
+    let _ = write!(buf, "${}\r\n", s.len());
+    buf.extend_from_slice(s.as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotSummary {
+    pub counts: HashMap<ValueKind, usize>,
+    // 每种类型中最大的 key 及其大小，字符串按字节数，hash 和 set 按元素个数
+    pub biggest: HashMap<ValueKind, (String, usize)>,
+    // 按 TTL_BUCKETS 的顺序，每组的名称和 key 个数
+    pub ttls: Vec<(&'static str, usize)>,
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::String(frame) => match &**frame {
+            RespFrame::BulkString(s) => s.len(),
+            frame => frame.encoded_len(),
+        },
+        Value::Hash(hash) => hash.len(),
+        Value::Set(set) => set.len(),
+    }
+}
+
+impl IntoIterator for Snapshot {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use super::Backend;
+
+// INFO stats 中的全局计数器，由 backend 和网络层累加
+// 还没有实现淘汰，evicted_keys 目前总是 0
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    pub total_connections_received: AtomicU64,
+    pub total_commands_processed: AtomicU64,
+    pub total_net_input_bytes: AtomicU64,
+    pub total_net_output_bytes: AtomicU64,
+    pub keyspace_hits: AtomicU64,
+    pub keyspace_misses: AtomicU64,
+    pub expired_keys: AtomicU64,
+    pub evicted_keys: AtomicU64,
+}
+
+impl ServerStats {
+    pub fn incr(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    // 读命令查找 key 时调用，key 存在记为命中
+    pub fn record_lookup(&self, found: bool) {
+        if found {
+            Self::incr(&self.keyspace_hits, 1);
+        } else {
+            Self::incr(&self.keyspace_misses, 1);
+        }
+    }
+
+    // 按 INFO stats 中的顺序返回 (名称, 值)
+    pub fn fields(&self) -> Vec<(&'static str, u64)> {
+        [
+            (
+                "total_connections_received",
+                &self.total_connections_received,
+            ),
+            ("total_commands_processed", &self.total_commands_processed),
+            ("total_net_input_bytes", &self.total_net_input_bytes),
+            ("total_net_output_bytes", &self.total_net_output_bytes),
+            ("expired_keys", &self.expired_keys),
+            ("evicted_keys", &self.evicted_keys),
+            ("keyspace_hits", &self.keyspace_hits),
+            ("keyspace_misses", &self.keyspace_misses),
+        ]
+        .into_iter()
+        .map(|(name, counter)| (name, counter.load(Ordering::Relaxed)))
+        .collect()
+    }
+}
+
+// INFO keyspace 中一个数据库的统计，avg_ttl 是设置了过期时间的 key 的平均剩余毫秒数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceStats {
+    pub keys: usize,
+    pub expires: usize,
+    pub avg_ttl: u64,
+}
+
+impl Backend {
+    /*
+        还没有 SELECT，只有 db0 一个数据库。keys 和 expires 直接取各个 shard 中 map 的长度，
+        已经过期但还没有被删除的 key 也计算在内；avg_ttl 需要遍历过期时间，只计算还没有过期的 key
+    */
+    pub fn keyspace_stats(&self) -> KeyspaceStats {
+        let now = Instant::now();
+        let mut stats = KeyspaceStats::default();
+        let (mut total_ttl, mut live) = (0u128, 0u128);
+        for shard in self.shards.iter() {
+            let shard = shard.read();
+            stats.keys += shard.map.len() + shard.hmap.len() + shard.smap.len() + shard.cold.len();
+            stats.expires += shard.expires.len();
+            for at in shard.expires.values().filter(|at| **at > now) {
+                total_ttl += at.duration_since(now).as_millis();
+                live += 1;
+            }
+        }
+        stats.avg_ttl = total_ttl.checked_div(live).unwrap_or(0) as u64;
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_server_stats() {
+        let stats = ServerStats::default();
+        ServerStats::incr(&stats.total_net_input_bytes, 10);
+        stats.record_lookup(true);
+        stats.record_lookup(false);
+        stats.record_lookup(false);
+
+        let fields = stats.fields();
+        assert!(fields.contains(&("total_net_input_bytes", 10)));
+        assert!(fields.contains(&("keyspace_hits", 1)));
+        assert!(fields.contains(&("keyspace_misses", 2)));
+        assert!(fields.contains(&("expired_keys", 0)));
+    }
+
+    #[test]
+    fn test_keyspace_stats() {
+        let backend = Backend::new();
+        assert_eq!(backend.keyspace_stats(), KeyspaceStats::default());
+
+        backend.set_string("a", "1");
+        backend.set_with_ttl("b", "2", Duration::from_secs(100));
+        backend.set_with_ttl("c", "3", Duration::from_secs(200));
+        backend.sadd("s".to_string(), vec!["m".to_string()]);
+
+        let stats = backend.keyspace_stats();
+        assert_eq!((stats.keys, stats.expires), (4, 2));
+        assert!(stats.avg_ttl > 149_000 && stats.avg_ttl <= 150_000);
+    }
+}
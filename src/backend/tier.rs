@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::BytesMut;
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::{RespDecode, RespEncode, RespFrame};
+
+use super::{memory, shared, Backend, BackendInner, Shard, StringEntry, ValueKind};
+
+/*
+    冷数据分层：超过 tier-idle-seconds 没有被访问的字符串值写到磁盘，内存中只保留 key、版本号和过期时间，
+    再次访问时读回内存。hash 和 set 不分层，共享的小整数和很小的值也不写出。
+    每个值一个文件，文件名是值的版本号，版本号全局唯一所以不会冲突。
+    文件只在进程运行期间有效，重启之后内存中的元数据已经丢失，因此目录在 backend 释放时删除。
+    目录名带上进程号和 backend 的序号，同一进程里的多个 backend 不会共用目录
+*/
+#[derive(Debug)]
+pub(super) struct Tier {
+    // 访问时间以 epoch 之后的秒数记录，用 u32 可以保存在 StringEntry 的原子变量中
+    epoch: Instant,
+    id: u32,
+    dirs: Mutex<HashSet<PathBuf>>,
+}
+
+static NEXT_TIER_ID: AtomicU32 = AtomicU32::new(0);
+
+// 冷数据在内存中保留的元数据
+#[derive(Debug)]
+pub(super) struct ColdEntry {
+    version: u64,
+    file: Arc<ColdFile>,
+}
+
+// 冷数据的文件，最后一个引用释放时删除。快照在锁内只拷贝引用、在锁外读取文件，
+// 这期间 key 被覆盖或读回内存也不会删除快照还需要的文件
+#[derive(Debug)]
+pub(super) struct ColdFile(PathBuf);
+
+impl Drop for ColdFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// 比这更小的值写到磁盘省下的内存不值得一次文件读写
+const MIN_SPILL_SIZE: usize = 256;
+
+impl Default for Tier {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            id: NEXT_TIER_ID.fetch_add(1, Ordering::Relaxed),
+            dirs: Mutex::default(),
+        }
+    }
+}
+
+impl Tier {
+    pub(super) fn clock(&self) -> u32 {
+        self.epoch.elapsed().as_secs() as u32
+    }
+
+    fn dir(&self, base: &str) -> io::Result<PathBuf> {
+        let dir = Path::new(base).join(format!("tier-{}-{}", std::process::id(), self.id));
+        let mut dirs = self.dirs.lock();
+        if !dirs.contains(&dir) {
+            fs::create_dir_all(&dir)?;
+            dirs.insert(dir.clone());
+        }
+        Ok(dir)
+    }
+}
+
+impl Drop for Tier {
+    fn drop(&mut self) {
+        for dir in self.dirs.get_mut().drain() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+impl StringEntry {
+    pub(super) fn touch(&self, now: u32) {
+        self.last_access.store(now, Ordering::Relaxed);
+    }
+}
+
+impl Shard {
+    // 值已经写到磁盘的字符串 key
+    pub(super) fn is_cold(&self, key: &str) -> bool {
+        !self.cold.is_empty() && self.cold.contains_key(key)
+    }
+
+    // 删除冷数据的元数据和文件，返回 key 是否是冷数据
+    pub(super) fn drop_cold(&mut self, key: &str) -> bool {
+        self.cold.remove(key).is_some()
+    }
+
+    pub(super) fn cold_keys(&self) -> impl Iterator<Item = &String> {
+        self.cold.keys()
+    }
+
+    // 冷数据的 key 和文件，由调用方释放锁之后用 read_cold 读取
+    pub(super) fn cold_files(&self) -> impl Iterator<Item = (&String, &Arc<ColdFile>)> {
+        self.cold.iter().map(|(key, cold)| (key, &cold.file))
+    }
+}
+
+// 快照等需要遍历所有值的场景读取冷数据，不读回内存，读取失败的 key 跳过
+pub(super) fn read_cold(
+    files: Vec<(String, Arc<ColdFile>)>,
+) -> impl Iterator<Item = (String, Arc<RespFrame>)> {
+    files
+        .into_iter()
+        .filter_map(|(key, file)| match read_value(&file.0) {
+            Ok(value) => Some((key, Arc::new(value))),
+            Err(e) => {
+                warn!("Failed to read cold value of {}: {}", key, e);
+                None
+            }
+        })
+}
+
+impl BackendInner {
+    /*
+        把冷数据读回内存。与 spill 相反，先在读锁下拿到文件的引用，在锁外读取文件，
+        再加写锁确认版本号没有变化后才换回内存，读文件期间不阻塞这个 shard 的请求。
+        读取期间 key 被覆盖、删除或者已经被其他请求读回时丢弃读到的值。已过期的 key 不读取，由调用方删除
+    */
+    pub(super) fn page_in(&self, key: &str) {
+        let lock = self.shard(key);
+        let (version, file) = {
+            let shard = lock.read();
+            match shard.cold.get(key) {
+                Some(cold) if !shard.is_expired(key, Instant::now()) => {
+                    (cold.version, cold.file.clone())
+                }
+                _ => return,
+            }
+        };
+        let value = read_value(&file.0);
+        let mut shard = lock.write();
+        if shard
+            .cold
+            .get(key)
+            .is_some_and(|cold| cold.version == version)
+        {
+            self.restore_cold(&mut shard, key, value);
+        }
+    }
+
+    // 已经持有写锁时使用。page_in 读回之后加锁之前 key 又被写出时才会走到这里，在锁内读取文件
+    pub(super) fn page_in_locked(&self, shard: &mut Shard, key: &str) {
+        let Some(cold) = shard.cold.get(key) else {
+            return;
+        };
+        let value = read_value(&cold.file.0);
+        self.restore_cold(shard, key, value);
+    }
+
+    // 文件读取失败时 key 被删除
+    fn restore_cold(&self, shard: &mut Shard, key: &str, value: io::Result<RespFrame>) {
+        let Some((key, cold)) = shard.cold.remove_entry(key) else {
+            return;
+        };
+        match value {
+            Ok(value) => {
+                let value = Arc::new(value);
+                shard
+                    .memory
+                    .add(ValueKind::String, memory::stored_size(&value));
+                let entry = StringEntry {
+                    value,
+                    version: cold.version,
+                    last_access: AtomicU32::new(self.tier.clock()),
+                };
+                shard.map.insert(key, entry);
+            }
+            Err(e) => {
+                warn!("Failed to page in {}, dropping the key: {}", key, e);
+                shard.memory.sub(ValueKind::String, memory::key_size(&key));
+                shard.expires.swap_remove(&key);
+            }
+        }
+    }
+}
+
+impl Backend {
+    // 把超过 tier-idle-seconds 没有访问的字符串值写到 dir 下的分层目录，返回写出的个数
+    pub fn spill_idle(&self) -> io::Result<usize> {
+        let idle = self.config.read().tier_idle_seconds;
+        if idle == 0 {
+            return Ok(0);
+        }
+        self.spill(self.tier.clock(), idle.min(u32::MAX as usize) as u32)
+    }
+
+    // 当前在磁盘上的值的个数
+    pub fn cold_keys(&self) -> usize {
+        self.shards.iter().map(|s| s.read().cold.len()).sum()
+    }
+
+    /*
+        每个 shard 先在读锁下挑出候选并在锁外写文件，再加写锁确认值没有被修改或访问后才换成冷数据，
+        写文件期间不阻塞这个 shard 的请求
+    */
+    fn spill(&self, now: u32, idle: u32) -> io::Result<usize> {
+        let is_idle =
+            |e: &StringEntry| now.saturating_sub(e.last_access.load(Ordering::Relaxed)) >= idle;
+        let dir = self.tier.dir(&self.config.read().dir)?;
+
+        let mut spilled = 0;
+        for lock in self.shards.iter() {
+            let candidates = lock
+                .read()
+                .map
+                .iter()
+                .filter(|(_, e)| is_idle(e))
+                .filter(|(_, e)| {
+                    !shared::is_shared(&e.value) && memory::stored_size(&e.value) >= MIN_SPILL_SIZE
+                })
+                .map(|(k, e)| (k.clone(), e.value.clone(), e.version))
+                .collect::<Vec<_>>();
+            if candidates.is_empty() {
+                continue;
+            }
+
+            let mut written = Vec::with_capacity(candidates.len());
+            for (key, value, version) in candidates {
+                let path = dir.join(format!("{}.resp", version));
+                if let Err(e) = fs::write(&path, value.encode()) {
+                    for (_, _, path) in written {
+                        let _ = fs::remove_file(path);
+                    }
+                    return Err(e);
+                }
+                written.push((key, version, path));
+            }
+
+            let mut shard = lock.write();
+            for (key, version, path) in written {
+                let unchanged = shard
+                    .map
+                    .get(&key)
+                    .is_some_and(|e| e.version == version && is_idle(e));
+                if !unchanged {
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+                if let Some(entry) = shard.map.remove(&key) {
+                    shard
+                        .memory
+                        .sub(ValueKind::String, memory::stored_size(&entry.value));
+                }
+                let file = Arc::new(ColdFile(path));
+                shard.cold.insert(key, ColdEntry { version, file });
+                spilled += 1;
+            }
+        }
+        Ok(spilled)
+    }
+}
+
+fn read_value(path: &Path) -> io::Result<RespFrame> {
+    let mut buf = BytesMut::from(&fs::read(path)?[..]);
+    RespFrame::decode(&mut buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::BulkString;
+
+    fn backend(name: &str) -> Backend {
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-tier-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let backend = Backend::new();
+        backend.set_config("dir", dir.to_str().unwrap()).unwrap();
+        backend
+    }
+
+    // 模拟 20 秒之后 idle 为 10 秒的一次检查
+    fn spill_later(backend: &Backend) -> usize {
+        backend.spill(backend.tier.clock() + 20, 10).unwrap()
+    }
+
+    #[test]
+    fn test_spill_and_page_in() {
+        let backend = backend("spill");
+        let big = "x".repeat(1024);
+        backend.set_string("cold", big.clone());
+        backend.set_string("small", "v");
+        backend.set_with_ttl("volatile", big.clone(), Duration::from_secs(100));
+        let before = backend.memory_stats();
+
+        // 没有配置 tier-idle-seconds 时不写出
+        assert_eq!(backend.spill_idle().unwrap(), 0);
+        assert_eq!(spill_later(&backend), 2);
+        assert_eq!(backend.cold_keys(), 2);
+        assert!(backend.memory_stats().strings + 2048 < before.strings);
+
+        // key 仍在内存中，快照包含冷数据
+        assert_eq!(backend.keys(None, |_| true).len(), 3);
+        assert_eq!(backend.snapshot().len(), 3);
+        assert_eq!(backend.cold_keys(), 2);
+
+        // 快照在锁外读取冷数据，期间覆盖 key 不会删除快照需要的文件
+        let shard = backend.shard("cold").read();
+        let (_, file) = shard.cold_files().find(|(k, _)| *k == "cold").unwrap();
+        let files = vec![("cold".to_string(), file.clone())];
+        drop(shard);
+        backend.set_string("cold", big.clone());
+        let values = read_cold(files).collect::<Vec<_>>();
+        assert_eq!(*values[0].1, BulkString::from(big.clone()).into());
+        assert_eq!(backend.cold_keys(), 1);
+        assert_eq!(spill_later(&backend), 1);
+
+        // 访问时读回内存
+        assert_eq!(backend.get_string("cold"), Some(big.clone()));
+        assert_eq!(backend.cold_keys(), 1);
+        let values = backend.mget(&["volatile", "small"]);
+        assert_eq!(values[0].as_deref(), Some(&BulkString::from(big).into()));
+        assert_eq!(backend.cold_keys(), 0);
+        assert!(backend.ttl("volatile").is_some());
+        assert_eq!(backend.memory_stats(), before);
+    }
+
+    #[test]
+    fn test_overwrite_and_delete_cold_value() {
+        let backend = backend("overwrite");
+        let big = "x".repeat(1024);
+        backend.set_string("a", big.clone());
+        backend.set_string("b", big.clone());
+        assert_eq!(spill_later(&backend), 2);
+
+        backend.set_string("a", "new");
+        assert_eq!(backend.get_string("a"), Some("new".to_string()));
+        assert_eq!(backend.del_many(&["b"]), 1);
+        assert_eq!(backend.exists_many(&["b"]), 0);
+        assert_eq!(backend.cold_keys(), 0);
+        let dir = backend.tier.dirs.lock().iter().next().cloned().unwrap();
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_backends_use_separate_dirs() {
+        let first = backend("shared");
+        let second = backend("shared");
+        let big = "x".repeat(1024);
+        first.set_string("k", big.clone());
+        second.set_string("k", big.clone());
+        assert_eq!(spill_later(&first), 1);
+        assert_eq!(spill_later(&second), 1);
+
+        // 释放一个 backend 只删除它自己的目录
+        drop(first);
+        assert_eq!(second.get_string("k"), Some(big));
+    }
+}
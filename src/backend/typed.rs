@@ -0,0 +1,453 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{BulkString, RespFrame};
+
+use super::{Backend, KeyOp, Value};
+
+// EXPIRE 系列命令的 NX / XX / GT / LT 选项，没有设置过期时间的 key 在 GT / LT 中视为永不过期
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpireCondition {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+}
+
+impl ExpireCondition {
+    fn allows(&self, current: Option<Instant>, at: Instant) -> bool {
+        match current {
+            Some(current) => {
+                let rejected = self.nx || (self.gt && at <= current) || (self.lt && at >= current);
+                !rejected
+            }
+            None => !self.xx && !self.gt,
+        }
+    }
+}
+
+// 供嵌入方直接使用的类型化接口，读写时不需要构造 RespFrame。
+// 值统一以 BulkString 保存，与通过网络写入的数据可以互相读取
+impl Backend {
+    // 值不是合法的 UTF-8 时返回 None，此时可以使用 get_bytes
+    pub fn get_string(&self, key: &str) -> Option<String> {
+        self.get_bytes(key)
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+    }
+
+    pub fn get_bytes(&self, key: &str) -> Option<Vec<u8>> {
+        self.get(key).and_then(|value| frame_bytes(&value))
+    }
+
+    pub fn set_string(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.set(key.into(), BulkString::from(value.into()).into());
+    }
+
+    // 写入值并在 ttl 之后过期
+    pub fn set_with_ttl(&self, key: impl Into<String>, value: impl Into<String>, ttl: Duration) {
+        let key = key.into();
+        let mut shard = self.shard(&key).write();
+        self.put_string(
+            &mut shard,
+            key.clone(),
+            BulkString::from(value.into()).into(),
+        );
+        shard.expires.insert(key, Instant::now() + ttl);
+    }
+
+    // 仅当 key 不存在时写入，ttl 为 None 表示不过期，返回是否写入。
+    // 缓存回填用它写入从源站读到的值，不会覆盖读取期间其他请求写入的新值
+    pub fn set_if_absent(&self, key: &str, value: RespFrame, ttl: Option<Duration>) -> bool {
+        let mut shard = self.shard(key).write();
+        self.purge_expired(&mut shard, key);
+        if shard.contains_key(key) {
+            return false;
+        }
+        self.put_string(&mut shard, key.to_string(), value);
+        if let Some(ttl) = ttl {
+            shard.expires.insert(key.to_string(), Instant::now() + ttl);
+        }
+        true
+    }
+
+    // 与 set_if_absent 相同，一次写入从源站读到的整个 hash
+    pub fn hset_if_absent(
+        &self,
+        key: &str,
+        fields: Vec<(String, RespFrame)>,
+        ttl: Option<Duration>,
+    ) -> bool {
+        let mut shard = self.shard(key).write();
+        self.purge_expired(&mut shard, key);
+        if fields.is_empty() || shard.contains_key(key) {
+            return false;
+        }
+        for (field, value) in fields {
+            self.put_hash_field(&mut shard, key.to_string(), field, value);
+        }
+        if let Some(ttl) = ttl {
+            shard.expires.insert(key.to_string(), Instant::now() + ttl);
+        }
+        true
+    }
+
+    // 剩余的生存时间，key 不存在或没有设置过期时间时返回 None
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        shard
+            .expires
+            .get(key)
+            .map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    // key 不存在时返回 None，存在但没有设置过期时间时返回 Some(None)
+    pub fn expiry(&self, key: &str) -> Option<Option<Duration>> {
+        self.expire_if_needed(key);
+        let shard = self.shard(key).read();
+        if !shard.contains_key(key) {
+            return None;
+        }
+        Some(
+            shard
+                .expires
+                .get(key)
+                .map(|at| at.saturating_duration_since(Instant::now())),
+        )
+    }
+
+    // 为已存在的任意类型的 key 设置过期时间点，返回 key 是否存在。
+    // 与 redis 一样，过期时间点已经过去时直接删除 key
+    pub fn expire_at(&self, key: &str, at: Instant) -> bool {
+        self.expire_at_if(key, at, ExpireCondition::default())
+    }
+
+    // 与 expire_at 相同，但只在满足 condition 时设置，返回是否设置了
+    pub fn expire_at_if(&self, key: &str, at: Instant, condition: ExpireCondition) -> bool {
+        let mut shard = self.shard(key).write();
+        if self.purge_expired(&mut shard, key) || !shard.contains_key(key) {
+            return false;
+        }
+        if !condition.allows(shard.expires.get(key).copied(), at) {
+            return false;
+        }
+        if at <= Instant::now() {
+            shard.remove_key(key);
+            self.notify(key, KeyOp::Del);
+        } else {
+            shard.expires.insert(key.to_string(), at);
+            self.notify(key, KeyOp::Expire);
+        }
+        true
+    }
+
+    // 清除过期时间，返回是否清除了
+    pub fn persist(&self, key: &str) -> bool {
+        let mut shard = self.shard(key).write();
+        if self.purge_expired(&mut shard, key) || shard.expires.swap_remove(key).is_none() {
+            return false;
+        }
+        self.notify(key, KeyOp::Persist);
+        true
+    }
+
+    pub fn exists(&self, key: &str) -> bool {
+        self.key_type(key).is_some()
+    }
+
+    // 删除任意类型的 key，返回 key 是否存在
+    pub fn remove(&self, key: &str) -> bool {
+        let mut shard = self.shard(key).write();
+        if self.purge_expired(&mut shard, key) {
+            return false;
+        }
+        let removed = shard.remove_key(key);
+        if removed {
+            self.notify(key, KeyOp::Del);
+        }
+        removed
+    }
+
+    pub fn hash(&self, key: impl Into<String>) -> HashRef<'_> {
+        HashRef {
+            backend: self,
+            key: key.into(),
+        }
+    }
+
+    pub fn set_of(&self, key: impl Into<String>) -> SetRef<'_> {
+        SetRef {
+            backend: self,
+            key: key.into(),
+        }
+    }
+
+    // 与 keys / entries 一样逐个 shard 收集，不是一致性视图
+    pub fn iter_keys(&self) -> impl Iterator<Item = String> {
+        self.keys(None, |_| true).into_iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (String, Value)> {
+        self.entries(None, |_| true).into_iter()
+    }
+}
+
+// 某个 hash 的句柄，只保存 key，每次操作时才访问 backend
+#[derive(Debug, Clone)]
+pub struct HashRef<'a> {
+    backend: &'a Backend,
+    key: String,
+}
+
+impl HashRef<'_> {
+    pub fn get(&self, field: &str) -> Option<String> {
+        self.backend
+            .hget(&self.key, field)
+            .and_then(|value| frame_bytes(&value))
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+    }
+
+    // 返回是否新增了字段
+    pub fn set(&self, field: impl Into<String>, value: impl Into<String>) -> bool {
+        let field = field.into();
+        let backend = self.backend;
+        let mut shard = backend.shard(&self.key).write();
+        backend.purge_expired(&mut shard, &self.key);
+        let is_new = !shard
+            .hmap
+            .get(&self.key)
+            .is_some_and(|hash| hash.contains_key(&field));
+        backend.put_hash_field(
+            &mut shard,
+            self.key.clone(),
+            field,
+            BulkString::from(value.into()).into(),
+        );
+        is_new
+    }
+
+    pub fn remove(&self, field: &str) -> bool {
+        let backend = self.backend;
+        let mut shard = backend.shard(&self.key).write();
+        backend.purge_expired(&mut shard, &self.key);
+        backend
+            .remove_hash_field(&mut shard, &self.key, field)
+            .is_some()
+    }
+
+    pub fn contains(&self, field: &str) -> bool {
+        self.backend.hget(&self.key, field).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.backend.collection_len(&self.key).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 遍历调用时的拷贝，值不是合法 UTF-8 的字段会被跳过
+    pub fn iter(&self) -> impl Iterator<Item = (String, String)> {
+        self.backend
+            .hgetall(&self.key)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(field, value)| {
+                let value = String::from_utf8(frame_bytes(&value)?).ok()?;
+                Some((field, value))
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetRef<'a> {
+    backend: &'a Backend,
+    key: String,
+}
+
+impl SetRef<'_> {
+    // 返回是否新增了成员
+    pub fn add(&self, member: impl Into<String>) -> bool {
+        self.backend.sadd(self.key.clone(), [member.into()]) == RespFrame::Integer(1)
+    }
+
+    pub fn remove(&self, member: &str) -> bool {
+        let backend = self.backend;
+        let mut shard = backend.shard(&self.key).write();
+        backend.purge_expired(&mut shard, &self.key);
+        backend.remove_set_members(&mut shard, &self.key, [member]) > 0
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        self.backend.sismember(&self.key, member)
+    }
+
+    pub fn len(&self) -> usize {
+        self.backend.collection_len(&self.key).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = String> {
+        self.backend
+            .smembers(&self.key)
+            .unwrap_or_default()
+            .into_iter()
+    }
+}
+
+// 字符串值的原始字节，整数形式的值按十进制文本返回
+fn frame_bytes(frame: &Arc<RespFrame>) -> Option<Vec<u8>> {
+    match &**frame {
+        RespFrame::BulkString(s) => Some(s.to_vec()),
+        RespFrame::SimpleString(s) => Some(s.as_bytes().to_vec()),
+        RespFrame::Integer(n) => Some(n.to_string().into_bytes()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_strings() {
+        let backend = Backend::new();
+        backend.set_string("k", "v");
+        assert_eq!(backend.get_string("k"), Some("v".to_string()));
+        assert_eq!(backend.get_bytes("k"), Some(b"v".to_vec()));
+        assert!(backend.exists("k"));
+        assert_eq!(backend.ttl("k"), None);
+
+        // 通过命令写入的值也可以读取
+        backend.set("n".to_string(), BulkString::from("42").into());
+        assert_eq!(backend.get_string("n"), Some("42".to_string()));
+
+        assert!(backend.remove("k"));
+        assert!(!backend.remove("k"));
+        assert_eq!(backend.get_string("k"), None);
+    }
+
+    #[test]
+    fn test_set_with_ttl() {
+        let backend = Backend::new();
+        let mut events = backend.subscribe_events();
+
+        backend.set_with_ttl("k", "v", Duration::from_secs(100));
+        let ttl = backend.ttl("k").unwrap();
+        assert!(ttl > Duration::from_secs(99) && ttl <= Duration::from_secs(100));
+
+        // 重新 set 会清除过期时间
+        backend.set_string("k", "v2");
+        assert_eq!(backend.ttl("k"), None);
+
+        backend.set_with_ttl("k", "v", Duration::ZERO);
+        assert_eq!(backend.get_string("k"), None);
+        assert!(!backend.exists("k"));
+        assert!(backend.iter_keys().next().is_none());
+
+        let ops = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|e| e.op)
+            .collect::<Vec<_>>();
+        assert_eq!(ops.last(), Some(&KeyOp::Expired));
+        assert_eq!(
+            backend
+                .stats()
+                .expired_keys
+                .load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+    }
+
+    #[test]
+    fn test_expire_at_and_persist() {
+        let backend = Backend::new();
+        let later = Instant::now() + Duration::from_secs(100);
+        assert!(!backend.expire_at("k", later));
+        assert_eq!(backend.expiry("k"), None);
+
+        backend.hash("h").set("f", "v");
+        assert!(backend.expire_at("h", later));
+        assert!(backend.expiry("h").unwrap().unwrap() > Duration::from_secs(99));
+        assert!(backend.persist("h"));
+        assert!(!backend.persist("h"));
+        assert_eq!(backend.expiry("h"), Some(None));
+
+        // 过期时间点已经过去时删除 key
+        assert!(backend.expire_at("h", Instant::now()));
+        assert!(!backend.exists("h"));
+    }
+
+    #[test]
+    fn test_expire_conditions() {
+        let backend = Backend::new();
+        backend.set_string("k", "v");
+        let now = Instant::now();
+        let (near, far) = (
+            now + Duration::from_secs(10),
+            now + Duration::from_secs(100),
+        );
+        let nx = ExpireCondition {
+            nx: true,
+            ..Default::default()
+        };
+        let gt = ExpireCondition {
+            gt: true,
+            ..Default::default()
+        };
+        let xx_lt = ExpireCondition {
+            xx: true,
+            lt: true,
+            ..Default::default()
+        };
+
+        // 没有过期时间的 key 视为永不过期：GT 和 XX 都不设置
+        assert!(!backend.expire_at_if("k", far, gt));
+        assert!(!backend.expire_at_if("k", far, xx_lt));
+        assert!(backend.expire_at_if("k", far, nx));
+        assert!(!backend.expire_at_if("k", near, nx));
+
+        assert!(!backend.expire_at_if("k", near, gt));
+        assert!(backend.expire_at_if("k", near, xx_lt));
+        assert!(!backend.expire_at_if("k", far, xx_lt));
+        assert!(backend.expire_at_if("k", far, gt));
+        assert!(backend.expiry("k").unwrap().unwrap() > Duration::from_secs(99));
+    }
+
+    #[test]
+    fn test_hash_and_set_refs() {
+        let backend = Backend::new();
+        let hash = backend.hash("h");
+        assert!(hash.set("f1", "v1"));
+        assert!(!hash.set("f1", "v2"));
+        assert!(hash.set("f2", "v3"));
+        assert_eq!(hash.get("f1"), Some("v2".to_string()));
+        assert_eq!(hash.len(), 2);
+        let mut fields = hash.iter().collect::<Vec<_>>();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                ("f1".to_string(), "v2".to_string()),
+                ("f2".to_string(), "v3".to_string())
+            ]
+        );
+        assert!(hash.remove("f1"));
+        assert!(!hash.contains("f1"));
+
+        let set = backend.set_of("s");
+        assert!(set.add("a"));
+        assert!(!set.add("a"));
+        assert!(set.contains("a"));
+        assert!(set.remove("a"));
+        assert!(set.is_empty());
+        assert!(!backend.exists("s"));
+
+        let mut keys = backend.iter_keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["h".to_string()]);
+    }
+}
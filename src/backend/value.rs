@@ -0,0 +1,41 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::RespFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    String,
+    Hash,
+    Set,
+}
+
+// backend 中一个 key 对应的值，用于对外遍历和快照。与 backend 共享同一份数据，
+// 持有期间 backend 再修改这个 key 时会先复制一份，因此 Value 的内容不会改变
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Arc<RespFrame>),
+    Hash(Arc<HashMap<String, Arc<RespFrame>>>),
+    Set(Arc<HashSet<String>>),
+}
+
+impl ValueKind {
+    // 与 redis TYPE 命令的返回值保持一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueKind::String => "string",
+            ValueKind::Hash => "hash",
+            ValueKind::Set => "set",
+        }
+    }
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::String(_) => ValueKind::String,
+            Value::Hash(_) => ValueKind::Hash,
+            Value::Set(_) => ValueKind::Set,
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// 类似 redis-benchmark 的压测工具：多个连接并发发送请求，统计吞吐和延迟分布
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use clap::{ArgAction, Parser};
+use simple_redis::{client::Client, BulkString, RespArray, RespFrame};
+use tokio::task::JoinSet;
+
+const WORKLOADS: &[&str] = &["set", "get", "incr", "hset"];
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "simple-redis-benchmark",
+    version,
+    about = "Benchmark tool for simple-redis",
+    disable_help_flag = true
+)]
+struct BenchArgs {
+    #[arg(
+        short = 'h',
+        long,
+        default_value = "127.0.0.1",
+        help = "Server hostname"
+    )]
+    host: String,
+    #[arg(short, long, default_value_t = 6379, help = "Server port")]
+    port: u16,
+    #[arg(
+        short,
+        long,
+        default_value_t = 50,
+        help = "Number of parallel connections"
+    )]
+    clients: usize,
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 100_000,
+        help = "Total number of requests"
+    )]
+    requests: usize,
+    #[arg(
+        short = 'P',
+        long,
+        default_value_t = 1,
+        help = "Pipeline <numreq> requests"
+    )]
+    pipeline: usize,
+    #[arg(
+        short = 'd',
+        long,
+        default_value_t = 3,
+        help = "Data size of SET/HSET values in bytes"
+    )]
+    data_size: usize,
+    #[arg(
+        short = 'r',
+        long,
+        default_value_t = 10_000,
+        help = "Number of distinct keys"
+    )]
+    keyspace: usize,
+    #[arg(
+        short = 't',
+        long,
+        value_delimiter = ',',
+        default_value = "set,get,incr,hset",
+        help = "Comma separated list of workloads"
+    )]
+    tests: Vec<String>,
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    help: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    elapsed: Duration,
+    // 每个请求的延迟（微秒），pipeline 中的请求都记为整批的往返时间
+    latencies: Vec<u64>,
+    errors: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = BenchArgs::parse();
+    if args.clients == 0 || args.pipeline == 0 || args.keyspace == 0 {
+        bail!("--clients, --pipeline and --keyspace must be greater than 0");
+    }
+    for test in &args.tests {
+        let test = test.to_ascii_lowercase();
+        if !WORKLOADS.contains(&test.as_str()) {
+            bail!("unknown workload {}, expected one of {:?}", test, WORKLOADS);
+        }
+        let report = run(&args, &test).await?;
+        print_report(&args, &test, report);
+    }
+    Ok(())
+}
+
+async fn run(args: &BenchArgs, test: &str) -> Result<Report> {
+    let value = "x".repeat(args.data_size);
+    let mut tasks = JoinSet::new();
+    let start = Instant::now();
+    for client_id in 0..args.clients {
+        // 请求数不能整除时，前面的连接多发一个
+        let count =
+            args.requests / args.clients + usize::from(client_id < args.requests % args.clients);
+        let mut client = Client::connect((args.host.as_str(), args.port)).await?;
+        let (test, value) = (test.to_string(), value.clone());
+        let (pipeline, keyspace) = (args.pipeline, args.keyspace);
+        tasks.spawn(async move {
+            let mut report = Report::default();
+            let mut sent = 0;
+            while sent < count {
+                let batch = pipeline.min(count - sent);
+                let frames = (sent..sent + batch)
+                    .map(|i| request(&test, (client_id * count + i) % keyspace, &value))
+                    .collect();
+                let begin = Instant::now();
+                let replies = client.pipeline(frames).await?;
+                let us = begin.elapsed().as_micros() as u64;
+                report.latencies.extend(std::iter::repeat_n(us, batch));
+                report.errors += replies.iter().filter(|r| r.is_error()).count();
+                sent += batch;
+            }
+            anyhow::Ok(report)
+        });
+    }
+
+    let mut total = Report::default();
+    while let Some(report) = tasks.join_next().await {
+        let report = report??;
+        total.latencies.extend(report.latencies);
+        total.errors += report.errors;
+    }
+    total.elapsed = start.elapsed();
+    Ok(total)
+}
+
+fn request(test: &str, n: usize, value: &str) -> RespFrame {
+    let key = format!("key:{:012}", n);
+    let args = match test {
+        "set" => vec!["SET", &key, value],
+        "get" => vec!["GET", &key],
+        "incr" => vec!["INCR", "counter"],
+        _ => vec!["HSET", "myhash", &key, value],
+    };
+    RespArray::new(
+        args.into_iter()
+            .map(|arg| BulkString::from(arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn print_report(args: &BenchArgs, test: &str, mut report: Report) {
+    report.latencies.sort_unstable();
+    let completed = report.latencies.len();
+    let seconds = report.elapsed.as_secs_f64();
+    println!("====== {} ======", test.to_ascii_uppercase());
+    println!(
+        "  {} requests completed in {:.2} seconds",
+        completed, seconds
+    );
+    println!("  {} parallel clients", args.clients);
+    println!("  {} bytes payload", args.data_size);
+    println!("  pipeline depth {}", args.pipeline);
+    if report.errors > 0 {
+        println!("  {} requests replied with an error", report.errors);
+    }
+    println!();
+    println!(
+        "  throughput summary: {:.2} requests per second",
+        completed as f64 / seconds.max(f64::EPSILON)
+    );
+
+    let ms = |us: u64| us as f64 / 1000.0;
+    let avg = report.latencies.iter().sum::<u64>() as f64 / completed.max(1) as f64;
+    println!("  latency summary (msec):");
+    println!(
+        "  {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "avg", "min", "p50", "p95", "p99", "max"
+    );
+    println!(
+        "  {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3}",
+        avg / 1000.0,
+        ms(percentile(&report.latencies, 0.0)),
+        ms(percentile(&report.latencies, 50.0)),
+        ms(percentile(&report.latencies, 95.0)),
+        ms(percentile(&report.latencies, 99.0)),
+        ms(percentile(&report.latencies, 100.0)),
+    );
+    println!();
+}
+
+// sorted 已经按升序排列，p 为 0 ~ 100
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((sorted.len() as f64) * p / 100.0).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let sorted = (1..=100).collect::<Vec<u64>>();
+        assert_eq!(percentile(&sorted, 0.0), 1);
+        assert_eq!(percentile(&sorted, 50.0), 50);
+        assert_eq!(percentile(&sorted, 99.0), 99);
+        assert_eq!(percentile(&sorted, 100.0), 100);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+}
@@ -0,0 +1,176 @@
+// 类似 redis-cli 的命令行客户端：交互模式、单条命令模式以及 --pipe 批量导入模式
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use clap::{ArgAction, Parser};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use simple_redis::{client::Client, split_args, BulkString, RespArray, RespDecode, RespFrame};
+
+const HISTORY_FILE: &str = ".simple_redis_cli_history";
+
+// 与 redis-cli 一样用 -h 指定主机，因此 help 只保留长参数
+#[derive(Debug, Parser)]
+#[command(
+    name = "simple-redis-cli",
+    version,
+    about = "Command line client for simple-redis",
+    disable_help_flag = true
+)]
+struct CliArgs {
+    #[arg(
+        short = 'h',
+        long,
+        default_value = "127.0.0.1",
+        help = "Server hostname"
+    )]
+    host: String,
+    #[arg(short, long, default_value_t = 6379, help = "Server port")]
+    port: u16,
+    #[arg(short = 'a', long, help = "Password to AUTH with after connecting")]
+    pass: Option<String>,
+    #[arg(long, help = "Send commands read from stdin in a single pipeline")]
+    pipe: bool,
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    help: Option<bool>,
+    // 指定了命令时只执行这一条命令
+    #[arg(trailing_var_arg = true)]
+    command: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = CliArgs::parse();
+    let mut client = Client::connect((args.host.as_str(), args.port)).await?;
+    if let Some(pass) = &args.pass {
+        let reply = client.send(command_frame(["AUTH", pass.as_str()])).await?;
+        if reply.is_error() {
+            eprintln!("AUTH failed: {}", reply);
+        }
+    }
+
+    if args.pipe {
+        return pipe(&mut client).await;
+    }
+    if !args.command.is_empty() {
+        let reply = client.send(command_frame(&args.command)).await?;
+        println!("{}", reply);
+        return Ok(());
+    }
+    repl(&mut client, &format!("{}:{}", args.host, args.port)).await
+}
+
+async fn repl(client: &mut Client, addr: &str) -> Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let prompt = format!("{}> ", addr);
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let Some(words) = split_args(&line) else {
+            println!("Invalid argument(s)");
+            continue;
+        };
+        if words.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        if matches!(words[0].to_ascii_lowercase().as_str(), "quit" | "exit") {
+            break;
+        }
+
+        let reply = client.send(command_frame(&words)).await?;
+        println!("{}", reply);
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+// 与 redis-cli --pipe 一样输出错误和回复的个数
+async fn pipe(client: &mut Client) -> Result<()> {
+    let mut input = Vec::new();
+    io::stdin().read_to_end(&mut input)?;
+    let commands = parse_pipe_input(&input)?;
+
+    println!("All data transferred. Waiting for the last reply...");
+    let replies = client.pipeline(commands).await?;
+    let errors = replies.iter().filter(|reply| reply.is_error()).count();
+    for reply in replies.iter().filter(|reply| reply.is_error()) {
+        eprintln!("{}", reply);
+    }
+    println!("errors: {}, replies: {}", errors, replies.len());
+    Ok(())
+}
+
+// 输入以 * 开头时按 RESP 协议解析，否则每行是一条 inline 命令
+fn parse_pipe_input(input: &[u8]) -> Result<Vec<RespFrame>> {
+    if input.first() == Some(&b'*') {
+        let mut buf = BytesMut::from(input);
+        let mut frames = Vec::new();
+        while !buf.is_empty() {
+            frames.push(RespFrame::decode(&mut buf)?);
+        }
+        return Ok(frames);
+    }
+
+    let text = std::str::from_utf8(input)?;
+    let mut frames = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let words = split_args(line).ok_or_else(|| anyhow!("line {}: unbalanced quotes", i + 1))?;
+        if !words.is_empty() {
+            frames.push(command_frame(&words));
+        }
+    }
+    Ok(frames)
+}
+
+fn command_frame<I, T>(words: I) -> RespFrame
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    RespArray::new(
+        words
+            .into_iter()
+            .map(|word| BulkString::from(word.as_ref()).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipe_input() -> Result<()> {
+        let frames = parse_pipe_input(b"set k \"a b\"\n\nget k\n")?;
+        assert_eq!(
+            frames,
+            vec![
+                command_frame(["set", "k", "a b"]),
+                command_frame(["get", "k"])
+            ]
+        );
+
+        let frames = parse_pipe_input(b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*1\r\n$4\r\nping\r\n")?;
+        assert_eq!(
+            frames,
+            vec![command_frame(["get", "k"]), command_frame(["ping"])]
+        );
+
+        assert!(parse_pipe_input(b"set k \"v\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+
+use crate::{Backend, ServerConfig, ValueKind};
+
+// 服务器的命令行参数。除 --config 外都与 redis.conf 中的同名配置项对应，
+// 先加载配置文件，再用命令行中指定的值覆盖
+#[derive(Debug, Default, Parser)]
+#[command(name = "simple-redis", version, about = "A simple redis server")]
+pub struct ServerArgs {
+    #[arg(long, help = "Port to listen on [default: 6379]")]
+    pub port: Option<u16>,
+    #[arg(
+        long,
+        num_args = 1..,
+        help = "Addresses to listen on [default: 0.0.0.0]"
+    )]
+    pub bind: Option<Vec<String>>,
+    #[arg(long, value_parser = ["yes", "no"], help = "Run one SO_REUSEPORT accept loop per core")]
+    pub reuseport: Option<String>,
+    #[arg(long, help = "Working directory for data files")]
+    pub dir: Option<String>,
+    #[arg(long, help = "File name BGSAVE writes to inside dir")]
+    pub dbfilename: Option<String>,
+    // 支持 100mb、1gb 这样的单位
+    #[arg(long, help = "Memory limit, e.g. 100mb or 1gb; 0 means no limit")]
+    pub maxmemory: Option<String>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Move string values idle this long to disk; 0 disables"
+    )]
+    pub tier_idle_seconds: Option<usize>,
+    #[arg(long, help = "Password clients must AUTH with")]
+    pub requirepass: Option<String>,
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "Redis to forward unimplemented commands to"
+    )]
+    pub upstream: Option<String>,
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "Redis to read through and write through to"
+    )]
+    pub cache_origin: Option<String>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "TTL of values fetched from the cache origin; 0 means no expiry"
+    )]
+    pub cache_ttl: Option<usize>,
+    #[arg(
+        long,
+        value_name = "COUNT",
+        help = "Track the most accessed keys with this many counters; 0 disables"
+    )]
+    pub hotkeys_capacity: Option<usize>,
+    #[arg(long, value_parser = ["yes", "no"], help = "Save the dataset to dbfilename after connections finish on shutdown [default: yes]")]
+    pub save_on_shutdown: Option<String>,
+    #[arg(long, value_parser = ["yes", "no"], help = "Enable the append only file, only 'no' is supported")]
+    pub appendonly: Option<String>,
+    #[arg(
+        long,
+        value_parser = ["debug", "verbose", "notice", "warning", "nothing"],
+        help = "Log verbosity, overridden by RUST_LOG"
+    )]
+    pub loglevel: Option<String>,
+    #[arg(long, value_parser = ["pretty", "json"], help = "Log output format")]
+    pub logformat: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "redis.conf style configuration file"
+    )]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<ServerCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ServerCommand {
+    // 离线检查 BGSAVE 写出的文件，不启动服务器
+    #[command(about = "Print a summary of a dump file, or its entries as JSON")]
+    Inspect {
+        #[arg(help = "Dump file written by BGSAVE")]
+        file: PathBuf,
+        #[arg(long, help = "Print every entry as JSON instead of the summary")]
+        json: bool,
+    },
+}
+
+impl ServerArgs {
+    // 命令行中指定的配置项，以 (名称, 值) 的形式交给 ServerConfig::set
+    pub fn overrides(&self) -> Vec<(&'static str, String)> {
+        [
+            ("port", self.port.map(|port| port.to_string())),
+            ("bind", self.bind.as_ref().map(|bind| bind.join(" "))),
+            ("reuseport", self.reuseport.clone()),
+            ("dir", self.dir.clone()),
+            ("dbfilename", self.dbfilename.clone()),
+            ("maxmemory", self.maxmemory.clone()),
+            (
+                "tier-idle-seconds",
+                self.tier_idle_seconds.map(|secs| secs.to_string()),
+            ),
+            ("requirepass", self.requirepass.clone()),
+            ("upstream", self.upstream.clone()),
+            ("cache-origin", self.cache_origin.clone()),
+            ("cache-ttl", self.cache_ttl.map(|secs| secs.to_string())),
+            (
+                "hotkeys-capacity",
+                self.hotkeys_capacity.map(|count| count.to_string()),
+            ),
+            ("save-on-shutdown", self.save_on_shutdown.clone()),
+            ("appendonly", self.appendonly.clone()),
+            ("loglevel", self.loglevel.clone()),
+            ("logformat", self.logformat.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    // 返回最终的配置以及配置文件中被忽略的指令，日志初始化之后再输出这些指令
+    pub fn load_config(&self) -> Result<(ServerConfig, Vec<String>)> {
+        let mut config = ServerConfig::default();
+        let ignored = match &self.config {
+            Some(path) => config.load_file(path)?,
+            None => Vec::new(),
+        };
+        for (name, value) in self.overrides() {
+            config
+                .set(name, &value)
+                .with_context(|| format!("invalid value for --{}", name))?;
+        }
+        Ok((config, ignored))
+    }
+}
+
+/*
+    inspect 子命令：把文件载入一个新的 backend 再从快照统计，与服务器启动时的载入逻辑一致，
+    已经过期的 key 不会载入。输出每种类型的 key 个数和最大的 key、剩余生存时间的分布；
+    --json 时每个 key 输出一行 JSON，需要开启 json feature
+*/
+pub fn inspect(path: &Path, json: bool) -> Result<String> {
+    // load 把不存在的文件当作空数据集，这里需要报错
+    fs::metadata(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let backend = Backend::new();
+    backend
+        .load(path)
+        .with_context(|| format!("cannot load {}", path.display()))?;
+    if json {
+        return inspect_json(&backend);
+    }
+
+    let summary = backend.snapshot().summary();
+    let mut out = String::new();
+    let total = summary.counts.values().sum::<usize>();
+    let _ = writeln!(out, "keys: {}", total);
+    for (kind, unit) in [
+        (ValueKind::String, "bytes"),
+        (ValueKind::Hash, "fields"),
+        (ValueKind::Set, "members"),
+    ] {
+        let count = summary.counts.get(&kind).copied().unwrap_or(0);
+        let _ = write!(out, "{}: {}", kind.as_str(), count);
+        if let Some((key, size)) = summary.biggest.get(&kind) {
+            let _ = write!(out, ", biggest {:?} with {} {}", key, size, unit);
+        }
+        out.push('\n');
+    }
+    let _ = writeln!(out, "ttl:");
+    for (bucket, count) in summary.ttls {
+        let _ = writeln!(out, "  {}: {}", bucket, count);
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "json")]
+fn inspect_json(backend: &Backend) -> Result<String> {
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+    use serde_json::{json, Value as Json};
+
+    use crate::Value;
+
+    let snapshot = backend.snapshot();
+    let now = Instant::now();
+    let unix_now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+    let mut out = String::new();
+    for (key, value) in snapshot.iter() {
+        let json_value = match value {
+            Value::String(frame) => frame.to_json(),
+            Value::Hash(hash) => Json::Object(
+                hash.iter()
+                    .map(|(field, value)| (field.clone(), value.to_json()))
+                    .collect(),
+            ),
+            Value::Set(set) => Json::Array(set.iter().cloned().map(Json::String).collect()),
+        };
+        let expire_at = snapshot
+            .expire_at(key)
+            .map(|at| unix_now + at.saturating_duration_since(now).as_millis() as u64);
+        let entry = json!({
+            "key": key,
+            "type": value.kind().as_str(),
+            "value": json_value,
+            "expire_at": expire_at,
+        });
+        let _ = writeln!(out, "{}", entry);
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "json"))]
+fn inspect_json(_backend: &Backend) -> Result<String> {
+    anyhow::bail!("--json requires simple-redis to be built with the json feature")
+}
+
+// redis 的日志级别对应的 tracing 过滤条件，每个请求的日志属于 verbose
+pub fn log_filter(loglevel: &str) -> &'static str {
+    match loglevel {
+        "debug" => "debug",
+        "verbose" => "info",
+        "notice" | "warning" => "warn",
+        _ => "off",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_server_args() -> Result<()> {
+        let args = ServerArgs::try_parse_from([
+            "simple-redis",
+            "--port",
+            "6380",
+            "--bind",
+            "127.0.0.1",
+            "::1",
+            "--maxmemory",
+            "1gb",
+            "--appendonly",
+            "no",
+            "--logformat",
+            "json",
+        ])?;
+        let (config, _) = args.load_config()?;
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.bind, "127.0.0.1 ::1");
+        assert_eq!(config.maxmemory, 1024 * 1024 * 1024);
+        assert!(!config.appendonly);
+        assert_eq!(config.loglevel, "notice");
+        assert_eq!(config.logformat, "json");
+
+        assert!(ServerArgs::try_parse_from(["simple-redis", "--loglevel", "trace"]).is_err());
+        let args = ServerArgs::try_parse_from(["simple-redis", "--appendonly", "yes"])?;
+        assert!(args.load_config().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_file_with_overrides() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("simple-redis-{}.conf", std::process::id()));
+        fs::write(
+            &path,
+            "# comment\nport 7000\n\nloglevel debug\nrequirepass foo\n",
+        )?;
+
+        let args = ServerArgs {
+            config: Some(path.clone()),
+            requirepass: Some("bar".to_string()),
+            ..Default::default()
+        };
+        let config = args.load_config();
+        fs::remove_file(&path)?;
+
+        let (config, _) = config?;
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.loglevel, "debug");
+        assert_eq!(config.requirepass, "bar");
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_dump() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_string("k1", "v");
+        backend.set_string("k2", "longer");
+        backend.set_of("s").add("a");
+        backend.expire_at(
+            "k1",
+            std::time::Instant::now() + std::time::Duration::from_secs(10),
+        );
+        let path =
+            std::env::temp_dir().join(format!("simple-redis-{}.inspect", std::process::id()));
+        backend.snapshot().write_to(fs::File::create(&path)?)?;
+
+        let args = ServerArgs::try_parse_from([
+            "simple-redis",
+            "inspect",
+            path.to_str().unwrap(),
+            "--json",
+        ])?;
+        assert!(matches!(
+            args.command,
+            Some(ServerCommand::Inspect { json: true, .. })
+        ));
+
+        let summary = inspect(&path, false);
+        let json = inspect(&path, true);
+        fs::remove_file(&path)?;
+        let summary = summary?;
+        assert!(summary.starts_with("keys: 3\n"));
+        assert!(summary.contains("string: 2, biggest \"k2\" with 6 bytes\n"));
+        assert!(summary.contains("hash: 0\n"));
+        assert!(summary.contains("  < 1m: 1\n"));
+        assert!(summary.contains("  no expiry: 2\n"));
+        if cfg!(feature = "json") {
+            let json = json?;
+            assert_eq!(json.lines().count(), 3);
+            assert!(json.contains(r#""key":"s","type":"set","value":["a"]"#));
+        } else {
+            assert!(json.is_err());
+        }
+
+        assert!(inspect(&path, false).is_err());
+        Ok(())
+    }
+}
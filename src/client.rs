@@ -0,0 +1,305 @@
+// 异步客户端，与服务器共用 RespCodec。常用命令有对应的方法，其他命令通过 command / send 发送
+use anyhow::{anyhow, Result};
+use futures::SinkExt;
+use thiserror::Error;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+use crate::{BulkString, RespArray, RespCodec, RespFrame};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ClientError {
+    // 服务器回复的错误，例如 "ERR unknown command"
+    #[error("{0}")]
+    Server(String),
+    #[error("Unexpected reply: {0}")]
+    UnexpectedReply(String),
+    #[error("Connection closed by server")]
+    Closed,
+}
+
+impl ClientError {
+    // 服务器错误的错误码，例如 "WRONGTYPE"、"MOVED"
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Server(e) => e.split_whitespace().next(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Client {
+    framed: Framed<TcpStream, RespCodec>,
+}
+
+// SUBSCRIBE 之后连接只能接收消息，因此 subscribe 会消耗 Client
+#[derive(Debug)]
+pub struct Subscriber {
+    framed: Framed<TcpStream, RespCodec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub channel: String,
+    pub payload: Vec<u8>,
+}
+
+impl Client {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Self {
+            framed: Framed::new(stream, RespCodec::default()),
+        })
+    }
+
+    // 原样发送一个帧并返回回复，错误回复也作为普通的帧返回
+    pub async fn send(&mut self, frame: RespFrame) -> Result<RespFrame> {
+        self.framed.send(frame).await?;
+        read_frame(&mut self.framed).await
+    }
+
+    // 一次写出所有请求再依次读取回复，回复与请求一一对应
+    pub async fn pipeline(&mut self, frames: Vec<RespFrame>) -> Result<Vec<RespFrame>> {
+        let count = frames.len();
+        for frame in frames {
+            self.framed.feed(frame).await?;
+        }
+        self.framed.flush().await?;
+
+        let mut replies = Vec::with_capacity(count);
+        for _ in 0..count {
+            replies.push(read_frame(&mut self.framed).await?);
+        }
+        Ok(replies)
+    }
+
+    // 参数编码为 BulkString 数组发送，错误回复转换为 ClientError::Server
+    pub async fn command<I, T>(&mut self, args: I) -> Result<RespFrame>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<BulkString>,
+    {
+        let frame = self.send(command_frame(args)).await?;
+        check_error(frame)
+    }
+
+    pub async fn ping(&mut self) -> Result<String> {
+        let frame = self.command(["PING"]).await?;
+        Ok(String::try_from(frame)?)
+    }
+
+    pub async fn get(&mut self, key: &str) -> Result<Option<String>> {
+        let frame = self.command(["GET", key]).await?;
+        optional_string(frame)
+    }
+
+    pub async fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.command(["SET", key, value]).await?;
+        Ok(())
+    }
+
+    pub async fn hget(&mut self, key: &str, field: &str) -> Result<Option<String>> {
+        let frame = self.command(["HGET", key, field]).await?;
+        optional_string(frame)
+    }
+
+    // redis 回复新增的字段个数，这里的服务器回复 OK，因此不返回回复内容
+    pub async fn hset(&mut self, key: &str, field: &str, value: &str) -> Result<()> {
+        self.command(["HSET", key, field, value]).await?;
+        Ok(())
+    }
+
+    // 等待每个频道的订阅确认后返回
+    pub async fn subscribe(mut self, channels: &[&str]) -> Result<Subscriber> {
+        let args = std::iter::once("SUBSCRIBE").chain(channels.iter().copied());
+        self.framed.send(command_frame(args)).await?;
+        for _ in channels {
+            let frame = check_error(read_frame(&mut self.framed).await?)?;
+            match push_items(frame) {
+                Some(items) if is_kind(&items, "subscribe") => {}
+                _ => return Err(ClientError::UnexpectedReply("subscribe".to_string()).into()),
+            }
+        }
+        Ok(Subscriber {
+            framed: self.framed,
+        })
+    }
+}
+
+impl Subscriber {
+    // 返回下一条消息，连接关闭时返回 None。订阅确认等非消息的推送会被跳过
+    pub async fn next_message(&mut self) -> Result<Option<Message>> {
+        loop {
+            let frame = match self.framed.next().await {
+                Some(frame) => frame?,
+                None => return Ok(None),
+            };
+            let Some(items) = push_items(frame) else {
+                continue;
+            };
+            if !is_kind(&items, "message") || items.len() != 3 {
+                continue;
+            }
+            let mut items = items.into_iter().skip(1);
+            let (Some(channel), Some(payload)) = (items.next(), items.next()) else {
+                continue;
+            };
+            return Ok(Some(Message {
+                channel: String::try_from(channel)?,
+                payload: Vec::<u8>::try_from(payload)?,
+            }));
+        }
+    }
+}
+
+fn command_frame<I, T>(args: I) -> RespFrame
+where
+    I: IntoIterator<Item = T>,
+    T: Into<BulkString>,
+{
+    RespArray::new(
+        args.into_iter()
+            .map(|arg| arg.into().into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+async fn read_frame(framed: &mut Framed<TcpStream, RespCodec>) -> Result<RespFrame> {
+    match framed.next().await {
+        Some(frame) => frame,
+        None => Err(ClientError::Closed.into()),
+    }
+}
+
+fn check_error(frame: RespFrame) -> Result<RespFrame> {
+    match frame {
+        RespFrame::Error(e) => Err(ClientError::Server(e.0.into()).into()),
+        frame => Ok(frame),
+    }
+}
+
+fn optional_string(frame: RespFrame) -> Result<Option<String>> {
+    match frame {
+        RespFrame::Null(_) | RespFrame::NullBulkString(_) => Ok(None),
+        frame => String::try_from(frame).map(Some).map_err(|e| anyhow!(e)),
+    }
+}
+
+// RESP2 下推送的消息是数组，RESP3 下是 push 类型
+fn push_items(frame: RespFrame) -> Option<Vec<RespFrame>> {
+    match frame {
+        RespFrame::Array(array) => Some(array.0),
+        RespFrame::Push(push) => Some(push.0),
+        _ => None,
+    }
+}
+
+fn is_kind(items: &[RespFrame], kind: &str) -> bool {
+    matches!(items.first(), Some(RespFrame::BulkString(s)) if s.eq_ignore_ascii_case(kind.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{network, Backend, RespEncode};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    async fn start_server() -> Result<std::net::SocketAddr> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(network::serve(
+            listener,
+            Backend::new(),
+            network::Shutdown::new(),
+        ));
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_client_commands() -> Result<()> {
+        let mut client = Client::connect(start_server().await?).await?;
+        assert_eq!(client.ping().await?, "PONG");
+
+        client.set("k", "v").await?;
+        assert_eq!(client.get("k").await?, Some("v".to_string()));
+        assert_eq!(client.get("missing").await?, None);
+
+        client.hset("h", "f", "1").await?;
+        assert_eq!(client.hget("h", "f").await?, Some("1".to_string()));
+
+        let err = client.command(["GET"]).await.unwrap_err();
+        let err = err.downcast_ref::<ClientError>().unwrap();
+        assert!(matches!(err, ClientError::Server(_)));
+        assert_eq!(err.code(), Some("ERR"));
+
+        client.hset("h2", "f", "v").await?;
+        let err = client.command(["GET", "h2"]).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ClientError>()
+                .and_then(ClientError::code),
+            Some("WRONGTYPE")
+        );
+
+        let reply = client
+            .send(
+                RespArray::new([
+                    BulkString::from("echo").into(),
+                    BulkString::from("hi").into(),
+                ])
+                .into(),
+            )
+            .await?;
+        assert_eq!(reply, BulkString::from("hi").into());
+
+        let replies = client
+            .pipeline(vec![
+                crate::resp!(["set", "a", "1"]),
+                crate::resp!(["get", "a"]),
+                crate::resp!(["get"]),
+            ])
+            .await?;
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[1], BulkString::from("1").into());
+        assert!(replies[2].is_error());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_messages() -> Result<()> {
+        // 用一个只回放固定数据的服务器，覆盖订阅确认之外的推送被跳过
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // 先读取 SUBSCRIBE 请求，避免关闭时有未读数据导致连接被重置
+            let mut buf = [0; 64];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let frames: [RespFrame; 3] = [
+                crate::resp!(["subscribe", "news", 1]),
+                crate::resp!(["pong", ""]),
+                crate::resp!(["message", "news", "hello"]),
+            ];
+            for frame in frames {
+                stream.write_all(&frame.encode()).await.unwrap();
+            }
+        });
+
+        let client = Client::connect(addr).await?;
+        let mut subscriber = client.subscribe(&["news"]).await?;
+        assert_eq!(
+            subscriber.next_message().await?,
+            Some(Message {
+                channel: "news".to_string(),
+                payload: b"hello".to_vec(),
+            })
+        );
+        assert_eq!(subscriber.next_message().await?, None);
+        Ok(())
+    }
+}
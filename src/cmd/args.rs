@@ -0,0 +1,154 @@
+use std::vec::IntoIter;
+
+use crate::{RespError, RespFrame};
+
+use super::CommandError;
+
+// 依次读取命令的参数。位置参数用 next_* 读取，之后的选项用 next_keyword 循环读取：
+//
+//   while let Some(keyword) = args.next_keyword()? {
+//       match keyword.as_str() {
+//           "NX" => nx = true,
+//           "EX" => ex = Some(args.value::<i64>("EX")?),
+//           _ => return Err(syntax_error()),
+//       }
+//   }
+//
+// 选项名不区分大小写，统一转换为大写
+#[derive(Debug)]
+pub struct ArgParser {
+    args: IntoIter<RespFrame>,
+}
+
+pub fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+impl ArgParser {
+    pub fn new(args: Vec<RespFrame>) -> Self {
+        Self {
+            args: args.into_iter(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.len() == 0
+    }
+
+    pub fn next_frame(&mut self, name: &str) -> Result<RespFrame, CommandError> {
+        self.args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument(format!("Missing {}", name)))
+    }
+
+    pub fn next_arg<T>(&mut self, name: &str) -> Result<T, CommandError>
+    where
+        T: TryFrom<RespFrame, Error = RespError>,
+    {
+        Ok(T::try_from(self.next_frame(name)?)?)
+    }
+
+    pub fn next_string(&mut self, name: &str) -> Result<String, CommandError> {
+        self.next_arg(name)
+    }
+
+    // 读取下一个选项名，没有更多参数时返回 None
+    pub fn next_keyword(&mut self) -> Result<Option<String>, CommandError> {
+        match self.args.next() {
+            Some(frame) => {
+                let keyword = String::try_from(frame).map_err(|_| syntax_error())?;
+                Ok(Some(keyword.to_ascii_uppercase()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // 选项后面跟着的值，比如 EX 10 中的 10、LIMIT 0 10 中的 0 和 10
+    pub fn value<T>(&mut self, option: &str) -> Result<T, CommandError>
+    where
+        T: TryFrom<RespFrame, Error = RespError>,
+    {
+        let frame = self.args.next().ok_or_else(syntax_error)?;
+        T::try_from(frame).map_err(|_| {
+            CommandError::InvalidArgument(format!("invalid value for option {}", option))
+        })
+    }
+
+    // 剩余的所有参数
+    pub fn rest<T>(&mut self) -> Result<Vec<T>, CommandError>
+    where
+        T: TryFrom<RespFrame, Error = RespError>,
+    {
+        Ok(self
+            .args
+            .by_ref()
+            .map(T::try_from)
+            .collect::<Result<Vec<T>, _>>()?)
+    }
+
+    // 所有参数都应该已经读取完，否则是语法错误
+    pub fn finish(self) -> Result<(), CommandError> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(syntax_error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use anyhow::Result;
+
+    fn parser(args: &[&str]) -> ArgParser {
+        ArgParser::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_arg_parser_options() -> Result<()> {
+        let mut args = parser(&["key", "ex", "10", "NX", "limit", "0", "5", "match", "k*"]);
+        assert_eq!(args.next_string("key")?, "key");
+
+        let (mut ex, mut nx, mut limit, mut pattern) = (None, false, None, None);
+        while let Some(keyword) = args.next_keyword()? {
+            match keyword.as_str() {
+                "EX" => ex = Some(args.value::<i64>("EX")?),
+                "NX" => nx = true,
+                "LIMIT" => limit = Some((args.value::<i64>("LIMIT")?, args.value::<i64>("LIMIT")?)),
+                "MATCH" => pattern = Some(args.value::<String>("MATCH")?),
+                _ => return Err(syntax_error().into()),
+            }
+        }
+        assert_eq!(ex, Some(10));
+        assert!(nx);
+        assert_eq!(limit, Some((0, 5)));
+        assert_eq!(pattern.as_deref(), Some("k*"));
+        args.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_arg_parser_errors() {
+        let mut args = parser(&["ex"]);
+        assert_eq!(args.next_keyword().unwrap().as_deref(), Some("EX"));
+        assert!(args.value::<i64>("EX").is_err());
+
+        let mut args = parser(&["ex", "abc"]);
+        args.next_keyword().unwrap();
+        assert!(args.value::<i64>("EX").is_err());
+
+        let mut args = parser(&[]);
+        assert!(args.next_string("key").is_err());
+
+        assert!(parser(&["extra"]).finish().is_err());
+    }
+}
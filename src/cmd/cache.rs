@@ -0,0 +1,279 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{proxy, CommandError, ConnectionContext};
+
+// hash 的 (字段, 值) 列表
+pub type HashFields = Vec<(String, RespFrame)>;
+
+/*
+    读穿 / 写穿缓存模式，让这个服务器可以直接放在已有的数据源前面作为缓存层：
+    - GET、MGET、EXISTS、HGET、HGETALL 读到本地不存在的 key 时从源站读取，读到的值按 cache-ttl 回填。
+      hash 整个回填，本地已有的 hash 中没有的字段不再询问源站；set 不经过源站
+    - 带 write flag 的命令先原样发给源站，源站成功后再在本地执行，源站失败时本地不修改
+    源站可以是嵌入方注册的 Origin，也可以是 cache-origin 配置的 redis，注册了 Origin 时优先使用。
+    MULTI 中排队的写命令不转发
+*/
+pub trait Origin: fmt::Debug + Send + Sync {
+    // 返回 None 表示源站也没有这个 key
+    fn fetch<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<RespFrame>, CommandError>>;
+
+    // hash 的所有字段，默认实现表示源站不提供 hash
+    fn fetch_hash<'a>(
+        &'a self,
+        _key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<HashFields>, CommandError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    // request 是客户端发来的完整写命令
+    fn write<'a>(&'a self, request: &'a RespArray) -> BoxFuture<'a, Result<(), CommandError>>;
+}
+
+lazy_static! {
+    static ref ORIGIN: RwLock<Option<Arc<dyn Origin>>> = RwLock::new(None);
+}
+
+pub fn set_origin(origin: impl Origin + 'static) {
+    *ORIGIN.write() = Some(Arc::new(origin));
+}
+
+pub fn clear_origin() {
+    *ORIGIN.write() = None;
+}
+
+enum Source {
+    Callback(Arc<dyn Origin>),
+    Redis(String),
+}
+
+fn source(backend: &Backend) -> Option<Source> {
+    if let Some(origin) = ORIGIN.read().clone() {
+        return Some(Source::Callback(origin));
+    }
+    let addr = backend.config().cache_origin;
+    (!addr.is_empty()).then_some(Source::Redis(addr))
+}
+
+pub fn cache_enabled(backend: &Backend) -> bool {
+    ORIGIN.read().is_some() || !backend.config().cache_origin.is_empty()
+}
+
+// 字符串 key 没有命中时调用。回填时 key 已经被其他请求写入则返回本地的新值
+pub(super) async fn read_through(
+    backend: &Backend,
+    key: &str,
+    ctx: &mut ConnectionContext,
+) -> Result<Option<RespFrame>, CommandError> {
+    let value = match source(backend) {
+        None => return Ok(None),
+        Some(Source::Callback(origin)) => origin.fetch(key).await?,
+        // 源站上不是字符串的 key 回复 WRONGTYPE，与不存在一样处理
+        Some(Source::Redis(addr)) => match query(ctx, &addr, "GET", key).await? {
+            RespFrame::Error(e) if e.starts_with("WRONGTYPE") => None,
+            RespFrame::Error(e) => return Err(CommandError::Upstream(e.to_string())),
+            value if value.is_null() => None,
+            value => Some(value),
+        },
+    };
+    let Some(value) = value else {
+        return Ok(None);
+    };
+
+    if backend.set_if_absent(key, value.clone(), cache_ttl(backend)) {
+        return Ok(Some(value));
+    }
+    Ok(backend.get(key).map(|value| (*value).clone()))
+}
+
+// hash key 不存在时调用，从源站读取整个 hash 回填，返回源站是否有这个 hash。
+// 之后由调用方按本地的数据回复
+pub(super) async fn read_through_hash(
+    backend: &Backend,
+    key: &str,
+    ctx: &mut ConnectionContext,
+) -> Result<bool, CommandError> {
+    let fields = match source(backend) {
+        None => return Ok(false),
+        Some(Source::Callback(origin)) => origin.fetch_hash(key).await?,
+        Some(Source::Redis(addr)) => match query(ctx, &addr, "HGETALL", key).await? {
+            RespFrame::Error(e) if e.starts_with("WRONGTYPE") => None,
+            RespFrame::Error(e) => return Err(CommandError::Upstream(e.to_string())),
+            RespFrame::Map(map) => Some(map.0.into_iter().collect()),
+            // RESP2 的回复是字段和值交替的数组
+            RespFrame::Array(array) => {
+                let mut fields = Vec::with_capacity(array.len() / 2);
+                let mut items = array.0.into_iter();
+                while let (Some(field), Some(value)) = (items.next(), items.next()) {
+                    fields.push((String::try_from(field)?, value));
+                }
+                Some(fields)
+            }
+            _ => None,
+        },
+    };
+    match fields {
+        Some(fields) if !fields.is_empty() => {
+            backend.hset_if_absent(key, fields, cache_ttl(backend));
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+// EXISTS 中本地不存在的 key，依次按字符串和 hash 询问源站
+pub(super) async fn read_through_any(
+    backend: &Backend,
+    key: &str,
+    ctx: &mut ConnectionContext,
+) -> Result<bool, CommandError> {
+    if read_through(backend, key, ctx).await?.is_some() {
+        return Ok(true);
+    }
+    read_through_hash(backend, key, ctx).await
+}
+
+async fn query(
+    ctx: &mut ConnectionContext,
+    addr: &str,
+    command: &str,
+    key: &str,
+) -> Result<RespFrame, CommandError> {
+    let request = RespArray::new(vec![
+        BulkString::from(command).into(),
+        BulkString::from(key).into(),
+    ]);
+    proxy::forward(&mut ctx.origin, addr, request).await
+}
+
+fn cache_ttl(backend: &Backend) -> Option<Duration> {
+    let ttl = backend.config().cache_ttl;
+    (ttl > 0).then(|| Duration::from_secs(ttl as u64))
+}
+
+// 由命令分发在本地执行写命令之前调用
+pub async fn write_through(
+    backend: &Backend,
+    request: &RespArray,
+    ctx: &mut ConnectionContext,
+) -> Result<(), CommandError> {
+    match source(backend) {
+        None => Ok(()),
+        Some(Source::Callback(origin)) => origin.write(request).await,
+        Some(Source::Redis(addr)) => {
+            match proxy::forward(&mut ctx.origin, &addr, request.clone()).await? {
+                RespFrame::Error(e) => Err(CommandError::Upstream(e.to_string())),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::cmd::{Command, CommandExecutor};
+
+    // 假的源站：key 为 hash 时 HGETALL 回复 {f: v}，GET 回复 WRONGTYPE；
+    // 其他 key 的 GET 回复 $6\r\norigin，HGETALL 回复空数组；其他命令回复 +OK。记录收到的请求个数
+    async fn fake_origin(requests: Arc<AtomicUsize>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 1024];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                requests.fetch_add(1, Ordering::Relaxed);
+                let request = &buf[..n];
+                let contains = |s: &[u8]| request.windows(s.len()).any(|w| w == s);
+                let reply: &[u8] = match (contains(b"HGETALL"), contains(b"GET")) {
+                    (true, _) if contains(b"hash") => b"*2\r\n$1\r\nf\r\n$1\r\nv\r\n",
+                    (true, _) => b"*0\r\n",
+                    (false, true) if contains(b"hash") => b"-WRONGTYPE wrong kind\r\n",
+                    (false, true) => b"$6\r\norigin\r\n",
+                    _ => b"+OK\r\n",
+                };
+                stream.write_all(reply).await.unwrap();
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_read_through_and_write_through() -> anyhow::Result<()> {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let addr = fake_origin(requests.clone()).await?;
+        let backend = Backend::new();
+        backend.set_config("cache-origin", &addr)?;
+        backend.set_config("cache-ttl", "60")?;
+        let mut ctx = ConnectionContext::new(1);
+
+        // 第一次从源站读取并回填，之后命中本地
+        for _ in 0..2 {
+            let cmd = Command::try_from(RespArray::try_from(crate::resp!(["get", "key"]))?)?;
+            let reply = cmd.execute(&backend, &mut ctx).await?;
+            assert_eq!(reply, BulkString::from("origin").into());
+        }
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+        assert!(backend.ttl("key").is_some());
+
+        let request = RespArray::try_from(crate::resp!(["set", "key", "new"]))?;
+        write_through(&backend, &request, &mut ctx).await?;
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_through_other_reads() -> anyhow::Result<()> {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let addr = fake_origin(requests.clone()).await?;
+        let backend = Backend::new();
+        backend.set_config("cache-origin", &addr)?;
+        let ctx = &mut ConnectionContext::new(1);
+        let backend = &backend;
+        async fn run(
+            backend: &Backend,
+            ctx: &mut ConnectionContext,
+            args: RespFrame,
+        ) -> anyhow::Result<RespFrame> {
+            let cmd = Command::try_from(RespArray::try_from(args)?)?;
+            Ok(cmd.execute(backend, ctx).await?)
+        }
+
+        let reply = run(backend, ctx, crate::resp!(["mget", "a", "b"])).await?;
+        let origin: RespFrame = BulkString::from("origin").into();
+        assert_eq!(reply, RespArray::new(vec![origin.clone(), origin]).into());
+
+        // hash 整个回填，之后读取其他字段不再询问源站
+        let reply = run(backend, ctx, crate::resp!(["hget", "hash", "f"])).await?;
+        assert_eq!(reply, BulkString::from("v").into());
+        let reply = run(backend, ctx, crate::resp!(["hget", "hash", "missing"])).await?;
+        assert!(reply.is_null());
+        let RespFrame::Map(map) = run(backend, ctx, crate::resp!(["hgetall", "hash"])).await?
+        else {
+            panic!("expected map");
+        };
+        assert_eq!(map.len(), 1);
+
+        let reply = run(backend, ctx, crate::resp!(["exists", "hash", "c", "hash2"])).await?;
+        assert_eq!(reply, RespFrame::Integer(3));
+        assert_eq!(backend.key_type("hash2"), Some(crate::ValueKind::Hash));
+        Ok(())
+    }
+}
@@ -1,17 +1,20 @@
 // 实现 echo 和 ping 等连接相关的命令
-use crate::{Backend, BulkString, RespArray, RespFrame, SimpleString};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespMap, RespVersion, SimpleString};
 
 use super::{
-    extract_args, map::extract_and_validate_args, validate_command, CommandError, CommandExecutor,
-    Echo, Ping,
+    extract_args, ArgParser, Auth, CommandError, CommandExecutor, ConnectionContext, Echo, Hello,
+    Ping, RESP_OK,
 };
 
-const PING: &str = "ping";
 const PONG: &str = "PONG";
 
 impl CommandExecutor for Echo {
-    fn execute(self, _: &Backend) -> RespFrame {
-        BulkString::new(self.message).into()
+    async fn execute(
+        self,
+        _: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        Ok(BulkString::new(self.message).into())
     }
 }
 
@@ -19,17 +22,10 @@ impl TryFrom<RespArray> for Echo {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["echo"], 1)?;
-        let mut args = extract_args(value, 1)?.into_iter();
-
-        match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Echo {
-                message: String::from_utf8(key.0)?,
-            }),
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid argument".to_string(),
-            )),
-        }
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let message = args.next_arg("message")?;
+        args.finish()?;
+        Ok(Echo { message })
     }
 }
 
@@ -42,13 +38,26 @@ impl TryFrom<RespArray> for Echo {
     ->PONG
     127.0.0.1:6379> ping hello
     ->"hello"
+
+    RESP2 连接在订阅模式下只能收到数组，与 redis 一样回复 ["pong", message]，没有参数时 message 为空字符串
 */
 impl CommandExecutor for Ping {
-    fn execute(self, _: &Backend) -> RespFrame {
-        if self.message == PONG {
-            SimpleString::new(self.message).into()
-        } else {
-            BulkString::new(self.message).into()
+    async fn execute(
+        self,
+        _: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if ctx.is_subscribed() && ctx.protocol == RespVersion::Resp2 {
+            let message = self.message.unwrap_or_default();
+            return Ok(RespArray::new(vec![
+                BulkString::from("pong").into(),
+                BulkString::new(message).into(),
+            ])
+            .into());
+        }
+        match self.message {
+            Some(message) => Ok(BulkString::new(message).into()),
+            None => Ok(SimpleString::new(PONG).into()),
         }
     }
 }
@@ -57,23 +66,124 @@ impl TryFrom<RespArray> for Ping {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        let command_len = value.len();
-
-        match command_len {
-            1 => {
-                validate_command(&value, &[PING], command_len - 1)?;
-                Ok(Ping {
-                    message: PONG.to_string(),
-                })
-            }
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let message = match args.is_empty() {
+            true => None,
+            false => Some(args.next_string("message")?),
+        };
+        args.finish()?;
+        Ok(Ping { message })
+    }
+}
+
+/*
+    HELLO [protover]
+    切换协议并返回服务器信息，回复本身已经使用新的协议编码
+    版本不支持时返回 NOPROTO，连接的协议保持不变
+*/
+impl CommandExecutor for Hello {
+    async fn execute(
+        self,
+        _: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if let Some(protover) = self.protover {
+            ctx.protocol = RespVersion::from_protover(protover).ok_or(CommandError::NoProto)?;
+        }
+
+        let mut map = RespMap::new();
+        map.insert("server".to_string(), BulkString::from("redis").into());
+        map.insert(
+            "version".to_string(),
+            BulkString::from(env!("CARGO_PKG_VERSION")).into(),
+        );
+        map.insert(
+            "proto".to_string(),
+            RespFrame::Integer(ctx.protocol.protover()),
+        );
+        map.insert("id".to_string(), RespFrame::Integer(ctx.id as i64));
+        map.insert("mode".to_string(), BulkString::from("standalone").into());
+        map.insert("role".to_string(), BulkString::from("master").into());
+        map.insert("modules".to_string(), RespArray::new([]).into());
+        Ok(map.into())
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let protover = match value.len() {
+            1 => None,
             2 => {
-                let (message, _) = extract_and_validate_args(value, PING, command_len - 1)?;
-                Ok(Ping { message })
+                let mut args = extract_args(value, 1)?.into_iter();
+                let protover = args
+                    .next()
+                    .ok_or_else(|| CommandError::InvalidArgument("Missing protover".to_string()))?;
+                Some(i64::try_from(protover).map_err(|_| {
+                    CommandError::InvalidArgument(
+                        "Protocol version is not an integer or out of range".to_string(),
+                    )
+                })?)
             }
-            _ => Err(CommandError::InvalidArgument(
-                "wrong number of arguments for 'ping' command".to_string(),
-            )),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "HELLO only supports the protover argument".to_string(),
+                ))
+            }
+        };
+
+        Ok(Hello { protover })
+    }
+}
+
+/*
+    AUTH [username] password
+    与 redis 一样只有 default 用户，requirepass 为空时 default 用户不需要密码：
+    - 只给出密码时返回错误，提示没有配置密码
+    - 给出 default 用户名时任意密码都能通过
+    密码错误时连接保持原来的认证状态
+*/
+impl CommandExecutor for Auth {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let requirepass = backend.config().requirepass;
+        if requirepass.is_empty() && self.username.is_none() {
+            return Err(CommandError::InvalidArgument(
+                "AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?"
+                    .to_string(),
+            ));
         }
+        let username = self.username.as_deref().unwrap_or("default");
+        if username != "default" || (!requirepass.is_empty() && self.password != requirepass) {
+            return Err(CommandError::WrongPass);
+        }
+        ctx.authenticated = true;
+        Ok(RESP_OK.clone())
+    }
+}
+
+impl TryFrom<RespArray> for Auth {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let first = args.next_string("password")?;
+        let auth = match args.is_empty() {
+            true => Auth {
+                username: None,
+                password: first,
+            },
+            false => Auth {
+                username: Some(first),
+                password: args.next_string("password")?,
+            },
+        };
+        args.finish()?;
+        Ok(auth)
     }
 }
 
@@ -97,16 +207,106 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_echo_command() -> Result<()> {
+    #[tokio::test]
+    async fn test_echo_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Echo {
             message: "hello".to_string(),
         };
 
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
         assert_eq!(result, BulkString::new("hello").into());
 
         Ok(())
     }
+
+    #[test]
+    fn test_hello_try_from() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n$1\r\n3\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Hello = frame.try_into()?;
+        assert_eq!(result.protover, Some(3));
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\nhello\r\n$1\r\nx\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        assert!(Hello::try_from(frame).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_negotiate() {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::new(1);
+        let cmd = Hello { protover: Some(3) };
+        let RespFrame::Map(map) = cmd.execute(&backend, &mut ctx).await.unwrap() else {
+            panic!("HELLO should reply with a map");
+        };
+        assert_eq!(ctx.protocol, RespVersion::Resp3);
+        assert_eq!(map.get("proto"), Some(&RespFrame::Integer(3)));
+        assert_eq!(map.get("id"), Some(&RespFrame::Integer(1)));
+
+        // 不支持的版本不改变当前协议，并返回 NOPROTO 错误
+        let cmd = Hello { protover: Some(4) };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ctx).await,
+            Err(CommandError::NoProto)
+        ));
+        assert_eq!(ctx.protocol, RespVersion::Resp3);
+    }
+
+    #[tokio::test]
+    async fn test_auth() {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::new(1);
+        let auth = |username: Option<&str>, password: &str| Auth {
+            username: username.map(str::to_string),
+            password: password.to_string(),
+        };
+
+        // 没有设置 requirepass
+        let err = auth(None, "pass")
+            .execute(&backend, &mut ctx)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("AUTH <password> called without"));
+        assert!(auth(Some("default"), "any")
+            .execute(&backend, &mut ctx)
+            .await
+            .is_ok());
+
+        backend.set_config("requirepass", "secret").unwrap();
+        ctx.authenticated = false;
+        for (username, password) in [(None, "wrong"), (Some("other"), "secret")] {
+            assert!(matches!(
+                auth(username, password).execute(&backend, &mut ctx).await,
+                Err(CommandError::WrongPass)
+            ));
+            assert!(!ctx.authenticated);
+        }
+        assert_eq!(
+            auth(None, "secret")
+                .execute(&backend, &mut ctx)
+                .await
+                .unwrap(),
+            RESP_OK.clone()
+        );
+        assert!(ctx.authenticated);
+    }
+
+    #[test]
+    fn test_auth_try_from() -> Result<()> {
+        let frame = RespArray::try_from(crate::resp!(["auth", "default", "secret"]))?;
+        let auth = Auth::try_from(frame)?;
+        assert_eq!(auth.username.as_deref(), Some("default"));
+        assert_eq!(auth.password, "secret");
+        Ok(())
+    }
 }
@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::{RespVersion, SubscriberQueue};
+
+use super::{Command, UpstreamConnection};
+
+// 每个连接独立的状态，执行命令时与 backend 一起传入
+// SELECT / AUTH / HELLO / CLIENT / SUBSCRIBE / MULTI 等命令通过它读取和修改连接状态
+#[derive(Debug, Default)]
+pub struct ConnectionContext {
+    pub id: u64,
+    pub name: Option<String>,
+    pub db: usize,
+    pub authenticated: bool,
+    pub protocol: RespVersion,
+    pub channels: BTreeSet<String>,
+    pub patterns: BTreeSet<String>,
+    // 第一次 SUBSCRIBE 时创建，连接从这里取出消息写给客户端
+    pub subscription: Option<Arc<SubscriberQueue>>,
+    pub monitor: bool,
+    // MULTI 之后排队等待 EXEC 的命令，None 表示不在事务中
+    pub multi: Option<Vec<Command>>,
+    // 转发没有实现的命令时建立的到 upstream 的连接
+    pub upstream: Option<UpstreamConnection>,
+    // 读穿 / 写穿缓存建立的到源站的连接
+    pub origin: Option<UpstreamConnection>,
+}
+
+impl ConnectionContext {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            authenticated: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn in_multi(&self) -> bool {
+        self.multi.is_some()
+    }
+
+    // 订阅了频道或模式之后，连接进入订阅模式
+    pub fn is_subscribed(&self) -> bool {
+        !self.channels.is_empty() || !self.patterns.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_context() {
+        let mut ctx = ConnectionContext::new(7);
+        assert_eq!(ctx.id, 7);
+        assert_eq!(ctx.protocol, RespVersion::Resp2);
+        assert!(ctx.authenticated);
+        assert!(!ctx.in_multi());
+        assert!(!ctx.is_subscribed());
+
+        ctx.patterns.insert("news.*".to_string());
+        assert!(ctx.is_subscribed());
+        ctx.multi = Some(Vec::new());
+        assert!(ctx.in_multi());
+    }
+}
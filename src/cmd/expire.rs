@@ -0,0 +1,397 @@
+// 实现 expire、ttl、pttl、persist 等与过期时间相关的命令
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{Backend, ExpireCondition, RespArray, RespFrame};
+
+use super::{
+    extract_args, single_key, syntax_error, ArgParser, CommandError, CommandExecutor,
+    ConnectionContext, Expire, ExpireAt, PExpire, PExpireAt, Persist, Pttl, Ttl,
+};
+
+/*
+    四个 EXPIRE 命令都先换算成距离现在的毫秒数，再转换为 Instant 交给 backend：
+    - 与 redis 一样，过期时间已经过去（包括非正数的相对时间）时直接删除 key
+    - 换算溢出或者超出 Instant 能表示的范围时返回 invalid expire time
+    NX / XX / GT / LT 与过期时间的比较由 backend 在同一把锁内完成
+*/
+fn expire_in(
+    backend: &Backend,
+    key: &str,
+    millis: i128,
+    condition: ExpireCondition,
+    command: &str,
+) -> Result<RespFrame, CommandError> {
+    let at = deadline(millis, command)?;
+    Ok(RespFrame::Integer(
+        backend.expire_at_if(key, at, condition) as i64
+    ))
+}
+
+// 距离现在 millis 毫秒的时间点，非正数返回当前时间
+pub(super) fn deadline(millis: i128, command: &str) -> Result<Instant, CommandError> {
+    let now = Instant::now();
+    match millis {
+        millis if millis <= 0 => Some(now),
+        millis if millis > i64::MAX as i128 => None,
+        millis => now.checked_add(Duration::from_millis(millis as u64)),
+    }
+    .ok_or_else(|| invalid_expire_time(command))
+}
+
+pub(super) fn unix_millis() -> i128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i128)
+}
+
+impl CommandExecutor for Expire {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let millis = self.seconds as i128 * 1000;
+        expire_in(backend, &self.key, millis, self.condition, "expire")
+    }
+}
+
+impl CommandExecutor for PExpire {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let millis = self.milliseconds as i128;
+        expire_in(backend, &self.key, millis, self.condition, "pexpire")
+    }
+}
+
+impl CommandExecutor for ExpireAt {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let millis = self.timestamp as i128 * 1000 - unix_millis();
+        expire_in(backend, &self.key, millis, self.condition, "expireat")
+    }
+}
+
+impl CommandExecutor for PExpireAt {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let millis = self.timestamp as i128 - unix_millis();
+        expire_in(backend, &self.key, millis, self.condition, "pexpireat")
+    }
+}
+
+// -2 表示 key 不存在，-1 表示没有设置过期时间
+fn remaining(backend: &Backend, key: &str, unit: fn(Duration) -> i64) -> RespFrame {
+    let ttl = match backend.expiry(key) {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(ttl)) => unit(ttl),
+    };
+    RespFrame::Integer(ttl)
+}
+
+impl CommandExecutor for Ttl {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        // 与 redis 一样四舍五入到秒
+        Ok(remaining(backend, &self.key, |ttl| {
+            ((ttl.as_millis() + 500) / 1000) as i64
+        }))
+    }
+}
+
+impl CommandExecutor for Pttl {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        Ok(remaining(backend, &self.key, |ttl| ttl.as_millis() as i64))
+    }
+}
+
+impl CommandExecutor for Persist {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        Ok(RespFrame::Integer(backend.persist(&self.key) as i64))
+    }
+}
+
+pub(super) fn invalid_expire_time(command: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("invalid expire time in '{}' command", command))
+}
+
+// key time [NX | XX | GT | LT]，选项的组合规则与 redis 相同：NX 不能与其他选项同时使用，GT 和 LT 互斥
+fn parse_expire(value: RespArray) -> Result<(String, i64, ExpireCondition), CommandError> {
+    let mut args = ArgParser::new(extract_args(value, 1)?);
+    let key = args.next_string("key")?;
+    let time = args.value::<i64>("time")?;
+    let mut condition = ExpireCondition::default();
+    while let Some(keyword) = args.next_keyword()? {
+        match keyword.as_str() {
+            "NX" => condition.nx = true,
+            "XX" => condition.xx = true,
+            "GT" => condition.gt = true,
+            "LT" => condition.lt = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    if condition.nx && (condition.xx || condition.gt || condition.lt) {
+        return Err(CommandError::InvalidArgument(
+            "NX and XX, GT or LT options at the same time are not compatible".to_string(),
+        ));
+    }
+    if condition.gt && condition.lt {
+        return Err(CommandError::InvalidArgument(
+            "GT and LT options at the same time are not compatible".to_string(),
+        ));
+    }
+    Ok((key, time, condition))
+}
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, seconds, condition) = parse_expire(value)?;
+        Ok(Expire {
+            key,
+            seconds,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PExpire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds, condition) = parse_expire(value)?;
+        Ok(PExpire {
+            key,
+            milliseconds,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition) = parse_expire(value)?;
+        Ok(ExpireAt {
+            key,
+            timestamp,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition) = parse_expire(value)?;
+        Ok(PExpireAt {
+            key,
+            timestamp,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Ttl {
+            key: single_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Pttl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Pttl {
+            key: single_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Persist {
+            key: single_key(value)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, BulkString};
+
+    async fn execute(backend: &Backend, frame: RespFrame) -> anyhow::Result<RespFrame> {
+        let cmd = Command::try_from(RespArray::try_from(frame)?)?;
+        Ok(cmd
+            .execute(backend, &mut ConnectionContext::default())
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn test_expire_ttl_persist() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let ttl = crate::resp!(["ttl", "k"]);
+        assert_eq!(
+            execute(&backend, ttl.clone()).await?,
+            RespFrame::Integer(-2)
+        );
+        assert_eq!(
+            execute(&backend, crate::resp!(["expire", "k", "10"])).await?,
+            RespFrame::Integer(0)
+        );
+
+        backend.set("k".to_string(), BulkString::from("v").into());
+        assert_eq!(
+            execute(&backend, ttl.clone()).await?,
+            RespFrame::Integer(-1)
+        );
+        assert_eq!(
+            execute(&backend, crate::resp!(["expire", "k", "10"])).await?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            execute(&backend, ttl.clone()).await?,
+            RespFrame::Integer(10)
+        );
+        let RespFrame::Integer(ms) = execute(&backend, crate::resp!(["pttl", "k"])).await? else {
+            panic!("expected integer");
+        };
+        assert!(ms > 9000 && ms <= 10000);
+
+        assert_eq!(
+            execute(&backend, crate::resp!(["persist", "k"])).await?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            execute(&backend, ttl.clone()).await?,
+            RespFrame::Integer(-1)
+        );
+
+        // 非正数的过期时间删除 key，GET 不会返回旧值
+        assert_eq!(
+            execute(&backend, crate::resp!(["expire", "k", "-1"])).await?,
+            RespFrame::Integer(1)
+        );
+        assert!(execute(&backend, crate::resp!(["get", "k"]))
+            .await?
+            .is_null());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expire_variants() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), BulkString::from("v").into());
+        let pttl = || async {
+            match execute(&backend, crate::resp!(["pttl", "k"])).await? {
+                RespFrame::Integer(ms) => Ok::<_, anyhow::Error>(ms),
+                frame => panic!("unexpected {:?}", frame),
+            }
+        };
+
+        execute(&backend, crate::resp!(["pexpire", "k", "5000"])).await?;
+        let ms = pttl().await?;
+        assert!(ms > 4000 && ms <= 5000);
+
+        let at = (unix_millis() / 1000 + 100).to_string();
+        execute(&backend, crate::resp!(["expireat", "k", at.as_str()])).await?;
+        let ms = pttl().await?;
+        assert!(ms > 98000 && ms <= 100000);
+
+        // GT 只延长，LT 只缩短
+        let at = (unix_millis() + 50_000).to_string();
+        let reply = execute(
+            &backend,
+            crate::resp!(["pexpireat", "k", at.as_str(), "gt"]),
+        )
+        .await?;
+        assert_eq!(reply, RespFrame::Integer(0));
+        let reply = execute(
+            &backend,
+            crate::resp!(["pexpireat", "k", at.as_str(), "lt"]),
+        )
+        .await?;
+        assert_eq!(reply, RespFrame::Integer(1));
+        assert!(pttl().await? <= 50000);
+        let reply = execute(&backend, crate::resp!(["expire", "k", "100", "nx"])).await?;
+        assert_eq!(reply, RespFrame::Integer(0));
+
+        // 过去的时间点删除 key
+        let reply = execute(&backend, crate::resp!(["expireat", "k", "1"])).await?;
+        assert_eq!(reply, RespFrame::Integer(1));
+        assert_eq!(pttl().await?, -2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_options() -> anyhow::Result<()> {
+        for args in [
+            crate::resp!(["expire", "k", "1", "nx", "xx"]),
+            crate::resp!(["pexpire", "k", "1", "gt", "lt"]),
+            crate::resp!(["expireat", "k", "1", "ex"]),
+        ] {
+            assert!(Command::try_from(RespArray::try_from(args)?).is_err());
+        }
+        let cmd = Command::try_from(RespArray::try_from(crate::resp!([
+            "pexpireat",
+            "k",
+            "1",
+            "xx",
+            "GT"
+        ]))?)?;
+        let Command::PExpireAt(cmd) = cmd else {
+            panic!("expected pexpireat");
+        };
+        assert!(cmd.condition.xx && cmd.condition.gt);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expire_invalid_time() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        backend.set("k".to_string(), BulkString::from("v").into());
+        let cmd = Command::try_from(RespArray::try_from(crate::resp!([
+            "expire",
+            "k",
+            "9223372036854775807"
+        ]))?)?;
+        assert!(cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await
+            .is_err());
+        assert!(
+            Command::try_from(RespArray::try_from(crate::resp!(["expire", "k", "x"]))?).is_err()
+        );
+        Ok(())
+    }
+}
@@ -1,109 +1,104 @@
-use crate::{backend::Backend, BulkString, RespArray, RespFrame, RespNull};
+use crate::{backend::Backend, BulkString, RespArray, RespFrame, RespMap, Value, ValueKind};
 
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, HGet, HGetAll, HMGet, HSet,
-    TryIntoBulkString, RESP_OK,
+    cache, check_locked_type, check_type, extract_args, matches_pattern, parse_cursor,
+    parse_scan_options, scan_reply, single_key, ArgParser, CommandError, CommandExecutor,
+    ConnectionContext, HGet, HGetAll, HMGet, HScan, HSet, RESP_OK,
 };
 
 impl CommandExecutor for HGet {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::Hash)?;
+        // 配置了缓存源站时从源站读取并回填整个 hash
+        if !backend.exists(&self.key) {
+            cache::read_through_hash(backend, &self.key, ctx).await?;
         }
+        Ok(backend
+            .hget(&self.key, &self.field)
+            .map(|value| (*value).clone())
+            .into())
     }
 }
 
 impl CommandExecutor for HGetAll {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        let hmap = backend.hgetall(&self.key);
-
-        match hmap {
-            Some(hmap) => {
-                // let mut map = RespMap::new();
-                let mut data = Vec::with_capacity(hmap.len());
-
-                hmap.iter().for_each(|v| {
-                    let key = v.key().to_owned();
-
-                    data.push((key, v.value().clone()));
-                });
-                if self.sort {
-                    data.sort_by(|a, b| a.0.cmp(&b.0));
-                }
-
-                RespArray::new(
-                    data.into_iter()
-                        .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
-                        .collect::<Vec<RespFrame>>(),
-                )
-                .into()
-            }
-            None => RespArray::new([]).into(),
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if !backend.exists(&self.key) {
+            cache::read_through_hash(backend, &self.key, ctx).await?;
         }
+        let hmap = match backend.get_value(&self.key) {
+            Some(Value::Hash(hmap)) => hmap,
+            Some(_) => return Err(CommandError::WrongType),
+            None => Default::default(),
+        };
+
+        // RESP3 返回 map，RESP2 连接由网络层展开成 key/value 交替的数组
+        let mut map = RespMap::new();
+        hmap.iter().for_each(|(key, value)| {
+            map.insert(key.clone(), (**value).clone());
+        });
+        Ok(map.into())
     }
 }
 
 impl CommandExecutor for HSet {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
-        RESP_OK.clone()
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let mut guard = backend.lock_key(&self.key);
+        check_locked_type(&guard, &self.key, ValueKind::Hash)?;
+        guard.hset(&self.key, &self.field, self.value);
+        Ok(RESP_OK.clone())
     }
 }
 
 impl CommandExecutor for HMGet {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        if let Some(hmap) = backend.hmget(&self.key, &self.fields) {
-            let data = self
-                .fields
-                .iter()
-                .map(|field| {
-                    hmap.get(field)
-                        .map(|v| v.value().clone())
-                        .unwrap_or_else(|| RespFrame::Null(RespNull))
-                })
-                .collect::<Vec<_>>();
-
-            RespArray::new(data).into()
-        } else {
-            // 这对 key 不存在的情况，返回一个 fields 大小的空数组
-            let data = vec![RespFrame::Null(RespNull); self.fields.len()];
-            RespArray::new(data).into()
-        }
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::Hash)?;
+        // key 不存在时每个 field 都返回 null
+        let values = backend
+            .hmget(&self.key, &self.fields)
+            .unwrap_or_else(|| vec![None; self.fields.len()]);
+        Ok(RespArray::new(
+            values
+                .into_iter()
+                .map(|value| RespFrame::from(value.map(|v| (*v).clone())))
+                .collect::<Vec<_>>(),
+        )
+        .into())
     }
 }
 
 impl TryFrom<RespArray> for HGet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hget"], 2)?;
-
-        let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0)?,
-                field: String::from_utf8(field.0)?,
-            }),
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key or field".to_string(),
-            )),
-        }
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let field = args.next_string("field")?;
+        args.finish()?;
+        Ok(HGet { key, field })
     }
 }
 
 impl TryFrom<RespArray> for HGetAll {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hgetall"], 1)?;
-
-        let mut args = extract_args(value, 1)?.into_iter();
-        match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0)?,
-                sort: false,
-            }),
-            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
-        }
+        Ok(HGetAll {
+            key: single_key(value)?,
+        })
     }
 }
 
@@ -111,26 +106,9 @@ impl TryFrom<RespArray> for HMGet {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        match value.len() > 2 {
-            true => validate_command(&value, &["hmget"], value.len() - 1)?,
-            false => {
-                return Err(CommandError::InvalidArgument(
-                    "wrong number of arguments for 'hmget' command".to_string(),
-                ))
-            }
-        }
-
-        let mut args = extract_args(value, 1)?.into_iter();
-
-        let key = args
-            .next()
-            .ok_or_else(|| CommandError::InvalidArgument("Missing key".to_string()))?
-            .try_into_bulk_string()?;
-
-        let fields = args
-            .map(RespFrame::try_into_bulk_string)
-            .collect::<Result<Vec<String>, Self::Error>>()?;
-
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let fields = args.rest()?;
         Ok(HMGet { key, fields })
     }
 }
@@ -138,21 +116,46 @@ impl TryFrom<RespArray> for HMGet {
 impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], 3)?;
-
-        let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
-                    value,
-                })
-            }
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key, field or value".to_string(),
-            )),
-        }
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let field = args.next_string("field")?;
+        let value = args.next_frame("value")?;
+        args.finish()?;
+        Ok(HSet { key, field, value })
+    }
+}
+
+impl CommandExecutor for HScan {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::Hash)?;
+        let (cursor, fields) = backend.hscan(&self.key, self.cursor, self.count);
+        let items = fields
+            .into_iter()
+            .filter(|(field, _)| matches_pattern(&self.pattern, field))
+            .flat_map(|(field, value)| [BulkString::from(field).into(), (*value).clone()])
+            .collect();
+        Ok(scan_reply(cursor, items))
+    }
+}
+
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let cursor = parse_cursor(&mut args)?;
+        let (pattern, count) = parse_scan_options(&mut args, |_, _| Ok(false))?;
+        Ok(HScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
     }
 }
 
@@ -160,7 +163,7 @@ impl TryFrom<RespArray> for HSet {
 mod tests {
     use crate::{
         cmd::{HGet, HGetAll, HSet},
-        RespDecode,
+        BulkString, RespDecode, RespEncode, RespVersion,
     };
 
     use super::*;
@@ -208,8 +211,8 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_hset_hget_hgetall_commands() -> Result<()> {
+    #[tokio::test]
+    async fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = Backend::new();
 
         let cmd = HSet {
@@ -218,7 +221,9 @@ mod tests {
             value: RespFrame::BulkString(b"world".into()),
         };
 
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = HSet {
@@ -226,33 +231,57 @@ mod tests {
             field: "hello1".to_string(),
             value: RespFrame::BulkString(b"world1".into()),
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut ConnectionContext::default())
+            .await?;
 
         let cmd = HGet {
             key: "map".to_string(),
             field: "hello".to_string(),
         };
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAll {
             key: "map".to_string(),
-            sort: true,
         };
 
-        let result = cmd.execute(&backend);
-        let expected = RespArray::new([
-            BulkString::from("hello").into(),
-            BulkString::from("world").into(),
-            BulkString::from("hello1").into(),
-            BulkString::from("world1").into(),
-        ]);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        let mut expected = RespMap::new();
+        expected.insert("hello".to_string(), BulkString::from("world").into());
+        expected.insert("hello1".to_string(), BulkString::from("world1").into());
         assert_eq!(result, expected.into());
         Ok(())
     }
 
-    #[test]
-    fn test_hmget_command() {
+    #[tokio::test]
+    async fn test_hgetall_empty_and_missing() -> Result<()> {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::default();
+        let hgetall = || HGetAll {
+            key: "h".to_string(),
+        };
+
+        // key 不存在时与 redis 一样返回空的 map，RESP2 下是空数组而不是 null
+        let reply = hgetall().execute(&backend, &mut ctx).await?;
+        assert_eq!(reply, RespMap::new().into());
+        assert_eq!(reply.into_version(RespVersion::Resp2).encode(), b"*0\r\n");
+
+        // 删除最后一个 field 后 key 也被删除，不会留下空的 hash
+        backend.hash("h").set("f", "v");
+        backend.lock_key("h").hdel("h", "f");
+        assert!(!backend.exists("h"));
+        assert_eq!(backend.key_type("h"), None);
+        let reply = hgetall().execute(&backend, &mut ctx).await?;
+        assert_eq!(reply, RespMap::new().into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hmget_command() {
         let backend = Backend::new();
         let cmd = HSet {
             key: "map".to_string(),
@@ -260,26 +289,60 @@ mod tests {
             value: RespFrame::BulkString(b"v1".into()),
         };
 
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut ConnectionContext::default())
+            .await
+            .unwrap();
         let cmd = HSet {
             key: "map".to_string(),
             field: "k2".to_string(),
             value: RespFrame::BulkString(b"v2".into()),
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut ConnectionContext::default())
+            .await
+            .unwrap();
 
         let cmd = HMGet {
             key: "map".to_string(),
             fields: vec!["k1".to_string(), "k2".to_string(), "k3".to_string()],
         };
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result, crate::resp!(["v1", "v2", null]));
+
+        let cmd = HMGet {
+            key: "missing".to_string(),
+            fields: vec!["k1".to_string(), "k2".to_string()],
+        };
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result, crate::resp!([null, null]));
+    }
 
-        let expected = RespArray::new([
-            BulkString::from("v1").into(),
-            BulkString::from("v2").into(),
-            RespNull.into(),
-        ]);
+    #[tokio::test]
+    async fn test_hscan_command() -> Result<()> {
+        let frame = RespArray::try_from(crate::resp!(["hscan", "h", "0"]))?;
+        let cmd: HScan = frame.try_into()?;
+        assert_eq!((cmd.key.as_str(), cmd.cursor, cmd.count), ("h", 0, 10));
+        assert_eq!(cmd.pattern, None);
 
-        assert_eq!(result, expected.into())
+        let backend = Backend::new();
+        backend.hash("h").set("f", "v");
+        let reply = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(
+            reply,
+            RespArray::new([
+                BulkString::from("0").into(),
+                RespArray::new([BulkString::from("f").into(), BulkString::from("v").into()]).into(),
+            ])
+            .into()
+        );
+        Ok(())
     }
 }
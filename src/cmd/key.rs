@@ -0,0 +1,351 @@
+// 实现 object、keys、scan、del 等与 key 本身相关、不区分数据类型的命令
+use crate::{glob_match, Backend, BulkString, RespArray, RespFrame, RespNull, ValueKind};
+
+use super::{
+    cache, extract_args, extract_keys, matches_pattern, parse_cursor, parse_scan_options,
+    scan_reply, ArgParser, CommandError, CommandExecutor, ConnectionContext, Del, Exists, Keys,
+    ObjectEncoding, Scan, Unlink,
+};
+
+impl CommandExecutor for ObjectEncoding {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        match backend.object_encoding(&self.key) {
+            Some(encoding) => Ok(BulkString::from(encoding).into()),
+            None => Ok(RespFrame::Null(RespNull)),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ObjectEncoding {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 2)?);
+        let key = args.next_string("key")?;
+        args.finish()?;
+        Ok(ObjectEncoding { key })
+    }
+}
+
+impl CommandExecutor for Scan {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        // 与 redis 一样 COUNT 限制的是遍历的 key 个数，MATCH 和 TYPE 在取出之后再过滤，因此一批可能为空
+        let (cursor, keys) = backend.scan(self.cursor, self.count);
+        let keys = keys
+            .into_iter()
+            .filter(|key| matches_pattern(&self.pattern, key))
+            .filter(|key| {
+                self.kind
+                    .is_none_or(|kind| backend.key_type(key) == Some(kind))
+            })
+            .map(|key| BulkString::from(key).into())
+            .collect();
+        Ok(scan_reply(cursor, keys))
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let cursor = parse_cursor(&mut args)?;
+        let mut kind = None;
+        let (pattern, count) = parse_scan_options(&mut args, |keyword, args| match keyword {
+            "TYPE" => {
+                kind = Some(parse_kind(&args.value::<String>("TYPE")?)?);
+                Ok(true)
+            }
+            _ => Ok(false),
+        })?;
+        Ok(Scan {
+            cursor,
+            pattern,
+            count,
+            kind,
+        })
+    }
+}
+
+impl CommandExecutor for Keys {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let keys = backend
+            .keys(None, |key| {
+                glob_match(self.pattern.as_bytes(), key.as_bytes(), false)
+            })
+            .into_iter()
+            .map(|key| BulkString::from(key).into())
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(keys).into())
+    }
+}
+
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let pattern = args.next_string("pattern")?;
+        args.finish()?;
+        Ok(Keys { pattern })
+    }
+}
+
+impl CommandExecutor for Del {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let keys = self.keys.iter().map(String::as_str).collect::<Vec<_>>();
+        Ok(RespFrame::Integer(backend.del_many(&keys) as i64))
+    }
+}
+
+impl CommandExecutor for Unlink {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        Del { keys: self.keys }.execute(backend, ctx).await
+    }
+}
+
+impl CommandExecutor for Exists {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if !cache::cache_enabled(backend) {
+            let keys = self.keys.iter().map(String::as_str).collect::<Vec<_>>();
+            return Ok(RespFrame::Integer(backend.exists_many(&keys) as i64));
+        }
+        // 缓存模式下本地不存在的 key 询问源站，重复的 key 与 redis 一样重复计数
+        let mut count = 0;
+        for key in &self.keys {
+            if backend.exists(key) || cache::read_through_any(backend, key, ctx).await? {
+                count += 1;
+            }
+        }
+        Ok(RespFrame::Integer(count))
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Del {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unlink {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Exists {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+fn parse_kind(name: &str) -> Result<ValueKind, CommandError> {
+    [ValueKind::String, ValueKind::Hash, ValueKind::Set]
+        .into_iter()
+        .find(|kind| kind.as_str().eq_ignore_ascii_case(name))
+        .ok_or_else(|| CommandError::InvalidArgument(format!("unknown type name '{}'", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cmd::Command, RespDecode};
+
+    use super::*;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_object_encoding_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nobject\r\n$8\r\nencoding\r\n$3\r\nkey\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ObjectEncoding = frame.try_into()?;
+        assert_eq!(result.key, "key");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_object_encoding_command() {
+        let backend = Backend::new();
+        backend.set("counter".to_string(), BulkString::from("100").into());
+
+        let cmd = ObjectEncoding {
+            key: "counter".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            BulkString::from("int").into()
+        );
+
+        let cmd = ObjectEncoding {
+            key: "missing".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RespFrame::Null(RespNull)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_command() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$4\r\nscan\r\n$1\r\n0\r\n$5\r\ncount\r\n$1\r\n2\r\n$4\r\ntype\r\n$4\r\nHASH\r\n");
+        let cmd: Scan = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.cursor, 0);
+        assert_eq!(cmd.count, 2);
+        assert_eq!(cmd.kind, Some(ValueKind::Hash));
+
+        let backend = Backend::new();
+        for i in 0..5 {
+            backend.set_string(format!("k{}", i), "v");
+        }
+        backend.hash("h").set("f", "v");
+        backend.hash("hash").set("f", "v");
+
+        let (mut cursor, mut keys) = (0, Vec::new());
+        loop {
+            let cmd = Scan {
+                cursor,
+                pattern: Some("?".to_string()),
+                count: 2,
+                kind: Some(ValueKind::Hash),
+            };
+            let reply = cmd
+                .execute(&backend, &mut ConnectionContext::default())
+                .await?;
+            let RespFrame::Array(reply) = reply else {
+                panic!("expected an array reply");
+            };
+            let mut reply = reply.0.into_iter();
+            let (Some(next), Some(RespFrame::Array(batch))) = (reply.next(), reply.next()) else {
+                panic!("unexpected scan reply");
+            };
+            for key in batch.0 {
+                keys.push(String::try_from(key)?);
+            }
+            cursor = String::try_from(next)?.parse()?;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(keys, vec!["h"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_invalid_arguments() {
+        let parse = |args: &[&str]| {
+            Scan::try_from(RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::from(*arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            ))
+        };
+        assert!(parse(&["scan", "0"]).is_ok());
+        assert!(parse(&["scan", "-1"]).is_err());
+        assert!(parse(&["scan", "0", "count", "0"]).is_err());
+        assert!(parse(&["scan", "0", "type", "list"]).is_err());
+        assert!(parse(&["scan", "0", "nosuch"]).is_err());
+        assert!(parse(&["scan", "0", "match"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keys_command() -> Result<()> {
+        let backend = Backend::new();
+        for key in ["user:1", "user:2", "order:1"] {
+            backend.set_string(key, "v");
+        }
+
+        let cmd: Keys = RespArray::try_from(crate::resp!(["keys", "user:*"]))?.try_into()?;
+        let RespFrame::Array(keys) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?
+        else {
+            panic!("expected an array reply");
+        };
+        let mut keys = keys
+            .0
+            .into_iter()
+            .map(String::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        keys.sort();
+        assert_eq!(keys, vec!["user:1", "user:2"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_del_exists_unlink_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_string("a", "v");
+        backend.hash("h").set("f", "v");
+        backend.set_of("s").add("m");
+        let mut ctx = ConnectionContext::default();
+
+        let cmd: Exists =
+            RespArray::try_from(crate::resp!(["EXISTS", "a", "a", "h", "x"]))?.try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut ctx).await?,
+            RespFrame::Integer(3)
+        );
+
+        let cmd: Del = RespArray::try_from(crate::resp!(["del", "a", "h", "x"]))?.try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut ctx).await?,
+            RespFrame::Integer(2)
+        );
+
+        let cmd: Unlink = RespArray::try_from(crate::resp!(["unlink", "s"]))?.try_into()?;
+        assert_eq!(
+            cmd.execute(&backend, &mut ctx).await?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(backend.exists_many(&["a", "h", "s"]), 0);
+
+        // 参数个数由命令表检查
+        assert!(Command::try_from(RespArray::try_from(crate::resp!(["del"]))?).is_err());
+        assert!(Command::try_from(RespArray::try_from(crate::resp!(["exists"]))?).is_err());
+        Ok(())
+    }
+}
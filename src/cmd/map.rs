@@ -1,47 +1,125 @@
-use crate::{backend::Backend, RespArray, RespFrame, RespNull};
+use std::time::Instant;
 
-use super::{extract_args, validate_command, CommandError, CommandExecutor, Get, Set, RESP_OK};
+use crate::{backend::Backend, RespArray, RespFrame, RespNull, ValueKind};
+
+use super::{
+    cache, check_locked_type, check_type,
+    expire::{deadline, invalid_expire_time, unix_millis},
+    extract_args, extract_keys, single_key, syntax_error, ArgParser, CommandError, CommandExecutor,
+    ConnectionContext, Get, MGet, MSet, Set, SetCondition, SetExpiry, RESP_OK,
+};
 
 impl CommandExecutor for Get {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        match backend.get(&self.key) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::String)?;
+        if let Some(value) = backend.get(&self.key) {
+            return Ok((*value).clone());
+        }
+        // 配置了缓存源站时从源站读取并回填
+        match cache::read_through(backend, &self.key, ctx).await? {
+            Some(value) => Ok(value),
+            None => Ok(RespFrame::Null(RespNull)),
         }
     }
 }
 
 impl CommandExecutor for Set {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        backend.set(self.key, self.value);
-        RESP_OK.clone()
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if self.condition.is_none() && !self.get && self.expiry.is_none() {
+            backend.set(self.key, self.value);
+            return Ok(RESP_OK.clone());
+        }
+        let keep_ttl = self.expiry == Some(SetExpiry::KeepTtl);
+        let at = self.expiry.map(set_deadline).transpose()?.flatten();
+
+        // 检查旧值、写入以及设置过期时间需要在同一把锁内完成
+        let mut guard = backend.lock_key(&self.key);
+        if self.get {
+            check_locked_type(&guard, &self.key, ValueKind::String)?;
+        }
+        let old = guard.get(&self.key);
+        // NX / XX 按 key 是否存在判断，不区分类型
+        let exists = guard.key_type(&self.key).is_some();
+        let apply = match self.condition {
+            Some(SetCondition::Nx) => !exists,
+            Some(SetCondition::Xx) => exists,
+            None => true,
+        };
+        if apply {
+            guard.set(&self.key, self.value);
+            if !keep_ttl {
+                guard.set_expiry(&self.key, at);
+            }
+        }
+
+        match (self.get, apply) {
+            (true, _) => Ok(old.map(|value| (*value).clone()).into()),
+            (false, true) => Ok(RESP_OK.clone()),
+            (false, false) => Ok(RespFrame::Null(RespNull)),
+        }
     }
 }
 
-// 通用函数，用于验证命令并提取参数
-pub fn extract_and_validate_args(
-    value: RespArray,
-    command: &'static str,
-    expected_args: usize,
-) -> Result<(String, Option<RespFrame>), CommandError> {
-    validate_command(&value, &[command], expected_args)?;
-
-    let mut args = extract_args(value, 1)?.into_iter();
-    let key = match args.next() {
-        Some(RespFrame::BulkString(key)) => String::from_utf8(key.0)?,
-        _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+// 与 redis 一样，过期时间点已经过去时写入的 key 立即过期
+fn set_deadline(expiry: SetExpiry) -> Result<Option<Instant>, CommandError> {
+    let millis = match expiry {
+        SetExpiry::Ex(seconds) => seconds as i128 * 1000,
+        SetExpiry::Px(millis) => millis as i128,
+        SetExpiry::ExAt(timestamp) => timestamp as i128 * 1000 - unix_millis(),
+        SetExpiry::PxAt(timestamp) => timestamp as i128 - unix_millis(),
+        SetExpiry::KeepTtl => return Ok(None),
     };
+    deadline(millis, "set").map(Some)
+}
 
-    let value = args.next();
-    Ok((key, value))
+impl CommandExecutor for MGet {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        // 不是字符串的 key 与不存在的 key 一样返回 null，不报 WRONGTYPE
+        let keys = self.keys.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut values = Vec::with_capacity(keys.len());
+        for (key, value) in keys.iter().zip(backend.mget(&keys)) {
+            let value = match value {
+                Some(value) => Some((*value).clone()),
+                // 本地不存在的 key 从缓存源站读取
+                None if !backend.exists(key) => cache::read_through(backend, key, ctx).await?,
+                None => None,
+            };
+            values.push(RespFrame::from(value));
+        }
+        Ok(RespArray::new(values).into())
+    }
+}
+
+impl CommandExecutor for MSet {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        backend.mset(self.pairs);
+        Ok(RESP_OK.clone())
+    }
 }
 
 // Get命令的TryFrom实现
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        let (key, _) = extract_and_validate_args(value, "get", 1)?;
-        Ok(Get { key })
+        Ok(Get {
+            key: single_key(value)?,
+        })
     }
 }
 
@@ -49,17 +127,80 @@ impl TryFrom<RespArray> for Get {
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        let (key, value) = extract_and_validate_args(value, "set", 2)?;
-        match value {
-            Some(value) => Ok(Set { key, value }),
-            _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let value = args.next_frame("value")?;
+
+        let (mut condition, mut get, mut expiry) = (None, false, None);
+        while let Some(keyword) = args.next_keyword()? {
+            match keyword.as_str() {
+                "NX" | "XX" if condition.is_some() => return Err(syntax_error()),
+                "NX" => condition = Some(SetCondition::Nx),
+                "XX" => condition = Some(SetCondition::Xx),
+                "GET" => get = true,
+                "EX" | "PX" | "EXAT" | "PXAT" | "KEEPTTL" if expiry.is_some() => {
+                    return Err(syntax_error())
+                }
+                "EX" => expiry = Some(SetExpiry::Ex(expire_value(&mut args, "EX")?)),
+                "PX" => expiry = Some(SetExpiry::Px(expire_value(&mut args, "PX")?)),
+                "EXAT" => expiry = Some(SetExpiry::ExAt(expire_value(&mut args, "EXAT")?)),
+                "PXAT" => expiry = Some(SetExpiry::PxAt(expire_value(&mut args, "PXAT")?)),
+                "KEEPTTL" => expiry = Some(SetExpiry::KeepTtl),
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(Set {
+            key,
+            value,
+            condition,
+            get,
+            expiry,
+        })
+    }
+}
+
+// 与 redis 一样过期时间必须是正数
+fn expire_value(args: &mut ArgParser, option: &str) -> Result<i64, CommandError> {
+    match args.value::<i64>(option)? {
+        time if time > 0 => Ok(time),
+        _ => Err(invalid_expire_time("set")),
+    }
+}
+
+impl TryFrom<RespArray> for MGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(MGet {
+            keys: extract_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for MSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // arity 只能检查最少的参数个数，key 和 value 必须成对出现
+        if value.len().is_multiple_of(2) {
+            return Err(CommandError::WrongArity("mset".to_string()));
         }
+
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let mut pairs = Vec::with_capacity(args.len() / 2);
+        while !args.is_empty() {
+            let key = args.next_string("key")?;
+            let value = args.next_frame("value")?;
+            pairs.push((key, value));
+        }
+        Ok(MSet { pairs })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::RespDecode;
+    use std::time::Duration;
+
+    use crate::{cmd::Command, BulkString, RespDecode};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -93,21 +234,237 @@ mod tests {
     }
 
     #[test]
-    fn test_set_get_command() -> Result<()> {
+    fn test_set_options_from_resp_array() -> Result<()> {
+        let frame = RespArray::try_from(crate::resp!(["SET", "k", "v", "nx", "GET"]))?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.condition, Some(SetCondition::Nx));
+        assert!(result.get);
+
+        let frame = RespArray::try_from(crate::resp!(["set", "k", "v", "nx", "xx"]))?;
+        assert!(Set::try_from(frame).is_err());
+
+        let frame = RespArray::try_from(crate::resp!(["set", "k", "v", "px"]))?;
+        assert!(Set::try_from(frame).is_err());
+
+        let frame = RespArray::try_from(crate::resp!(["set", "k", "v", "EX", "10", "xx"]))?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.expiry, Some(SetExpiry::Ex(10)));
+        assert_eq!(result.condition, Some(SetCondition::Xx));
+
+        let frame = RespArray::try_from(crate::resp!(["set", "k", "v", "keepttl"]))?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.expiry, Some(SetExpiry::KeepTtl));
+
+        for args in [["ex", "10", "px", "5"], ["ex", "10", "keepttl", "nx"]] {
+            let mut request = vec!["set", "k", "v"];
+            request.extend(args);
+            let frame = RespArray::new(
+                request
+                    .into_iter()
+                    .map(|arg| BulkString::from(arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            );
+            assert!(Set::try_from(frame).is_err());
+        }
+
+        let frame = RespArray::try_from(crate::resp!(["set", "k", "v", "ex", "0"]))?;
+        assert_eq!(
+            Set::try_from(frame).unwrap_err().to_string(),
+            "invalid expire time in 'set' command"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_expiry_command() -> Result<()> {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::default();
+        let set = |expiry, condition| Set {
+            key: "k".to_string(),
+            value: BulkString::from("v").into(),
+            condition,
+            get: false,
+            expiry,
+        };
+
+        set(Some(SetExpiry::Ex(100)), None)
+            .execute(&backend, &mut ctx)
+            .await?;
+        let ttl = backend.ttl("k").unwrap();
+        assert!(ttl > Duration::from_secs(99) && ttl <= Duration::from_secs(100));
+
+        // KEEPTTL 保留原来的过期时间，没有过期选项时清除
+        set(Some(SetExpiry::KeepTtl), Some(SetCondition::Xx))
+            .execute(&backend, &mut ctx)
+            .await?;
+        assert!(backend.ttl("k").is_some());
+        set(None, Some(SetCondition::Xx))
+            .execute(&backend, &mut ctx)
+            .await?;
+        assert_eq!(backend.ttl("k"), None);
+
+        // NX 条件不满足时不修改过期时间
+        let reply = set(Some(SetExpiry::Px(50)), Some(SetCondition::Nx))
+            .execute(&backend, &mut ctx)
+            .await?;
+        assert_eq!(reply, RespNull.into());
+        assert_eq!(backend.ttl("k"), None);
+
+        // 已经过去的时间点写入后立即过期
+        set(Some(SetExpiry::PxAt(1)), None)
+            .execute(&backend, &mut ctx)
+            .await?;
+        assert_eq!(backend.get("k"), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_condition_command() {
+        let backend = Backend::new();
+        let set = |value: &str, condition, get| Set {
+            key: "k".to_string(),
+            value: BulkString::from(value).into(),
+            condition,
+            get,
+            expiry: None,
+        };
+
+        assert_eq!(
+            set("v1", Some(SetCondition::Xx), false)
+                .execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RespNull.into()
+        );
+        assert_eq!(
+            set("v1", Some(SetCondition::Nx), false)
+                .execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            set("v2", Some(SetCondition::Nx), true)
+                .execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            BulkString::from("v1").into()
+        );
+        assert_eq!(
+            set("v3", None, true)
+                .execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            BulkString::from("v1").into()
+        );
+        assert_eq!(
+            backend.get("k").map(|v| (*v).clone()),
+            Some(BulkString::from("v3").into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_condition_on_other_types() {
+        let backend = Backend::new();
+        backend.hset(
+            "h".to_string(),
+            "f".to_string(),
+            BulkString::from("v").into(),
+        );
+        backend.sadd("s", ["a"]);
+        let set = |key: &str, condition, get| Set {
+            key: key.to_string(),
+            value: BulkString::from("v").into(),
+            condition,
+            get,
+            expiry: None,
+        };
+
+        // hash / set 也算已存在
+        for key in ["h", "s"] {
+            assert_eq!(
+                set(key, Some(SetCondition::Nx), false)
+                    .execute(&backend, &mut ConnectionContext::default())
+                    .await
+                    .unwrap(),
+                RespNull.into()
+            );
+        }
+        assert_eq!(backend.key_type("h"), Some(ValueKind::Hash));
+        assert_eq!(backend.key_type("s"), Some(ValueKind::Set));
+
+        assert!(set("s", Some(SetCondition::Xx), true)
+            .execute(&backend, &mut ConnectionContext::default())
+            .await
+            .is_err());
+        assert_eq!(backend.key_type("s"), Some(ValueKind::Set));
+
+        assert_eq!(
+            set("h", Some(SetCondition::Xx), false)
+                .execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RESP_OK.clone()
+        );
+        assert_eq!(backend.key_type("h"), Some(ValueKind::String));
+    }
+
+    #[tokio::test]
+    async fn test_set_get_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Set {
             key: "hello".to_string(),
             value: RespFrame::BulkString(b"world".into()),
+            condition: None,
+            get: false,
+            expiry: None,
         };
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = Get {
             key: "hello".to_string(),
         };
-        let result = cmd.execute(&backend);
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_mset_mget_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.hash("h").set("f", "v");
+        let mut ctx = ConnectionContext::default();
+
+        let cmd: MSet =
+            RespArray::try_from(crate::resp!(["mset", "a", "1", "b", "2"]))?.try_into()?;
+        assert_eq!(cmd.pairs.len(), 2);
+        assert_eq!(cmd.execute(&backend, &mut ctx).await?, RESP_OK.clone());
+
+        let cmd: MGet =
+            RespArray::try_from(crate::resp!(["mget", "b", "missing", "h", "a"]))?.try_into()?;
+        let result = cmd.execute(&backend, &mut ctx).await?;
+        assert_eq!(
+            result,
+            RespArray::new([
+                BulkString::from("2").into(),
+                RespFrame::Null(RespNull),
+                RespFrame::Null(RespNull),
+                BulkString::from("1").into(),
+            ])
+            .into()
+        );
+
+        assert!(MSet::try_from(RespArray::try_from(crate::resp!(["mset", "a"]))?).is_err());
+        assert!(
+            MSet::try_from(RespArray::try_from(crate::resp!(["mset", "a", "1", "b"]))?).is_err()
+        );
+        assert!(Command::try_from(RespArray::try_from(crate::resp!(["mget"]))?).is_err());
+        Ok(())
+    }
 }
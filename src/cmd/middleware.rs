@@ -0,0 +1,177 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+use crate::{RespArray, RespFrame};
+
+use super::{CommandError, ConnectionContext};
+
+/*
+    命令分发中的中间件，嵌入方用来实现自定义认证、按租户给 key 加前缀、统计等，而不需要修改分发代码。
+    before 按注册顺序在解析命令之前调用，after 按相反的顺序在命令执行之后调用。
+    hook 是同步的，其中不能再注册中间件
+*/
+pub trait Middleware: fmt::Debug + Send + Sync {
+    // 可以修改请求；返回 Ok(Some(reply)) 时不再执行命令而是直接回复，返回 Err 时拒绝执行并回复错误。
+    // 被拦截的请求不会调用 after
+    fn before(
+        &self,
+        _request: &mut RespArray,
+        _ctx: &mut ConnectionContext,
+    ) -> Result<Option<RespFrame>, CommandError> {
+        Ok(None)
+    }
+
+    // 可以观察或修改回复，name 与延迟统计中的命令名相同
+    fn after(
+        &self,
+        _name: &str,
+        _elapsed: Duration,
+        _reply: &mut Result<RespFrame, CommandError>,
+        _ctx: &mut ConnectionContext,
+    ) {
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MiddlewareChain {
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+lazy_static! {
+    static ref MIDDLEWARE: RwLock<MiddlewareChain> = RwLock::new(MiddlewareChain::default());
+}
+
+impl MiddlewareChain {
+    pub fn push(&mut self, middleware: impl Middleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.middlewares.is_empty()
+    }
+
+    // 返回 Some 时请求被拦截，不再执行命令
+    pub fn before(
+        &self,
+        request: &mut RespArray,
+        ctx: &mut ConnectionContext,
+    ) -> Option<RespFrame> {
+        for middleware in self.middlewares.iter() {
+            match middleware.before(request, ctx) {
+                Ok(None) => {}
+                Ok(Some(reply)) => return Some(reply),
+                Err(e) => return Some(e.into()),
+            }
+        }
+        None
+    }
+
+    pub fn after(
+        &self,
+        name: &str,
+        elapsed: Duration,
+        reply: &mut Result<RespFrame, CommandError>,
+        ctx: &mut ConnectionContext,
+    ) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after(name, elapsed, reply, ctx);
+        }
+    }
+}
+
+// 注册到全局的中间件链，之后所有连接上的命令都会经过它
+pub fn register_middleware(middleware: impl Middleware + 'static) {
+    MIDDLEWARE.write().push(middleware);
+}
+
+// 分发时使用的中间件链，没有注册中间件时返回 None，调用时不持有锁
+pub fn middleware_chain() -> Option<MiddlewareChain> {
+    let chain = MIDDLEWARE.read();
+    (!chain.is_empty()).then(|| chain.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::BulkString;
+
+    // 给所有 key 加上租户前缀
+    #[derive(Debug)]
+    struct Prefix(&'static str);
+
+    impl Middleware for Prefix {
+        fn before(
+            &self,
+            request: &mut RespArray,
+            _: &mut ConnectionContext,
+        ) -> Result<Option<RespFrame>, CommandError> {
+            if let Some(RespFrame::BulkString(key)) = request.get_mut(1) {
+                let prefixed = format!("{}{}", self.0, String::from_utf8_lossy(key));
+                *key = BulkString::from(prefixed);
+            }
+            Ok(None)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Deny;
+
+    impl Middleware for Deny {
+        fn before(
+            &self,
+            request: &mut RespArray,
+            _: &mut ConnectionContext,
+        ) -> Result<Option<RespFrame>, CommandError> {
+            match request.get(1) {
+                Some(RespFrame::BulkString(key)) if key.starts_with(b"t1:secret") => {
+                    Err(CommandError::NoPerm("get".to_string()))
+                }
+                _ => Ok(None),
+            }
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Count(Arc<AtomicUsize>);
+
+    impl Middleware for Count {
+        fn after(
+            &self,
+            _: &str,
+            _: Duration,
+            reply: &mut Result<RespFrame, CommandError>,
+            _: &mut ConnectionContext,
+        ) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            *reply = Ok(RespFrame::Integer(42));
+        }
+    }
+
+    #[test]
+    fn test_middleware_chain() -> anyhow::Result<()> {
+        let mut chain = MiddlewareChain::default();
+        chain.push(Prefix("t1:"));
+        chain.push(Deny);
+        let count = Arc::new(AtomicUsize::new(0));
+        chain.push(Count(count.clone()));
+        let mut ctx = ConnectionContext::new(1);
+
+        let mut request = RespArray::try_from(crate::resp!(["get", "key"]))?;
+        assert_eq!(chain.before(&mut request, &mut ctx), None);
+        assert_eq!(RespFrame::from(request), crate::resp!(["get", "t1:key"]));
+
+        let mut request = RespArray::try_from(crate::resp!(["get", "secret"]))?;
+        let reply = chain.before(&mut request, &mut ctx).unwrap();
+        assert_eq!(reply, CommandError::NoPerm("get".to_string()).into());
+
+        let mut reply = Ok(RespFrame::Integer(1));
+        chain.after("get", Duration::ZERO, &mut reply, &mut ctx);
+        assert_eq!(reply?, RespFrame::Integer(42));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+}
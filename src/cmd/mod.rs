@@ -2,36 +2,155 @@ use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
-use crate::{backend::Backend, RespArray, RespError, RespFrame, SimpleString};
-
+use crate::{
+    backend::{Backend, KeyGuard},
+    glob_match, BulkString, ConfigError, ExpireCondition, RespArray, RespError, RespFrame,
+    SimpleError, SimpleString, ValueKind,
+};
+
+mod args;
+mod cache;
 mod conn;
+mod context;
+mod expire;
 mod hmap;
+mod key;
 mod map;
+mod middleware;
+mod plugin;
+mod proxy;
+mod pubsub;
+mod registry;
+mod replication;
+mod server;
 mod smap;
 
+pub use args::{syntax_error, ArgParser};
+pub use cache::{cache_enabled, clear_origin, set_origin, write_through, HashFields, Origin};
+pub use context::ConnectionContext;
+pub use middleware::{middleware_chain, register_middleware, Middleware, MiddlewareChain};
+pub use plugin::{load_plugin, loaded_plugins, Plugin, PluginError, PluginInfo};
+pub use proxy::UpstreamConnection;
+pub use registry::{
+    command_info, command_key_count, command_spec, command_specs, register_command, rename_command,
+    CommandParser, CommandRegistry, CommandSpec, CustomCommand, DynCommand, KeySpec,
+};
+
 lazy_static! {
     pub static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
-    pub static ref RESP_INT_0: RespFrame = RespFrame::Integer(0);
-    pub static ref RESP_INT_1: RespFrame = RespFrame::Integer(1);
-    pub static ref RESP_INT_2: RespFrame = RespFrame::Integer(2);
 }
 
+pub const RESP_INT_0: RespFrame = RespFrame::Integer(0);
+pub const RESP_INT_1: RespFrame = RespFrame::Integer(1);
+pub const RESP_INT_2: RespFrame = RespFrame::Integer(2);
+
+/*
+    按命令表中的 flags 统一做执行前的检查，不在每个命令中各自判断，注册的自定义命令同样适用：
+    - 设置了 requirepass 时，还没有 AUTH 的连接只能执行带 no-auth 的命令
+    - 内存超过 maxmemory 时拒绝带 denyoom 的命令，读命令和 DEL 等释放内存的命令仍然可以执行
+    write 由网络层用来决定写入操作日志和缓存模式下的写穿。
+    还没有副本、ACL、MULTI 和 AOF，readonly / admin / loading / stale 等 flags 目前只是标注，不做检查
+*/
+pub fn check_command(
+    flags: &[&str],
+    backend: &Backend,
+    ctx: &ConnectionContext,
+) -> Result<(), CommandError> {
+    if !ctx.authenticated && !flags.contains(&"no-auth") {
+        return Err(CommandError::NoAuth);
+    }
+    if flags.contains(&"denyoom") && backend.over_maxmemory() {
+        return Err(CommandError::Oom);
+    }
+    Ok(())
+}
+
+// 与 redis 一样 SCAN 系列命令默认每次返回 10 个元素
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+// 命令执行失败的原因。回复给客户端时以 code() 返回的 redis 错误码作为前缀，
+// 客户端库依赖错误码区分错误类型，新增的错误应该使用与 redis 相同的错误码和描述
 #[derive(Error, Debug)]
 pub enum CommandError {
     #[error("Invalid command: {0}")]
     InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
+    // 被 rename-command 禁用或改名前的命令名，参数为请求中的命令名
+    #[error("unknown command '{0}'")]
+    UnknownCommand(String),
+    // 与 redis 的回复一致，原样作为错误描述，比如 "syntax error"
+    #[error("{0}")]
     InvalidArgument(String),
+    // 参数个数不符合命令表中的 arity，参数为命令名
+    #[error("wrong number of arguments for '{0}' command")]
+    WrongArity(String),
 
     #[error("{0}")]
     RespError(#[from] RespError),
     #[error("Utf8 error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("unsupported protocol version")]
+    NoProto,
+    #[error("Authentication required.")]
+    NoAuth,
+    #[error("invalid username-password pair or user is disabled.")]
+    WrongPass,
+    // 参数为命令名
+    #[error("this user has no permissions to run the '{0}' command")]
+    NoPerm(String),
+    #[error("command not allowed when used memory > 'maxmemory'.")]
+    Oom,
+    #[error("Background save already in progress")]
+    SaveInProgress,
+    #[error("upstream error: {0}")]
+    Upstream(String),
+    #[error("{0}")]
+    Replication(String),
 }
 
+impl CommandError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::WrongType => "WRONGTYPE",
+            CommandError::NoProto => "NOPROTO",
+            CommandError::NoAuth => "NOAUTH",
+            CommandError::WrongPass => "WRONGPASS",
+            CommandError::NoPerm(_) => "NOPERM",
+            CommandError::Oom => "OOM",
+            CommandError::InvalidCommand(_)
+            | CommandError::UnknownCommand(_)
+            | CommandError::InvalidArgument(_)
+            | CommandError::WrongArity(_)
+            | CommandError::RespError(_)
+            | CommandError::Utf8Error(_)
+            | CommandError::Config(_)
+            | CommandError::SaveInProgress
+            | CommandError::Upstream(_)
+            | CommandError::Replication(_) => "ERR",
+        }
+    }
+}
+
+// 命令执行失败时回复给客户端的错误，格式为 "错误码 描述"
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        SimpleError::new(format!("{} {}", e.code(), e)).into()
+    }
+}
+
+// 执行是异步的，阻塞类命令可以在这里等待而不占用 runtime 的线程
+// 所有实现都在 crate 内，future 是否 Send 由编译器根据具体类型推导
+#[allow(async_fn_in_trait)]
 #[enum_dispatch]
 pub trait CommandExecutor {
-    fn execute(self, backend: &Backend) -> RespFrame;
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError>;
 }
 
 #[enum_dispatch(CommandExecutor)]
@@ -47,6 +166,42 @@ pub enum Command {
     Ping(Ping),
     SAdd(SAdd),
     SisMember(SisMember),
+    SMembers(SMembers),
+    Hello(Hello),
+    Auth(Auth),
+    ConfigGet(ConfigGet),
+    ConfigSet(ConfigSet),
+    ObjectEncoding(ObjectEncoding),
+    Info(Info),
+    LatencyLatest(LatencyLatest),
+    LatencyHistory(LatencyHistory),
+    LatencyReset(LatencyReset),
+    Scan(Scan),
+    HScan(HScan),
+    SScan(SScan),
+    Keys(Keys),
+    MGet(MGet),
+    MSet(MSet),
+    Del(Del),
+    Unlink(Unlink),
+    Exists(Exists),
+    Expire(Expire),
+    PExpire(PExpire),
+    ExpireAt(ExpireAt),
+    PExpireAt(PExpireAt),
+    Ttl(Ttl),
+    Pttl(Pttl),
+    Persist(Persist),
+    BgSave(BgSave),
+    ModuleList(ModuleList),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Publish(Publish),
+    Role(Role),
+    ReplicaOf(ReplicaOf),
+    Failover(Failover),
+    // 通过 register_command 注册的命令
+    Custom(CustomCommand),
     // unrecognized command
     Unrecognized(Unrecognized),
 }
@@ -60,6 +215,30 @@ pub struct Get {
 pub struct Set {
     pub key: String,
     pub value: RespFrame,
+    pub condition: Option<SetCondition>,
+    // 带 GET 选项时返回旧值
+    pub get: bool,
+    // 没有过期选项时与 redis 一样清除原来的过期时间
+    pub expiry: Option<SetExpiry>,
+}
+
+// SET 的过期选项，时间的单位与对应的 EXPIRE 命令相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetExpiry {
+    Ex(i64),
+    Px(i64),
+    ExAt(i64),
+    PxAt(i64),
+    // 保留原来的过期时间
+    KeepTtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    // 只在 key 不存在时设置
+    Nx,
+    // 只在 key 已存在时设置
+    Xx,
 }
 
 #[derive(Debug)]
@@ -74,6 +253,11 @@ pub struct SisMember {
     pub value: String,
 }
 
+#[derive(Debug)]
+pub struct SMembers {
+    pub key: String,
+}
+
 #[derive(Debug)]
 pub struct HGet {
     pub key: String,
@@ -90,7 +274,6 @@ pub struct HSet {
 #[derive(Debug)]
 pub struct HGetAll {
     pub key: String,
-    sort: bool,
 }
 
 #[derive(Debug)]
@@ -106,11 +289,214 @@ pub struct Echo {
 
 #[derive(Debug)]
 pub struct Ping {
-    pub message: String,
+    // 没有参数时为 None
+    pub message: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Hello {
+    pub protover: Option<i64>,
+}
+
+// AUTH [username] password，只有 default 一个用户，密码为 requirepass
+#[derive(Debug)]
+pub struct Auth {
+    pub username: Option<String>,
+    pub password: String,
+}
+
+#[derive(Debug)]
+pub struct ConfigGet {
+    pub pattern: String,
+}
+
+#[derive(Debug)]
+pub struct ConfigSet {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub struct ObjectEncoding {
+    pub key: String,
+}
+
+// INFO [section ...]，没有指定时返回所有 section
+#[derive(Debug)]
+pub struct Info {
+    pub sections: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct LatencyLatest;
+
+#[derive(Debug)]
+pub struct LatencyHistory {
+    pub command: String,
+}
+
+// 没有指定命令时重置所有命令的统计
+#[derive(Debug)]
+pub struct LatencyReset {
+    pub commands: Vec<String>,
+}
+
+// BGSAVE，在后台把数据集保存到 dir/dbfilename
+#[derive(Debug)]
+pub struct BgSave;
+
+// MODULE LIST，返回通过 load_plugin 加载的插件
+#[derive(Debug)]
+pub struct ModuleList;
+
+// SUBSCRIBE channel [channel ...]
+#[derive(Debug)]
+pub struct Subscribe {
+    pub channels: Vec<String>,
+}
+
+// UNSUBSCRIBE [channel ...]，没有指定时取消所有订阅
+#[derive(Debug)]
+pub struct Unsubscribe {
+    pub channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    pub channel: String,
+    pub message: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct Role;
+
+// REPLICAOF host port | REPLICAOF NO ONE
+#[derive(Debug)]
+pub struct ReplicaOf {
+    pub target: ReplicaTarget,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplicaTarget {
+    NoOne,
+    Master { host: String, port: u16 },
+}
+
+// FAILOVER [TO host port [FORCE]] [ABORT] [TIMEOUT milliseconds]
+#[derive(Debug)]
+pub struct Failover {
+    pub to: Option<(String, u16)>,
+    pub force: bool,
+    pub abort: bool,
+    pub timeout: Option<u64>,
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]，游标的语义见 backend 的 scan 模块
+#[derive(Debug)]
+pub struct Scan {
+    pub cursor: u64,
+    pub pattern: Option<String>,
+    pub count: usize,
+    pub kind: Option<ValueKind>,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    pub key: String,
+    pub cursor: u64,
+    pub pattern: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug)]
+pub struct SScan {
+    pub key: String,
+    pub cursor: u64,
+    pub pattern: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pub pattern: String,
+}
+
+#[derive(Debug)]
+pub struct MGet {
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct MSet {
+    pub pairs: Vec<(String, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct Del {
+    pub keys: Vec<String>,
+}
+
+// 没有后台释放内存的线程，与 DEL 的行为相同
+#[derive(Debug)]
+pub struct Unlink {
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    pub keys: Vec<String>,
+}
+
+// EXPIRE key seconds [NX | XX | GT | LT]
+#[derive(Debug)]
+pub struct Expire {
+    pub key: String,
+    pub seconds: i64,
+    pub condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct PExpire {
+    pub key: String,
+    pub milliseconds: i64,
+    pub condition: ExpireCondition,
 }
 
+// 过期时间点是 unix 时间戳，EXPIREAT 以秒为单位，PEXPIREAT 以毫秒为单位
 #[derive(Debug)]
-pub struct Unrecognized;
+pub struct ExpireAt {
+    pub key: String,
+    pub timestamp: i64,
+    pub condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct PExpireAt {
+    pub key: String,
+    pub timestamp: i64,
+    pub condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct Ttl {
+    pub key: String,
+}
+
+#[derive(Debug)]
+pub struct Pttl {
+    pub key: String,
+}
+
+#[derive(Debug)]
+pub struct Persist {
+    pub key: String,
+}
+
+// 命令表中没有的命令，保留原始请求以便转发给 upstream
+#[derive(Debug)]
+pub struct Unrecognized {
+    pub request: RespArray,
+}
 
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
@@ -118,9 +504,10 @@ impl TryFrom<RespFrame> for Command {
     fn try_from(value: RespFrame) -> Result<Self, Self::Error> {
         match value {
             RespFrame::Array(array) => Command::try_from(array),
-            _ => Err(CommandError::InvalidCommand(
-                "Command must be an Array".to_string(),
-            )),
+            frame => Err(CommandError::InvalidCommand(format!(
+                "Command must be an Array, got {}",
+                frame.type_name()
+            ))),
         }
     }
 }
@@ -130,126 +517,340 @@ impl TryFrom<RespArray> for Command {
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
         match value.first() {
-            Some(RespFrame::BulkString(ref cmd)) => {
-                match cmd.as_ref().to_ascii_lowercase().as_slice() {
-                    b"get" => Ok(Get::try_from(value)?.into()),
-                    b"set" => Ok(Set::try_from(value)?.into()),
-                    b"sadd" => Ok(SAdd::try_from(value)?.into()),
-                    b"sismember" => Ok(SisMember::try_from(value)?.into()),
-                    b"hget" => Ok(HGet::try_from(value)?.into()),
-                    b"hset" => Ok(HSet::try_from(value)?.into()),
-                    b"hgetall" => Ok(HGetAll::try_from(value)?.into()),
-                    b"hmget" => Ok(HMGet::try_from(value)?.into()),
-                    b"echo" => Ok(Echo::try_from(value)?.into()),
-                    b"ping" => Ok(Ping::try_from(value)?.into()),
-                    _ => Ok(Unrecognized.into()),
-                }
-            }
-            _ => Err(CommandError::InvalidCommand(
+            Some(RespFrame::BulkString(_)) => registry::parse_command(value),
+            Some(frame) => Err(CommandError::InvalidCommand(format!(
+                "Command must have a BulkString as the first argument, got {}",
+                frame.type_name()
+            ))),
+            None => Err(CommandError::InvalidCommand(
                 "Command must have a BulkString as the first argument".to_string(),
             )),
         }
     }
 }
 
-impl CommandExecutor for Unrecognized {
-    fn execute(self, _: &Backend) -> RespFrame {
-        RESP_OK.clone()
+impl Command {
+    // 与命令表中的名称一致，子命令为 "config|get" 的形式
+    pub fn name(&self) -> &str {
+        match self {
+            Command::Get(_) => "get",
+            Command::Set(_) => "set",
+            Command::HGet(_) => "hget",
+            Command::HSet(_) => "hset",
+            Command::HGetAll(_) => "hgetall",
+            Command::HMGet(_) => "hmget",
+            Command::Echo(_) => "echo",
+            Command::Ping(_) => "ping",
+            Command::SAdd(_) => "sadd",
+            Command::SisMember(_) => "sismember",
+            Command::SMembers(_) => "smembers",
+            Command::Hello(_) => "hello",
+            Command::Auth(_) => "auth",
+            Command::ConfigGet(_) => "config|get",
+            Command::ConfigSet(_) => "config|set",
+            Command::ObjectEncoding(_) => "object|encoding",
+            Command::Info(_) => "info",
+            Command::LatencyLatest(_) => "latency|latest",
+            Command::LatencyHistory(_) => "latency|history",
+            Command::LatencyReset(_) => "latency|reset",
+            Command::Scan(_) => "scan",
+            Command::HScan(_) => "hscan",
+            Command::SScan(_) => "sscan",
+            Command::Keys(_) => "keys",
+            Command::MGet(_) => "mget",
+            Command::MSet(_) => "mset",
+            Command::Del(_) => "del",
+            Command::Unlink(_) => "unlink",
+            Command::Exists(_) => "exists",
+            Command::Expire(_) => "expire",
+            Command::PExpire(_) => "pexpire",
+            Command::ExpireAt(_) => "expireat",
+            Command::PExpireAt(_) => "pexpireat",
+            Command::Ttl(_) => "ttl",
+            Command::Pttl(_) => "pttl",
+            Command::Persist(_) => "persist",
+            Command::BgSave(_) => "bgsave",
+            Command::ModuleList(_) => "module|list",
+            Command::Subscribe(_) => "subscribe",
+            Command::Unsubscribe(_) => "unsubscribe",
+            Command::Publish(_) => "publish",
+            Command::Role(_) => "role",
+            Command::ReplicaOf(_) => "replicaof",
+            Command::Failover(_) => "failover",
+            Command::Custom(cmd) => cmd.name(),
+            Command::Unrecognized(_) => "unknown",
+        }
+    }
+
+    // 需要遍历整个大集合的命令会长时间占用 worker 线程，交给 blocking 线程池执行
+    pub fn is_heavy(&self, backend: &Backend) -> bool {
+        let key = match self {
+            Command::SMembers(cmd) => &cmd.key,
+            Command::HGetAll(cmd) => &cmd.key,
+            _ => return false,
+        };
+        let threshold = backend.config().heavy_command_threshold;
+        threshold > 0
+            && backend
+                .collection_len(key)
+                .is_some_and(|len| len > threshold)
     }
 }
 
-pub trait TryIntoBulkString {
-    fn try_into_bulk_string(self) -> Result<String, CommandError>;
+// 与 redis 一样游标是十进制的无符号 64 位整数
+fn parse_cursor(args: &mut ArgParser) -> Result<u64, CommandError> {
+    args.next_string("cursor")?
+        .parse()
+        .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))
 }
 
-impl TryIntoBulkString for RespFrame {
-    fn try_into_bulk_string(self) -> Result<String, CommandError> {
-        if let RespFrame::BulkString(bs) = self {
-            String::from_utf8(bs.0).map_err(|e| CommandError::InvalidArgument(e.to_string()))
-        } else {
-            Err(CommandError::InvalidArgument(
-                "Expected BulkString".to_string(),
-            ))
+// 解析 SCAN 系列命令共有的 MATCH 和 COUNT 选项，返回 (pattern, count)。
+// 其他选项由 option 处理，不认识的选项返回 false
+fn parse_scan_options(
+    args: &mut ArgParser,
+    mut option: impl FnMut(&str, &mut ArgParser) -> Result<bool, CommandError>,
+) -> Result<(Option<String>, usize), CommandError> {
+    let (mut pattern, mut count) = (None, DEFAULT_SCAN_COUNT);
+    while let Some(keyword) = args.next_keyword()? {
+        match keyword.as_str() {
+            "MATCH" => pattern = Some(args.value::<String>("MATCH")?),
+            "COUNT" => {
+                count = match args.value::<i64>("COUNT")? {
+                    n if n >= 1 => n as usize,
+                    _ => return Err(syntax_error()),
+                }
+            }
+            keyword if option(keyword, args)? => {}
+            _ => return Err(syntax_error()),
         }
     }
+    Ok((pattern, count))
 }
 
-fn validate_command(
-    value: &RespArray,
-    names: &[&'static str],
-    n_args: usize,
-) -> Result<(), CommandError> {
-    if value.len() != n_args + names.len() {
-        return Err(CommandError::InvalidArgument(format!(
-            "{} command must have exactly {} argument",
-            names.join(" "),
-            n_args
-        )));
+// 没有指定 MATCH 时所有元素都匹配
+fn matches_pattern(pattern: &Option<String>, item: &str) -> bool {
+    pattern
+        .as_ref()
+        .is_none_or(|pattern| glob_match(pattern.as_bytes(), item.as_bytes(), false))
+}
+
+// 回复为 [下一个游标, 元素数组]，RESP3 下也是数组
+fn scan_reply(cursor: u64, items: Vec<RespFrame>) -> RespFrame {
+    RespArray::new([
+        BulkString::from(cursor.to_string()).into(),
+        RespArray::new(items).into(),
+    ])
+    .into()
+}
+
+// key 已经以其他类型存在时返回 WRONGTYPE
+fn check_type(backend: &Backend, key: &str, kind: ValueKind) -> Result<(), CommandError> {
+    check_kind(backend.key_type(key), kind)
+}
+
+// 写命令在持有写锁时检查类型，检查与写入之间不会插入其他类型的写入
+fn check_locked_type(guard: &KeyGuard, key: &str, kind: ValueKind) -> Result<(), CommandError> {
+    check_kind(guard.key_type(key), kind)
+}
+
+fn check_kind(actual: Option<ValueKind>, kind: ValueKind) -> Result<(), CommandError> {
+    match actual {
+        Some(actual) if actual != kind => Err(CommandError::WrongType),
+        _ => Ok(()),
     }
+}
 
-    for (i, name) in names.iter().enumerate() {
-        match value[i] {
-            RespFrame::BulkString(ref cmd) => {
-                if cmd.as_ref().to_ascii_lowercase() != name.as_bytes() {
-                    return Err(CommandError::InvalidCommand(format!(
-                        "Invalid command: expected {}, got {}",
-                        name,
-                        String::from_utf8_lossy(cmd.as_ref())
-                    )));
-                }
+// 取出 config get 这类复合命令的子命令名称（小写）
+fn subcommand(value: &RespArray) -> Option<&[u8]> {
+    match value.get(1) {
+        Some(RespFrame::BulkString(sub)) => Some(sub.as_ref()),
+        _ => None,
+    }
+}
+
+/*
+    命令执行成功后、写入操作日志之前改写命令，使重放得到相同的数据：
+    - 改过名的命令换回原来的名称，订阅者不需要知道 rename-command 的配置
+    - 相对的过期时间换算成 unix 毫秒时间戳，EXPIRE / PEXPIRE 写成 PEXPIREAT，SET 的 EX / PX 写成 PXAT，
+      晚一些重放也会在原来的时刻过期
+    命令已经执行成功，参数都是合法的
+*/
+pub fn oplog_command(mut value: RespArray) -> RespArray {
+    registry::restore_command_name(&mut value);
+    let Some(RespFrame::BulkString(name)) = value.first() else {
+        return value;
+    };
+    let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+    let now = expire::unix_millis();
+    let absolute = |arg: &RespFrame, unit: i128| {
+        let RespFrame::BulkString(arg) = arg else {
+            return None;
+        };
+        let time = std::str::from_utf8(arg).ok()?.parse::<i64>().ok()?;
+        Some(BulkString::from((now + time as i128 * unit).to_string()).into())
+    };
+    match name.as_str() {
+        "EXPIRE" | "PEXPIRE" if value.len() > 2 => {
+            let unit = if name == "EXPIRE" { 1000 } else { 1 };
+            if let Some(at) = absolute(&value[2], unit) {
+                value.0[0] = BulkString::from("PEXPIREAT").into();
+                value.0[2] = at;
             }
-            _ => {
-                return Err(CommandError::InvalidCommand(
-                    "Command must have a BulkString as the first argument".to_string(),
-                ))
+        }
+        "SET" => {
+            let mut i = 3;
+            while i + 1 < value.len() {
+                let unit = match &value[i] {
+                    RespFrame::BulkString(arg) if arg.eq_ignore_ascii_case(b"EX") => 1000,
+                    RespFrame::BulkString(arg) if arg.eq_ignore_ascii_case(b"PX") => 1,
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                };
+                if let Some(at) = absolute(&value[i + 1], unit) {
+                    value.0[i] = BulkString::from("PXAT").into();
+                    value.0[i + 1] = at;
+                }
+                i += 2;
             }
         }
+        _ => {}
     }
-    Ok(())
+    value
+}
+
+// 有的客户端和代理会把参数作为 Integer 或 SimpleString 发送，网络层在查命令表和解析前
+// 统一转换为 BulkString，命令名、key 和写入的值都按文本形式处理
+pub fn normalize_args(value: &mut RespArray) {
+    for arg in value.0.iter_mut() {
+        match arg {
+            RespFrame::Integer(i) => *arg = BulkString::from(i.to_string()).into(),
+            RespFrame::SimpleString(s) => *arg = BulkString::from(&**s).into(),
+            _ => {}
+        }
+    }
+}
+
+// "命令 key [key ...]" 形式的参数，参数个数已经由命令表检查过
+fn extract_keys(value: RespArray) -> Result<Vec<String>, CommandError> {
+    extract_args(value, 1)?
+        .into_iter()
+        .map(|key| Ok(key.try_into()?))
+        .collect()
 }
 
+// 直接复用请求数组的 Vec 作为参数列表，不重新分配
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
-    Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
+    let mut args = value.0;
+    args.drain(..start.min(args.len()));
+    Ok(args)
+}
+
+// "命令 key" 形式的参数
+fn single_key(value: RespArray) -> Result<String, CommandError> {
+    let mut args = ArgParser::new(extract_args(value, 1)?);
+    let key = args.next_string("key")?;
+    args.finish()?;
+    Ok(key)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_validate_command_with_two_args() {
-        let value = RespArray(vec![
-            RespFrame::BulkString(b"set".into()),
-            RespFrame::BulkString(b"key".into()),
-            RespFrame::BulkString(b"value".into()),
-        ]);
-        assert!(validate_command(&value, &["set"], 2).is_ok());
-
-        let value = RespArray(vec![
-            RespFrame::BulkString(b"set".into()),
-            RespFrame::BulkString(b"key".into()),
-        ]);
-        assert!(validate_command(&value, &["set"], 2).is_err());
+    fn request(args: &[&str]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::from(*arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
 
-        let value = RespArray(vec![
-            RespFrame::BulkString(b"set".into()),
-            RespFrame::BulkString(b"key".into()),
-            RespFrame::BulkString(b"value".into()),
-            RespFrame::BulkString(b"extra".into()),
-        ]);
-        assert!(validate_command(&value, &["set"], 2).is_err());
+    #[test]
+    fn test_wrong_arity() {
+        let cases: [&[&str]; 6] = [
+            &["get"],
+            &["get", "a", "b"],
+            &["set", "key"],
+            &["ping", "a", "b"],
+            &["config", "get"],
+            &["mset", "a", "1", "b"],
+        ];
+        for args in cases {
+            let err = Command::try_from(request(args)).unwrap_err();
+            let name = match args[0] {
+                "config" => "config|get",
+                name => name,
+            };
+            assert_eq!(
+                RespFrame::from(err),
+                SimpleError::new(format!(
+                    "ERR wrong number of arguments for '{}' command",
+                    name
+                ))
+                .into()
+            );
+        }
+        assert!(Command::try_from(request(&["ping", "a"])).is_ok());
+        assert!(Command::try_from(request(&["set", "key", "value"])).is_ok());
     }
 
     #[test]
-    fn test_validate_command_with_one_arg() {
-        let value = RespArray(vec![
-            RespFrame::BulkString(b"ping".into()),
-            RespFrame::BulkString(b"extra".into()),
+    fn test_oplog_command_uses_absolute_expiry() {
+        let timestamp = |frame: &RespFrame| match frame {
+            RespFrame::BulkString(s) => std::str::from_utf8(s).unwrap().parse::<i128>().unwrap(),
+            frame => panic!("unexpected {:?}", frame),
+        };
+        let before = expire::unix_millis();
+        let set = oplog_command(request(&["set", "k", "v", "NX", "ex", "10", "GET"]));
+        let expire = oplog_command(request(&["PEXPIRE", "k", "500", "GT"]));
+        let after = expire::unix_millis();
+
+        assert_eq!(set.len(), 7);
+        assert_eq!(set[4], BulkString::from("PXAT").into());
+        assert!((before + 10_000..=after + 10_000).contains(&timestamp(&set[5])));
+        assert_eq!(set[6], BulkString::from("GET").into());
+        assert_eq!(expire[0], BulkString::from("PEXPIREAT").into());
+        assert!((before + 500..=after + 500).contains(&timestamp(&expire[2])));
+        assert_eq!(expire[3], BulkString::from("GT").into());
+
+        // 绝对时间和没有过期时间的命令不变
+        for args in [
+            &["EXPIREAT", "k", "100"][..],
+            &["SET", "k", "v", "PXAT", "100"],
+        ] {
+            assert_eq!(oplog_command(request(args)), request(args));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_integer_and_simple_string_args() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::default();
+        let mut set = RespArray::new(vec![
+            SimpleString::new("set").into(),
+            SimpleString::new("k").into(),
+            RespFrame::Integer(42),
         ]);
-        assert!(validate_command(&value, &["ping"], 1).is_ok());
+        normalize_args(&mut set);
+        Command::try_from(set)?.execute(&backend, &mut ctx).await?;
+        assert_eq!(
+            backend.get("k").as_deref(),
+            Some(&BulkString::from("42").into())
+        );
 
-        let value = RespArray(vec![RespFrame::BulkString(b"ping".into())]);
-        assert!(validate_command(&value, &["ping", "pong"], 1).is_err());
+        let mut expire = RespArray::new(vec![
+            BulkString::from("expire").into(),
+            SimpleString::new("k").into(),
+            RespFrame::Integer(100),
+        ]);
+        normalize_args(&mut expire);
+        let reply = Command::try_from(expire)?
+            .execute(&backend, &mut ctx)
+            .await?;
+        assert_eq!(reply, RespFrame::Integer(1));
+        Ok(())
     }
 
     #[test]
@@ -268,4 +869,96 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_command_error_to_frame() {
+        let frame: RespFrame = CommandError::WrongType.into();
+        assert_eq!(
+            frame,
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+                .into()
+        );
+        let frame: RespFrame = CommandError::InvalidArgument("syntax error".to_string()).into();
+        assert_eq!(frame, SimpleError::new("ERR syntax error").into());
+
+        let cases = [
+            (CommandError::NoAuth, "NOAUTH Authentication required."),
+            (
+                CommandError::WrongPass,
+                "WRONGPASS invalid username-password pair or user is disabled.",
+            ),
+            (
+                CommandError::NoPerm("flushall".to_string()),
+                "NOPERM this user has no permissions to run the 'flushall' command",
+            ),
+            (
+                CommandError::Oom,
+                "OOM command not allowed when used memory > 'maxmemory'.",
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(RespFrame::from(err), SimpleError::new(expected).into());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wrong_type() {
+        let backend = Backend::new();
+        backend.hset(
+            "key".to_string(),
+            "field".to_string(),
+            RespFrame::Integer(1),
+        );
+        let cmd = Get {
+            key: "key".to_string(),
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await,
+            Err(CommandError::WrongType)
+        ));
+
+        let cmd = HGet {
+            key: "key".to_string(),
+            field: "field".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RespFrame::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_check_command_uses_flags() {
+        let info = |cmd: &[&str]| {
+            let value = RespArray::new(
+                cmd.iter()
+                    .map(|s| BulkString::from(*s).into())
+                    .collect::<Vec<RespFrame>>(),
+            );
+            command_info(&value).unwrap().0
+        };
+        let backend = Backend::new();
+        backend.set_string("key", "value");
+        let mut ctx = ConnectionContext::new(1);
+        assert!(check_command(info(&["set", "k", "v"]), &backend, &ctx).is_ok());
+
+        backend.set_config("maxmemory", "1").unwrap();
+        assert!(matches!(
+            check_command(info(&["set", "k", "v"]), &backend, &ctx),
+            Err(CommandError::Oom)
+        ));
+        assert!(check_command(info(&["get", "k"]), &backend, &ctx).is_ok());
+        assert!(check_command(info(&["del", "k"]), &backend, &ctx).is_ok());
+
+        ctx.authenticated = false;
+        assert!(matches!(
+            check_command(info(&["get", "k"]), &backend, &ctx),
+            Err(CommandError::NoAuth)
+        ));
+        assert!(check_command(info(&["hello"]), &backend, &ctx).is_ok());
+        assert!(check_command(info(&["auth", "secret"]), &backend, &ctx).is_ok());
+    }
 }
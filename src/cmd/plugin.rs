@@ -0,0 +1,187 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use thiserror::Error;
+
+use super::{registry, CommandSpec};
+
+/*
+    插件是一组一起发布的命令，类似 redis 的 module。命令本身仍然是 CommandSpec + DynCommand，
+    解析函数自己处理参数，执行时可以访问 backend 和连接状态。
+    与 register_command 不同，插件不能覆盖已有的命令，加载要么全部成功要么什么都不注册。
+    插件在启动时编译进程序并调用 load_plugin 加载，不支持从动态库加载
+*/
+pub trait Plugin: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn version(&self) -> i64 {
+        1
+    }
+
+    fn commands(&self) -> Vec<CommandSpec>;
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PluginError {
+    #[error("plugin {0} is already loaded")]
+    AlreadyLoaded(String),
+    #[error("command {0} already exists")]
+    CommandExists(String),
+}
+
+// MODULE LIST 中显示的已加载插件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: i64,
+    pub commands: Vec<String>,
+}
+
+lazy_static! {
+    static ref PLUGINS: RwLock<Vec<PluginInfo>> = RwLock::new(Vec::new());
+}
+
+pub fn load_plugin(plugin: impl Plugin) -> Result<(), PluginError> {
+    // 持有插件列表的写锁直到注册完成，同名插件不会被并发加载两次
+    let mut plugins = PLUGINS.write();
+    let name = plugin.name().to_string();
+    if plugins.iter().any(|p| p.name.eq_ignore_ascii_case(&name)) {
+        return Err(PluginError::AlreadyLoaded(name));
+    }
+
+    let specs = plugin.commands();
+    let commands = specs.iter().map(|spec| spec.name.clone()).collect();
+    registry::register_new_commands(specs).map_err(PluginError::CommandExists)?;
+    plugins.push(PluginInfo {
+        name,
+        version: plugin.version(),
+        commands,
+    });
+    Ok(())
+}
+
+pub fn loaded_plugins() -> Vec<PluginInfo> {
+    PLUGINS.read().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::BoxFuture;
+
+    use super::*;
+    use crate::{
+        cmd::{
+            Command, CommandError, CommandExecutor, ConnectionContext, CustomCommand, DynCommand,
+            KeySpec,
+        },
+        Backend, BulkString, RespArray, RespFrame,
+    };
+
+    // 把参数追加到一个字符串 key 之后，返回新的长度
+    #[derive(Debug)]
+    struct Append {
+        key: String,
+        value: String,
+    }
+
+    impl DynCommand for Append {
+        fn name(&self) -> &str {
+            "plugintest.append"
+        }
+
+        fn execute_boxed<'a>(
+            self: Box<Self>,
+            backend: &'a Backend,
+            _: &'a mut ConnectionContext,
+        ) -> BoxFuture<'a, Result<RespFrame, CommandError>> {
+            Box::pin(async move {
+                let value = backend.get_string(&self.key).unwrap_or_default() + &self.value;
+                let len = value.len();
+                backend.set_string(self.key, value);
+                Ok(RespFrame::Integer(len as i64))
+            })
+        }
+    }
+
+    fn parse_append(value: RespArray) -> Result<Command, CommandError> {
+        let mut args = value.0.into_iter().skip(1);
+        let (Some(key), Some(value)) = (args.next(), args.next()) else {
+            return Err(CommandError::InvalidArgument(
+                "Missing argument".to_string(),
+            ));
+        };
+        let cmd = Append {
+            key: key.try_into()?,
+            value: value.try_into()?,
+        };
+        Ok(CustomCommand::new(cmd).into())
+    }
+
+    #[derive(Debug)]
+    struct TestPlugin(&'static str);
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            "plugintest"
+        }
+
+        fn commands(&self) -> Vec<CommandSpec> {
+            vec![CommandSpec::new(
+                self.0,
+                3,
+                &["write", "denyoom"],
+                KeySpec::single(1),
+                parse_append,
+            )]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_plugin() -> anyhow::Result<()> {
+        assert_eq!(
+            load_plugin(TestPlugin("get")),
+            Err(PluginError::CommandExists("get".to_string()))
+        );
+        assert!(loaded_plugins().iter().all(|p| p.name != "plugintest"));
+
+        load_plugin(TestPlugin("plugintest.append"))?;
+        assert_eq!(
+            load_plugin(TestPlugin("plugintest.other")),
+            Err(PluginError::AlreadyLoaded("plugintest".to_string()))
+        );
+        let info = loaded_plugins()
+            .into_iter()
+            .find(|p| p.name == "plugintest")
+            .unwrap();
+        assert_eq!(info.commands, vec!["plugintest.append"]);
+
+        let backend = Backend::new();
+        backend.set_string("key", "ab");
+        let value = RespArray::new(vec![
+            BulkString::from("PLUGINTEST.APPEND").into(),
+            BulkString::from("key").into(),
+            BulkString::from("cd").into(),
+        ]);
+        let cmd = Command::try_from(value)?;
+        assert_eq!(cmd.name(), "plugintest.append");
+        let ret = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(ret, RespFrame::Integer(4));
+        assert_eq!(backend.get_string("key"), Some("abcd".to_string()));
+
+        let cmd = Command::try_from(RespArray::try_from(crate::resp!(["module", "list"]))?)?;
+        let RespFrame::Array(modules) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?
+        else {
+            panic!("expected array");
+        };
+        assert!(modules.iter().any(|m| matches!(
+            m,
+            RespFrame::Map(m) if m.get("name") == Some(&BulkString::from("plugintest").into())
+        )));
+        Ok(())
+    }
+}
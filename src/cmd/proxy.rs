@@ -0,0 +1,119 @@
+use std::fmt;
+
+use crate::{client::Client, Backend, RespArray, RespFrame};
+
+use super::{CommandError, CommandExecutor, ConnectionContext, Unrecognized, RESP_OK};
+
+// 每个客户端连接各自使用一条到 upstream 的连接，同一个连接上的请求按顺序转发，
+// SELECT 之类修改连接状态的命令在 upstream 上也只影响这一个客户端
+pub struct UpstreamConnection {
+    addr: String,
+    client: Client,
+}
+
+impl fmt::Debug for UpstreamConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpstreamConnection")
+            .field("addr", &self.addr)
+            .finish()
+    }
+}
+
+/*
+    配置了 upstream 时，没有实现的命令原样转发给 upstream 并返回它的回复，错误回复也原样返回，
+    这样可以把这个服务器放在已有的 redis 前面逐步替换。没有配置时保持原来的行为回复 OK
+*/
+impl CommandExecutor for Unrecognized {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let addr = backend.config().upstream;
+        if addr.is_empty() {
+            return Ok(RESP_OK.clone());
+        }
+        forward(&mut ctx.upstream, &addr, self.request).await
+    }
+}
+
+// slot 保存连接上已经建立的连接，缓存源站与 upstream 各用一个
+pub(super) async fn forward(
+    slot: &mut Option<UpstreamConnection>,
+    addr: &str,
+    request: RespArray,
+) -> Result<RespFrame, CommandError> {
+    // 地址修改之后重新连接
+    let mut upstream = match slot.take() {
+        Some(upstream) if upstream.addr == addr => upstream,
+        _ => UpstreamConnection {
+            addr: addr.to_string(),
+            client: Client::connect(addr)
+                .await
+                .map_err(|e| CommandError::Upstream(e.to_string()))?,
+        },
+    };
+    // 出错时丢弃这条连接，下一个请求重新连接
+    let reply = upstream
+        .client
+        .send(request.into())
+        .await
+        .map_err(|e| CommandError::Upstream(e.to_string()))?;
+    *slot = Some(upstream);
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::cmd::Command;
+
+    #[tokio::test]
+    async fn test_forward_unrecognized_command() -> anyhow::Result<()> {
+        // 只接受一个连接的假 upstream，对每个请求回复 +FORWARDED
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 1024];
+            while stream.read(&mut buf).await.unwrap_or(0) > 0 {
+                stream.write_all(b"+FORWARDED\r\n").await.unwrap();
+            }
+        });
+
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::new(1);
+        let request = RespArray::try_from(crate::resp!(["lpush", "list", "a"]))?;
+        let cmd = Command::try_from(request.clone())?;
+        assert_eq!(cmd.execute(&backend, &mut ctx).await?, RESP_OK.clone());
+
+        backend.set_config("upstream", &addr.to_string())?;
+        for _ in 0..2 {
+            let cmd = Command::try_from(request.clone())?;
+            let reply = cmd.execute(&backend, &mut ctx).await?;
+            assert_eq!(reply, crate::SimpleString::new("FORWARDED").into());
+        }
+        // 两个请求复用同一条连接，假 upstream 只接受一个连接
+        assert!(ctx.upstream.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upstream_unavailable() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        drop(listener);
+
+        let backend = Backend::new();
+        backend.set_config("upstream", &addr.to_string())?;
+        let cmd = Command::try_from(RespArray::try_from(crate::resp!(["lpush", "list", "a"]))?)?;
+        let result = cmd.execute(&backend, &mut ConnectionContext::new(1)).await;
+        assert!(matches!(result, Err(CommandError::Upstream(_))));
+        Ok(())
+    }
+}
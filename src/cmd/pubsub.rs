@@ -0,0 +1,183 @@
+// 实现 subscribe、unsubscribe 和 publish 命令，消息的排队和积压策略见 backend 的 pubsub 模块
+use std::sync::Arc;
+
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull, RespPush, SubscriberQueue};
+
+use super::{
+    extract_args, ArgParser, CommandError, CommandExecutor, ConnectionContext, Publish, Subscribe,
+    Unsubscribe,
+};
+
+// 订阅确认：[kind, channel, 当前订阅的频道和模式总数]
+fn confirmation(kind: &str, channel: Option<String>, count: usize) -> RespFrame {
+    let channel = match channel {
+        Some(channel) => BulkString::from(channel).into(),
+        None => RespFrame::Null(RespNull),
+    };
+    RespPush::new(vec![
+        BulkString::from(kind).into(),
+        channel,
+        RespFrame::Integer(count as i64),
+    ])
+    .into()
+}
+
+/*
+    与 redis 一样每个频道回复一条确认。命令只能返回一个回复，第一条确认作为回复返回，
+    其余的放进这个连接的消息队列，紧接着回复写出
+*/
+fn reply(queue: &SubscriberQueue, mut confirmations: Vec<RespFrame>) -> RespFrame {
+    let first = confirmations.remove(0);
+    for frame in confirmations {
+        queue.push_reply(frame);
+    }
+    first
+}
+
+impl CommandExecutor for Subscribe {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let id = ctx.id;
+        let queue = ctx
+            .subscription
+            .get_or_insert_with(|| Arc::new(SubscriberQueue::new(id)))
+            .clone();
+        let mut confirmations = Vec::with_capacity(self.channels.len());
+        for channel in self.channels {
+            backend.subscribe(&channel, &queue);
+            ctx.channels.insert(channel.clone());
+            let count = ctx.channels.len() + ctx.patterns.len();
+            confirmations.push(confirmation("subscribe", Some(channel), count));
+        }
+        Ok(reply(&queue, confirmations))
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let channels = if self.channels.is_empty() {
+            ctx.channels.iter().cloned().collect()
+        } else {
+            self.channels
+        };
+        let Some(queue) = ctx.subscription.clone().filter(|_| !channels.is_empty()) else {
+            return Ok(confirmation("unsubscribe", None, ctx.patterns.len()));
+        };
+        let mut confirmations = Vec::with_capacity(channels.len());
+        for channel in channels {
+            backend.unsubscribe(&channel, ctx.id);
+            ctx.channels.remove(&channel);
+            let count = ctx.channels.len() + ctx.patterns.len();
+            confirmations.push(confirmation("unsubscribe", Some(channel), count));
+        }
+        Ok(reply(&queue, confirmations))
+    }
+}
+
+impl CommandExecutor for Publish {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let count = backend.publish(&self.channel, self.message);
+        Ok(RespFrame::Integer(count as i64))
+    }
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        Ok(Subscribe {
+            channels: args.rest()?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        Ok(Unsubscribe {
+            channels: args.rest()?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let channel = args.next_string("channel")?;
+        let message = args.next_frame("message")?;
+        args.finish()?;
+        Ok(Publish { channel, message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+
+    async fn execute(
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+        frame: RespFrame,
+    ) -> anyhow::Result<RespFrame> {
+        let cmd = Command::try_from(RespArray::try_from(frame)?)?;
+        Ok(cmd.execute(backend, ctx).await?)
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_publish_unsubscribe() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::new(1);
+        let reply = execute(&backend, &mut ctx, crate::resp!(["subscribe", "a", "b"])).await?;
+        assert_eq!(reply, confirmation("subscribe", Some("a".to_string()), 1));
+        assert!(ctx.is_subscribed());
+
+        let mut publisher = ConnectionContext::new(2);
+        let reply = execute(
+            &backend,
+            &mut publisher,
+            crate::resp!(["publish", "b", "hi"]),
+        )
+        .await?;
+        assert_eq!(reply, RespFrame::Integer(1));
+
+        // 第二条确认在消息之前
+        let queue = ctx.subscription.clone().unwrap();
+        assert_eq!(
+            queue.recv().await.unwrap(),
+            vec![
+                confirmation("subscribe", Some("b".to_string()), 2),
+                crate::message_frame("b", BulkString::from("hi").into()),
+            ]
+        );
+
+        execute(&backend, &mut ctx, crate::resp!(["unsubscribe"])).await?;
+        assert!(!ctx.is_subscribed());
+        let reply = execute(
+            &backend,
+            &mut publisher,
+            crate::resp!(["publish", "b", "hi"]),
+        )
+        .await?;
+        assert_eq!(reply, RespFrame::Integer(0));
+        let reply = execute(&backend, &mut ctx, crate::resp!(["unsubscribe"])).await?;
+        assert_eq!(reply, confirmation("unsubscribe", None, 0));
+        Ok(())
+    }
+}
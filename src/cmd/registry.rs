@@ -0,0 +1,795 @@
+use std::{cmp::Ordering, fmt};
+
+use futures::future::BoxFuture;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+use crate::{backend::Backend, RespArray, RespFrame};
+
+use super::{
+    subcommand, Auth, BgSave, Command, CommandError, CommandExecutor, ConfigGet, ConfigSet,
+    ConnectionContext, Del, Echo, Exists, Expire, ExpireAt, Failover, Get, HGet, HGetAll, HMGet,
+    HScan, HSet, Hello, Info, Keys, LatencyHistory, LatencyLatest, LatencyReset, MGet, MSet,
+    ModuleList, ObjectEncoding, PExpire, PExpireAt, Persist, Ping, Pttl, Publish, ReplicaOf, Role,
+    SAdd, SMembers, SScan, Scan, Set, SisMember, Subscribe, Ttl, Unlink, Unrecognized, Unsubscribe,
+};
+
+pub type CommandParser = fn(RespArray) -> Result<Command, CommandError>;
+
+// 命令参数中 key 的位置，与 redis COMMAND 返回的 first key / last key / step 含义相同，
+// last 为负数表示从末尾倒数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    pub first: i64,
+    pub last: i64,
+    pub step: i64,
+}
+
+impl KeySpec {
+    pub const NONE: KeySpec = KeySpec {
+        first: 0,
+        last: 0,
+        step: 0,
+    };
+
+    pub const fn single(pos: i64) -> Self {
+        KeySpec {
+            first: pos,
+            last: pos,
+            step: 1,
+        }
+    }
+
+    // argc 个参数（包括命令名）的请求中 key 的个数
+    pub fn count(&self, argc: usize) -> usize {
+        if self.first <= 0 || self.step <= 0 {
+            return 0;
+        }
+        let last = if self.last < 0 {
+            argc as i64 + self.last
+        } else {
+            self.last.min(argc as i64 - 1)
+        };
+        if last < self.first {
+            return 0;
+        }
+        ((last - self.first) / self.step + 1) as usize
+    }
+
+    // 请求中的 key，不是字符串的参数被跳过
+    pub fn keys(&self, value: &RespArray) -> Vec<String> {
+        let count = self.count(value.len());
+        (0..count)
+            .map(|i| (self.first + i as i64 * self.step) as usize)
+            .filter_map(|pos| match &value[pos] {
+                RespFrame::BulkString(key) => Some(String::from_utf8_lossy(key).into_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// 命令表中的一项。子命令的名称为 "config|get" 的形式
+// arity 与 redis 一致：正数表示参数个数（包括命令名）固定，负数表示至少 -arity 个。
+// 解析前由命令表检查参数个数，parser 中不需要再检查
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    pub name: String,
+    pub arity: i64,
+    // arity 为负数时最多的参数个数（包括命令名），比如 PING 最多只有一个参数
+    pub max_args: Option<usize>,
+    pub flags: &'static [&'static str],
+    pub keys: KeySpec,
+    pub parser: CommandParser,
+}
+
+impl CommandSpec {
+    pub fn new(
+        name: impl Into<String>,
+        arity: i64,
+        flags: &'static [&'static str],
+        keys: KeySpec,
+        parser: CommandParser,
+    ) -> Self {
+        Self {
+            name: name.into().to_ascii_lowercase(),
+            arity,
+            max_args: None,
+            flags,
+            keys,
+            parser,
+        }
+    }
+
+    pub fn with_max_args(mut self, max_args: usize) -> Self {
+        self.max_args = Some(max_args);
+        self
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    // argc 为包括命令名在内的参数个数
+    pub fn check_arity(&self, argc: usize) -> Result<(), CommandError> {
+        let matched = match self.arity {
+            arity if arity >= 0 => argc as i64 == arity,
+            arity => argc as i64 >= -arity,
+        };
+        match matched && self.max_args.is_none_or(|max| argc <= max) {
+            true => Ok(()),
+            false => Err(CommandError::WrongArity(self.name.clone())),
+        }
+    }
+}
+
+// 命令表按名称排序，查找时用二分查找并逐字节忽略大小写比较，不需要为每个请求分配小写的命令名
+#[derive(Debug, Default, Clone)]
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+    // (改名后的命令名, 原来的命令名)，解析前把请求中的命令名换回原来的名称
+    renamed: Vec<(String, String)>,
+    // 被禁用或改名前的命令名，包括还没有实现的命令，请求这些名称时回复 unknown command，不转发给 upstream
+    disabled: Vec<String>,
+}
+
+lazy_static! {
+    static ref REGISTRY: RwLock<CommandRegistry> = RwLock::new(CommandRegistry::builtin());
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        for spec in builtin_commands() {
+            registry.register(spec);
+        }
+        registry
+    }
+
+    // 同名的命令会被替换，返回原来的命令
+    pub fn register(&mut self, spec: CommandSpec) -> Option<CommandSpec> {
+        match self.position(spec.name.as_bytes(), None) {
+            Ok(i) => Some(std::mem::replace(&mut self.commands[i], spec)),
+            Err(i) => {
+                self.commands.insert(i, spec);
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandSpec> {
+        let i = self.position(name.as_bytes(), None).ok()?;
+        Some(&self.commands[i])
+    }
+
+    // 先按 "命令|子命令" 查找，找不到再按命令名查找
+    pub fn lookup(&self, value: &RespArray) -> Option<&CommandSpec> {
+        let name = match value.first() {
+            Some(RespFrame::BulkString(name)) => name.as_ref(),
+            _ => return None,
+        };
+        let found = match subcommand(value) {
+            Some(sub) => self
+                .position(name, Some(sub))
+                .or_else(|_| self.position(name, None)),
+            None => self.position(name, None),
+        };
+        found.ok().map(|i| &self.commands[i])
+    }
+
+    fn position(&self, name: &[u8], sub: Option<&[u8]>) -> Result<usize, usize> {
+        self.commands
+            .binary_search_by(|spec| compare_name(&spec.name, name, sub))
+    }
+
+    /*
+        命令连同它的子命令一起改名，新名称为空表示删除该命令。返回是否找到了该命令。
+        没有找到的命令同样记录下来：原来的名称不再可用，改名后的名称转发给 upstream 时换回原来的名称
+    */
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        let from = from.to_ascii_lowercase();
+        let to = to.to_ascii_lowercase();
+        let prefix = format!("{}|", from);
+        let (matched, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.commands)
+            .into_iter()
+            .partition(|spec| spec.name == from || spec.name.starts_with(&prefix));
+        self.commands = rest;
+
+        let found = !matched.is_empty();
+        if !to.is_empty() {
+            for mut spec in matched {
+                spec.name = format!("{}{}", to, &spec.name[from.len()..]);
+                self.register(spec);
+            }
+        }
+        let original = match self.renamed.iter().position(|(name, _)| *name == from) {
+            Some(i) => self.renamed.remove(i).1,
+            None => from.clone(),
+        };
+        if !self.disabled.contains(&from) {
+            self.disabled.push(from);
+        }
+        if !to.is_empty() {
+            self.disabled.retain(|name| *name != to);
+            self.renamed.retain(|(name, _)| *name != to);
+            self.renamed.push((to, original));
+        }
+        found
+    }
+
+    // 找到命令对应的解析函数，并把改过名的命令名换回原来的名称。参数个数不对时返回 WrongArity
+    fn resolve(&self, value: &mut RespArray) -> Option<Result<CommandParser, CommandError>> {
+        let spec = self.lookup(value)?;
+        if let Err(e) = spec.check_arity(value.len()) {
+            return Some(Err(e));
+        }
+        let parser = spec.parser;
+        self.restore_name(value);
+        Some(Ok(parser))
+    }
+
+    fn restore_name(&self, value: &mut RespArray) {
+        if let Some(RespFrame::BulkString(name)) = value.0.first_mut() {
+            let original = self
+                .renamed
+                .iter()
+                .find(|(to, _)| to.as_bytes().eq_ignore_ascii_case(name.as_ref()));
+            if let Some((_, original)) = original {
+                *name = original.as_str().into();
+            }
+        }
+    }
+
+    // 未注册的命令解析为 Unrecognized，被禁用或已经改名的名称返回 unknown command
+    fn unrecognized(&self, mut value: RespArray) -> Result<Command, CommandError> {
+        if let Some(RespFrame::BulkString(name)) = value.first() {
+            let disabled = self
+                .disabled
+                .iter()
+                .any(|disabled| disabled.as_bytes().eq_ignore_ascii_case(name.as_ref()));
+            if disabled {
+                return Err(CommandError::UnknownCommand(
+                    String::from_utf8_lossy(name).into_owned(),
+                ));
+            }
+        }
+        self.restore_name(&mut value);
+        Ok(Unrecognized { request: value }.into())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.iter()
+    }
+
+    pub fn parse(&self, mut value: RespArray) -> Result<Command, CommandError> {
+        match self.resolve(&mut value) {
+            Some(parser) => parser?(value),
+            None => self.unrecognized(value),
+        }
+    }
+}
+
+// 注册到全局命令表，之后所有连接都可以使用
+pub fn register_command(spec: CommandSpec) -> Option<CommandSpec> {
+    REGISTRY.write().register(spec)
+}
+
+// 所有命令都不存在时才全部注册，否则返回第一个已经存在的命令名，什么都不注册
+pub(super) fn register_new_commands(specs: Vec<CommandSpec>) -> Result<(), String> {
+    let mut registry = REGISTRY.write();
+    if let Some(spec) = specs.iter().find(|spec| registry.get(&spec.name).is_some()) {
+        return Err(spec.name.clone());
+    }
+    for spec in specs {
+        registry.register(spec);
+    }
+    Ok(())
+}
+
+pub fn rename_command(from: &str, to: &str) -> bool {
+    REGISTRY.write().rename(from, to)
+}
+
+// 改过名的命令换回原来的名称，没有改名时不变
+pub(super) fn restore_command_name(value: &mut RespArray) {
+    REGISTRY.read().restore_name(value);
+}
+
+pub fn command_spec(name: &str) -> Option<CommandSpec> {
+    REGISTRY.read().get(name).cloned()
+}
+
+// 请求中 key 的个数，未知的命令返回 0
+pub fn command_key_count(value: &RespArray) -> usize {
+    REGISTRY
+        .read()
+        .lookup(value)
+        .map_or(0, |spec| spec.keys.count(value.len()))
+}
+
+// 执行前需要的命令表信息：命令的 flags 以及 key 的位置，未知的命令返回 None
+pub fn command_info(value: &RespArray) -> Option<(&'static [&'static str], KeySpec)> {
+    REGISTRY
+        .read()
+        .lookup(value)
+        .map(|spec| (spec.flags, spec.keys))
+}
+
+pub fn command_specs() -> Vec<CommandSpec> {
+    REGISTRY.read().iter().cloned().collect()
+}
+
+pub(super) fn parse_command(mut value: RespArray) -> Result<Command, CommandError> {
+    // 解析前释放锁，parser 中可以访问命令表
+    let parser = {
+        let registry = REGISTRY.read();
+        match registry.resolve(&mut value) {
+            Some(parser) => parser?,
+            None => return registry.unrecognized(value),
+        }
+    };
+    parser(value)
+}
+
+// 表中的名称（已经是小写）与请求中的 "命令" 或 "命令|子命令" 比较，请求部分逐字节转换为小写
+fn compare_name(stored: &str, name: &[u8], sub: Option<&[u8]>) -> Ordering {
+    let sub = sub.into_iter().flat_map(|sub| b"|".iter().chain(sub));
+    let query = name.iter().chain(sub).map(u8::to_ascii_lowercase);
+    stored.bytes().cmp(query)
+}
+
+// 从第一个参数开始的所有参数都是 key，MGET、DEL 等命令使用
+const ALL_KEYS: KeySpec = KeySpec {
+    first: 1,
+    last: -1,
+    step: 1,
+};
+
+fn builtin_commands() -> Vec<CommandSpec> {
+    vec![
+        CommandSpec::new("get", 2, &["readonly", "fast"], KeySpec::single(1), |v| {
+            Ok(Get::try_from(v)?.into())
+        }),
+        CommandSpec::new("set", -3, &["write", "denyoom"], KeySpec::single(1), |v| {
+            Ok(Set::try_from(v)?.into())
+        }),
+        CommandSpec::new("mget", -2, &["readonly", "fast"], ALL_KEYS, |v| {
+            Ok(MGet::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "mset",
+            -3,
+            &["write", "denyoom"],
+            KeySpec {
+                first: 1,
+                last: -1,
+                step: 2,
+            },
+            |v| Ok(MSet::try_from(v)?.into()),
+        ),
+        CommandSpec::new("del", -2, &["write"], ALL_KEYS, |v| {
+            Ok(Del::try_from(v)?.into())
+        }),
+        CommandSpec::new("unlink", -2, &["write", "fast"], ALL_KEYS, |v| {
+            Ok(Unlink::try_from(v)?.into())
+        }),
+        CommandSpec::new("exists", -2, &["readonly", "fast"], ALL_KEYS, |v| {
+            Ok(Exists::try_from(v)?.into())
+        }),
+        CommandSpec::new("expire", -3, &["write", "fast"], KeySpec::single(1), |v| {
+            Ok(Expire::try_from(v)?.into())
+        }),
+        CommandSpec::new("pexpire", -3, &["write", "fast"], KeySpec::single(1), |v| {
+            Ok(PExpire::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "expireat",
+            -3,
+            &["write", "fast"],
+            KeySpec::single(1),
+            |v| Ok(ExpireAt::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "pexpireat",
+            -3,
+            &["write", "fast"],
+            KeySpec::single(1),
+            |v| Ok(PExpireAt::try_from(v)?.into()),
+        ),
+        CommandSpec::new("ttl", 2, &["readonly", "fast"], KeySpec::single(1), |v| {
+            Ok(Ttl::try_from(v)?.into())
+        }),
+        CommandSpec::new("pttl", 2, &["readonly", "fast"], KeySpec::single(1), |v| {
+            Ok(Pttl::try_from(v)?.into())
+        }),
+        CommandSpec::new("persist", 2, &["write", "fast"], KeySpec::single(1), |v| {
+            Ok(Persist::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "sadd",
+            -3,
+            &["write", "denyoom", "fast"],
+            KeySpec::single(1),
+            |v| Ok(SAdd::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "sismember",
+            3,
+            &["readonly", "fast"],
+            KeySpec::single(1),
+            |v| Ok(SisMember::try_from(v)?.into()),
+        ),
+        CommandSpec::new("smembers", 2, &["readonly"], KeySpec::single(1), |v| {
+            Ok(SMembers::try_from(v)?.into())
+        }),
+        CommandSpec::new("sscan", -3, &["readonly"], KeySpec::single(1), |v| {
+            Ok(SScan::try_from(v)?.into())
+        }),
+        CommandSpec::new("hget", 3, &["readonly", "fast"], KeySpec::single(1), |v| {
+            Ok(HGet::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "hset",
+            -4,
+            &["write", "denyoom", "fast"],
+            KeySpec::single(1),
+            |v| Ok(HSet::try_from(v)?.into()),
+        ),
+        CommandSpec::new("hgetall", 2, &["readonly"], KeySpec::single(1), |v| {
+            Ok(HGetAll::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "hmget",
+            -3,
+            &["readonly", "fast"],
+            KeySpec::single(1),
+            |v| Ok(HMGet::try_from(v)?.into()),
+        ),
+        CommandSpec::new("hscan", -3, &["readonly"], KeySpec::single(1), |v| {
+            Ok(HScan::try_from(v)?.into())
+        }),
+        CommandSpec::new("keys", 2, &["readonly"], KeySpec::NONE, |v| {
+            Ok(Keys::try_from(v)?.into())
+        }),
+        CommandSpec::new("scan", -2, &["readonly"], KeySpec::NONE, |v| {
+            Ok(Scan::try_from(v)?.into())
+        }),
+        CommandSpec::new("echo", 2, &["fast"], KeySpec::NONE, |v| {
+            Ok(Echo::try_from(v)?.into())
+        }),
+        CommandSpec::new("ping", -1, &["fast"], KeySpec::NONE, |v| {
+            Ok(Ping::try_from(v)?.into())
+        })
+        .with_max_args(2),
+        CommandSpec::new("hello", -1, &["fast", "no-auth"], KeySpec::NONE, |v| {
+            Ok(Hello::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "auth",
+            -2,
+            &["noscript", "loading", "stale", "fast", "no-auth"],
+            KeySpec::NONE,
+            |v| Ok(Auth::try_from(v)?.into()),
+        )
+        .with_max_args(3),
+        CommandSpec::new(
+            "config|get",
+            -3,
+            &["admin", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(ConfigGet::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "config|set",
+            -4,
+            &["admin", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(ConfigSet::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "object|encoding",
+            3,
+            &["readonly"],
+            KeySpec::single(2),
+            |v| Ok(ObjectEncoding::try_from(v)?.into()),
+        ),
+        CommandSpec::new("info", -1, &["loading", "stale"], KeySpec::NONE, |v| {
+            Ok(Info::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "latency|latest",
+            2,
+            &["admin", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(LatencyLatest::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "latency|history",
+            3,
+            &["admin", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(LatencyHistory::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "latency|reset",
+            -2,
+            &["admin", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(LatencyReset::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "module|list",
+            2,
+            &["admin", "noscript"],
+            KeySpec::NONE,
+            |v| Ok(ModuleList::try_from(v)?.into()),
+        ),
+        CommandSpec::new("bgsave", 1, &["admin", "noscript"], KeySpec::NONE, |v| {
+            Ok(BgSave::try_from(v)?.into())
+        }),
+        CommandSpec::new(
+            "subscribe",
+            -2,
+            &["pubsub", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(Subscribe::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "unsubscribe",
+            -1,
+            &["pubsub", "noscript", "loading", "stale"],
+            KeySpec::NONE,
+            |v| Ok(Unsubscribe::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "publish",
+            3,
+            &["pubsub", "loading", "stale", "fast"],
+            KeySpec::NONE,
+            |v| Ok(Publish::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "role",
+            1,
+            &["noscript", "loading", "stale", "fast"],
+            KeySpec::NONE,
+            |v| Ok(Role::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "replicaof",
+            3,
+            &["admin", "noscript", "stale"],
+            KeySpec::NONE,
+            |v| Ok(ReplicaOf::try_from(v)?.into()),
+        ),
+        CommandSpec::new(
+            "failover",
+            -1,
+            &["admin", "noscript", "stale"],
+            KeySpec::NONE,
+            |v| Ok(Failover::try_from(v)?.into()),
+        ),
+    ]
+}
+
+// 库的使用者注册的命令，执行时通过 trait object 分发
+// trait object 无法使用 async fn，实现时返回 Box::pin(async move { ... })
+pub trait DynCommand: fmt::Debug + Send + Sync {
+    // 用于延迟统计，默认所有自定义命令统计在一起
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    fn execute_boxed<'a>(
+        self: Box<Self>,
+        backend: &'a Backend,
+        ctx: &'a mut ConnectionContext,
+    ) -> BoxFuture<'a, Result<RespFrame, CommandError>>;
+}
+
+#[derive(Debug)]
+pub struct CustomCommand(Box<dyn DynCommand>);
+
+impl CustomCommand {
+    pub fn new(cmd: impl DynCommand + 'static) -> Self {
+        CustomCommand(Box::new(cmd))
+    }
+
+    pub fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+impl CommandExecutor for CustomCommand {
+    async fn execute(
+        self,
+        backend: &Backend,
+        ctx: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        self.0.execute_boxed(backend, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[derive(Debug)]
+    struct Double(i64);
+
+    impl DynCommand for Double {
+        fn execute_boxed<'a>(
+            self: Box<Self>,
+            _: &'a Backend,
+            _: &'a mut ConnectionContext,
+        ) -> BoxFuture<'a, Result<RespFrame, CommandError>> {
+            Box::pin(async move { Ok(RespFrame::Integer(self.0 * 2)) })
+        }
+    }
+
+    fn parse_double(value: RespArray) -> Result<Command, CommandError> {
+        let arg = value
+            .get(1)
+            .cloned()
+            .ok_or_else(|| CommandError::InvalidArgument("Missing argument".to_string()))?;
+        Ok(CustomCommand::new(Double(arg.try_into()?)).into())
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        let registry = CommandRegistry::builtin();
+        let spec = registry.get("GET").unwrap();
+        assert_eq!(spec.arity, 2);
+        assert_eq!(spec.keys, KeySpec::single(1));
+
+        let value = RespArray::new([
+            BulkString::from("CONFIG").into(),
+            BulkString::from("Get").into(),
+            BulkString::from("*").into(),
+        ]);
+        assert_eq!(registry.lookup(&value).unwrap().name, "config|get");
+        assert!(matches!(registry.parse(value), Ok(Command::ConfigGet(_))));
+
+        let value = RespArray::new([BulkString::from("nosuchcommand").into()]);
+        assert!(matches!(
+            registry.parse(value),
+            Ok(Command::Unrecognized(_))
+        ));
+    }
+
+    #[test]
+    fn test_lookup_ignores_case_without_allocating() {
+        assert_eq!(
+            compare_name("config|get", b"CONFIG", Some(b"GeT")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_name("config", b"CONFIG", Some(b"get")),
+            Ordering::Less
+        );
+        assert_eq!(compare_name("get", b"GETX", None), Ordering::Less);
+        assert_eq!(compare_name("hget", b"GET", None), Ordering::Greater);
+
+        let registry = CommandRegistry::builtin();
+        let names = registry
+            .iter()
+            .map(|spec| spec.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+        for name in names {
+            assert_eq!(registry.get(&name.to_ascii_uppercase()).unwrap().name, name);
+        }
+
+        // 子命令未注册时按命令名查找
+        let value = RespArray::new([
+            BulkString::from("Ping").into(),
+            BulkString::from("Hello").into(),
+        ]);
+        assert_eq!(registry.lookup(&value).unwrap().name, "ping");
+    }
+
+    #[test]
+    fn test_key_spec_count() {
+        assert_eq!(KeySpec::NONE.count(3), 0);
+        assert_eq!(KeySpec::single(1).count(3), 1);
+        assert_eq!(KeySpec::single(2).count(2), 0);
+        let mset = KeySpec {
+            first: 1,
+            last: -1,
+            step: 2,
+        };
+        assert_eq!(mset.count(5), 2);
+
+        let value = RespArray::new([
+            BulkString::from("hget").into(),
+            BulkString::from("k").into(),
+            BulkString::from("f").into(),
+        ]);
+        assert_eq!(command_key_count(&value), 1);
+    }
+
+    #[test]
+    fn test_key_spec_keys() -> Result<()> {
+        let value = RespArray::try_from(crate::resp!(["mset", "a", "1", "b", "2"]))?;
+        let spec = command_spec("mset").unwrap();
+        assert_eq!(spec.keys.keys(&value), vec!["a", "b"]);
+        assert!(spec.has_flag("write"));
+
+        let value = RespArray::try_from(crate::resp!(["object", "encoding", "key"]))?;
+        assert_eq!(KeySpec::single(2).keys(&value), vec!["key"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_command() {
+        let mut registry = CommandRegistry::builtin();
+        assert!(registry.rename("CONFIG", "cfg"));
+        assert!(registry.get("config|get").is_none());
+        assert_eq!(registry.get("cfg|set").unwrap().name, "cfg|set");
+
+        let value = RespArray::new([
+            BulkString::from("CFG").into(),
+            BulkString::from("get").into(),
+            BulkString::from("*").into(),
+        ]);
+        assert!(matches!(registry.parse(value), Ok(Command::ConfigGet(_))));
+
+        assert!(registry.rename("hgetall", ""));
+        assert!(registry.get("hgetall").is_none());
+        assert!(!registry.rename("nosuchcommand", "x"));
+
+        // 禁用和改名前的名称，包括没有实现的命令，都回复 unknown command 而不是转发
+        assert!(!registry.rename("flushall", ""));
+        for args in [
+            crate::resp!(["HGETALL", "key"]),
+            crate::resp!(["config", "get", "*"]),
+            crate::resp!(["FlushAll"]),
+            crate::resp!(["nosuchcommand"]),
+        ] {
+            let value = RespArray::try_from(args).unwrap();
+            let name = String::try_from(value[0].clone()).unwrap();
+            let err = registry.parse(value).unwrap_err();
+            assert_eq!(err.to_string(), format!("unknown command '{}'", name));
+        }
+
+        // 没有实现的命令改名之后，以原来的名称转发
+        let value = RespArray::try_from(crate::resp!(["X", "arg"])).unwrap();
+        let Ok(Command::Unrecognized(cmd)) = registry.parse(value) else {
+            panic!("expected unrecognized command");
+        };
+        assert_eq!(cmd.request[0], BulkString::from("nosuchcommand").into());
+
+        // 名称被重新使用之后不再是禁用的
+        assert!(registry.rename("get", "config"));
+        let value = RespArray::try_from(crate::resp!(["config", "k"])).unwrap();
+        assert!(matches!(registry.parse(value), Ok(Command::Get(_))));
+    }
+
+    #[tokio::test]
+    async fn test_register_custom_command() -> Result<()> {
+        register_command(CommandSpec::new(
+            "test.double",
+            2,
+            &["fast"],
+            KeySpec::NONE,
+            parse_double,
+        ));
+        assert_eq!(command_spec("TEST.DOUBLE").unwrap().arity, 2);
+
+        let mut buf = BytesMut::from("*2\r\n$11\r\ntest.double\r\n$2\r\n21\r\n");
+        let cmd = Command::try_from(RespArray::decode(&mut buf)?)?;
+        assert_eq!(
+            cmd.execute(&Backend::new(), &mut ConnectionContext::default())
+                .await?,
+            RespFrame::Integer(42)
+        );
+        Ok(())
+    }
+}
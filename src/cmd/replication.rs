@@ -0,0 +1,210 @@
+// 实现 role、replicaof 和 failover 命令
+use crate::{Backend, BulkString, RespArray, RespFrame};
+
+use super::{
+    extract_args, syntax_error, ArgParser, CommandError, CommandExecutor, ConnectionContext,
+    Failover, ReplicaOf, ReplicaTarget, Role, RESP_OK,
+};
+
+/*
+    还没有实现复制，服务器总是没有副本的 master。FAILOVER 的协调切换没有实现：
+    不会暂停写入、等待副本同步，也不会把降级和提升记录到 ROLE / INFO 中。
+    这里只提供与 redis 对没有副本的 master 执行时一致的回复，
+    让依赖 ROLE / INFO replication 的客户端和哨兵脚本能够识别它：
+    - REPLICAOF NO ONE 直接返回 OK，ROLE / INFO 中的角色不变
+    - REPLICAOF host port 返回错误
+    - FAILOVER 检查参数之后总是返回没有副本时的错误，错误描述原样回复
+*/
+impl CommandExecutor for Role {
+    async fn execute(
+        self,
+        _: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        // [role, 复制偏移量, 副本列表]
+        Ok(RespArray::new(vec![
+            BulkString::from("master").into(),
+            RespFrame::Integer(0),
+            RespArray::new(vec![]).into(),
+        ])
+        .into())
+    }
+}
+
+impl CommandExecutor for ReplicaOf {
+    async fn execute(
+        self,
+        _: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        match self.target {
+            ReplicaTarget::NoOne => Ok(RESP_OK.clone()),
+            ReplicaTarget::Master { .. } => Err(CommandError::Replication(
+                "replication is not supported by this server".to_string(),
+            )),
+        }
+    }
+}
+
+impl CommandExecutor for Failover {
+    async fn execute(
+        self,
+        _: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let message = if self.abort {
+            "No failover in progress."
+        } else {
+            "FAILOVER requires connected replicas."
+        };
+        Err(CommandError::Replication(message.to_string()))
+    }
+}
+
+// INFO replication 中的字段，与 redis 没有副本的 master 一致
+pub(super) fn replication_info() -> &'static str {
+    "role:master\r\nconnected_slaves:0\r\nmaster_failover_state:no-failover\r\nmaster_repl_offset:0\r\n"
+}
+
+impl TryFrom<RespArray> for Role {
+    type Error = CommandError;
+
+    fn try_from(_: RespArray) -> Result<Self, Self::Error> {
+        Ok(Role)
+    }
+}
+
+impl TryFrom<RespArray> for ReplicaOf {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let host = args.next_string("host")?;
+        let port = args.next_string("port")?;
+        args.finish()?;
+
+        if host.eq_ignore_ascii_case("no") && port.eq_ignore_ascii_case("one") {
+            return Ok(ReplicaOf {
+                target: ReplicaTarget::NoOne,
+            });
+        }
+        let port = port
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("Invalid master port".to_string()))?;
+        Ok(ReplicaOf {
+            target: ReplicaTarget::Master { host, port },
+        })
+    }
+}
+
+// FAILOVER [TO host port [FORCE]] [ABORT] [TIMEOUT milliseconds]，组合规则与 redis 相同
+impl TryFrom<RespArray> for Failover {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let mut failover = Failover {
+            to: None,
+            force: false,
+            abort: false,
+            timeout: None,
+        };
+        while let Some(keyword) = args.next_keyword()? {
+            match keyword.as_str() {
+                "TO" if failover.to.is_none() => {
+                    let host = args.value::<String>("TO")?;
+                    let port = args.value::<i64>("TO")?;
+                    let port = u16::try_from(port).map_err(|_| syntax_error())?;
+                    failover.to = Some((host, port));
+                }
+                "FORCE" if !failover.force => failover.force = true,
+                "ABORT" if !failover.abort => failover.abort = true,
+                "TIMEOUT" if failover.timeout.is_none() => {
+                    let timeout = args.value::<i64>("TIMEOUT")?;
+                    if timeout <= 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "FAILOVER timeout must be greater than 0".to_string(),
+                        ));
+                    }
+                    failover.timeout = Some(timeout as u64);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        if failover.abort && (failover.to.is_some() || failover.timeout.is_some()) {
+            return Err(CommandError::InvalidArgument(
+                "FAILOVER ABORT can't be used with other arguments.".to_string(),
+            ));
+        }
+        if failover.force && (failover.to.is_none() || failover.timeout.is_none()) {
+            return Err(CommandError::InvalidArgument(
+                "FAILOVER with force option requires both a timeout and target HOST and IP."
+                    .to_string(),
+            ));
+        }
+        Ok(failover)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+
+    async fn execute(frame: RespFrame) -> anyhow::Result<Result<RespFrame, CommandError>> {
+        let cmd = Command::try_from(RespArray::try_from(frame)?)?;
+        Ok(cmd
+            .execute(&Backend::new(), &mut ConnectionContext::default())
+            .await)
+    }
+
+    #[tokio::test]
+    async fn test_role_and_replicaof() -> anyhow::Result<()> {
+        let RespFrame::Array(role) = execute(crate::resp!(["role"])).await?? else {
+            panic!("expected array");
+        };
+        assert_eq!(role[0], BulkString::from("master").into());
+
+        assert_eq!(
+            execute(crate::resp!(["replicaof", "no", "one"])).await??,
+            RESP_OK.clone()
+        );
+        assert!(execute(crate::resp!(["replicaof", "127.0.0.1", "6380"]))
+            .await?
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover_arguments() -> anyhow::Result<()> {
+        let err = execute(crate::resp!(["failover", "to", "127.0.0.1", "6380"]))
+            .await?
+            .unwrap_err();
+        assert_eq!(err.to_string(), "FAILOVER requires connected replicas.");
+        let err = execute(crate::resp!(["failover", "abort"]))
+            .await?
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No failover in progress.");
+
+        let err = Command::try_from(RespArray::try_from(crate::resp!([
+            "failover", "abort", "timeout", "10"
+        ]))?)
+        .unwrap_err();
+        assert_eq!(
+            RespFrame::from(err),
+            crate::SimpleError::new("ERR FAILOVER ABORT can't be used with other arguments.")
+                .into()
+        );
+
+        for args in [
+            crate::resp!(["failover", "abort", "timeout", "10"]),
+            crate::resp!(["failover", "to", "127.0.0.1", "6380", "force"]),
+            crate::resp!(["failover", "timeout", "0"]),
+            crate::resp!(["failover", "abort", "abort"]),
+        ] {
+            assert!(Command::try_from(RespArray::try_from(args)?).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,523 @@
+// 实现 config 等服务器管理相关的命令
+use std::fmt::Write;
+
+use crate::{
+    glob_match, Backend, BulkString, RespArray, RespFrame, RespMap, ServerConfig, SimpleString,
+};
+
+use super::{
+    extract_args, loaded_plugins, ArgParser, BgSave, CommandError, CommandExecutor, ConfigGet,
+    ConfigSet, ConnectionContext, Info, LatencyHistory, LatencyLatest, LatencyReset, ModuleList,
+    RESP_OK,
+};
+
+// INFO 输出的 section，按顺序输出
+const INFO_SECTIONS: &[(&str, &str)] = &[
+    ("server", "Server"),
+    ("persistence", "Persistence"),
+    ("stats", "Stats"),
+    ("replication", "Replication"),
+    ("latencystats", "Latencystats"),
+    ("keyspace", "Keyspace"),
+    ("hotkeys", "Hotkeys"),
+];
+// INFO hotkeys 输出的 key 个数
+const INFO_HOTKEYS: usize = 16;
+
+impl CommandExecutor for ConfigGet {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let config = backend.config();
+        let mut map = RespMap::new();
+        ServerConfig::NAMES
+            .iter()
+            .filter(|name| glob_match(self.pattern.as_bytes(), name.as_bytes(), true))
+            .filter_map(|name| config.get(name).map(|value| (*name, value)))
+            .for_each(|(name, value)| {
+                map.insert(name.to_string(), BulkString::from(value).into());
+            });
+
+        Ok(map.into())
+    }
+}
+
+impl CommandExecutor for ConfigSet {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        backend.set_config(&self.name, &self.value)?;
+        Ok(RESP_OK.clone())
+    }
+}
+
+/*
+    INFO [section ...]
+    与 redis 一样返回 "# Section" 开头、每行 "name:value" 的文本
+*/
+impl CommandExecutor for Info {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let all = self.sections.is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|s| matches!(s.as_str(), "all" | "default" | "everything"));
+
+        let mut info = String::new();
+        for (section, title) in INFO_SECTIONS {
+            if !all && !self.sections.iter().any(|s| s == section) {
+                continue;
+            }
+            if !info.is_empty() {
+                info.push_str("\r\n");
+            }
+            let _ = write!(info, "# {}\r\n", title);
+            write_section(&mut info, section, backend);
+        }
+        Ok(BulkString::from(info).into())
+    }
+}
+
+fn write_section(info: &mut String, section: &str, backend: &Backend) {
+    match section {
+        "server" => {
+            let _ = write!(
+                info,
+                "redis_version:{}\r\nprocess_id:{}\r\ntcp_port:{}\r\n",
+                env!("CARGO_PKG_VERSION"),
+                std::process::id(),
+                backend.config().port
+            );
+        }
+        "persistence" => {
+            let state = backend.save_state();
+            let _ = write!(
+                info,
+                "rdb_bgsave_in_progress:{}\r\nrdb_last_save_time:{}\r\nrdb_last_bgsave_status:{}\r\n",
+                state.in_progress() as u8,
+                state.last_save_time(),
+                state.last_status()
+            );
+        }
+        "replication" => info.push_str(super::replication::replication_info()),
+        "stats" => {
+            for (name, value) in backend.stats().fields() {
+                let _ = write!(info, "{}:{}\r\n", name, value);
+            }
+        }
+        // 与 redis 一样以微秒为单位，保留三位小数
+        "latencystats" => {
+            for (name, histogram) in backend.latency().all() {
+                let _ = write!(
+                    info,
+                    "latency_percentiles_usec_{}:p50={:.3},p99={:.3},p99.9={:.3}\r\n",
+                    name,
+                    histogram.percentile(50.0) as f64,
+                    histogram.percentile(99.0) as f64,
+                    histogram.percentile(99.9) as f64
+                );
+            }
+        }
+        // 与 redis 一样不输出没有 key 的数据库
+        "keyspace" => {
+            let stats = backend.keyspace_stats();
+            if stats.keys > 0 {
+                let _ = write!(
+                    info,
+                    "db0:keys={},expires={},avg_ttl={}\r\n",
+                    stats.keys, stats.expires, stats.avg_ttl
+                );
+            }
+        }
+        // 按访问次数从大到小，未开启 hotkeys-capacity 时为空
+        "hotkeys" => {
+            for (i, hot) in backend.hot_keys(INFO_HOTKEYS).iter().enumerate() {
+                let _ = write!(
+                    info,
+                    "hotkey_{}:key={},count={},error={}\r\n",
+                    i, hot.key, hot.count, hot.error
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/*
+    LATENCY LATEST
+    每个执行过的命令返回 [命令名, 最近一次执行的 unix 时间, 最近一次的延迟, 最大延迟]，
+    延迟的单位为微秒
+*/
+impl CommandExecutor for LatencyLatest {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let latest = backend
+            .latency()
+            .all()
+            .into_iter()
+            .map(|(name, histogram)| {
+                RespArray::new(vec![
+                    BulkString::from(name).into(),
+                    RespFrame::Integer(histogram.latest_at as i64),
+                    RespFrame::Integer(histogram.latest_us as i64),
+                    RespFrame::Integer(histogram.max_us as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(latest).into())
+    }
+}
+
+/*
+    LATENCY HISTORY command
+    返回命令最近 160 秒中每秒的最大延迟 [[unix 时间, 延迟], ...]
+*/
+impl CommandExecutor for LatencyHistory {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let history = match backend.latency().get(&self.command) {
+            Some(histogram) => histogram
+                .history()
+                .map(|(at, us)| {
+                    RespArray::new(vec![
+                        RespFrame::Integer(*at as i64),
+                        RespFrame::Integer(*us as i64),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<RespFrame>>(),
+            None => Vec::new(),
+        };
+        Ok(RespArray::new(history).into())
+    }
+}
+
+// LATENCY RESET [command ...]，返回重置的命令个数
+impl CommandExecutor for LatencyReset {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let count = backend.latency().reset(&self.commands);
+        Ok(RespFrame::Integer(count as i64))
+    }
+}
+
+impl CommandExecutor for BgSave {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        if !backend.bgsave() {
+            return Err(CommandError::SaveInProgress);
+        }
+        Ok(SimpleString::new("Background saving started").into())
+    }
+}
+
+// 与 redis 一样每个插件是一个包含 name 和 ver 的 map
+impl CommandExecutor for ModuleList {
+    async fn execute(
+        self,
+        _: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let modules = loaded_plugins()
+            .into_iter()
+            .map(|plugin| {
+                let mut map = RespMap::new();
+                map.insert("name".to_string(), BulkString::from(plugin.name).into());
+                map.insert("ver".to_string(), plugin.version.into());
+                map.into()
+            })
+            .collect::<Vec<RespFrame>>();
+        Ok(RespArray::new(modules).into())
+    }
+}
+
+impl TryFrom<RespArray> for ModuleList {
+    type Error = CommandError;
+
+    fn try_from(_: RespArray) -> Result<Self, Self::Error> {
+        Ok(ModuleList)
+    }
+}
+
+impl TryFrom<RespArray> for BgSave {
+    type Error = CommandError;
+
+    fn try_from(_: RespArray) -> Result<Self, Self::Error> {
+        Ok(BgSave)
+    }
+}
+
+impl TryFrom<RespArray> for Info {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let sections = ArgParser::new(extract_args(value, 1)?)
+            .rest::<String>()?
+            .into_iter()
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+        Ok(Info { sections })
+    }
+}
+
+impl TryFrom<RespArray> for LatencyLatest {
+    type Error = CommandError;
+
+    fn try_from(_: RespArray) -> Result<Self, Self::Error> {
+        Ok(LatencyLatest)
+    }
+}
+
+impl TryFrom<RespArray> for LatencyHistory {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 2)?);
+        let command = args.next_string("command")?.to_ascii_lowercase();
+        args.finish()?;
+        Ok(LatencyHistory { command })
+    }
+}
+
+impl TryFrom<RespArray> for LatencyReset {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let commands = ArgParser::new(extract_args(value, 2)?)
+            .rest::<String>()?
+            .into_iter()
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+        Ok(LatencyReset { commands })
+    }
+}
+
+impl TryFrom<RespArray> for ConfigGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 2)?);
+        let pattern = args.next_string("pattern")?;
+        args.finish()?;
+        Ok(ConfigGet { pattern })
+    }
+}
+
+impl TryFrom<RespArray> for ConfigSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 2)?);
+        let name = args.next_string("name")?;
+        let value = args.next_string("value")?;
+        args.finish()?;
+        Ok(ConfigSet { name, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RespDecode;
+
+    use super::*;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_config_set_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nconfig\r\n$3\r\nset\r\n$22\r\nset-max-intset-entries\r\n$2\r\n16\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: ConfigSet = frame.try_into()?;
+        assert_eq!(result.name, "set-max-intset-entries");
+        assert_eq!(result.value, "16");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_set_get_command() {
+        let backend = Backend::new();
+
+        let cmd = ConfigSet {
+            name: "hash-max-listpack-entries".to_string(),
+            value: "16".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            RESP_OK.clone()
+        );
+
+        let cmd = ConfigGet {
+            pattern: "hash-max-listpack-entries".to_string(),
+        };
+        let mut expected = RespMap::new();
+        expected.insert(
+            "hash-max-listpack-entries".to_string(),
+            BulkString::from("16").into(),
+        );
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await
+                .unwrap(),
+            expected.into()
+        );
+
+        // 模式按 glob 匹配，不区分大小写
+        let cmd = ConfigGet {
+            pattern: "PROTO-MAX-*-LEN".to_string(),
+        };
+        let RespFrame::Map(map) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await
+            .unwrap()
+        else {
+            panic!("expected a map reply");
+        };
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            vec!["proto-max-bulk-len", "proto-max-multibulk-len"]
+        );
+
+        let cmd = ConfigSet {
+            name: "no-such-option".to_string(),
+            value: "1".to_string(),
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await,
+            Err(CommandError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_latency_commands() -> Result<()> {
+        let backend = Backend::new();
+        let mut ctx = ConnectionContext::default();
+        backend
+            .latency()
+            .record("get", std::time::Duration::from_micros(100));
+
+        let RespFrame::Array(latest) = LatencyLatest.execute(&backend, &mut ctx).await? else {
+            panic!("expected array");
+        };
+        assert_eq!(latest.len(), 1);
+
+        let cmd = LatencyHistory::try_from(RespArray::try_from(crate::resp!([
+            "latency", "history", "GET"
+        ]))?)?;
+        let RespFrame::Array(history) = cmd.execute(&backend, &mut ctx).await? else {
+            panic!("expected array");
+        };
+        assert_eq!(history.len(), 1);
+
+        let cmd = LatencyReset::try_from(RespArray::try_from(crate::resp!(["latency", "reset"]))?)?;
+        assert_eq!(
+            cmd.execute(&backend, &mut ctx).await?,
+            RespFrame::Integer(1)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_info_latencystats() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .latency()
+            .record("get", std::time::Duration::from_micros(100));
+
+        let cmd = Info::try_from(RespArray::try_from(crate::resp!(["info", "LatencyStats"]))?)?;
+        let RespFrame::BulkString(info) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?
+        else {
+            panic!("expected bulk string");
+        };
+        let info = String::from_utf8_lossy(info.as_ref()).to_string();
+        assert!(info.starts_with("# Latencystats\r\n"));
+        assert!(info.contains("latency_percentiles_usec_get:p50=100.000,p99=100.000"));
+        assert!(!info.contains("# Server"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bgsave_command() -> Result<()> {
+        let backend = Backend::new();
+        let dir = std::env::temp_dir().join(format!("simple-redis-bgsave-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        backend.set_config("dir", dir.to_str().unwrap())?;
+        backend.set("key".to_string(), RespFrame::BulkString("value".into()));
+
+        let cmd = BgSave::try_from(RespArray::try_from(crate::resp!(["bgsave"]))?)?;
+        let ret = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(ret, SimpleString::new("Background saving started").into());
+        while backend.save_state().in_progress() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(backend.save_path().exists());
+
+        let cmd = Info::try_from(RespArray::try_from(crate::resp!(["info", "persistence"]))?)?;
+        let RespFrame::BulkString(info) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?
+        else {
+            panic!("expected bulk string");
+        };
+        let info = String::from_utf8_lossy(info.as_ref()).to_string();
+        assert!(info.contains("rdb_bgsave_in_progress:0\r\n"));
+        assert!(info.contains("rdb_last_bgsave_status:ok\r\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_info_keyspace() -> Result<()> {
+        let backend = Backend::new();
+        let info = || async {
+            let cmd = Info::try_from(RespArray::try_from(crate::resp!(["info", "keyspace"]))?)?;
+            let RespFrame::BulkString(info) = cmd
+                .execute(&backend, &mut ConnectionContext::default())
+                .await?
+            else {
+                panic!("expected bulk string");
+            };
+            Ok::<_, anyhow::Error>(String::from_utf8_lossy(info.as_ref()).to_string())
+        };
+        assert_eq!(info().await?, "# Keyspace\r\n");
+
+        backend.set_string("a", "1");
+        backend.set_string("b", "2");
+        assert_eq!(
+            info().await?,
+            "# Keyspace\r\ndb0:keys=2,expires=0,avg_ttl=0\r\n"
+        );
+        Ok(())
+    }
+}
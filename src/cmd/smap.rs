@@ -1,19 +1,56 @@
-use crate::{Backend, RespArray, RespFrame};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespSet, Value, ValueKind};
 
 use super::{
-    extract_args, validate_command, CommandError, CommandExecutor, SAdd, SisMember,
-    TryIntoBulkString,
+    check_locked_type, check_type, extract_args, matches_pattern, parse_cursor, parse_scan_options,
+    scan_reply, single_key, ArgParser, CommandError, CommandExecutor, ConnectionContext, SAdd,
+    SMembers, SScan, SisMember,
 };
 
 impl CommandExecutor for SAdd {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        backend.sadd(&self.key, &self.values)
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let mut guard = backend.lock_key(&self.key);
+        check_locked_type(&guard, &self.key, ValueKind::Set)?;
+        let added = guard.sadd_members(&self.key, self.values);
+        Ok(RespFrame::Integer(added))
     }
 }
 
 impl CommandExecutor for SisMember {
-    fn execute(self, backend: &Backend) -> RespFrame {
-        backend.sismember(&self.key, &self.value)
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::Set)?;
+        Ok(backend.sismember(&self.key, &self.value).into())
+    }
+}
+
+impl CommandExecutor for SMembers {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        let mut members = match backend.get_value(&self.key) {
+            Some(Value::Set(set)) => set.iter().cloned().collect::<Vec<_>>(),
+            Some(_) => return Err(CommandError::WrongType),
+            None => Vec::new(),
+        };
+        if backend.config().sort_replies {
+            members.sort_unstable();
+        }
+        Ok(RespSet::new(
+            members
+                .into_iter()
+                .map(|member| BulkString::from(member).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into())
     }
 }
 
@@ -22,26 +59,9 @@ impl TryFrom<RespArray> for SAdd {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        match value.len() > 2 {
-            true => validate_command(&value, &["sadd"], value.len() - 1)?,
-            false => {
-                return Err(CommandError::InvalidArgument(
-                    "wrong number of arguments for 'sadd' command".to_string(),
-                ))
-            }
-        }
-
-        let mut args = extract_args(value, 1)?.into_iter();
-
-        let key = args
-            .next()
-            .ok_or_else(|| CommandError::InvalidArgument("Missing key".to_string()))?
-            .try_into_bulk_string()?;
-
-        let values = args
-            .map(RespFrame::try_into_bulk_string)
-            .collect::<Result<Vec<String>, Self::Error>>()?;
-
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let values = args.rest()?;
         Ok(SAdd { key, values })
     }
 }
@@ -50,20 +70,54 @@ impl TryFrom<RespArray> for SAdd {
 impl TryFrom<RespArray> for SisMember {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sismember"], 2)?;
-
-        let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => {
-                Ok(SisMember {
-                    key: String::from_utf8(key.0)?,
-                    value: String::from_utf8(field.0)?,
-                })
-            }
-            _ => Err(CommandError::InvalidArgument(
-                "Invalid key or field".to_string(),
-            )),
-        }
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let value = args.next_string("member")?;
+        args.finish()?;
+        Ok(SisMember { key, value })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SMembers {
+            key: single_key(value)?,
+        })
+    }
+}
+
+impl CommandExecutor for SScan {
+    async fn execute(
+        self,
+        backend: &Backend,
+        _: &mut ConnectionContext,
+    ) -> Result<RespFrame, CommandError> {
+        check_type(backend, &self.key, ValueKind::Set)?;
+        let (cursor, members) = backend.sscan(&self.key, self.cursor, self.count);
+        let items = members
+            .into_iter()
+            .filter(|member| matches_pattern(&self.pattern, member))
+            .map(|member| BulkString::from(member).into())
+            .collect();
+        Ok(scan_reply(cursor, items))
+    }
+}
+
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = ArgParser::new(extract_args(value, 1)?);
+        let key = args.next_string("key")?;
+        let cursor = parse_cursor(&mut args)?;
+        let (pattern, count) = parse_scan_options(&mut args, |_, _| Ok(false))?;
+        Ok(SScan {
+            key,
+            cursor,
+            pattern,
+            count,
+        })
     }
 }
 
@@ -75,65 +129,198 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_sadd_one_value_command() -> Result<()> {
+    fn test_sadd_try_from() -> Result<()> {
+        let frame = RespArray::try_from(crate::resp!(["sadd", "k1", "v1", "v2"]))?;
+        let result: SAdd = frame.try_into()?;
+        assert_eq!(result.key, "k1");
+        assert_eq!(result.values, vec!["v1", "v2"]);
+
+        let frame = RespArray::try_from(crate::resp!(["sadd", "k1", 1]))?;
+        assert!(SAdd::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sadd_one_value_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = SAdd {
             key: "k1".to_string(),
             values: vec!["v1".to_string()],
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_1.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RESP_INT_1);
 
         let cmd = SAdd {
             key: "k1".to_string(),
             values: vec!["v1".to_string()],
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_0.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RESP_INT_0);
 
         let cmd = SAdd {
             key: "k1".to_string(),
             values: vec!["v2".to_string()],
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_1.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RESP_INT_1);
         Ok(())
     }
-    #[test]
-    fn test_sadd_more_value_command() -> Result<()> {
+    #[tokio::test]
+    async fn test_sadd_more_value_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = SAdd {
             key: "k1".to_string(),
             values: vec!["v1".to_string(), "v2".to_string()],
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_2.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RESP_INT_2);
         Ok(())
     }
 
-    #[test]
-    fn test_sismember_command() -> Result<()> {
+    #[tokio::test]
+    async fn test_sismember_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = SisMember {
             key: "k1".to_string(),
             value: "v1".to_string(),
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_0.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RespFrame::Boolean(false));
 
         // sadd 添加数据
         let cmd = SAdd {
             key: "k1".to_string(),
             values: vec!["v1".to_string()],
         };
-        cmd.execute(&backend);
+        cmd.execute(&backend, &mut ConnectionContext::default())
+            .await?;
 
         let cmd = SisMember {
             key: "k1".to_string(),
             value: "v1".to_string(),
         };
-        let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_INT_1.clone());
+        let result = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        assert_eq!(result, RespFrame::Boolean(true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_smembers_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = SMembers {
+            key: "k1".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await?,
+            RespSet::new([]).into()
+        );
+
+        backend.sadd("k1", ["v1"]);
+        let cmd = SMembers {
+            key: "k1".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await?,
+            RespSet::new([BulkString::from("v1").into()]).into()
+        );
+
+        backend.set_config("sort-replies", "yes")?;
+        backend.sadd("k1", ["c", "a", "b"]);
+        let cmd = SMembers {
+            key: "k1".to_string(),
+        };
+        let RespFrame::Set(set) = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?
+        else {
+            panic!("expected a set reply");
+        };
+        let members = set
+            .0
+            .into_iter()
+            .map(String::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(members, vec!["a", "b", "c", "v1"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sscan_command() -> Result<()> {
+        let frame = RespArray::try_from(crate::resp!([
+            "sscan", "k1", "0", "COUNT", "100", "MATCH", "[ab]"
+        ]))?;
+        let cmd: SScan = frame.try_into()?;
+        assert_eq!((cmd.key.as_str(), cmd.cursor, cmd.count), ("k1", 0, 100));
+        assert_eq!(cmd.pattern.as_deref(), Some("[ab]"));
+
+        let backend = Backend::new();
+        backend.sadd("k1", ["a", "b", "c"]);
+        let reply = cmd
+            .execute(&backend, &mut ConnectionContext::default())
+            .await?;
+        let RespFrame::Array(reply) = reply else {
+            panic!("expected an array reply");
+        };
+        assert_eq!(reply[0], BulkString::from("0").into());
+        assert!(matches!(&reply[1], RespFrame::Array(members) if members.len() == 2));
+
+        backend.set_string("str", "v");
+        let cmd = SScan {
+            key: "str".to_string(),
+            cursor: 0,
+            pattern: None,
+            count: 10,
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ConnectionContext::default())
+                .await,
+            Err(CommandError::WrongType)
+        ));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_aggregate_reads_check_type() {
+        let backend = Backend::new();
+        backend.set_string("str", "v");
+        backend.hash("h").set("f", "v");
+        let mut ctx = ConnectionContext::default();
+
+        let cmd = SMembers {
+            key: "str".to_string(),
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ctx).await,
+            Err(CommandError::WrongType)
+        ));
+        let cmd = crate::cmd::HGetAll {
+            key: "str".to_string(),
+        };
+        assert!(matches!(
+            cmd.execute(&backend, &mut ctx).await,
+            Err(CommandError::WrongType)
+        ));
+
+        let cmd = SMembers {
+            key: "missing".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend, &mut ctx).await.unwrap(),
+            RespSet::new(Vec::<RespFrame>::new()).into()
+        );
+    }
 }
@@ -0,0 +1,233 @@
+// 解析 redis.conf 格式的配置文件：每行一条指令，参数之间用空白分隔，
+// 支持双引号（可以使用 \n、\xHH 等转义）和单引号，以及 include 其他配置文件
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use super::{ConfigError, ServerConfig};
+
+// include 嵌套的最大层数，防止循环 include
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum ConfigFileError {
+    #[error("failed to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{path}:{line}: {message}")]
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ServerConfig {
+    // 依次应用配置文件中的指令。不认识的指令只记录下来并跳过，
+    // 以便直接使用为 redis 准备的配置文件，返回这些被忽略的指令
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<String>, ConfigFileError> {
+        let mut ignored = Vec::new();
+        self.load_file_nested(path.as_ref(), 0, &mut ignored)?;
+        Ok(ignored)
+    }
+
+    fn load_file_nested(
+        &mut self,
+        path: &Path,
+        depth: usize,
+        ignored: &mut Vec<String>,
+    ) -> Result<(), ConfigFileError> {
+        let content = fs::read_to_string(path).map_err(|source| ConfigFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        for (index, line) in content.lines().enumerate() {
+            let syntax_error = |message: String| ConfigFileError::Syntax {
+                path: path.to_path_buf(),
+                line: index + 1,
+                message,
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let args = split_args(line).ok_or_else(|| syntax_error("unbalanced quotes".into()))?;
+            let Some((name, values)) = args.split_first() else {
+                continue;
+            };
+
+            if name.eq_ignore_ascii_case("include") {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(syntax_error("too many nested includes".into()));
+                }
+                for include in values {
+                    self.load_file_nested(Path::new(include), depth + 1, ignored)?;
+                }
+                continue;
+            }
+
+            // 与 redis 一样只能在配置文件中使用，不能通过 CONFIG SET 修改
+            if name.eq_ignore_ascii_case("rename-command") {
+                let [from, to] = values else {
+                    return Err(syntax_error(
+                        "wrong number of arguments for rename-command".into(),
+                    ));
+                };
+                self.rename_commands.push((from.clone(), to.clone()));
+                continue;
+            }
+
+            // 多个参数的指令（例如 bind 127.0.0.1 ::1）与 CONFIG GET 一样以空格连接
+            match self.set(name, &values.join(" ")) {
+                Ok(()) => {}
+                Err(ConfigError::UnknownOption(_)) => {
+                    ignored.push(format!("{}:{}: {}", path.display(), index + 1, name));
+                }
+                Err(e) => return Err(syntax_error(e.to_string())),
+            }
+        }
+        Ok(())
+    }
+}
+
+// 与 redis 的 sdssplitargs 相同的切分规则，引号不成对或者闭合的引号后面紧跟其他字符时返回 None
+pub fn split_args(line: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Some(args);
+        };
+
+        let mut arg = String::new();
+        match first {
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => arg.push(unescape(&mut chars)?),
+                        c => arg.push(c),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return None;
+                }
+            }
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        '\\' if chars.peek() == Some(&'\'') => arg.push(chars.next()?),
+                        c => arg.push(c),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return None;
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    arg.push(c);
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<char> {
+    let c = match chars.next()? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'b' => '\u{8}',
+        'a' => '\u{7}',
+        'x' => {
+            let hex: String = chars.clone().take(2).collect();
+            match u8::from_str_radix(&hex, 16) {
+                Ok(byte) if hex.len() == 2 => {
+                    chars.nth(1);
+                    byte as char
+                }
+                _ => 'x',
+            }
+        }
+        c => c,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args("bind 127.0.0.1   ::1").unwrap(),
+            vec!["bind", "127.0.0.1", "::1"]
+        );
+        assert_eq!(
+            split_args(r#"requirepass "a b\x41\"""#).unwrap(),
+            vec!["requirepass", "a bA\""]
+        );
+        assert_eq!(
+            split_args(r"dir '/tmp/it\'s'").unwrap(),
+            vec!["dir", "/tmp/it's"]
+        );
+        assert_eq!(
+            split_args(r#"requirepass """#).unwrap(),
+            vec!["requirepass", ""]
+        );
+        assert!(split_args(r#"dir "/tmp"#).is_none());
+        assert!(split_args(r#"dir "/tmp"x"#).is_none());
+    }
+
+    #[test]
+    fn test_load_file_with_include() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-conf-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let base = dir.join("base.conf");
+        let main = dir.join("redis.conf");
+        fs::write(&base, "port 7001\nmaxmemory 2mb\n")?;
+        fs::write(
+            &main,
+            format!(
+                "# redis.conf\ninclude {}\nport 7002\nbind 127.0.0.1 ::1\ndaemonize no\nappendonly no\nrename-command FLUSHALL \"\"\n",
+                base.display()
+            ),
+        )?;
+
+        let mut config = ServerConfig::default();
+        let ignored = config.load_file(&main);
+        let bad = dir.join("bad.conf");
+        fs::write(&bad, "port abc\n")?;
+        let invalid = ServerConfig::default().load_file(&bad);
+        fs::remove_dir_all(&dir)?;
+
+        let ignored = ignored?;
+        assert_eq!(config.port, 7002);
+        assert_eq!(config.maxmemory, 2 * 1024 * 1024);
+        assert_eq!(config.bind, "127.0.0.1 ::1");
+        assert!(!config.appendonly);
+        assert_eq!(
+            config.rename_commands,
+            vec![("FLUSHALL".to_string(), String::new())]
+        );
+        assert_eq!(ignored.len(), 1);
+        assert!(ignored[0].ends_with(":5: daemonize"));
+        assert!(matches!(
+            invalid,
+            Err(ConfigFileError::Syntax { line: 1, .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,485 @@
+mod file;
+
+use thiserror::Error;
+
+use crate::RespLimits;
+
+pub use file::{split_args, ConfigFileError};
+
+// 运行时可以通过 CONFIG GET / CONFIG SET 读写的配置项，名称与 redis.conf 保持一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub hash_max_listpack_entries: usize,
+    pub hash_max_listpack_value: usize,
+    pub set_max_intset_entries: usize,
+    pub set_max_listpack_entries: usize,
+    pub set_max_listpack_value: usize,
+    pub proto_max_bulk_len: usize,
+    pub proto_max_multibulk_len: usize,
+    // 集合元素超过该数量时，SMEMBERS / HGETALL 等命令在 blocking 线程池中执行，0 表示不启用
+    pub heavy_command_threshold: usize,
+    // pipeline 中累积的回复超过 cork-max-bytes 字节或者等待超过 cork-max-delay-us 微秒时立即写出
+    pub cork_max_bytes: usize,
+    pub cork_max_delay_us: usize,
+    // 客户端空闲超过 timeout 秒后关闭连接，0 表示不关闭
+    pub timeout: usize,
+    // 收到 SIGINT / SIGTERM 后等待正在执行的命令完成的最长秒数
+    pub shutdown_timeout: usize,
+    // 连接都结束之后把数据集保存到 dir 下的 dbfilename，下次启动时载入
+    pub save_on_shutdown: bool,
+    // 打开后记录每个连接收发的所有帧，用于排查客户端兼容问题
+    pub trace_frames: bool,
+    // 打开后 SMEMBERS 的回复按成员排序，便于测试和比较输出。HGETALL 的回复本身按字段有序
+    pub sort_replies: bool,
+    // 以下配置在启动时通过命令行参数或配置文件设置，运行时修改要到重启后才生效
+    pub port: u16,
+    // 可以有多个以空格分隔的地址，例如 "127.0.0.1 ::1"
+    pub bind: String,
+    // 开启后每个地址按 CPU 数创建多个 SO_REUSEPORT 的监听 socket，各自运行一个 accept 循环
+    pub reuseport: bool,
+    pub dir: String,
+    // BGSAVE 写入 dir 下的这个文件，内容是重建数据集的 RESP 命令序列，不是 RDB 格式
+    pub dbfilename: String,
+    pub loglevel: String,
+    // pretty 为便于阅读的单行文本，json 每行输出一个 JSON 对象，便于日志系统采集
+    pub logformat: String,
+    pub appendonly: bool,
+    // 0 表示不限制内存
+    pub maxmemory: usize,
+    // 字符串值超过这么多秒没有被访问就写到 dir 下的磁盘文件，访问时再读回内存，0 表示不启用
+    pub tier_idle_seconds: usize,
+    // 为空表示不需要 AUTH
+    pub requirepass: String,
+    // 没有实现的命令转发到这个 redis（host:port），为空表示不转发
+    pub upstream: String,
+    // 读穿 / 写穿缓存的源站（host:port），GET 没有命中时从源站读取并回填，写命令先写到源站。为空表示不启用
+    pub cache_origin: String,
+    // 从源站回填的 key 的过期秒数，0 表示不过期
+    pub cache_ttl: usize,
+    // 每个订阅者最多积压的消息数，0 表示不限制；超过时按 pubsub-overflow 断开订阅者或者丢弃最早的消息
+    pub pubsub_queue_limit: usize,
+    pub pubsub_overflow: String,
+    // 统计访问最多的 key 时保留的计数器个数，0 表示不统计
+    pub hotkeys_capacity: usize,
+    // 配置文件中的 rename-command，启动时应用到命令表，新名称为空表示禁用该命令
+    pub rename_commands: Vec<(String, String)>,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownOption(String),
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - argument couldn't be parsed into an integer: {value}")]
+    InvalidValue { name: String, value: String },
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - argument must be 'yes' or 'no'")]
+    InvalidBool { name: String },
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - argument must be a memory value")]
+    InvalidMemory { name: String },
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - argument(s) must be one of the following: {choices}")]
+    InvalidChoice { name: String, choices: &'static str },
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - dbfilename can't be a path, just a filename")]
+    InvalidFileName { name: String },
+    #[error("CONFIG SET failed (possibly related to argument '{name}') - {reason}")]
+    Unsupported { name: String, reason: &'static str },
+}
+
+const LOG_LEVELS: &[&str] = &["debug", "verbose", "notice", "warning", "nothing"];
+const LOG_FORMATS: &[&str] = &["pretty", "json"];
+const PUBSUB_OVERFLOW: &[&str] = &["disconnect", "drop-oldest"];
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            set_max_intset_entries: 512,
+            set_max_listpack_entries: 128,
+            set_max_listpack_value: 64,
+            proto_max_bulk_len: RespLimits::default().max_bulk_len,
+            proto_max_multibulk_len: RespLimits::default().max_multibulk_len,
+            heavy_command_threshold: 1024,
+            cork_max_bytes: 64 * 1024,
+            cork_max_delay_us: 1000,
+            timeout: 0,
+            shutdown_timeout: 10,
+            save_on_shutdown: true,
+            trace_frames: false,
+            sort_replies: false,
+            port: 6379,
+            bind: "0.0.0.0".to_string(),
+            reuseport: false,
+            dir: ".".to_string(),
+            dbfilename: "dump.resp".to_string(),
+            loglevel: "notice".to_string(),
+            logformat: "pretty".to_string(),
+            appendonly: false,
+            maxmemory: 0,
+            tier_idle_seconds: 0,
+            requirepass: String::new(),
+            upstream: String::new(),
+            cache_origin: String::new(),
+            cache_ttl: 0,
+            pubsub_queue_limit: 16384,
+            pubsub_overflow: "disconnect".to_string(),
+            hotkeys_capacity: 0,
+            rename_commands: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub const NAMES: &'static [&'static str] = &[
+        "hash-max-listpack-entries",
+        "hash-max-listpack-value",
+        "set-max-intset-entries",
+        "set-max-listpack-entries",
+        "set-max-listpack-value",
+        "proto-max-bulk-len",
+        "proto-max-multibulk-len",
+        "heavy-command-threshold",
+        "cork-max-bytes",
+        "cork-max-delay-us",
+        "timeout",
+        "shutdown-timeout",
+        "save-on-shutdown",
+        "trace-frames",
+        "sort-replies",
+        "port",
+        "bind",
+        "reuseport",
+        "dir",
+        "dbfilename",
+        "loglevel",
+        "logformat",
+        "appendonly",
+        "maxmemory",
+        "tier-idle-seconds",
+        "requirepass",
+        "upstream",
+        "cache-origin",
+        "cache-ttl",
+        "pubsub-queue-limit",
+        "pubsub-overflow",
+        "hotkeys-capacity",
+    ];
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        let name = name.to_ascii_lowercase();
+        let value = match name.as_str() {
+            "save-on-shutdown" => return Some(yes_no(self.save_on_shutdown).to_string()),
+            "trace-frames" => return Some(yes_no(self.trace_frames).to_string()),
+            "sort-replies" => return Some(yes_no(self.sort_replies).to_string()),
+            "appendonly" => return Some(yes_no(self.appendonly).to_string()),
+            "reuseport" => return Some(yes_no(self.reuseport).to_string()),
+            "bind" => return Some(self.bind.clone()),
+            "dir" => return Some(self.dir.clone()),
+            "dbfilename" => return Some(self.dbfilename.clone()),
+            "loglevel" => return Some(self.loglevel.clone()),
+            "logformat" => return Some(self.logformat.clone()),
+            "requirepass" => return Some(self.requirepass.clone()),
+            "upstream" => return Some(self.upstream.clone()),
+            "cache-origin" => return Some(self.cache_origin.clone()),
+            "pubsub-overflow" => return Some(self.pubsub_overflow.clone()),
+            "port" => self.port as usize,
+            "maxmemory" => self.maxmemory,
+            "hash-max-listpack-entries" => self.hash_max_listpack_entries,
+            "hash-max-listpack-value" => self.hash_max_listpack_value,
+            "set-max-intset-entries" => self.set_max_intset_entries,
+            "set-max-listpack-entries" => self.set_max_listpack_entries,
+            "set-max-listpack-value" => self.set_max_listpack_value,
+            "proto-max-bulk-len" => self.proto_max_bulk_len,
+            "proto-max-multibulk-len" => self.proto_max_multibulk_len,
+            "heavy-command-threshold" => self.heavy_command_threshold,
+            "cork-max-bytes" => self.cork_max_bytes,
+            "cork-max-delay-us" => self.cork_max_delay_us,
+            "timeout" => self.timeout,
+            "shutdown-timeout" => self.shutdown_timeout,
+            "tier-idle-seconds" => self.tier_idle_seconds,
+            "cache-ttl" => self.cache_ttl,
+            "pubsub-queue-limit" => self.pubsub_queue_limit,
+            "hotkeys-capacity" => self.hotkeys_capacity,
+            _ => return None,
+        };
+        Some(value.to_string())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigError> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "save-on-shutdown" => {
+                self.save_on_shutdown =
+                    parse_yes_no(value).ok_or(ConfigError::InvalidBool { name })?;
+                return Ok(());
+            }
+            "trace-frames" => {
+                self.trace_frames = parse_yes_no(value).ok_or(ConfigError::InvalidBool { name })?;
+                return Ok(());
+            }
+            "sort-replies" => {
+                self.sort_replies = parse_yes_no(value).ok_or(ConfigError::InvalidBool { name })?;
+                return Ok(());
+            }
+            // 还没有实现 AOF，配置文件和命令行中的 appendonly yes 在启动时报错，而不是静默忽略
+            "appendonly" => {
+                if parse_yes_no(value).ok_or(ConfigError::InvalidBool { name: name.clone() })? {
+                    return Err(ConfigError::Unsupported {
+                        name,
+                        reason: "the append only file is not supported",
+                    });
+                }
+                return Ok(());
+            }
+            "reuseport" => {
+                self.reuseport = parse_yes_no(value).ok_or(ConfigError::InvalidBool { name })?;
+                return Ok(());
+            }
+            "bind" => {
+                self.bind = value.to_string();
+                return Ok(());
+            }
+            "dir" => {
+                self.dir = value.to_string();
+                return Ok(());
+            }
+            // 与 redis 一样只能是文件名，不能包含路径
+            "dbfilename" => {
+                if value.is_empty() || value.contains(['/', '\\']) {
+                    return Err(ConfigError::InvalidFileName { name });
+                }
+                self.dbfilename = value.to_string();
+                return Ok(());
+            }
+            "requirepass" => {
+                self.requirepass = value.to_string();
+                return Ok(());
+            }
+            "upstream" => {
+                self.upstream = value.to_string();
+                return Ok(());
+            }
+            "cache-origin" => {
+                self.cache_origin = value.to_string();
+                return Ok(());
+            }
+            "loglevel" => {
+                let level = value.to_ascii_lowercase();
+                if !LOG_LEVELS.contains(&level.as_str()) {
+                    return Err(ConfigError::InvalidChoice {
+                        name,
+                        choices: "debug, verbose, notice, warning, nothing",
+                    });
+                }
+                self.loglevel = level;
+                return Ok(());
+            }
+            "logformat" => {
+                let format = value.to_ascii_lowercase();
+                if !LOG_FORMATS.contains(&format.as_str()) {
+                    return Err(ConfigError::InvalidChoice {
+                        name,
+                        choices: "pretty, json",
+                    });
+                }
+                self.logformat = format;
+                return Ok(());
+            }
+            "pubsub-overflow" => {
+                let policy = value.to_ascii_lowercase();
+                if !PUBSUB_OVERFLOW.contains(&policy.as_str()) {
+                    return Err(ConfigError::InvalidChoice {
+                        name,
+                        choices: "disconnect, drop-oldest",
+                    });
+                }
+                self.pubsub_overflow = policy;
+                return Ok(());
+            }
+            "maxmemory" => {
+                self.maxmemory = parse_memory(value).ok_or(ConfigError::InvalidMemory { name })?;
+                return Ok(());
+            }
+            "port" => {
+                self.port = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    name: name.clone(),
+                    value: value.to_string(),
+                })?;
+                return Ok(());
+            }
+            _ => {}
+        }
+        let slot = match name.as_str() {
+            "hash-max-listpack-entries" => &mut self.hash_max_listpack_entries,
+            "hash-max-listpack-value" => &mut self.hash_max_listpack_value,
+            "set-max-intset-entries" => &mut self.set_max_intset_entries,
+            "set-max-listpack-entries" => &mut self.set_max_listpack_entries,
+            "set-max-listpack-value" => &mut self.set_max_listpack_value,
+            "proto-max-bulk-len" => &mut self.proto_max_bulk_len,
+            "proto-max-multibulk-len" => &mut self.proto_max_multibulk_len,
+            "heavy-command-threshold" => &mut self.heavy_command_threshold,
+            "cork-max-bytes" => &mut self.cork_max_bytes,
+            "cork-max-delay-us" => &mut self.cork_max_delay_us,
+            "timeout" => &mut self.timeout,
+            "shutdown-timeout" => &mut self.shutdown_timeout,
+            "tier-idle-seconds" => &mut self.tier_idle_seconds,
+            "cache-ttl" => &mut self.cache_ttl,
+            "pubsub-queue-limit" => &mut self.pubsub_queue_limit,
+            "hotkeys-capacity" => &mut self.hotkeys_capacity,
+            _ => return Err(ConfigError::UnknownOption(name)),
+        };
+
+        *slot = value.parse().map_err(|_| ConfigError::InvalidValue {
+            name: name.clone(),
+            value: value.to_string(),
+        })?;
+        Ok(())
+    }
+
+    pub fn limits(&self) -> RespLimits {
+        RespLimits {
+            max_bulk_len: self.proto_max_bulk_len,
+            max_multibulk_len: self.proto_max_multibulk_len,
+            ..Default::default()
+        }
+    }
+}
+
+// 与 redis 一样支持 k/kb/m/mb/g/gb 单位，k/m/g 以 1000 为基数，kb/mb/gb 以 1024 为基数
+pub fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn parse_yes_no(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_get_set() {
+        let mut config = ServerConfig::default();
+        assert_eq!(
+            config.get("hash-max-listpack-entries"),
+            Some("128".to_string())
+        );
+
+        config.set("HASH-MAX-LISTPACK-ENTRIES", "16").unwrap();
+        assert_eq!(config.hash_max_listpack_entries, 16);
+        assert_eq!(
+            config.get("hash-max-listpack-entries"),
+            Some("16".to_string())
+        );
+
+        assert_eq!(config.get("unknown"), None);
+        assert!(matches!(
+            config.set("unknown", "1"),
+            Err(ConfigError::UnknownOption(_))
+        ));
+        assert!(matches!(
+            config.set("set-max-intset-entries", "abc"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_config_server_options() {
+        let mut config = ServerConfig::default();
+        config.set("port", "6380").unwrap();
+        config.set("maxmemory", "100mb").unwrap();
+        config.set("loglevel", "WARNING").unwrap();
+        config.set("requirepass", "secret").unwrap();
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.maxmemory, 100 * 1024 * 1024);
+        assert_eq!(config.get("loglevel"), Some("warning".to_string()));
+        assert_eq!(config.get("requirepass"), Some("secret".to_string()));
+
+        assert!(matches!(
+            config.set("port", "65536"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            config.set("maxmemory", "1tb"),
+            Err(ConfigError::InvalidMemory { .. })
+        ));
+        assert!(matches!(
+            config.set("loglevel", "trace"),
+            Err(ConfigError::InvalidChoice { .. })
+        ));
+        config.set("appendonly", "no").unwrap();
+        assert!(matches!(
+            config.set("appendonly", "yes"),
+            Err(ConfigError::Unsupported { .. })
+        ));
+
+        config.set("dbfilename", "backup.resp").unwrap();
+        assert_eq!(config.get("dbfilename"), Some("backup.resp".to_string()));
+        assert!(matches!(
+            config.set("dbfilename", "../etc/passwd"),
+            Err(ConfigError::InvalidFileName { .. })
+        ));
+
+        config.set("logformat", "JSON").unwrap();
+        assert_eq!(config.get("logformat"), Some("json".to_string()));
+        assert!(matches!(
+            config.set("logformat", "xml"),
+            Err(ConfigError::InvalidChoice { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("0"), Some(0));
+        assert_eq!(parse_memory("1k"), Some(1000));
+        assert_eq!(parse_memory("1KB"), Some(1024));
+        assert_eq!(parse_memory("2gb"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory("mb"), None);
+        assert_eq!(parse_memory("-1"), None);
+    }
+
+    #[test]
+    fn test_config_bool_option() {
+        let mut config = ServerConfig::default();
+        assert_eq!(config.get("trace-frames"), Some("no".to_string()));
+
+        config.set("trace-frames", "YES").unwrap();
+        assert!(config.trace_frames);
+        assert_eq!(config.get("trace-frames"), Some("yes".to_string()));
+
+        assert!(matches!(
+            config.set("trace-frames", "1"),
+            Err(ConfigError::InvalidBool { .. })
+        ));
+
+        assert_eq!(config.get("save-on-shutdown"), Some("yes".to_string()));
+        config.set("save-on-shutdown", "no").unwrap();
+        assert!(!config.save_on_shutdown);
+    }
+}
@@ -1,8 +1,14 @@
 mod backend;
+mod config;
+mod pattern;
 mod resp;
 
+pub mod cli;
+pub mod client;
 pub mod cmd;
 pub mod network;
 
 pub use backend::*;
+pub use config::*;
+pub use pattern::*;
 pub use resp::*;
@@ -1,31 +1,120 @@
 use anyhow::Result;
-use simple_redis::{network, Backend};
-use tokio::net::TcpListener;
+use clap::Parser;
+use simple_redis::{
+    cli::{self, ServerArgs, ServerCommand},
+    cmd,
+    network::{self, Shutdown},
+    Backend, BackendConfig,
+};
+use std::time::Duration;
+use tokio::{signal, task::JoinSet};
 use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
-
-    let addr: &str = "0.0.0.0:6379";
-    info!("Simple-Redis-Server is listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
-
-    let backend = Backend::new();
-
-    loop {
-        let (stream, raddr) = listener.accept().await?;
-        info!("Accepted connection from: {}", raddr);
-        let cloned_backend = backend.clone();
-        tokio::spawn(async move {
-            match network::stream_handler(stream, cloned_backend).await {
-                Ok(_) => {
-                    info!("Connection closed: {}", raddr);
-                }
-                Err(e) => {
-                    warn!("Connection error: {}: {:?}", raddr, e);
-                }
+    let args = ServerArgs::parse();
+    if let Some(ServerCommand::Inspect { file, json }) = &args.command {
+        print!("{}", cli::inspect(file, *json)?);
+        return Ok(());
+    }
+    let (config, ignored) = args.load_config()?;
+
+    // RUST_LOG 优先于 loglevel
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(cli::log_filter(&config.loglevel)));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.logformat.as_str() {
+        "json" => subscriber.json().init(),
+        _ => subscriber.init(),
+    }
+    for directive in ignored {
+        warn!("Ignoring unsupported config directive {}", directive);
+    }
+
+    // 为 redis 准备的配置文件可能会禁用或改名这里还没有实现的命令，原来的名称同样不再可用
+    for (from, to) in &config.rename_commands {
+        if !cmd::rename_command(from, to) {
+            info!("rename-command for unimplemented command {}", from);
+        }
+    }
+
+    let listeners = network::bind_listeners(&config)?;
+    let backend = Backend::with_config(BackendConfig {
+        server: config,
+        ..Default::default()
+    });
+    let path = backend.save_path();
+    let loaded = backend.load(&path)?;
+    if loaded > 0 {
+        info!("Loaded {} keys from {}", loaded, path.display());
+    }
+    // 设置 SIMPLE_REDIS_TRACE_FRAMES 后启动即记录所有帧，也可以通过 CONFIG SET trace-frames yes 打开
+    if std::env::var_os("SIMPLE_REDIS_TRACE_FRAMES").is_some() {
+        backend.set_config("trace-frames", "yes")?;
+    }
+
+    // tier-idle-seconds 可以在运行时修改，未启用时 spill_idle 直接返回
+    let tier = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let backend = tier.clone();
+            match tokio::task::spawn_blocking(move || backend.spill_idle()).await {
+                Ok(Err(e)) => warn!("Failed to move idle values to disk: {}", e),
+                Err(e) => warn!("Tier task failed: {}", e),
+                Ok(Ok(_)) => {}
             }
-        });
+        }
+    });
+
+    let shutdown = Shutdown::new();
+    let mut accept_loops = JoinSet::new();
+    for listener in listeners {
+        info!(
+            "Simple-Redis-Server is listening on {}",
+            listener.local_addr()?
+        );
+        accept_loops.spawn(network::serve(listener, backend.clone(), shutdown.clone()));
+    }
+
+    // 任意一个 accept 循环出错时退出
+    tokio::select! {
+        Some(result) = accept_loops.join_next() => result??,
+        result = shutdown_signal() => result?,
+    }
+
+    info!("Shutting down, waiting for connections to finish");
+    shutdown.trigger();
+    let grace = Duration::from_secs(backend.config().shutdown_timeout as u64);
+    if !shutdown.wait_connections(grace).await {
+        warn!("Some connections did not finish within {:?}", grace);
+    }
+    // 与 redis 关闭时的 SAVE 一样，保存失败只记录日志，不影响退出
+    if backend.config().save_on_shutdown {
+        let path = backend.save_path();
+        let saver = backend.clone();
+        match tokio::task::spawn_blocking(move || saver.save()).await {
+            Ok(Ok(())) => info!("DB saved on disk to {}", path.display()),
+            Ok(Err(e)) => warn!("Failed to save the DB to {}: {}", path.display(), e),
+            Err(e) => warn!("Save task failed: {}", e),
+        }
+    }
+    info!("Bye");
+    Ok(())
+}
+
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
     }
+    #[cfg(not(unix))]
+    signal::ctrl_c().await?;
+    Ok(())
 }
@@ -1,16 +1,40 @@
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecode, RespEncode, RespError, RespFrame,
+    cmd::{
+        cache_enabled, check_command, command_info, middleware_chain, normalize_args,
+        oplog_command, write_through, Command, CommandError, CommandExecutor, ConnectionContext,
+        KeySpec,
+    },
+    Backend, RespCodec, RespEncode, RespError, RespFrame, ServerConfig, ServerStats, SimpleError,
 };
 use anyhow::Result;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use futures::{FutureExt, SinkExt};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
+    net::{TcpListener, TcpStream},
+    runtime::Handle,
+    time,
+};
 use tokio_stream::StreamExt;
-use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tokio_util::{codec::Framed, sync::CancellationToken, task::TaskTracker};
+use tracing::{debug, field, info, info_span, warn, Instrument, Span};
 
-#[derive(Debug)]
-struct RespFrameCodec;
+// 帧日志中最多输出的字符数
+const TRACE_BODY_LIMIT: usize = 256;
+
+// connect_duplex 每个方向的缓冲区大小
+const DUPLEX_BUFFER_SIZE: usize = 64 * 1024;
+
+// 连接 id，与 CLIENT ID 一样从 1 开始递增
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Debug)]
 struct RedisRequest {
@@ -23,55 +47,899 @@ struct RedisResponse {
     frame: RespFrame,
 }
 
-pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
-    let mut framed = Framed::new(stream, RespFrameCodec);
+// 与 redis 的 tcp-backlog 默认值一致
+const LISTEN_BACKLOG: i32 = 511;
+
+// 为 bind 中的每个地址创建监听 socket。开启 reuseport 时每个地址按 CPU 数创建多个 socket，
+// 由内核把新连接分配给它们
+pub fn bind_listeners(config: &ServerConfig) -> Result<Vec<TcpListener>> {
+    let copies = if config.reuseport {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        1
+    };
+
+    let mut listeners = Vec::new();
+    for host in config.bind.split_whitespace() {
+        for addr in (host, config.port).to_socket_addrs()? {
+            for _ in 0..copies {
+                listeners.push(bind_listener(addr, config.reuseport)?);
+            }
+        }
+    }
+    Ok(listeners)
+}
+
+fn bind_listener(addr: SocketAddr, reuseport: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    // 与 redis 一样 IPv6 socket 只接受 IPv6 连接，这样可以同时监听 0.0.0.0 和 ::
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(reuseport)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+// 服务器关闭的信号。触发后 accept 循环不再接受新连接，
+// 每个连接执行完当前的命令、写出已有的回复后关闭
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    connections: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+        self.connections.close();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub async fn triggered(&self) {
+        self.token.cancelled().await
+    }
+
+    // 等待所有连接关闭，超过 grace 仍未关闭时返回 false
+    pub async fn wait_connections(&self, grace: Duration) -> bool {
+        time::timeout(grace, self.connections.wait()).await.is_ok()
+    }
+}
+
+// 一个监听 socket 的 accept 循环，每个连接在单独的 task 中处理
+pub async fn serve(listener: TcpListener, backend: Backend, shutdown: Shutdown) -> Result<()> {
     loop {
-        match framed.next().await {
+        let (stream, raddr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown.triggered() => return Ok(()),
+        };
+        let cloned_backend = backend.clone();
+        let cloned_shutdown = shutdown.clone();
+        shutdown.connections.spawn(async move {
+            // 错误在 stream_handler 的连接 span 之外记录，带上对端地址
+            if let Err(e) = stream_handler(stream, cloned_backend, cloned_shutdown).await {
+                warn!("Connection error: {}: {:?}", raddr, e);
+            }
+        });
+    }
+}
+
+// 每个连接一个 span，连接中的日志和命令的 span 都带上连接 id 和对端地址
+pub async fn stream_handler(stream: TcpStream, backend: Backend, shutdown: Shutdown) -> Result<()> {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+    handle_connection(stream, &peer, backend, shutdown).await
+}
+
+// 处理任意的双向字节流，peer 只用于日志。测试中可以用内存中的 duplex 代替 TCP 连接
+pub async fn handle_connection<S>(
+    stream: S,
+    peer: &str,
+    backend: Backend,
+    shutdown: Shutdown,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    let span = info_span!("connection", id, peer = %peer);
+    ServerStats::incr(&backend.stats().total_connections_received, 1);
+    async move {
+        info!("Accepted connection");
+        let result = connection_loop(stream, id, backend.clone(), shutdown).await;
+        backend.unsubscribe_all(id);
+        if result.is_ok() {
+            info!("Connection closed");
+        }
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+// 在当前进程中建立一个连接，返回客户端一端，服务端一端与 TCP 连接一样计入 shutdown 的连接
+pub fn connect_duplex(backend: Backend, shutdown: &Shutdown) -> DuplexStream {
+    let (client, server) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+    let cloned_shutdown = shutdown.clone();
+    shutdown.connections.spawn(async move {
+        if let Err(e) = handle_connection(server, "duplex", backend, cloned_shutdown).await {
+            warn!("Connection error: duplex: {:?}", e);
+        }
+    });
+    client
+}
+
+async fn connection_loop<S>(stream: S, id: u64, backend: Backend, shutdown: Shutdown) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let config = backend.config();
+    let mut trace = config.trace_frames;
+    let mut timeout = config.timeout;
+    let stream = CountingStream::new(stream, backend.clone());
+    let mut framed = Framed::new(
+        stream,
+        RespCodec::new(config.limits()).with_inline_commands(),
+    );
+    let mut cork = WriteCork::new(&config);
+    framed.set_backpressure_boundary(cork.max_bytes);
+    let mut ctx = new_context(id, &backend);
+    loop {
+        if shutdown.is_triggered() {
+            framed.flush().await?;
+            return Ok(());
+        }
+        // 读缓冲区中还有完整的帧时继续处理，回复先写入写缓冲区；
+        // 没有可处理的请求时才把累积的回复一次性写出，再等待新的数据
+        let next = match framed.next().now_or_never() {
+            Some(next) => next,
+            None => {
+                framed.flush().await?;
+                cork.flushed();
+                let idle = async {
+                    match idle_timeout(timeout, &ctx) {
+                        Some(duration) => time::sleep(duration).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                let subscription = ctx.subscription.clone();
+                let messages = async {
+                    match &subscription {
+                        Some(queue) => queue.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    next = framed.next() => next,
+                    messages = messages => {
+                        let Some(messages) = messages else {
+                            warn!("Closing subscriber that fell behind pubsub-queue-limit");
+                            return Ok(());
+                        };
+                        for frame in messages {
+                            framed.feed(frame.into_version(ctx.protocol)).await?;
+                        }
+                        continue;
+                    }
+                    _ = idle => {
+                        info!("Closing idle client");
+                        return Ok(());
+                    }
+                    _ = shutdown.triggered() => return Ok(()),
+                }
+            }
+        };
+        match next {
             Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
+                if trace {
+                    trace_frame("in", &frame);
+                }
+                debug!("Received frame: {:?}", frame);
                 let request = RedisRequest {
                     frame,
                     backend: backend.clone(),
                 };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
+                let response = request_handler(request, &mut ctx).await;
+                // CONFIG SET 修改的配置从下一个请求开始生效
+                let config = backend.config();
+                framed.codec_mut().set_limits(config.limits());
+                cork.update(&config);
+                framed.set_backpressure_boundary(cork.max_bytes);
+                debug!("Sending response: {:?}", response.frame);
+                let frame = response.frame.into_version(ctx.protocol);
+                if trace {
+                    trace_frame("out", &frame);
+                }
+                trace = config.trace_frames;
+                timeout = config.timeout;
+                write_reply(&mut framed, frame).await?;
+                if cork.should_flush(framed.write_buffer().len()) {
+                    framed.flush().await?;
+                    cork.flushed();
+                }
+            }
+            Some(Err(e)) => match e.downcast_ref::<RespError>() {
+                // 格式错误的帧已经被跳过，回复错误后继续处理后面的请求
+                Some(err @ RespError::FrameDiscarded(_, _)) => {
+                    warn!("Protocol error: {}", err);
+                    let frame = SimpleError::new(format!("ERR Protocol error: {}", err)).into();
+                    framed.send(frame).await?;
+                    // Framed 在解码出错后不会再产生新的帧，用原来的缓冲区重新创建
+                    framed = Framed::from_parts(framed.into_parts());
+                }
+                // 超过限制的帧无法恢复，回复错误后关闭连接
+                Some(err) => {
+                    warn!("Protocol error: {}", err);
+                    let frame = SimpleError::new(format!("ERR Protocol error: {}", err)).into();
+                    framed.send(frame).await?;
+                    return Ok(());
+                }
+                None => return Err(e),
+            },
+            None => {
+                framed.flush().await?;
+                return Ok(());
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
         }
     }
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
-    let (frame, backend) = (request.frame, request.backend);
+// 统计连接读写的字节数，累加到 total_net_input_bytes / total_net_output_bytes
+struct CountingStream<S> {
+    inner: S,
+    backend: Backend,
+}
 
-    let cmd = Command::try_from(frame)?;
-    info!("Executing command: {:?}", cmd);
+impl<S> CountingStream<S> {
+    fn new(inner: S, backend: Backend) -> Self {
+        Self { inner, backend }
+    }
+}
 
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+impl<S: AsyncRead + Unpin> AsyncRead for CountingStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let n = (buf.filled().len() - before) as u64;
+            ServerStats::incr(&self.backend.stats().total_net_input_bytes, n);
+        }
+        poll
+    }
 }
 
-impl Encoder<RespFrame> for RespFrameCodec {
-    type Error = anyhow::Error;
+impl<S: AsyncWrite + Unpin> AsyncWrite for CountingStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            ServerStats::incr(&self.backend.stats().total_net_output_bytes, n as u64);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
 
-    fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        let encoded = item.encode();
-        dst.extend_from_slice(&encoded);
-        Ok(())
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+// 订阅和 MONITOR 的连接只接收消息，不会因为空闲而被关闭
+fn idle_timeout(timeout: usize, ctx: &ConnectionContext) -> Option<Duration> {
+    if timeout == 0 || ctx.is_subscribed() || ctx.monitor {
+        return None;
+    }
+    Some(Duration::from_secs(timeout as u64))
+}
+
+// 回复的合并写出策略：累积的字节数达到 max_bytes，或者距离第一条未写出的回复超过 max_delay 时写出。
+// 所有回复都编码在 Framed 的同一块写缓冲区中，每次写出只需要一次 write 调用
+#[derive(Debug)]
+struct WriteCork {
+    max_bytes: usize,
+    max_delay: Duration,
+    since: Option<Instant>,
+}
+
+impl WriteCork {
+    fn new(config: &ServerConfig) -> Self {
+        let mut cork = Self {
+            max_bytes: 0,
+            max_delay: Duration::ZERO,
+            since: None,
+        };
+        cork.update(config);
+        cork
+    }
+
+    fn update(&mut self, config: &ServerConfig) {
+        self.max_bytes = config.cork_max_bytes;
+        self.max_delay = Duration::from_micros(config.cork_max_delay_us as u64);
+    }
+
+    fn should_flush(&mut self, buffered: usize) -> bool {
+        let since = *self.since.get_or_insert_with(Instant::now);
+        buffered >= self.max_bytes || since.elapsed() >= self.max_delay
+    }
+
+    fn flushed(&mut self) {
+        self.since = None;
+    }
+}
+
+// 元素很多的聚合回复（LRANGE / HGETALL 一个很大的 key）不一次性编码到写缓冲区，
+// 先写出头部，再逐个元素编码：写缓冲区超过 cork-max-bytes 时 Framed 在 feed 下一个元素前先写出，
+// 一个回复占用的写缓冲区不会超过 cork-max-bytes 加上单个元素的大小。嵌套的大聚合同样处理
+const STREAM_REPLY_ELEMENTS: usize = 1024;
+
+async fn write_reply<S>(framed: &mut Framed<S, RespCodec>, frame: RespFrame) -> Result<()>
+where
+    S: AsyncWrite + Unpin + Send,
+{
+    let large = match &frame {
+        RespFrame::Array(array) => array.len() >= STREAM_REPLY_ELEMENTS,
+        RespFrame::Set(set) => set.len() >= STREAM_REPLY_ELEMENTS,
+        RespFrame::Map(map) => map.len() * 2 >= STREAM_REPLY_ELEMENTS,
+        _ => false,
+    };
+    if !large {
+        return framed.feed(frame).await;
+    }
+    let Ok((header, elements)) = frame.into_elements() else {
+        unreachable!("only aggregate frames are streamed");
+    };
+    framed
+        .write_buffer_mut()
+        .extend_from_slice(header.as_bytes());
+    for element in elements {
+        Box::pin(write_reply(framed, element)).await?;
+    }
+    Ok(())
+}
+
+// 记录连接收发的帧：方向、编码后的字节数以及截断后的内容，连接 id 来自所在的连接 span
+fn trace_frame(direction: &str, frame: &RespFrame) {
+    let body = frame.to_string();
+    let body = match body.char_indices().nth(TRACE_BODY_LIMIT) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    };
+    info!("{} {} bytes\n{}", direction, frame.encoded_len(), body);
+}
+
+// HELLO 等命令会修改连接状态，回复按执行之后 ctx 中的协议编码
+async fn request_handler(request: RedisRequest, ctx: &mut ConnectionContext) -> RedisResponse {
+    let (mut frame, backend) = (request.frame, request.backend);
+    let middleware = middleware_chain();
+    if let (Some(chain), RespFrame::Array(array)) = (&middleware, &mut frame) {
+        if let Some(frame) = chain.before(array, ctx) {
+            return RedisResponse { frame };
+        }
+    }
+    if let RespFrame::Array(array) = &mut frame {
+        normalize_args(array);
+    }
+    let info = match &frame {
+        RespFrame::Array(array) => command_info(array),
+        _ => None,
+    };
+    let (flags, key_spec) = info.unwrap_or((&[], KeySpec::NONE));
+    // 有操作日志的订阅者时保留写命令的原始参数，执行成功后写入日志
+    let logged = match &frame {
+        RespFrame::Array(array) if flags.contains(&"write") && backend.oplog_enabled() => {
+            Some(array.clone())
+        }
+        _ => None,
+    };
+    // 缓存模式下写命令先发给源站，同样需要保留原始参数
+    let through = match &frame {
+        RespFrame::Array(array)
+            if flags.contains(&"write") && !ctx.in_multi() && cache_enabled(&backend) =>
+        {
+            Some(array.clone())
+        }
+        _ => None,
+    };
+    let keys = match &frame {
+        RespFrame::Array(array) => key_spec.count(array.len()),
+        _ => 0,
+    };
+    if let RespFrame::Array(array) = &frame {
+        if keys > 0 && backend.hotkeys_enabled() {
+            backend.record_hot_keys(&key_spec.keys(array));
+        }
+    }
+
+    // 命令格式错误只回复错误，连接继续可用
+    let cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            warn!("Invalid command: {}", e);
+            return RedisResponse { frame: e.into() };
+        }
+    };
+    if let Err(e) = check_command(flags, &backend, ctx) {
+        return RedisResponse { frame: e.into() };
+    }
+    // 源站写入失败时本地不执行
+    if let Some(request) = &through {
+        if let Err(e) = write_through(&backend, request, ctx).await {
+            return RedisResponse { frame: e.into() };
+        }
+    }
+    ServerStats::incr(&backend.stats().total_commands_processed, 1);
+    let name = cmd.name().to_string();
+    // 执行时间和回复类型在命令执行完后记录
+    let span = info_span!(
+        "command",
+        name = %name,
+        keys,
+        duration_us = field::Empty,
+        reply = field::Empty
+    );
+    debug!(parent: &span, "Executing command: {:?}", cmd);
+
+    // 执行失败回复对应的错误，连接继续可用
+    let start = Instant::now();
+    let mut frame = async {
+        if cmd.is_heavy(&backend) {
+            execute_blocking(cmd, backend.clone(), ctx).await
+        } else {
+            cmd.execute(&backend, ctx).await
+        }
+    }
+    .instrument(span.clone())
+    .await;
+    let elapsed = start.elapsed();
+    if name != "unknown" {
+        backend.latency().record(&name, elapsed);
+    }
+
+    if let (Ok(_), Some(command)) = (&frame, logged) {
+        let keys = key_spec.keys(&command);
+        backend.append_oplog(oplog_command(command), keys);
+    }
+
+    if let Some(chain) = &middleware {
+        chain.after(&name, elapsed, &mut frame, ctx);
+    }
+
+    let frame = frame.unwrap_or_else(RespFrame::from);
+    span.record("duration_us", elapsed.as_micros() as u64);
+    span.record("reply", frame.type_name());
+    info!(parent: &span, "Executed command");
+    RedisResponse { frame }
+}
+
+// 设置了 requirepass 时新连接需要先通过 AUTH 认证
+fn new_context(id: u64, backend: &Backend) -> ConnectionContext {
+    let mut ctx = ConnectionContext::new(id);
+    ctx.authenticated = backend.config().requirepass.is_empty();
+    ctx
+}
+
+// 在 blocking 线程池中执行命令。连接等待它完成后才处理下一个请求，
+// 因此同一个连接上的命令仍然按顺序执行
+async fn execute_blocking(
+    cmd: Command,
+    backend: Backend,
+    ctx: &mut ConnectionContext,
+) -> Result<RespFrame, CommandError> {
+    let handle = Handle::current();
+    let mut owned = std::mem::take(ctx);
+    let (id, authenticated) = (owned.id, owned.authenticated);
+    let span = Span::current();
+    let task = tokio::task::spawn_blocking(move || {
+        let result = handle.block_on(cmd.execute(&backend, &mut owned).instrument(span));
+        (result, owned)
+    });
+    match task.await {
+        Ok((result, owned)) => {
+            *ctx = owned;
+            result
+        }
+        // 命令 panic 时连接状态已经丢失，恢复为新连接的状态，保留认证状态
+        Err(e) => {
+            *ctx = ConnectionContext::new(id);
+            ctx.authenticated = authenticated;
+            Err(CommandError::InvalidCommand(format!(
+                "command failed: {}",
+                e
+            )))
+        }
     }
 }
 
-impl Decoder for RespFrameCodec {
-    type Item = RespFrame;
-    type Error = anyhow::Error;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray};
+
+    #[tokio::test]
+    async fn test_invalid_command_replies_error() {
+        let request = RedisRequest {
+            frame: RespArray::new([BulkString::from("get").into()]).into(),
+            backend: Backend::new(),
+        };
+
+        let response = request_handler(request, &mut ConnectionContext::new(1)).await;
+        assert!(matches!(response.frame, RespFrame::Error(_)));
+    }
+
+    #[derive(Clone, Default)]
+    struct LogBuffer(std::sync::Arc<parking_lot::Mutex<Vec<u8>>>);
+
+    impl io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_command_span() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_max_level(tracing::Level::INFO)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = RedisRequest {
+            frame: RespArray::new([
+                BulkString::from("hget").into(),
+                BulkString::from("k").into(),
+                BulkString::from("f").into(),
+            ])
+            .into(),
+            backend: Backend::new(),
+        };
+        request_handler(request, &mut ConnectionContext::new(1)).await;
+
+        let logs = String::from_utf8(logs.0.lock().clone()).unwrap();
+        assert!(logs.contains("Executed command"));
+        assert!(logs.contains(r#""name":"hget""#));
+        assert!(logs.contains(r#""keys":1"#));
+        assert!(logs.contains(r#""reply":"null""#));
+        assert!(logs.contains("duration_us"));
+    }
+
+    #[tokio::test]
+    async fn test_heavy_command_runs_blocking() {
+        let backend = Backend::new();
+        backend.set_config("heavy-command-threshold", "2").unwrap();
+        backend.sadd("set", ["a", "b", "c"]);
+
+        let frame: RespFrame = RespArray::new([
+            BulkString::from("smembers").into(),
+            BulkString::from("set").into(),
+        ])
+        .into();
+        assert!(Command::try_from(frame.clone()).unwrap().is_heavy(&backend));
+
+        let mut ctx = ConnectionContext::new(3);
+        let request = RedisRequest { frame, backend };
+        let response = request_handler(request, &mut ctx).await;
+        let RespFrame::Set(set) = response.frame else {
+            panic!("SMEMBERS should reply with a set");
+        };
+        assert_eq!(set.len(), 3);
+        assert_eq!(ctx.id, 3);
+    }
+
+    #[tokio::test]
+    async fn test_large_reply_is_streamed() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let (mut client, server) = tokio::io::duplex(1 << 20);
+        let mut framed = Framed::new(server, RespCodec::default());
+        framed.set_backpressure_boundary(1024);
+
+        let elements = (0..10_000)
+            .map(|i| BulkString::from(format!("value-{}", i)).into())
+            .collect::<Vec<RespFrame>>();
+        let reply: RespFrame = RespArray::new(elements).into();
+        let expected = reply.encode();
+        write_reply(&mut framed, reply).await?;
+        // 写缓冲区中只剩下最后一部分元素
+        assert!(framed.write_buffer().len() < 1024 + 32);
+        framed.flush().await?;
+        drop(framed);
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await?;
+        assert_eq!(received, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_cork() {
+        let mut config = ServerConfig {
+            cork_max_bytes: 16,
+            cork_max_delay_us: 1_000_000,
+            ..Default::default()
+        };
+        let mut cork = WriteCork::new(&config);
+        assert!(!cork.should_flush(8));
+        assert!(cork.should_flush(16));
+        cork.flushed();
+
+        config.cork_max_delay_us = 0;
+        cork.update(&config);
+        assert!(cork.should_flush(1));
+    }
+
+    #[tokio::test]
+    async fn test_bind_multiple_addresses() -> Result<()> {
+        let config = ServerConfig {
+            port: 0,
+            bind: "127.0.0.1 127.0.0.2".to_string(),
+            ..Default::default()
+        };
+        let listeners = bind_listeners(&config)?;
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(listeners[1].local_addr()?.ip().to_string(), "127.0.0.2");
+
+        // 多个 SO_REUSEPORT socket 监听同一个端口
+        let port = listeners[0].local_addr()?.port();
+        drop(listeners);
+        let config = ServerConfig {
+            port,
+            bind: "127.0.0.1".to_string(),
+            reuseport: true,
+            ..Default::default()
+        };
+        let listeners = bind_listeners(&config)?;
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        assert_eq!(listeners.len(), cpus);
+        for listener in &listeners {
+            assert_eq!(listener.local_addr()?.port(), port);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let mut ctx = ConnectionContext::new(1);
+        assert_eq!(idle_timeout(0, &ctx), None);
+        assert_eq!(idle_timeout(5, &ctx), Some(Duration::from_secs(5)));
+
+        ctx.channels.insert("news".to_string());
+        assert_eq!(idle_timeout(5, &ctx), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_client_closed() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let backend = Backend::new();
+        backend.set_config("timeout", "10")?;
+        tokio::spawn(serve(listener, backend, Shutdown::new()));
+
+        let mut client = TcpStream::connect(addr).await?;
+        let start = time::Instant::now();
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        assert!(buf.is_empty());
+        assert!(start.elapsed() >= Duration::from_secs(10));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let shutdown = Shutdown::new();
+        let accept_loop = tokio::spawn(serve(listener, Backend::new(), shutdown.clone()));
 
-    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
-            Err(e) => Err(e.into()),
+        let mut client = TcpStream::connect(addr).await?;
+        client.write_all(b"*1\r\n$4\r\nping\r\n").await?;
+        let mut buf = [0; 7];
+        client.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"+PONG\r\n");
+
+        shutdown.trigger();
+        accept_loop.await??;
+        assert!(shutdown.wait_connections(Duration::from_secs(1)).await);
+        // 连接已经被服务器关闭
+        assert_eq!(client.read(&mut buf).await?, 0);
+        Ok(())
+    }
+
+    // 通过 duplex 发送 input 并关闭写端，返回服务器写出的全部数据
+    async fn duplex_roundtrip(backend: Backend, input: &[&[u8]]) -> Result<Vec<u8>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let shutdown = Shutdown::new();
+        let mut client = connect_duplex(backend, &shutdown);
+        for chunk in input {
+            client.write_all(chunk).await?;
         }
+        client.shutdown().await?;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        shutdown.trigger();
+        assert!(shutdown.wait_connections(Duration::from_secs(1)).await);
+        Ok(buf)
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests() -> Result<()> {
+        // 一次写入多个请求，最后一个请求不完整
+        let buf = duplex_roundtrip(
+            Backend::new(),
+            &[
+                b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\n*2\r\n$3\r\nget\r\n$1\r\nk\r\n*1\r\n$4\r\nping\r\n*2\r\n$4\r\necho",
+                b"\r\n$2\r\nhi\r\n",
+            ],
+        )
+        .await?;
+        assert_eq!(buf, b"+OK\r\n$1\r\nv\r\n+PONG\r\n$2\r\nhi\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hot_keys_tracked() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_config("hotkeys-capacity", "8")?;
+        duplex_roundtrip(
+            backend.clone(),
+            &[b"*2\r\n$3\r\nget\r\n$1\r\na\r\n*2\r\n$3\r\nget\r\n$1\r\na\r\n*3\r\n$4\r\nmget\r\n$1\r\na\r\n$1\r\nb\r\n"],
+        )
+        .await?;
+        let hot = backend.hot_keys(8);
+        assert_eq!(hot.len(), 2);
+        assert_eq!((hot[0].key.as_str(), hot[0].count), ("a", 3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inline_ping_and_subscribe_mode() -> Result<()> {
+        let buf = duplex_roundtrip(
+            Backend::new(),
+            &[b"PING\r\nsubscribe ch\r\nPING\r\n*2\r\n$4\r\nping\r\n$2\r\nhi\r\n"],
+        )
+        .await?;
+        assert_eq!(
+            buf,
+            b"+PONG\r\n*3\r\n$9\r\nsubscribe\r\n$2\r\nch\r\n:1\r\n*2\r\n$4\r\npong\r\n$0\r\n\r\n*2\r\n$4\r\npong\r\n$2\r\nhi\r\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partial_frames() -> Result<()> {
+        // 每次只写入一个字节
+        let request = b"*2\r\n$4\r\necho\r\n$5\r\nhello\r\n";
+        let chunks = request.chunks(1).collect::<Vec<_>>();
+        let buf = duplex_roundtrip(Backend::new(), &chunks).await?;
+        assert_eq!(buf, b"$5\r\nhello\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error() -> Result<()> {
+        // 格式错误的帧回复错误后继续处理后面的请求
+        let buf = duplex_roundtrip(Backend::new(), &[b"*1\r\n$x\r\n*1\r\n$4\r\nping\r\n"]).await?;
+        let text = String::from_utf8(buf)?;
+        assert!(text.starts_with("-ERR Protocol error"), "{}", text);
+        assert!(text.ends_with("+PONG\r\n"), "{}", text);
+
+        // 负数的长度
+        let buf = duplex_roundtrip(Backend::new(), &[b"*-5\r\n*1\r\n$4\r\nping\r\n"]).await?;
+        let text = String::from_utf8(buf)?;
+        assert!(
+            text.starts_with("-ERR Protocol error: Invalid frame length"),
+            "{}",
+            text
+        );
+        assert!(text.ends_with("+PONG\r\n"), "{}", text);
+
+        // 超过限制的帧回复错误后关闭连接
+        let backend = Backend::new();
+        backend.set_config("proto-max-bulk-len", "4")?;
+        let buf = duplex_roundtrip(
+            backend,
+            &[b"*2\r\n$4\r\necho\r\n$5\r\nhello\r\n*1\r\n$4\r\nping\r\n"],
+        )
+        .await?;
+        let text = String::from_utf8(buf)?;
+        assert!(text.starts_with("-ERR Protocol error"), "{}", text);
+        assert!(!text.contains("PONG"), "{}", text);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_negotiation() -> Result<()> {
+        let buf = duplex_roundtrip(
+            Backend::new(),
+            &[b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*2\r\n$5\r\nhello\r\n$1\r\n3\r\n*2\r\n$3\r\nget\r\n$1\r\nk\r\n"],
+        )
+        .await?;
+        // HELLO 3 之后空值以 RESP3 的 null 回复
+        assert!(buf.starts_with(b"$-1\r\n%"));
+        assert!(buf.ends_with(b"_\r\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requirepass() -> Result<()> {
+        let backend = Backend::new();
+        backend.set_config("requirepass", "secret")?;
+        let buf = duplex_roundtrip(
+            backend,
+            &[b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*2\r\n$4\r\nauth\r\n$5\r\nwrong\r\n*2\r\n$4\r\nauth\r\n$6\r\nsecret\r\n*2\r\n$3\r\nget\r\n$1\r\nk\r\n"],
+        )
+        .await?;
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            "-NOAUTH Authentication required.\r\n-WRONGPASS invalid username-password pair or user is disabled.\r\n+OK\r\n$-1\r\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stats_counters() -> Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let backend = Backend::new();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let cloned_backend = backend.clone();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream_handler(stream, cloned_backend, Shutdown::new())
+                .await
+                .unwrap();
+        });
+
+        let request = b"*2\r\n$3\r\nget\r\n$1\r\nk\r\n*3\r\n$4\r\nhget\r\n$1\r\nh\r\n$1\r\nf\r\n";
+        let mut client = TcpStream::connect(addr).await?;
+        client.write_all(request).await?;
+        client.shutdown().await?;
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await?;
+        server.await?;
+
+        let stats = backend.stats().fields();
+        assert!(stats.contains(&("total_connections_received", 1)));
+        assert!(stats.contains(&("total_commands_processed", 2)));
+        assert!(stats.contains(&("total_net_input_bytes", request.len() as u64)));
+        assert!(stats.contains(&("total_net_output_bytes", buf.len() as u64)));
+        assert!(stats.contains(&("keyspace_misses", 2)));
+        Ok(())
     }
 }
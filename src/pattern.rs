@@ -0,0 +1,208 @@
+// 与 redis stringmatchlen 语义一致的 glob 匹配，KEYS、SCAN MATCH、CONFIG GET 等命令共用：
+//
+//   *       任意长度的任意字符
+//   ?       任意一个字符
+//   [abc]   括号中的任意一个字符，[^abc] 为取反，[a-z] 为范围，范围两端颠倒时自动交换
+//   \x      字符 x 本身，用于匹配 * ? [ \ 等特殊字符
+//
+// 未闭合的 [ 一直延续到模式末尾，模式末尾单独的 \ 匹配 \ 本身。
+// 按字节匹配，nocase 只忽略 ASCII 字母的大小写。
+// 遇到不匹配时回退到上一个 * 多吞一个字符，时间复杂度为 O(模式长度 * 字符串长度)，
+// 不会像递归实现那样被 "a*a*a*a*b" 这样的模式拖慢
+pub fn glob_match(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // 上一个 * 之后的模式位置，以及这个 * 当前匹配到的字符串位置
+    let mut star: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, s));
+                continue;
+            }
+            Some(_) => {
+                if let Some(next) = match_one(pattern, p, string[s], nocase) {
+                    p = next;
+                    s += 1;
+                    continue;
+                }
+            }
+            None => {}
+        }
+        match star {
+            Some((star_p, star_s)) => {
+                p = star_p;
+                s = star_s + 1;
+                star = Some((star_p, s));
+            }
+            None => return false,
+        }
+    }
+    // 字符串已经用完，剩下的模式只能是 *
+    pattern[p.min(pattern.len())..].iter().all(|&c| c == b'*')
+}
+
+// pattern[p] 开始的一个单字符模式是否匹配 c，匹配时返回之后的模式位置
+fn match_one(pattern: &[u8], p: usize, c: u8, nocase: bool) -> Option<usize> {
+    let eq = |a: u8, b: u8| match nocase {
+        true => a.eq_ignore_ascii_case(&b),
+        false => a == b,
+    };
+
+    match pattern[p] {
+        b'?' => Some(p + 1),
+        b'[' => {
+            let (matched, next) = match_class(pattern, p + 1, c, nocase);
+            matched.then_some(next)
+        }
+        b'\\' if p + 1 < pattern.len() => eq(pattern[p + 1], c).then_some(p + 2),
+        literal => eq(literal, c).then_some(p + 1),
+    }
+}
+
+// 从 [ 之后开始解析字符集合，返回是否匹配以及 ] 之后的模式位置
+fn match_class(pattern: &[u8], mut p: usize, c: u8, nocase: bool) -> (bool, usize) {
+    let fold = |b: u8| match nocase {
+        true => b.to_ascii_lowercase(),
+        false => b,
+    };
+    let c = fold(c);
+
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            matched |= fold(pattern[p + 1]) == c;
+            p += 2;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' {
+            let (mut start, mut end) = (fold(pattern[p]), fold(pattern[p + 2]));
+            if start > end {
+                std::mem::swap(&mut start, &mut end);
+            }
+            matched |= (start..=end).contains(&c);
+            p += 3;
+        } else {
+            matched |= fold(pattern[p]) == c;
+            p += 1;
+        }
+    }
+    // 跳过 ]，未闭合时 p 已经在模式末尾
+    (matched != negate, (p + 1).min(pattern.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, string: &str) -> bool {
+        glob_match(pattern.as_bytes(), string.as_bytes(), false)
+    }
+
+    #[test]
+    fn test_glob_wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "anything"));
+        assert!(matches("h?llo", "hello"));
+        assert!(!matches("h?llo", "hllo"));
+        assert!(matches("h*llo", "hllo"));
+        assert!(matches("h*llo", "heeeello"));
+        assert!(matches("user:*:name", "user:1000:name"));
+        assert!(!matches("user:*:name", "user:1000:email"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+        assert!(!matches("", "a"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn test_glob_classes() {
+        assert!(matches("h[ae]llo", "hello"));
+        assert!(matches("h[ae]llo", "hallo"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[^e]llo", "hello"));
+        assert!(matches("h[a-b]llo", "hbllo"));
+        assert!(matches("h[b-a]llo", "hbllo"));
+        assert!(!matches("h[a-b]llo", "hcllo"));
+        assert!(matches("[\\]]", "]"));
+        // 未闭合的 [ 延续到模式末尾
+        assert!(matches("a[bc", "ab"));
+    }
+
+    #[test]
+    fn test_glob_escapes_and_case() {
+        assert!(matches("a\\*b", "a*b"));
+        assert!(!matches("a\\*b", "axb"));
+        assert!(matches("a\\?", "a?"));
+        assert!(matches("a\\", "a\\"));
+
+        assert!(!matches("HELLO", "hello"));
+        assert!(glob_match(b"HE[L]lo", b"hello", true));
+        assert!(glob_match(b"h[A-Z]llo", b"hello", true));
+    }
+
+    #[test]
+    fn test_glob_pathological_pattern() {
+        let pattern = "a*".repeat(50) + "b";
+        let string = "a".repeat(100);
+        assert!(!matches(&pattern, &string));
+    }
+
+    #[cfg(feature = "testing")]
+    mod fuzz {
+        use super::*;
+        use proptest::prelude::*;
+
+        // 直接按定义递归的参考实现，只用于比较小输入上的结果
+        fn reference(pattern: &[u8], string: &[u8]) -> bool {
+            match pattern.first() {
+                None => string.is_empty(),
+                Some(b'*') => {
+                    (0..=string.len()).any(|skip| reference(&pattern[1..], &string[skip..]))
+                }
+                Some(_) => match string.first() {
+                    Some(&c) => match match_one(pattern, 0, c, false) {
+                        Some(next) => reference(&pattern[next..], &string[1..]),
+                        None => false,
+                    },
+                    None => false,
+                },
+            }
+        }
+
+        fn escape(literal: &[u8]) -> Vec<u8> {
+            literal
+                .iter()
+                .flat_map(|&c| match c {
+                    b'*' | b'?' | b'[' | b']' | b'\\' => vec![b'\\', c],
+                    c => vec![c],
+                })
+                .collect()
+        }
+
+        proptest! {
+            #[test]
+            fn test_glob_matches_reference(
+                pattern in proptest::collection::vec(prop::sample::select(b"ab*?[]^-\\".to_vec()), 0..8),
+                string in proptest::collection::vec(prop::sample::select(b"ab*[]-\\".to_vec()), 0..8),
+            ) {
+                prop_assert_eq!(glob_match(&pattern, &string, false), reference(&pattern, &string));
+            }
+
+            #[test]
+            fn test_glob_never_panics(pattern in any::<Vec<u8>>(), string in any::<Vec<u8>>(), nocase in any::<bool>()) {
+                let _ = glob_match(&pattern, &string, nocase);
+            }
+
+            #[test]
+            fn test_escaped_literal_matches_itself(literal in any::<Vec<u8>>()) {
+                prop_assert!(glob_match(&escape(&literal), &literal, false));
+            }
+        }
+    }
+}
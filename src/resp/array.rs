@@ -1,31 +1,26 @@
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 use bytes::{Buf, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
 use super::{
-    calc_total_length, extract_fixed_data, frame::RespFrame, parse_length, BUF_CAP, CRLF_LEN,
+    calc_total_length,
+    frame::RespFrame,
+    parse_length,
+    stream::{decode_streamed_aggregate, is_streamed, streamed_aggregate_length},
+    write_formatted, CRLF_LEN,
 };
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 pub struct RespArray(pub(crate) Vec<RespFrame>);
 
-const NULL_RESP_ARRAY: &[u8] = b"*-1\r\n";
-
 impl RespEncode for RespArray {
-    fn encode(self) -> Vec<u8> {
-        if self.is_null() {
-            // 如果是空数组，返回对应的编码
-            NULL_RESP_ARRAY.to_vec()
-        } else {
-            let mut buf = Vec::with_capacity(BUF_CAP);
-            buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!("*{}\r\n", self.0.len()));
 
-            for item in self.0 {
-                buf.extend_from_slice(&item.encode());
-            }
-            buf
+        for item in self.iter() {
+            item.encode_into(buf);
         }
     }
 }
@@ -33,10 +28,9 @@ impl RespEncode for RespArray {
 impl RespDecode for RespArray {
     const PREFIX: &'static str = "*";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        if buf.starts_with(NULL_RESP_ARRAY) {
-            // 如果是空数组的编码，直接返回空数组
-            extract_fixed_data(buf, std::str::from_utf8(NULL_RESP_ARRAY)?, "NullArray")?;
-            Ok(RespArray::null())
+        if is_streamed(buf, Self::PREFIX) {
+            let frames = decode_streamed_aggregate(buf, Self::PREFIX, false, RespFrame::decode)?;
+            Ok(RespArray::new(frames))
         } else {
             let (end, len) = parse_length(buf, Self::PREFIX)?;
             let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
@@ -57,9 +51,8 @@ impl RespDecode for RespArray {
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        if buf.starts_with(NULL_RESP_ARRAY) {
-            // 如果是空数组的编码，返回对应的长度
-            Ok(NULL_RESP_ARRAY.len())
+        if is_streamed(buf, Self::PREFIX) {
+            streamed_aggregate_length(buf, Self::PREFIX, false)
         } else {
             let (end, len) = parse_length(buf, Self::PREFIX)?;
             calc_total_length(buf, end, len, Self::PREFIX)
@@ -71,14 +64,6 @@ impl RespArray {
     pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
         RespArray(s.into())
     }
-
-    pub fn null() -> Self {
-        RespArray(Vec::new())
-    }
-
-    pub fn is_null(&self) -> bool {
-        self.0.is_empty()
-    }
 }
 
 impl Deref for RespArray {
@@ -89,10 +74,16 @@ impl Deref for RespArray {
     }
 }
 
+impl DerefMut for RespArray {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::BulkString;
+    use crate::{BulkString, RespNullArray};
     use anyhow::Result;
 
     #[test]
@@ -111,19 +102,37 @@ mod tests {
     }
 
     #[test]
-    fn test_null_array_encode() {
-        let s: RespFrame = RespArray::null().into();
-        assert_eq!(s.encode(), b"*-1\r\n");
+    fn test_empty_array_encode() {
+        // 空数组不再被当作 null
+        let s: RespFrame = RespArray::new([]).into();
+        assert_eq!(s.encode(), b"*0\r\n");
     }
 
     #[test]
-    fn test_null_array_decode() -> Result<()> {
+    fn test_empty_array_decode() -> Result<()> {
         let mut buf = BytesMut::new();
-        buf.extend_from_slice(b"*-1\r\n");
+        buf.extend_from_slice(b"*0\r\n");
 
         let frame = RespArray::decode(&mut buf)?;
-        assert_eq!(frame, RespArray::null());
+        assert_eq!(frame, RespArray::new([]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_and_null_array_round_trip() -> Result<()> {
+        let empty: RespFrame = RespArray::new([]).into();
+        let null: RespFrame = RespNullArray.into();
+        assert_ne!(empty, null);
+
+        let mut buf = BytesMut::new();
+        empty.encode_into(&mut buf);
+        null.encode_into(&mut buf);
+        assert_eq!(&buf[..], b"*0\r\n*-1\r\n");
 
+        assert_eq!(RespFrame::decode(&mut buf)?, empty);
+        assert_eq!(RespFrame::decode(&mut buf)?, null);
+        assert!(buf.is_empty());
         Ok(())
     }
 
@@ -0,0 +1,120 @@
+use bytes::{Buf, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError, RespFrame, RespMap};
+
+use super::{
+    calc_total_length,
+    map::{decode_key, encode_key, MapKeyEncoding},
+    parse_length, remaining, write_formatted, CRLF_LEN,
+};
+
+// - attribute: "|<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><reply>"
+// attribute 本身不是一个独立的回复，而是附加在紧随其后的回复上的元数据
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct RespAttribute {
+    pub attributes: RespMap,
+    pub frame: Box<RespFrame>,
+}
+
+impl RespAttribute {
+    pub fn new(attributes: RespMap, frame: impl Into<RespFrame>) -> Self {
+        RespAttribute {
+            attributes,
+            frame: Box::new(frame.into()),
+        }
+    }
+}
+
+impl RespEncode for RespAttribute {
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!("|{}\r\n", self.attributes.len()));
+
+        for (key, value) in self.attributes.iter() {
+            encode_key(key, MapKeyEncoding::default(), buf);
+            value.encode_into(buf);
+        }
+        self.frame.encode_into(buf);
+    }
+}
+
+impl RespDecode for RespAttribute {
+    const PREFIX: &'static str = "|";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let total_len = Self::expect_length(buf)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        buf.advance(end + CRLF_LEN);
+
+        let mut attributes = RespMap::new();
+        for _ in 0..len {
+            let key = decode_key(buf)?;
+            let value = RespFrame::decode(buf)?;
+            attributes.insert(key, value);
+        }
+        let frame = RespFrame::decode(buf)?;
+
+        Ok(RespAttribute::new(attributes, frame))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total = calc_total_length(buf, end, len, Self::PREFIX)?;
+        Ok(total + RespFrame::expect_length(remaining(buf, total)?)?)
+    }
+}
+
+impl RespFrame {
+    // 回复上附带的 attribute，没有时返回 None
+    pub fn attributes(&self) -> Option<&RespMap> {
+        match self {
+            RespFrame::Attribute(attr) => Some(&attr.attributes),
+            _ => None,
+        }
+    }
+
+    // 去掉 attribute，返回真正的回复
+    pub fn into_reply(self) -> RespFrame {
+        match self {
+            RespFrame::Attribute(attr) => attr.frame.into_reply(),
+            frame => frame,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use anyhow::Result;
+
+    #[test]
+    fn test_attribute_encode() {
+        let mut attributes = RespMap::new();
+        attributes.insert("ttl".to_string(), 3600.into());
+        let frame: RespFrame = RespAttribute::new(attributes, BulkString::from("value")).into();
+
+        assert_eq!(&frame.encode(), b"|1\r\n+ttl\r\n:3600\r\n$5\r\nvalue\r\n");
+    }
+
+    #[test]
+    fn test_attribute_decode() -> Result<()> {
+        let mut buf = BytesMut::from("|1\r\n+ttl\r\n:3600\r\n$5\r\nvalue\r\n");
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame.attributes().map(|a| a.len()), Some(1));
+        assert_eq!(frame.into_reply(), BulkString::from("value").into());
+        assert!(buf.is_empty());
+
+        // 后面的回复还没收到时需要继续等待
+        let mut buf = BytesMut::from("|1\r\n+ttl\r\n:3600\r\n$5\r\nval");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap_err(),
+            RespError::NotComplete
+        );
+
+        Ok(())
+    }
+}
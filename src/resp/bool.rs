@@ -24,11 +24,11 @@ impl RespDecode for bool {
 
 // - boolean: "#<t|f>\r\n"
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        if self {
-            b"#t\r\n".to_vec()
+    fn encode_into(&self, buf: &mut BytesMut) {
+        if *self {
+            buf.extend_from_slice(b"#t\r\n");
         } else {
-            b"#f\r\n".to_vec()
+            buf.extend_from_slice(b"#f\r\n");
         }
     }
 }
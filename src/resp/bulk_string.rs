@@ -1,61 +1,68 @@
 use std::ops::Deref;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_fixed_data, parse_length, CRLF_LEN};
-// 添加一个表示空字符串的常量
-const NULL_BULK_STRING: &[u8] = b"$-1\r\n";
+use super::{
+    parse_length,
+    stream::{decode_streamed_string, is_streamed, streamed_string_length},
+    write_formatted, CRLF_LEN,
+};
+// 不小于该长度的 bulk string 直接引用读缓冲区中的数据而不拷贝；
+// 更短的数据仍然拷贝一份，避免几个字节的小值长期占住整块读缓冲区
+const ZERO_COPY_THRESHOLD: usize = 1024;
 
+// 使用 Bytes 存储数据，clone 只增加引用计数
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Ord)]
-pub struct BulkString(pub(crate) Vec<u8>);
+pub struct BulkString(pub(crate) Bytes);
 
 // - bulk string: "$<length>\r\n<data>\r\n"
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
-        if self.is_null() {
-            // 如果是空字符串，返回对应的编码
-            NULL_BULK_STRING.to_vec()
-        } else {
-            let mut buf = Vec::with_capacity(self.len() + 16);
-            buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
-            buf.extend_from_slice(&self);
-            buf.extend_from_slice(b"\r\n");
-            buf
-        }
+    fn encode_into(&self, buf: &mut BytesMut) {
+        buf.reserve(self.len() + 16);
+        write_formatted(buf, format_args!("${}\r\n", self.len()));
+        buf.extend_from_slice(self);
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
 impl RespDecode for BulkString {
     const PREFIX: &'static str = "$";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        if buf.starts_with(NULL_BULK_STRING) {
-            // 如果是空字符串的编码，直接返回空字符串
-            extract_fixed_data(
-                buf,
-                std::str::from_utf8(NULL_BULK_STRING)?,
-                "NullBulkString",
-            )?;
-            Ok(BulkString::null())
+        if is_streamed(buf, Self::PREFIX) {
+            Ok(BulkString::new(decode_streamed_string(buf, Self::PREFIX)?))
         } else {
             let (end, len) = parse_length(buf, Self::PREFIX)?;
             let remained = &buf[end + CRLF_LEN..];
             if remained.len() < len + CRLF_LEN {
                 return Err(RespError::NotComplete);
             }
+            // 数据之后必须紧跟 CRLF，否则长度与数据不符
+            if &remained[len..len + CRLF_LEN] != b"\r\n" {
+                return Err(RespError::InvalidFrame(format!(
+                    "bulk string is not {} bytes long",
+                    len
+                )));
+            }
 
             buf.advance(end + CRLF_LEN);
 
-            let data = buf.split_to(len + CRLF_LEN);
-            Ok(BulkString::new(data[..len].to_vec()))
+            let data = if len >= ZERO_COPY_THRESHOLD {
+                buf.split_to(len).freeze()
+            } else {
+                let data = Bytes::copy_from_slice(&buf[..len]);
+                buf.advance(len);
+                data
+            };
+            buf.advance(CRLF_LEN);
+            Ok(BulkString(data))
         }
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
-        if buf.starts_with(NULL_BULK_STRING) {
-            // 如果是空字符串的编码，返回对应的长度
-            Ok(NULL_BULK_STRING.len())
+        if is_streamed(buf, Self::PREFIX) {
+            streamed_string_length(buf, Self::PREFIX)
         } else {
             let (end, len) = parse_length(buf, Self::PREFIX)?;
             Ok(end + CRLF_LEN + len + CRLF_LEN)
@@ -65,15 +72,11 @@ impl RespDecode for BulkString {
 
 impl BulkString {
     pub fn new(s: impl Into<Vec<u8>>) -> Self {
-        BulkString(s.into())
-    }
-
-    pub fn null() -> Self {
-        BulkString(Vec::new())
+        BulkString(Bytes::from(s.into()))
     }
 
-    pub fn is_null(&self) -> bool {
-        self.0.is_empty()
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
     }
 }
 
@@ -84,7 +87,7 @@ impl AsRef<[u8]> for BulkString {
 }
 
 impl Deref for BulkString {
-    type Target = Vec<u8>;
+    type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -93,25 +96,31 @@ impl Deref for BulkString {
 
 impl From<&str> for BulkString {
     fn from(value: &str) -> Self {
-        BulkString(value.as_bytes().to_vec())
+        BulkString(Bytes::copy_from_slice(value.as_bytes()))
     }
 }
 
 impl From<String> for BulkString {
     fn from(s: String) -> Self {
-        BulkString(s.into_bytes())
+        BulkString(Bytes::from(s))
     }
 }
 
 impl From<&[u8]> for BulkString {
     fn from(value: &[u8]) -> Self {
-        BulkString(value.to_vec())
+        BulkString(Bytes::copy_from_slice(value))
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(s.to_vec())
+        BulkString(Bytes::copy_from_slice(s))
+    }
+}
+
+impl From<Bytes> for BulkString {
+    fn from(value: Bytes) -> Self {
+        BulkString(value)
     }
 }
 
@@ -128,9 +137,10 @@ mod tests {
     }
 
     #[test]
-    fn test_null_bulk_string_encode() {
-        let s: RespFrame = BulkString::null().into();
-        assert_eq!(s.encode(), b"$-1\r\n");
+    fn test_empty_bulk_string_encode() {
+        // 空字符串不再被当作 null
+        let s: RespFrame = BulkString::new("").into();
+        assert_eq!(s.encode(), b"$0\r\n\r\n");
     }
 
     #[test]
@@ -153,12 +163,37 @@ mod tests {
     }
 
     #[test]
-    fn test_null_bulk_string_decode() -> Result<()> {
+    fn test_large_bulk_string_decode_is_zero_copy() -> Result<()> {
+        let payload = vec![b'x'; ZERO_COPY_THRESHOLD * 4];
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(format!("${}\r\n", payload.len()).as_bytes());
+        buf.extend_from_slice(&payload);
+        buf.extend_from_slice(b"\r\n$3\r\nfoo\r\n");
+
+        let start = buf.as_ptr() as usize;
+        let end = start + buf.len();
+
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame.as_ref(), payload.as_slice());
+        // 解码结果直接指向原来的读缓冲区
+        let ptr = frame.as_ptr() as usize;
+        assert!(ptr >= start && ptr < end);
+
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::new("foo"));
+        assert!(buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_bulk_string_decode() -> Result<()> {
         let mut buf = BytesMut::new();
 
-        buf.extend_from_slice(b"$-1\r\n");
+        buf.extend_from_slice(b"$0\r\n\r\n");
         let frame = BulkString::decode(&mut buf)?;
-        assert_eq!(frame, BulkString::null());
+        assert_eq!(frame, BulkString::new(""));
+        assert!(buf.is_empty());
 
         Ok(())
     }
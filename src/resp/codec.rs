@@ -0,0 +1,297 @@
+use anyhow::Result;
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{RespArray, RespEncode, RespError, RespFrame, RespLimits};
+
+use super::{limits::missing_bytes, shared::shared_encoding};
+
+const FRAME_PREFIXES: &[u8] = b"+-:$*_#,%~|>";
+// 缺少的数据不小于该长度时（通常是一个很大的 bulk string），一次性预留好空间，
+// 后续的读取直接写入这块内存，避免缓冲区反复扩容拷贝，与 redis 的 PROTO_MBULK_BIG_ARG 一致
+const BIG_ARG_THRESHOLD: usize = 32 * 1024;
+// 每次最多预留这么多。长度来自还没有认证的客户端发来的头部，一个 $536870912 就能让服务器分配 512MB，
+// 所以按步长随数据到达逐步扩容，每个连接在数据到达之前最多多占用这么多内存
+const MAX_RESERVE_STEP: usize = 4 * 1024 * 1024;
+// 一行 inline 命令的最大长度，与 redis 的 PROTO_INLINE_MAX_SIZE 一致
+const INLINE_MAX_SIZE: usize = 64 * 1024;
+
+// 用于 Framed<TcpStream, RespCodec>：先用 expect_length 判断帧是否完整，
+// 不完整时预留好剩余的空间，等数据到齐后再解码
+#[derive(Debug, Default, Clone)]
+pub struct RespCodec {
+    limits: RespLimits,
+    inline: bool,
+}
+
+impl RespCodec {
+    pub fn new(limits: RespLimits) -> Self {
+        Self {
+            limits,
+            inline: false,
+        }
+    }
+
+    // 服务器端接受 telnet / redis-cli 发送的 inline 命令，客户端收到的回复总是以类型前缀开头
+    pub fn with_inline_commands(mut self) -> Self {
+        self.inline = true;
+        self
+    }
+
+    pub fn limits(&self) -> RespLimits {
+        self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: RespLimits) {
+        self.limits = limits;
+    }
+}
+
+impl Encoder<RespFrame> for RespCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
+        match shared_encoding(&item) {
+            Some(encoded) => dst.extend_from_slice(encoded),
+            None => item.encode_into(dst),
+        }
+        Ok(())
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = RespFrame;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RespFrame>> {
+        while self.inline && src.first().is_some_and(|b| !FRAME_PREFIXES.contains(b)) {
+            match decode_inline(src)? {
+                None => return Ok(None),
+                Some(args) if args.is_empty() => continue,
+                Some(args) => return Ok(Some(RespArray::new(args).into())),
+            }
+        }
+
+        match self.limits.expect_length(src) {
+            Ok(len) if len > src.len() => {
+                src.reserve((len - src.len()).min(MAX_RESERVE_STEP));
+                return Ok(None);
+            }
+            Ok(_) => {}
+            Err(RespError::NotComplete) => {
+                let missing = missing_bytes();
+                if missing >= BIG_ARG_THRESHOLD {
+                    src.reserve(missing.min(MAX_RESERVE_STEP));
+                }
+                return Ok(None);
+            }
+            Err(e) => return Err(resync(src, e).into()),
+        }
+
+        match self.limits.decode(src) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(RespError::NotComplete) => Ok(None),
+            Err(e) => Err(resync(src, e).into()),
+        }
+    }
+}
+
+// 不以类型前缀开头的一行是 inline 命令，按空白切分成 BulkString 参数，不支持引号。
+// 行还不完整时返回 None，空行返回空的参数列表
+fn decode_inline(src: &mut BytesMut) -> Result<Option<Vec<RespFrame>>, RespError> {
+    let Some(end) = src.iter().position(|&b| b == b'\n') else {
+        if src.len() > INLINE_MAX_SIZE {
+            let len = src.len();
+            src.clear();
+            return Err(RespError::FrameTooLarge(len, INLINE_MAX_SIZE));
+        }
+        return Ok(None);
+    };
+    let line = src.split_to(end + 1);
+    let args = line[..]
+        .split(|b: &u8| b.is_ascii_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .map(RespFrame::from)
+        .collect();
+    Ok(Some(args))
+}
+
+// 格式错误的帧会一直留在缓冲区里，导致后面的帧也无法解析。
+// 丢弃数据直到下一个以合法类型前缀开头的行，找不到就清空缓冲区。
+// 超过长度 / 嵌套限制的帧不做处理，由调用方关闭连接
+fn resync(src: &mut BytesMut, err: RespError) -> RespError {
+    if matches!(
+        err,
+        RespError::FrameTooLarge(_, _) | RespError::NestingTooDeep(_)
+    ) {
+        return err;
+    }
+
+    let discard = src
+        .windows(3)
+        .position(|w| &w[..2] == b"\r\n" && FRAME_PREFIXES.contains(&w[2]))
+        .map(|pos| pos + 2)
+        .unwrap_or(src.len());
+    src.advance(discard);
+    RespError::FrameDiscarded(discard, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray};
+
+    #[test]
+    fn test_codec_partial_frame() -> Result<()> {
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::from("*2\r\n$5\r\nhello\r\n$100\r\nab");
+        assert_eq!(codec.decode(&mut buf)?, None);
+
+        let mut buf = BytesMut::from("$100\r\nab");
+        assert_eq!(codec.decode(&mut buf)?, None);
+        assert!(buf.capacity() >= 106);
+
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$1\r\nk\r\n+OK");
+        let frame = codec.decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Some(
+                RespArray::new([BulkString::from("get").into(), BulkString::from("k").into()])
+                    .into()
+            )
+        );
+        assert_eq!(codec.decode(&mut buf)?, None);
+        buf.extend_from_slice(b"\r\n");
+        assert_eq!(codec.decode(&mut buf)?, Some(RespFrame::from("OK")));
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_resync() -> Result<()> {
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::from("*1\r\n$x\r\nabc\r\n*1\r\n$4\r\nping\r\n");
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RespError>(),
+            Some(RespError::FrameDiscarded(4, _))
+        ));
+        // 跳过了数组头，"$x" 本身也是格式错误的帧
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RespError>(),
+            Some(RespError::FrameDiscarded(9, _))
+        ));
+        let frame = codec.decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            Some(RespArray::new([BulkString::from("ping").into()]).into())
+        );
+
+        // 未知的类型前缀
+        let mut buf = BytesMut::from("hello\r\n");
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RespError>(),
+            Some(RespError::FrameDiscarded(7, _))
+        ));
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_inline_commands() -> Result<()> {
+        let mut codec = RespCodec::default().with_inline_commands();
+        let mut buf = BytesMut::from("\r\nPING\r\nset  k v\n*1\r\n$4\r\nping\r\nECHO hi");
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(RespArray::new([BulkString::from("PING").into()]).into())
+        );
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(
+                RespArray::new([
+                    BulkString::from("set").into(),
+                    BulkString::from("k").into(),
+                    BulkString::from("v").into()
+                ])
+                .into()
+            )
+        );
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(RespArray::new([BulkString::from("ping").into()]).into())
+        );
+        // 没有换行的 inline 命令等待后续数据
+        assert_eq!(codec.decode(&mut buf)?, None);
+        assert_eq!(&buf[..], b"ECHO hi");
+
+        let mut buf = BytesMut::from(vec![b'x'; INLINE_MAX_SIZE + 1].as_slice());
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RespError>(),
+            Some(RespError::FrameTooLarge(_, INLINE_MAX_SIZE))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_reserve_big_arg() -> Result<()> {
+        let mut codec = RespCodec::default();
+        let len = 1024 * 1024;
+        let mut buf =
+            BytesMut::from(format!("*3\r\n$3\r\nset\r\n$1\r\nk\r\n${}\r\nab", len).as_str());
+        assert_eq!(codec.decode(&mut buf)?, None);
+        assert!(buf.capacity() >= buf.len() + len);
+
+        // 数据到齐之前不会再扩容
+        let ptr = buf.as_ptr();
+        buf.extend_from_slice(&vec![b'x'; len - 2]);
+        buf.extend_from_slice(b"\r\n");
+        assert_eq!(buf.as_ptr(), ptr);
+
+        let Some(RespFrame::Array(frame)) = codec.decode(&mut buf)? else {
+            panic!("expected an array");
+        };
+        assert_eq!(frame.len(), 3);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_reserve_is_capped() -> Result<()> {
+        let mut codec = RespCodec::default();
+        let len = 500 * 1024 * 1024;
+        let mut buf = BytesMut::from(format!("*2\r\n$3\r\nget\r\n${}\r\nab", len).as_str());
+        assert_eq!(codec.decode(&mut buf)?, None);
+        assert!(buf.capacity() >= MAX_RESERVE_STEP);
+        assert!(buf.capacity() < 2 * MAX_RESERVE_STEP);
+
+        // 数据到达之后继续按步长扩容
+        buf.extend_from_slice(&vec![b'x'; MAX_RESERVE_STEP]);
+        assert_eq!(codec.decode(&mut buf)?, None);
+        assert!(buf.capacity() >= buf.len() + MAX_RESERVE_STEP);
+        assert!(buf.capacity() < buf.len() + 2 * MAX_RESERVE_STEP);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_limits() {
+        let mut codec = RespCodec::new(RespLimits {
+            max_bulk_len: 4,
+            ..Default::default()
+        });
+        let mut buf = BytesMut::from("$5\r\nhello\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_codec_encode() -> Result<()> {
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::new();
+        codec.encode(RespFrame::Integer(1), &mut buf)?;
+        codec.encode(RespFrame::from("OK"), &mut buf)?;
+        assert_eq!(&buf[..], b":1\r\n+OK\r\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+// RespFrame 到基础类型的转换，命令解析参数时统一使用
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{RespArray, RespError, RespFrame, RespMap, RespNull};
+
+impl RespFrame {
+    // 字符串类型的帧返回其内容，非 utf8 或者不是字符串时返回 None
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            RespFrame::BulkString(s) => std::str::from_utf8(s).ok(),
+            RespFrame::SimpleString(s) => Some(&s.0),
+            _ => None,
+        }
+    }
+}
+
+fn unexpected(expected: &str, frame: &RespFrame) -> RespError {
+    RespError::InvalidFrameType(format!("expected {}, got {}", expected, frame.type_name()))
+}
+
+impl TryFrom<RespFrame> for String {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::BulkString(s) => {
+                String::from_utf8(s.0.into()).map_err(|e| e.utf8_error().into())
+            }
+            RespFrame::SimpleString(s) => Ok(s.0.into()),
+            frame => Err(unexpected("string", &frame)),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for Vec<u8> {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::BulkString(s) => Ok(s.0.into()),
+            RespFrame::SimpleString(s) => Ok(String::from(s.0).into_bytes()),
+            frame => Err(unexpected("string", &frame)),
+        }
+    }
+}
+
+// 数字既可以是对应的 RESP 类型，也可以是字符串形式的参数
+impl TryFrom<RespFrame> for i64 {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Integer(i) => Ok(i),
+            frame => match frame.as_str() {
+                Some(s) => Ok(s.parse()?),
+                None => Err(unexpected("integer", &frame)),
+            },
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for f64 {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Double(d) => Ok(d),
+            RespFrame::Integer(i) => Ok(i as f64),
+            frame => match frame.as_str() {
+                Some(s) => Ok(s.parse()?),
+                None => Err(unexpected("double", &frame)),
+            },
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for bool {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Boolean(b) => Ok(b),
+            RespFrame::Integer(0) => Ok(false),
+            RespFrame::Integer(1) => Ok(true),
+            frame => Err(unexpected("boolean", &frame)),
+        }
+    }
+}
+
+impl TryFrom<RespFrame> for RespArray {
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        match frame {
+            RespFrame::Array(a) => Ok(a),
+            frame => Err(unexpected("array", &frame)),
+        }
+    }
+}
+
+// null 转换为 None，其余的按 T 转换
+impl<T> TryFrom<RespFrame> for Option<T>
+where
+    T: TryFrom<RespFrame, Error = RespError>,
+{
+    type Error = RespError;
+
+    fn try_from(frame: RespFrame) -> Result<Self, Self::Error> {
+        if frame.is_null() {
+            Ok(None)
+        } else {
+            T::try_from(frame).map(Some)
+        }
+    }
+}
+
+// 标准集合到 RespFrame 的转换，方便构造回复
+impl<T: Into<RespFrame>> From<Vec<T>> for RespArray {
+    fn from(v: Vec<T>) -> Self {
+        RespArray::new(v.into_iter().map(Into::into).collect::<Vec<_>>())
+    }
+}
+
+impl<T: Into<RespFrame>> From<Vec<T>> for RespFrame {
+    fn from(v: Vec<T>) -> Self {
+        RespArray::from(v).into()
+    }
+}
+
+impl<T: Into<RespFrame>> From<BTreeMap<String, T>> for RespMap {
+    fn from(m: BTreeMap<String, T>) -> Self {
+        m.into_iter().map(|(k, v)| (k, v.into())).collect()
+    }
+}
+
+impl<T: Into<RespFrame>> From<BTreeMap<String, T>> for RespFrame {
+    fn from(m: BTreeMap<String, T>) -> Self {
+        RespMap::from(m).into()
+    }
+}
+
+impl<T: Into<RespFrame>> From<HashMap<String, T>> for RespMap {
+    fn from(m: HashMap<String, T>) -> Self {
+        m.into_iter().map(|(k, v)| (k, v.into())).collect()
+    }
+}
+
+impl<T: Into<RespFrame>> From<HashMap<String, T>> for RespFrame {
+    fn from(m: HashMap<String, T>) -> Self {
+        RespMap::from(m).into()
+    }
+}
+
+impl FromIterator<(String, RespFrame)> for RespMap {
+    fn from_iter<I: IntoIterator<Item = (String, RespFrame)>>(iter: I) -> Self {
+        RespMap(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<RespFrame> for RespArray {
+    fn from_iter<I: IntoIterator<Item = RespFrame>>(iter: I) -> Self {
+        RespArray(iter.into_iter().collect())
+    }
+}
+
+// None 转换为 null，由网络层按协议版本编码
+impl<T: Into<RespFrame>> From<Option<T>> for RespFrame {
+    fn from(v: Option<T>) -> Self {
+        match v {
+            Some(v) => v.into(),
+            None => RespNull.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespEncode, SimpleString};
+
+    #[test]
+    fn test_primitive_conversions() {
+        assert_eq!(i64::try_from(RespFrame::Integer(3)), Ok(3));
+        assert_eq!(
+            i64::try_from(RespFrame::from(BulkString::from("-7"))),
+            Ok(-7)
+        );
+        assert!(i64::try_from(RespFrame::from(BulkString::from("x"))).is_err());
+
+        assert_eq!(
+            f64::try_from(RespFrame::from(BulkString::from("1.5"))),
+            Ok(1.5)
+        );
+        assert_eq!(f64::try_from(RespFrame::Integer(2)), Ok(2.0));
+
+        assert_eq!(bool::try_from(RespFrame::Boolean(true)), Ok(true));
+        assert_eq!(bool::try_from(RespFrame::Integer(0)), Ok(false));
+
+        assert_eq!(
+            String::try_from(RespFrame::from(SimpleString::new("OK"))),
+            Ok("OK".to_string())
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(RespFrame::from(BulkString::from(&[0xff, 0x00][..]))),
+            Ok(vec![0xff, 0x00])
+        );
+        assert!(String::try_from(RespFrame::from(BulkString::from(&[0xff][..]))).is_err());
+        assert!(String::try_from(RespFrame::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn test_collection_into_frame() {
+        let frame: RespFrame = vec![1i64, 2].into();
+        assert_eq!(
+            frame,
+            RespArray::new([RespFrame::Integer(1), RespFrame::Integer(2)]).into()
+        );
+
+        let mut hmap = HashMap::new();
+        hmap.insert("a".to_string(), BulkString::from("1"));
+        let frame: RespFrame = hmap.into();
+        let mut expected = RespMap::new();
+        expected.insert("a".to_string(), BulkString::from("1").into());
+        assert_eq!(frame, expected.into());
+
+        let frame: RespFrame = BTreeMap::from([("b".to_string(), true)]).into();
+        assert_eq!(frame.encode(), b"%1\r\n+b\r\n#t\r\n");
+
+        assert_eq!(RespFrame::from(None::<i64>), RespNull.into());
+        assert_eq!(RespFrame::from(Some(3i64)), RespFrame::Integer(3));
+    }
+
+    #[test]
+    fn test_option_conversion() {
+        assert_eq!(Option::<i64>::try_from(RespFrame::from(RespNull)), Ok(None));
+        assert_eq!(Option::<i64>::try_from(RespFrame::Integer(1)), Ok(Some(1)));
+        assert_eq!(
+            RespFrame::from(BulkString::from("abc")).as_str(),
+            Some("abc")
+        );
+    }
+}
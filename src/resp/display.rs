@@ -0,0 +1,187 @@
+// 与 redis-cli 一致的可读输出，repl、monitor 和日志都使用它
+use std::fmt::{self, Display, Write};
+
+use crate::RespFrame;
+
+use super::double::format_double;
+
+impl Display for RespFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_tty(self))
+    }
+}
+
+impl RespFrame {
+    // 带缩进的类型树，方便调试时查看帧的结构
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        write_pretty(self, 0, &mut out);
+        out
+    }
+}
+
+fn format_tty(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::SimpleString(s) => s.0.to_string(),
+        RespFrame::Error(e) => format!("(error) {}", e.0),
+        RespFrame::Integer(i) => format!("(integer) {}", i),
+        RespFrame::BulkString(s) => quote(s),
+        RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+            "(nil)".to_string()
+        }
+        RespFrame::Boolean(b) => format!("({})", b),
+        RespFrame::Double(d) => format!("(double) {}", format_double(*d)),
+        RespFrame::Array(a) => format_items(a.iter().map(format_tty), ")", "(empty array)"),
+        RespFrame::Push(p) => format_items(p.iter().map(format_tty), ")", "(empty array)"),
+        RespFrame::Set(s) => format_items(s.iter().map(format_tty), "~", "(empty set)"),
+        RespFrame::Map(m) => format_items(
+            m.iter()
+                .map(|(k, v)| format!("{} => {}", quote(k.as_bytes()), format_tty(v))),
+            "#",
+            "(empty hash)",
+        ),
+        RespFrame::Attribute(a) => {
+            let attributes = format_items(
+                a.attributes
+                    .iter()
+                    .map(|(k, v)| format!("{} => {}", quote(k.as_bytes()), format_tty(v))),
+                "|",
+                "(empty attribute)",
+            );
+            format!("{}\n{}", attributes, format_tty(&a.frame))
+        }
+    }
+}
+
+// 每个元素前加上 "1) " 这样的序号，序号右对齐，元素的后续行按序号宽度缩进
+fn format_items(items: impl ExactSizeIterator<Item = String>, sep: &str, empty: &str) -> String {
+    let len = items.len();
+    if len == 0 {
+        return empty.to_string();
+    }
+
+    let width = len.to_string().len();
+    items
+        .enumerate()
+        .map(|(i, item)| {
+            let label = format!("{:>width$}{} ", i + 1, sep);
+            let indent = " ".repeat(label.len());
+            format!("{}{}", label, item.replace('\n', &format!("\n{}", indent)))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 与 redis-cli 一样给字符串加引号并转义不可打印字符
+fn quote(s: &[u8]) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for &c in s {
+        match c {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            c if c.is_ascii_graphic() || c == b' ' => out.push(c as char),
+            c => {
+                let _ = write!(out, "\\x{:02x}", c);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn write_pretty(frame: &RespFrame, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let _ = match frame {
+        RespFrame::Array(a) => writeln!(out, "{}Array({})", indent, a.len()),
+        RespFrame::Push(p) => writeln!(out, "{}Push({})", indent, p.len()),
+        RespFrame::Set(s) => writeln!(out, "{}Set({})", indent, s.len()),
+        RespFrame::Map(m) => writeln!(out, "{}Map({})", indent, m.len()),
+        RespFrame::Attribute(a) => writeln!(out, "{}Attribute({})", indent, a.attributes.len()),
+        RespFrame::BulkString(s) => writeln!(out, "{}BulkString({})", indent, quote(s)),
+        frame => writeln!(out, "{}{:?}", indent, frame),
+    };
+
+    match frame {
+        RespFrame::Array(a) => a.iter().for_each(|v| write_pretty(v, depth + 1, out)),
+        RespFrame::Push(p) => p.iter().for_each(|v| write_pretty(v, depth + 1, out)),
+        RespFrame::Set(s) => s.iter().for_each(|v| write_pretty(v, depth + 1, out)),
+        RespFrame::Map(m) => m.iter().for_each(|(k, v)| {
+            let _ = writeln!(out, "{}  {}:", indent, quote(k.as_bytes()));
+            write_pretty(v, depth + 2, out);
+        }),
+        RespFrame::Attribute(a) => {
+            a.attributes.iter().for_each(|(k, v)| {
+                let _ = writeln!(out, "{}  {}:", indent, quote(k.as_bytes()));
+                write_pretty(v, depth + 2, out);
+            });
+            write_pretty(&a.frame, depth + 1, out);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespMap, RespNull, SimpleError, SimpleString};
+
+    #[test]
+    fn test_display_scalars() {
+        assert_eq!(RespFrame::from(SimpleString::new("OK")).to_string(), "OK");
+        assert_eq!(
+            RespFrame::from(SimpleError::new("ERR oops")).to_string(),
+            "(error) ERR oops"
+        );
+        assert_eq!(RespFrame::Integer(3).to_string(), "(integer) 3");
+        assert_eq!(
+            RespFrame::from(BulkString::from("a\"b\n\x01")).to_string(),
+            r#""a\"b\n\x01""#
+        );
+        assert_eq!(RespFrame::from(RespNull).to_string(), "(nil)");
+        assert_eq!(RespFrame::Boolean(true).to_string(), "(true)");
+        assert_eq!(RespFrame::Double(1.5).to_string(), "(double) 1.5");
+    }
+
+    #[test]
+    fn test_display_nested() {
+        let inner: RespFrame =
+            RespArray::new([BulkString::from("b").into(), BulkString::from("c").into()]).into();
+        let mut items = vec![BulkString::from("a").into(), inner];
+        items.extend((0..8).map(RespFrame::Integer));
+        let frame: RespFrame = RespArray::new(items).into();
+
+        let expected = [
+            " 1) \"a\"",
+            " 2) 1) \"b\"",
+            "    2) \"c\"",
+            " 3) (integer) 0",
+        ];
+        let output = frame.to_string();
+        let lines = output.lines().take(4).collect::<Vec<_>>();
+        assert_eq!(lines, expected);
+        assert!(output.ends_with("10) (integer) 7"));
+
+        let mut map = RespMap::new();
+        map.insert("k".to_string(), BulkString::from("v").into());
+        assert_eq!(RespFrame::from(map).to_string(), "1# \"k\" => \"v\"");
+        assert_eq!(
+            RespFrame::from(RespArray::new([])).to_string(),
+            "(empty array)"
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        let frame: RespFrame = RespArray::new([BulkString::from("a").into(), 1.into()]).into();
+        assert_eq!(
+            frame.pretty(),
+            "Array(2)\n  BulkString(\"a\")\n  Integer(1)\n"
+        );
+    }
+}
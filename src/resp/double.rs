@@ -2,7 +2,7 @@ use bytes::BytesMut;
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, write_formatted, CRLF_LEN};
 
 impl RespDecode for f64 {
     const PREFIX: &'static str = ",";
@@ -20,18 +20,31 @@ impl RespDecode for f64 {
 }
 
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
+// 格式与 redis 保持一致：inf / -inf / nan，不带 '+'，只有很大或很小的数才使用科学计数法
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
-            format!(",{:+e}\r\n", self)
-        } else {
-            let sign = if self < 0.0 { "" } else { "+" };
-            format!(",{}{}\r\n", sign, self)
-        };
-
-        buf.extend_from_slice(&ret.into_bytes());
-        buf
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!(",{}\r\n", format_double(*self)));
+    }
+}
+
+pub(crate) fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    let abs = value.abs();
+    if abs != 0.0 && !(1e-4..1e17).contains(&abs) {
+        // 与 printf 的 %g 一样，指数带符号且至少两位，比如 1.5e+17、1.23456e-09
+        let s = format!("{:e}", value);
+        let (mantissa, exp) = s.split_once('e').unwrap_or((&s, "0"));
+        let exp: i32 = exp.parse().unwrap_or(0);
+        let sign = if exp < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exp.abs())
+    } else {
+        value.to_string()
     }
 }
 
@@ -44,16 +57,28 @@ mod tests {
     #[test]
     fn test_double_encode() {
         let s: RespFrame = 123.456.into();
-        assert_eq!(s.encode(), b",+123.456\r\n");
+        assert_eq!(s.encode(), b",123.456\r\n");
 
         let s: RespFrame = (-123.456).into();
         assert_eq!(s.encode(), b",-123.456\r\n");
 
         let s: RespFrame = 1.23456e+8.into();
-        assert_eq!(s.encode(), b",+1.23456e8\r\n");
+        assert_eq!(s.encode(), b",123456000\r\n");
+
+        let s: RespFrame = 1.5e+17.into();
+        assert_eq!(s.encode(), b",1.5e+17\r\n");
 
         let s: RespFrame = (-1.23456e-9).into();
-        assert_eq!(s.encode(), b",-1.23456e-9\r\n");
+        assert_eq!(s.encode(), b",-1.23456e-09\r\n");
+
+        let s: RespFrame = f64::INFINITY.into();
+        assert_eq!(s.encode(), b",inf\r\n");
+
+        let s: RespFrame = f64::NEG_INFINITY.into();
+        assert_eq!(s.encode(), b",-inf\r\n");
+
+        let s: RespFrame = f64::NAN.into();
+        assert_eq!(s.encode(), b",nan\r\n");
     }
 
     #[test]
@@ -68,6 +93,11 @@ mod tests {
         let frame = f64::decode(&mut buf)?;
         assert_eq!(frame, 1.23456e-9);
 
+        buf.extend_from_slice(b",inf\r\n,-inf\r\n,nan\r\n");
+        assert_eq!(f64::decode(&mut buf)?, f64::INFINITY);
+        assert_eq!(f64::decode(&mut buf)?, f64::NEG_INFINITY);
+        assert!(f64::decode(&mut buf)?.is_nan());
+
         Ok(())
     }
 }
@@ -1,15 +1,25 @@
-use bytes::BytesMut;
-use enum_dispatch::enum_dispatch;
+use std::{
+    hash::{Hash, Hasher},
+    mem,
+};
 
-use crate::{RespDecode, RespError};
+use crate::{RespDecode, RespEncode, RespError};
+use bytes::BytesMut;
 
 use super::{
-    array::RespArray, bulk_string::BulkString, map::RespMap, null::RespNull, set::RespSet,
-    simple_error::SimpleError, simple_string::SimpleString,
+    array::RespArray,
+    attribute::RespAttribute,
+    bulk_string::BulkString,
+    limits::DepthGuard,
+    map::RespMap,
+    null::{is_null_array, is_null_bulk_string, RespNull, RespNullArray, RespNullBulkString},
+    push::RespPush,
+    set::RespSet,
+    simple_error::SimpleError,
+    simple_string::SimpleString,
 };
 
-#[enum_dispatch(RespEncode)]
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialOrd)]
 pub enum RespFrame {
     SimpleString(SimpleString),
     Error(SimpleError),
@@ -21,12 +31,123 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Attribute(RespAttribute),
+    Push(RespPush),
+    NullBulkString(RespNullBulkString),
+    NullArray(RespNullArray),
+}
+
+// f64 本身没有实现 Eq / Hash，这里按位比较 double：所有的 nan 相等，0.0 与 -0.0 不相等，
+// 这样 RespFrame 才能作为集合的成员
+fn double_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+// 不再用 enum_dispatch 生成：它会为 i64 / f64 / bool 生成 TryInto，与 convert 中的 TryFrom 冲突
+impl RespEncode for RespFrame {
+    fn encode_into(&self, buf: &mut BytesMut) {
+        match self {
+            RespFrame::SimpleString(f) => f.encode_into(buf),
+            RespFrame::Error(f) => f.encode_into(buf),
+            RespFrame::Integer(f) => f.encode_into(buf),
+            RespFrame::BulkString(f) => f.encode_into(buf),
+            RespFrame::Array(f) => f.encode_into(buf),
+            RespFrame::Null(f) => f.encode_into(buf),
+            RespFrame::Boolean(f) => f.encode_into(buf),
+            RespFrame::Double(f) => f.encode_into(buf),
+            RespFrame::Map(f) => f.encode_into(buf),
+            RespFrame::Set(f) => f.encode_into(buf),
+            RespFrame::Attribute(f) => f.encode_into(buf),
+            RespFrame::Push(f) => f.encode_into(buf),
+            RespFrame::NullBulkString(f) => f.encode_into(buf),
+            RespFrame::NullArray(f) => f.encode_into(buf),
+        }
+    }
+}
+
+macro_rules! impl_from_variant {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        $(
+            impl From<$ty> for RespFrame {
+                fn from(v: $ty) -> Self {
+                    RespFrame::$variant(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_variant!(
+    SimpleString(SimpleString),
+    Error(SimpleError),
+    Integer(i64),
+    BulkString(BulkString),
+    Array(RespArray),
+    Null(RespNull),
+    Boolean(bool),
+    Double(f64),
+    Map(RespMap),
+    Set(RespSet),
+    Attribute(RespAttribute),
+    Push(RespPush),
+    NullBulkString(RespNullBulkString),
+    NullArray(RespNullArray),
+);
+
+impl PartialEq for RespFrame {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RespFrame::SimpleString(a), RespFrame::SimpleString(b)) => a == b,
+            (RespFrame::Error(a), RespFrame::Error(b)) => a == b,
+            (RespFrame::Integer(a), RespFrame::Integer(b)) => a == b,
+            (RespFrame::BulkString(a), RespFrame::BulkString(b)) => a == b,
+            (RespFrame::Array(a), RespFrame::Array(b)) => a == b,
+            (RespFrame::Null(a), RespFrame::Null(b)) => a == b,
+            (RespFrame::Boolean(a), RespFrame::Boolean(b)) => a == b,
+            (RespFrame::Double(a), RespFrame::Double(b)) => double_bits(*a) == double_bits(*b),
+            (RespFrame::Map(a), RespFrame::Map(b)) => a == b,
+            (RespFrame::Set(a), RespFrame::Set(b)) => a == b,
+            (RespFrame::Attribute(a), RespFrame::Attribute(b)) => a == b,
+            (RespFrame::Push(a), RespFrame::Push(b)) => a == b,
+            (RespFrame::NullBulkString(_), RespFrame::NullBulkString(_)) => true,
+            (RespFrame::NullArray(_), RespFrame::NullArray(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for RespFrame {}
+
+impl Hash for RespFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            RespFrame::SimpleString(s) => s.hash(state),
+            RespFrame::Error(e) => e.hash(state),
+            RespFrame::Integer(i) => i.hash(state),
+            RespFrame::BulkString(s) => s.hash(state),
+            RespFrame::Array(a) => a.hash(state),
+            RespFrame::Null(n) => n.hash(state),
+            RespFrame::Boolean(b) => b.hash(state),
+            RespFrame::Double(d) => double_bits(*d).hash(state),
+            RespFrame::Map(m) => m.hash(state),
+            RespFrame::Set(s) => s.hash(state),
+            RespFrame::Attribute(a) => a.hash(state),
+            RespFrame::Push(p) => p.hash(state),
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {}
+        }
+    }
 }
 
 impl RespDecode for RespFrame {
     const PREFIX: &'static str = "";
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let _depth = DepthGuard::enter()?;
         let mut iter = buf.iter().peekable();
         match iter.peek() {
             Some(b'+') => {
@@ -41,10 +162,18 @@ impl RespDecode for RespFrame {
                 let frame = i64::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'$') if is_null_bulk_string(buf) => {
+                let frame = RespNullBulkString::decode(buf)?;
+                Ok(frame.into())
+            }
             Some(b'$') => {
                 let frame = BulkString::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'*') if is_null_array(buf) => {
+                let frame = RespNullArray::decode(buf)?;
+                Ok(frame.into())
+            }
             Some(b'*') => {
                 let frame = RespArray::decode(buf)?;
                 Ok(frame.into())
@@ -69,6 +198,14 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'|') => {
+                let frame = RespAttribute::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect_length: unknown frame type: {:?}",
@@ -78,11 +215,14 @@ impl RespDecode for RespFrame {
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let _depth = DepthGuard::enter()?;
         let mut iter = buf.iter().peekable();
         match iter.peek() {
+            Some(b'*') if is_null_array(buf) => RespNullArray::expect_length(buf),
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
+            Some(b'$') if is_null_bulk_string(buf) => RespNullBulkString::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
             Some(b'+') => SimpleString::expect_length(buf),
@@ -90,26 +230,54 @@ impl RespDecode for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => f64::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
-            _ => Err(RespError::NotComplete),
+            Some(b'|') => RespAttribute::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
+            None => Err(RespError::NotComplete),
+            Some(prefix) => Err(RespError::InvalidFrameType(format!(
+                "unknown frame type: {:?}",
+                **prefix as char
+            ))),
+        }
+    }
+}
+
+impl RespFrame {
+    // 把聚合类型拆成编码后的头部和元素，元素可以逐个编码写出，不需要一次编码整个帧。
+    // map 的 key 与默认编码一样放在对应的 value 前面；不是聚合类型时原样返回
+    pub fn into_elements(self) -> Result<(String, Vec<RespFrame>), RespFrame> {
+        match self {
+            RespFrame::Array(array) => Ok((format!("*{}\r\n", array.len()), array.0)),
+            RespFrame::Set(set) => Ok((format!("~{}\r\n", set.len()), set.0.into_iter().collect())),
+            RespFrame::Push(push) => Ok((format!(">{}\r\n", push.len()), push.0)),
+            RespFrame::Map(map) => {
+                let header = format!("%{}\r\n", map.len());
+                let mut elements = Vec::with_capacity(map.len() * 2);
+                for (key, value) in map.0 {
+                    elements.push(SimpleString::new_or_bulk(&key));
+                    elements.push(value);
+                }
+                Ok((header, elements))
+            }
+            frame => Err(frame),
         }
     }
 }
 
 impl From<&str> for RespFrame {
     fn from(s: &str) -> Self {
-        SimpleString(s.to_string()).into()
+        SimpleString(s.into()).into()
     }
 }
 
 impl From<&[u8]> for RespFrame {
     fn from(s: &[u8]) -> Self {
-        BulkString(s.to_vec()).into()
+        BulkString::from(s).into()
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for RespFrame {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(s.to_vec()).into()
+        BulkString::from(s).into()
     }
 }
 
@@ -122,7 +290,7 @@ mod tests {
     fn decode_simple_string() -> Result<()> {
         let mut buf = BytesMut::from("+OK\r\n");
         let frame = RespFrame::decode(&mut buf)?;
-        assert_eq!(frame, SimpleString("OK".to_string()).into());
+        assert_eq!(frame, SimpleString("OK".into()).into());
         Ok(())
     }
 
@@ -130,7 +298,42 @@ mod tests {
     fn decode_simple_error() -> Result<()> {
         let mut buf = BytesMut::from("-ERROR\r\n");
         let frame = RespFrame::decode(&mut buf)?;
-        assert_eq!(frame, SimpleError("ERROR".to_string()).into());
+        assert_eq!(frame, SimpleError("ERROR".into()).into());
         Ok(())
     }
+
+    #[test]
+    fn test_into_elements() {
+        let mut map = RespMap::new();
+        map.insert("a".to_string(), RespFrame::Integer(1));
+        map.insert("b".to_string(), BulkString::from("x").into());
+        let frames: Vec<RespFrame> = vec![
+            RespArray::new(vec![RespFrame::Integer(1), BulkString::from("a").into()]).into(),
+            RespSet::new(vec![RespFrame::Integer(1)]).into(),
+            map.into(),
+        ];
+        for frame in frames {
+            let expected = frame.encode();
+            let (header, elements) = frame.into_elements().unwrap();
+            let mut buf = BytesMut::from(header.as_bytes());
+            for element in elements {
+                element.encode_into(&mut buf);
+            }
+            assert_eq!(buf.to_vec(), expected);
+        }
+        assert!(RespFrame::Integer(1).into_elements().is_err());
+    }
+
+    #[test]
+    fn test_frame_eq_hash() {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        assert!(set.insert(RespFrame::Double(f64::NAN)));
+        assert!(!set.insert(RespFrame::Double(f64::NAN)));
+        assert!(set.insert(RespFrame::Double(1.5)));
+        assert!(set.insert(RespFrame::Integer(1)));
+        assert!(!set.insert(RespFrame::Double(1.5)));
+        assert_eq!(set.len(), 3);
+    }
 }
@@ -2,7 +2,7 @@ use bytes::BytesMut;
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, write_formatted, CRLF_LEN};
 
 impl RespDecode for i64 {
     const PREFIX: &'static str = ":";
@@ -21,11 +21,10 @@ impl RespDecode for i64 {
 }
 
 // - integer: ":[<+|->]<value>\r\n"
+// 与 redis 一致，编码时正数不带 '+'，解码时两种写法都接受
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        // let sign = if self < 0 { "" } else { "" };
-        // format!(":{}{}\r\n", sign, self).into_bytes()
-        format!(":{}\r\n", self).into_bytes()
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!(":{}\r\n", self));
     }
 }
 
@@ -56,6 +55,10 @@ mod tests {
         buf.extend_from_slice(b":-123\r\n");
         let frame = i64::decode(&mut buf)?;
         assert_eq!(frame, -123);
+
+        buf.extend_from_slice(b":+123\r\n");
+        let frame = i64::decode(&mut buf)?;
+        assert_eq!(frame, 123);
         Ok(())
     }
 }
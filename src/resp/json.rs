@@ -0,0 +1,102 @@
+// RespFrame 与 JSON 之间的转换，需要开启 json feature
+//
+// - array / set / push 对应 JSON 数组，map 对应 JSON 对象
+// - integer / double 对应数字，inf 和 nan 无法用 JSON 数字表示，转换为字符串
+// - 字符串类型对应 JSON 字符串，非 utf8 的内容有损转换
+// - error 转换为 {"error": "..."}
+use serde_json::{Map, Number, Value};
+
+use crate::{BulkString, RespArray, RespFrame, RespMap, RespNull};
+
+use super::double::format_double;
+
+impl RespFrame {
+    pub fn to_json(&self) -> Value {
+        match self {
+            RespFrame::SimpleString(s) => Value::String(s.0.to_string()),
+            RespFrame::Error(e) => {
+                let mut map = Map::new();
+                map.insert("error".to_string(), Value::String(e.0.to_string()));
+                Value::Object(map)
+            }
+            RespFrame::Integer(i) => Value::Number((*i).into()),
+            RespFrame::BulkString(s) => Value::String(String::from_utf8_lossy(s).into_owned()),
+            RespFrame::Array(a) => Value::Array(a.iter().map(RespFrame::to_json).collect()),
+            RespFrame::Set(s) => Value::Array(s.iter().map(RespFrame::to_json).collect()),
+            RespFrame::Push(p) => Value::Array(p.iter().map(RespFrame::to_json).collect()),
+            RespFrame::Map(m) => Value::Object(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), v.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+                Value::Null
+            }
+            RespFrame::Boolean(b) => Value::Bool(*b),
+            RespFrame::Double(d) => Number::from_f64(*d)
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::String(format_double(*d))),
+            RespFrame::Attribute(a) => a.frame.to_json(),
+        }
+    }
+
+    pub fn from_json(value: &Value) -> RespFrame {
+        match value {
+            Value::Null => RespNull.into(),
+            Value::Bool(b) => (*b).into(),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => i.into(),
+                None => n.as_f64().unwrap_or(f64::NAN).into(),
+            },
+            Value::String(s) => BulkString::from(s.as_str()).into(),
+            Value::Array(a) => {
+                RespArray::new(a.iter().map(RespFrame::from_json).collect::<Vec<_>>()).into()
+            }
+            Value::Object(o) => {
+                let mut map = RespMap::new();
+                for (k, v) in o {
+                    map.insert(k.clone(), RespFrame::from_json(v));
+                }
+                map.into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_json() {
+        let mut map = RespMap::new();
+        map.insert("name".to_string(), BulkString::from("alice").into());
+        map.insert("score".to_string(), 1.5.into());
+        map.insert("inf".to_string(), f64::INFINITY.into());
+        let frame: RespFrame =
+            RespArray::new([1.into(), true.into(), RespNull.into(), map.into()]).into();
+
+        assert_eq!(
+            frame.to_json(),
+            json!([1, true, null, {"name": "alice", "score": 1.5, "inf": "inf"}])
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let value = json!({"a": [1, 2.5, "x"], "b": null, "c": false});
+        let frame = RespFrame::from_json(&value);
+
+        let mut map = RespMap::new();
+        map.insert(
+            "a".to_string(),
+            RespArray::new([1.into(), 2.5.into(), BulkString::from("x").into()]).into(),
+        );
+        map.insert("b".to_string(), RespNull.into());
+        map.insert("c".to_string(), false.into());
+        assert_eq!(frame, map.into());
+
+        assert_eq!(frame.to_json(), value);
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::RespFrame;
+
+// RespFrame 的类型，不携带数据，用于错误信息和分发
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RespFrameKind {
+    SimpleString,
+    Error,
+    Integer,
+    BulkString,
+    Array,
+    Null,
+    Boolean,
+    Double,
+    Map,
+    Set,
+    Attribute,
+    Push,
+}
+
+impl RespFrameKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RespFrameKind::SimpleString => "simple-string",
+            RespFrameKind::Error => "error",
+            RespFrameKind::Integer => "integer",
+            RespFrameKind::BulkString => "bulk-string",
+            RespFrameKind::Array => "array",
+            RespFrameKind::Null => "null",
+            RespFrameKind::Boolean => "boolean",
+            RespFrameKind::Double => "double",
+            RespFrameKind::Map => "map",
+            RespFrameKind::Set => "set",
+            RespFrameKind::Attribute => "attribute",
+            RespFrameKind::Push => "push",
+        }
+    }
+}
+
+impl fmt::Display for RespFrameKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl RespFrame {
+    // RESP2 的 null bulk string / null array 与 RESP3 的 null 都归为 Null
+    pub fn kind(&self) -> RespFrameKind {
+        match self {
+            RespFrame::SimpleString(_) => RespFrameKind::SimpleString,
+            RespFrame::Error(_) => RespFrameKind::Error,
+            RespFrame::Integer(_) => RespFrameKind::Integer,
+            RespFrame::BulkString(_) => RespFrameKind::BulkString,
+            RespFrame::Array(_) => RespFrameKind::Array,
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+                RespFrameKind::Null
+            }
+            RespFrame::Boolean(_) => RespFrameKind::Boolean,
+            RespFrame::Double(_) => RespFrameKind::Double,
+            RespFrame::Map(_) => RespFrameKind::Map,
+            RespFrame::Set(_) => RespFrameKind::Set,
+            RespFrame::Attribute(_) => RespFrameKind::Attribute,
+            RespFrame::Push(_) => RespFrameKind::Push,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.kind().as_str()
+    }
+
+    pub fn is_simple_string(&self) -> bool {
+        self.kind() == RespFrameKind::SimpleString
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.kind() == RespFrameKind::Error
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.kind() == RespFrameKind::Integer
+    }
+
+    pub fn is_bulk_string(&self) -> bool {
+        self.kind() == RespFrameKind::BulkString
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.kind() == RespFrameKind::Array
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind() == RespFrameKind::Null
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        self.kind() == RespFrameKind::Boolean
+    }
+
+    pub fn is_double(&self) -> bool {
+        self.kind() == RespFrameKind::Double
+    }
+
+    pub fn is_map(&self) -> bool {
+        self.kind() == RespFrameKind::Map
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.kind() == RespFrameKind::Set
+    }
+
+    pub fn is_attribute(&self) -> bool {
+        self.kind() == RespFrameKind::Attribute
+    }
+
+    pub fn is_push(&self) -> bool {
+        self.kind() == RespFrameKind::Push
+    }
+
+    // simple string 或 bulk string
+    pub fn is_string(&self) -> bool {
+        self.is_simple_string() || self.is_bulk_string()
+    }
+
+    // 包含其他帧的聚合类型
+    pub fn is_aggregate(&self) -> bool {
+        matches!(
+            self.kind(),
+            RespFrameKind::Array
+                | RespFrameKind::Map
+                | RespFrameKind::Set
+                | RespFrameKind::Attribute
+                | RespFrameKind::Push
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespNull, RespNullBulkString};
+
+    #[test]
+    fn test_frame_kind() {
+        let frame: RespFrame = BulkString::from("a").into();
+        assert_eq!(frame.kind(), RespFrameKind::BulkString);
+        assert_eq!(frame.type_name(), "bulk-string");
+        assert!(frame.is_string() && !frame.is_aggregate());
+
+        let frame: RespFrame = RespArray::new([]).into();
+        assert!(frame.is_array() && frame.is_aggregate());
+        assert_eq!(frame.kind().to_string(), "array");
+
+        assert!(RespFrame::from(RespNull).is_null());
+        assert!(RespFrame::from(RespNullBulkString).is_null());
+        assert_eq!(RespFrame::Double(1.0).type_name(), "double");
+    }
+}
@@ -0,0 +1,155 @@
+use std::cell::Cell;
+
+use bytes::BytesMut;
+
+use crate::{RespDecode, RespError, RespFrame};
+
+// 解码时允许的最大长度，防止恶意的 "$9999999999\r\n" 或 "*1000000000\r\n"
+// 让服务器一直缓存数据等待一个永远不会完整的帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespLimits {
+    // bulk string 的最大字节数，对应 redis 的 proto-max-bulk-len
+    pub max_bulk_len: usize,
+    // array / set / map 等聚合类型的最大元素个数
+    pub max_multibulk_len: usize,
+    // 聚合类型的最大嵌套层数，解码是递归实现的，过深的嵌套会耗尽栈空间
+    pub max_depth: usize,
+}
+
+impl Default for RespLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_depth: 128,
+        }
+    }
+}
+
+thread_local! {
+    // RespDecode::decode 没有额外的参数，解码期间通过线程局部变量传递限制
+    static LIMITS: Cell<RespLimits> = Cell::new(RespLimits::default());
+    // 当前的嵌套层数
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    // 上一次 expect_length 发现帧不完整时，至少还缺多少字节
+    static MISSING: Cell<usize> = const { Cell::new(0) };
+}
+
+impl RespLimits {
+    pub fn decode(&self, buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+        let previous = LIMITS.with(|limits| limits.replace(*self));
+        let ret = RespFrame::decode(buf);
+        LIMITS.with(|limits| limits.set(previous));
+        ret
+    }
+
+    pub fn expect_length(&self, buf: &[u8]) -> Result<usize, RespError> {
+        MISSING.with(|missing| missing.set(0));
+        let previous = LIMITS.with(|limits| limits.replace(*self));
+        let ret = RespFrame::expect_length(buf);
+        LIMITS.with(|limits| limits.set(previous));
+        ret
+    }
+}
+
+pub(super) fn check_length(prefix: &str, len: usize) -> Result<(), RespError> {
+    let limits = LIMITS.with(|limits| limits.get());
+    let max = match prefix {
+        "$" | ";" => limits.max_bulk_len,
+        "*" | "~" | "%" | ">" | "|" => limits.max_multibulk_len,
+        _ => return Ok(()),
+    };
+    if len > max {
+        return Err(RespError::FrameTooLarge(len, max));
+    }
+    Ok(())
+}
+
+pub(super) fn record_missing(n: usize) {
+    MISSING.with(|missing| missing.set(missing.get().max(n)));
+}
+
+pub(super) fn missing_bytes() -> usize {
+    MISSING.with(|missing| missing.get())
+}
+
+// 进入一层嵌套，guard 被 drop 时退出
+pub(super) struct DepthGuard;
+
+impl DepthGuard {
+    pub(super) fn enter() -> Result<Self, RespError> {
+        let max = LIMITS.with(|limits| limits.get().max_depth);
+        let depth = DEPTH.with(|depth| depth.get()) + 1;
+        if depth > max {
+            return Err(RespError::NestingTooDeep(max));
+        }
+        DEPTH.with(|d| d.set(depth));
+        Ok(DepthGuard)
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_decode_limits() {
+        let limits = RespLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            ..Default::default()
+        };
+
+        let mut buf = BytesMut::from("$9999999999\r\n");
+        assert_eq!(
+            limits.decode(&mut buf).unwrap_err(),
+            RespError::FrameTooLarge(9999999999, 4)
+        );
+
+        let mut buf = BytesMut::from("*1000000000\r\n");
+        assert_eq!(
+            limits.decode(&mut buf).unwrap_err(),
+            RespError::FrameTooLarge(1000000000, 2)
+        );
+
+        let mut buf = BytesMut::from("$4\r\nabcd\r\n");
+        assert_eq!(
+            limits.decode(&mut buf).unwrap(),
+            BulkString::from("abcd").into()
+        );
+
+        // 限制只在 decode 期间生效
+        let mut buf = BytesMut::from("$5\r\nabcde\r\n");
+        assert!(RespFrame::decode(&mut buf).is_ok());
+    }
+
+    #[test]
+    fn test_decode_depth_limit() {
+        let mut buf = BytesMut::from("*1\r\n".repeat(100_000).as_str());
+        buf.extend_from_slice(b":1\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap_err(),
+            RespError::NestingTooDeep(128)
+        );
+        assert!(RespFrame::expect_length(&buf).is_err());
+
+        let limits = RespLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let mut buf = BytesMut::from("*1\r\n*1\r\n:1\r\n");
+        assert!(limits.decode(&mut buf).is_ok());
+        let mut buf = BytesMut::from("*1\r\n*1\r\n*1\r\n:1\r\n");
+        assert!(limits.decode(&mut buf).is_err());
+
+        // 出错后嵌套层数被正确恢复
+        assert_eq!(DEPTH.with(|depth| depth.get()), 0);
+    }
+}
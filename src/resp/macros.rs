@@ -0,0 +1,155 @@
+// resp! 宏：用类似字面量的写法构造 RespFrame
+//
+//   resp!(["SET", "k", 1, true])  => 数组，字符串元素编码为 BulkString
+//   resp!({ "a" => 1, "b" => [1, 2] })  => map
+//   resp!(null)  => null
+//
+// 元素可以嵌套，其余表达式通过 IntoRespValue 转换
+use crate::{BulkString, RespArray, RespFrame, RespMap, RespNull, RespSet, SimpleError};
+
+#[doc(hidden)]
+pub trait IntoRespValue {
+    fn into_resp_value(self) -> RespFrame;
+}
+
+// 宏里的字符串都当作命令参数，使用 BulkString
+impl IntoRespValue for &str {
+    fn into_resp_value(self) -> RespFrame {
+        BulkString::from(self).into()
+    }
+}
+
+impl IntoRespValue for String {
+    fn into_resp_value(self) -> RespFrame {
+        BulkString::from(self).into()
+    }
+}
+
+impl IntoRespValue for &[u8] {
+    fn into_resp_value(self) -> RespFrame {
+        BulkString::from(self).into()
+    }
+}
+
+macro_rules! impl_into_resp_value {
+    ($($ty:ty => $conv:expr),* $(,)?) => {
+        $(
+            impl IntoRespValue for $ty {
+                fn into_resp_value(self) -> RespFrame {
+                    $conv(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_resp_value!(
+    i64 => RespFrame::Integer,
+    i32 => |v| RespFrame::Integer(v as i64),
+    u32 => |v| RespFrame::Integer(v as i64),
+    usize => |v| RespFrame::Integer(v as i64),
+    f64 => RespFrame::Double,
+    bool => RespFrame::Boolean,
+    RespFrame => |v| v,
+    BulkString => RespFrame::from,
+    SimpleError => RespFrame::from,
+    RespArray => RespFrame::from,
+    RespMap => RespFrame::from,
+    RespSet => RespFrame::from,
+    RespNull => RespFrame::from,
+);
+
+#[macro_export]
+macro_rules! resp {
+    (null) => {
+        $crate::RespFrame::from($crate::RespNull)
+    };
+    ([ $($tt:tt)* ]) => {
+        $crate::RespFrame::from($crate::RespArray::new(
+            $crate::resp!(@array [] $($tt)*),
+        ))
+    };
+    ({ $($tt:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::RespMap::new();
+        $crate::resp!(@map map $($tt)*);
+        $crate::RespFrame::from(map)
+    }};
+    ($value:expr) => {
+        $crate::IntoRespValue::into_resp_value($value)
+    };
+
+    // 数组元素，逐个取出并递归转换
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::resp!(@array [$($elems,)* $crate::resp!(null),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] [ $($inner:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::resp!(@array [$($elems,)* $crate::resp!([$($inner)*]),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::resp!(@array [$($elems,)* $crate::resp!({$($inner)*}),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::resp!(@array [$($elems,)* $crate::resp!($next),] $($($rest)*)?)
+    };
+
+    // map 的 key => value，key 统一转换为 String
+    (@map $map:ident) => {};
+    (@map $map:ident $key:expr => null $(, $($rest:tt)*)?) => {
+        $map.insert(::std::string::ToString::to_string(&$key), $crate::resp!(null));
+        $crate::resp!(@map $map $($($rest)*)?);
+    };
+    (@map $map:ident $key:expr => [ $($inner:tt)* ] $(, $($rest:tt)*)?) => {
+        $map.insert(::std::string::ToString::to_string(&$key), $crate::resp!([$($inner)*]));
+        $crate::resp!(@map $map $($($rest)*)?);
+    };
+    (@map $map:ident $key:expr => { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $map.insert(::std::string::ToString::to_string(&$key), $crate::resp!({$($inner)*}));
+        $crate::resp!(@map $map $($($rest)*)?);
+    };
+    (@map $map:ident $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $map.insert(::std::string::ToString::to_string(&$key), $crate::resp!($value));
+        $crate::resp!(@map $map $($($rest)*)?);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, RespArray, RespEncode, RespFrame, RespMap, RespNull};
+
+    #[test]
+    fn test_resp_macro_array() {
+        let frame = resp!(["SET", "k", 1, true]);
+        let expected: RespFrame = RespArray::new([
+            BulkString::from("SET").into(),
+            BulkString::from("k").into(),
+            RespFrame::Integer(1),
+            RespFrame::Boolean(true),
+        ])
+        .into();
+        assert_eq!(frame, expected);
+        assert_eq!(resp!([]), RespArray::new([]).into());
+    }
+
+    #[test]
+    fn test_resp_macro_nested() {
+        let key = String::from("k");
+        let frame = resp!([key, -1, 1.5, null, [1, [2]], { "a" => [null], "b" => 2 },]);
+        assert_eq!(
+            frame.encode(),
+            b"*6\r\n$1\r\nk\r\n:-1\r\n,1.5\r\n_\r\n*2\r\n:1\r\n*1\r\n:2\r\n%2\r\n+a\r\n*1\r\n_\r\n+b\r\n:2\r\n"
+        );
+    }
+
+    #[test]
+    fn test_resp_macro_map() {
+        let mut expected = RespMap::new();
+        expected.insert("a".to_string(), RespFrame::Integer(1));
+        expected.insert("b".to_string(), RespNull.into());
+        assert_eq!(resp!({ "a" => 1, "b" => null }), expected.into());
+        assert_eq!(resp!({}), RespMap::new().into());
+    }
+}
@@ -7,14 +7,39 @@ use bytes::{Buf, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 
-use super::{calc_total_length, parse_length, simple_string::SimpleString, BUF_CAP, CRLF_LEN};
+use super::{
+    bulk_string::BulkString,
+    calc_total_length, parse_length,
+    simple_string::SimpleString,
+    stream::{decode_streamed_aggregate, is_streamed, streamed_aggregate_length},
+    write_formatted, CRLF_LEN,
+};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
 
+// 编码 map 时 key 使用的类型，redis 本身使用 BulkString
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapKeyEncoding {
+    #[default]
+    SimpleString,
+    BulkString,
+}
+
 impl RespDecode for RespMap {
     const PREFIX: &'static str = "%";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        if is_streamed(buf, Self::PREFIX) {
+            let pairs = decode_streamed_aggregate(buf, Self::PREFIX, true, |buf| {
+                Ok((decode_key(buf)?, RespFrame::decode(buf)?))
+            })?;
+            let mut frames = RespMap::new();
+            for (key, value) in pairs {
+                frames.insert(key, value);
+            }
+            return Ok(frames);
+        }
+
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
 
@@ -26,32 +51,53 @@ impl RespDecode for RespMap {
 
         let mut frames = RespMap::new();
         for _ in 0..len {
-            let key = SimpleString::decode(buf)?;
+            let key = decode_key(buf)?;
             let value = RespFrame::decode(buf)?;
-            frames.insert(key.0, value);
+            frames.insert(key, value);
         }
 
         Ok(frames)
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        if is_streamed(buf, Self::PREFIX) {
+            return streamed_aggregate_length(buf, Self::PREFIX, true);
+        }
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         calc_total_length(buf, end, len, Self::PREFIX)
     }
 }
 
 // - map: "%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>"
-// we only support string key which encode to SimpleString
+// we only support string key which encode to SimpleString by default
 impl RespEncode for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("%{}\r\n", self.0.len()).into_bytes());
+    fn encode_into(&self, buf: &mut BytesMut) {
+        self.encode_with(MapKeyEncoding::default(), buf)
+    }
+}
 
-        for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(key).encode());
-            buf.extend_from_slice(&value.encode());
+// 解码时 key 可以是 SimpleString 或 BulkString
+pub(super) fn decode_key(buf: &mut BytesMut) -> Result<String, RespError> {
+    match buf.first() {
+        Some(b'$') => {
+            let key = BulkString::decode(buf)?;
+            Ok(std::str::from_utf8(&key)?.to_string())
         }
-        buf
+        _ => Ok(SimpleString::decode(buf)?.0.into()),
+    }
+}
+
+pub(super) fn key_length(buf: &[u8]) -> Result<usize, RespError> {
+    match buf.first() {
+        Some(b'$') => BulkString::expect_length(buf),
+        _ => SimpleString::expect_length(buf),
+    }
+}
+
+pub(super) fn encode_key(key: &str, keys: MapKeyEncoding, buf: &mut BytesMut) {
+    match keys {
+        MapKeyEncoding::SimpleString => SimpleString::new_or_bulk(key).encode_into(buf),
+        MapKeyEncoding::BulkString => BulkString::from(key).encode_into(buf),
     }
 }
 
@@ -74,6 +120,15 @@ impl RespMap {
     pub fn new() -> Self {
         RespMap(BTreeMap::new())
     }
+
+    pub fn encode_with(&self, keys: MapKeyEncoding, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!("%{}\r\n", self.0.len()));
+
+        for (key, value) in self.iter() {
+            encode_key(key, keys, buf);
+            value.encode_into(buf);
+        }
+    }
 }
 
 impl Default for RespMap {
@@ -100,7 +155,7 @@ mod tests {
         let frame: RespFrame = map.into();
         assert_eq!(
             &frame.encode(),
-            b"%2\r\n+key\r\n$5\r\nvalue\r\n+test\r\n,+123.456\r\n"
+            b"%2\r\n+key\r\n$5\r\nvalue\r\n+test\r\n,123.456\r\n"
         );
 
         // 因为 RespMap 底层使用的是 TreeMap 因此会对key进行排序，
@@ -116,7 +171,7 @@ mod tests {
         let frame1: RespFrame = map1.into();
         assert_eq!(
             &frame1.encode(),
-            b"%2\r\n+a\r\n,+123.456\r\n+key\r\n$5\r\nvalue\r\n"
+            b"%2\r\n+a\r\n,123.456\r\n+key\r\n$5\r\nvalue\r\n"
         );
     }
 
@@ -136,4 +191,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_map_bulk_string_keys() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"%2\r\n$5\r\nhello\r\n$5\r\nworld\r\n+foo\r\n:1\r\n");
+
+        let frame = RespMap::decode(&mut buf)?;
+        let mut map = RespMap::new();
+        map.insert("hello".to_string(), BulkString::from("world").into());
+        map.insert("foo".to_string(), 1.into());
+        assert_eq!(frame, map);
+
+        let mut buf = BytesMut::new();
+        map.encode_with(MapKeyEncoding::BulkString, &mut buf);
+        assert_eq!(
+            &buf[..],
+            b"%2\r\n$3\r\nfoo\r\n:1\r\n$5\r\nhello\r\n$5\r\nworld\r\n"
+        );
+
+        let mut buf = BytesMut::from("%1\r\n:1\r\n:2\r\n");
+        assert!(RespMap::decode(&mut buf).is_err());
+
+        Ok(())
+    }
 }
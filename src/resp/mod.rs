@@ -1,17 +1,38 @@
 mod array;
+mod attribute;
 mod bool;
 mod bulk_string;
+mod codec;
+mod convert;
+mod display;
 mod double;
 mod frame;
 mod integer;
+#[cfg(feature = "json")]
+mod json;
+mod kind;
+mod limits;
+#[macro_use]
+mod macros;
 mod map;
 mod null;
+mod pool;
+mod protocol;
+mod push;
+#[cfg(feature = "serde")]
+mod serde;
 mod set;
+mod shared;
 mod simple_error;
 mod simple_string;
+mod small;
+mod stream;
+#[cfg(feature = "testing")]
+mod testing;
+
+use std::fmt::{self, Write};
 
 use bytes::{Buf, BytesMut};
-use enum_dispatch::enum_dispatch;
 use thiserror::Error;
 
 const CRLF: &[u8] = b"\r\n";
@@ -19,13 +40,55 @@ const CRLF_LEN: usize = CRLF.len();
 const BUF_CAP: usize = 4096;
 
 pub use self::{
-    array::RespArray, bulk_string::BulkString, frame::RespFrame, map::RespMap, null::RespNull,
-    set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    array::RespArray,
+    attribute::RespAttribute,
+    bulk_string::BulkString,
+    codec::RespCodec,
+    frame::RespFrame,
+    kind::RespFrameKind,
+    limits::RespLimits,
+    macros::IntoRespValue,
+    map::{MapKeyEncoding, RespMap},
+    null::{RespNull, RespNullArray, RespNullBulkString},
+    protocol::RespVersion,
+    push::RespPush,
+    set::RespSet,
+    simple_error::SimpleError,
+    simple_string::SimpleString,
+    small::SmallString,
 };
 
-#[enum_dispatch]
+#[cfg(feature = "serde")]
+pub use self::serde::{from_frame, to_frame, SerdeError};
+
+#[cfg(feature = "testing")]
+pub use self::testing::{arb_command_frame, arb_encoded_frame, arb_frame};
+
+// 编码直接写入调用方的缓冲区，网络层可以复用连接上的写缓冲区
 pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+    fn encode_into(&self, buf: &mut BytesMut);
+
+    // 临时的编码缓冲区来自当前线程的缓冲区池，只分配返回的 Vec
+    fn encode(&self) -> Vec<u8> {
+        pool::with_buffer(|buf| {
+            self.encode_into(buf);
+            buf.to_vec()
+        })
+    }
+
+    // 编码后的字节数，不需要保留编码结果
+    fn encoded_len(&self) -> usize {
+        pool::with_buffer(|buf| {
+            self.encode_into(buf);
+            buf.len()
+        })
+    }
+}
+
+// BytesMut 的 fmt::Write 不会失败
+fn write_formatted(buf: &mut BytesMut, args: fmt::Arguments) {
+    buf.write_fmt(args)
+        .expect("writing to BytesMut never fails");
 }
 
 pub trait RespDecode: Sized {
@@ -45,9 +108,22 @@ pub enum RespError {
     #[error("Invalid frame length： {0}")]
     InvalidFrameLength(isize),
 
+    #[error("Invalid length header: {0:?}")]
+    InvalidLengthHeader(String),
+
+    #[error("Frame length {0} exceeds limit {1}")]
+    FrameTooLarge(usize, usize),
+
+    #[error("Frame nesting exceeds max depth {0}")]
+    NestingTooDeep(usize),
+
     #[error("Frame is not complete")]
     NotComplete,
 
+    // 解码失败后跳过了多少字节以找到下一个帧
+    #[error("{1}, discarded {0} bytes")]
+    FrameDiscarded(usize, String),
+
     #[error("Parse error: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
 
@@ -78,6 +154,15 @@ fn extract_fixed_data(
     Ok(())
 }
 
+// simple string / error 中的 CR 或 LF 会让这一行提前结束，之后的数据被当作新的帧
+fn has_line_break(data: &[u8]) -> bool {
+    data.iter().any(|b| matches!(b, b'\r' | b'\n'))
+}
+
+fn line_break_error(kind: &str) -> RespError {
+    RespError::InvalidFrame(format!("{} contains CR or LF", kind))
+}
+
 fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
     if buf.len() < 3 {
         return Err(RespError::NotComplete);
@@ -109,35 +194,59 @@ fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
     None
 }
 
+// 长度只能是十进制数字，可以带一个负号：不接受 "+3"、空白和空的长度。
+// 负数的长度（null 的 -1 由调用方在这之前处理）返回 InvalidFrameLength，
+// 无法解析或者超出 i64 范围的返回 InvalidLengthHeader，超过限制的返回 FrameTooLarge
 fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
     let end = extract_simple_frame_data(buf, prefix)?;
-    let s = String::from_utf8_lossy(&buf[prefix.len()..end]);
-    Ok((end, s.parse()?))
+    let header = &buf[prefix.len()..end];
+    let digits = header.strip_prefix(b"-").unwrap_or(header);
+    let invalid = || RespError::InvalidLengthHeader(String::from_utf8_lossy(header).into_owned());
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(invalid());
+    }
+    let len = std::str::from_utf8(header)?
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+    let len = usize::try_from(len).map_err(|_| RespError::InvalidFrameLength(len as isize))?;
+    limits::check_length(prefix, len)?;
+    Ok((end, len))
+}
+
+// expect_length 返回的长度可能超过已经收到的数据，此时帧还不完整，不能直接切片
+fn remaining(buf: &[u8], from: usize) -> Result<&[u8], RespError> {
+    match buf.get(from..) {
+        Some(data) => Ok(data),
+        None => {
+            limits::record_missing(from - buf.len());
+            Err(RespError::NotComplete)
+        }
+    }
 }
 
 fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
     let mut total = end + CRLF_LEN;
-    let mut data = &buf[total..];
+    let mut data = remaining(buf, total)?;
     match prefix {
-        "*" | "~" => {
+        "*" | "~" | ">" => {
             // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = remaining(data, len)?;
                 total += len;
             }
             Ok(total)
         }
-        "%" => {
+        "%" | "|" => {
             // find nth CRLF in the buffer. For map, we need to find 2 CRLF for each key-value pair
             for _ in 0..len {
-                let len = SimpleString::expect_length(data)?;
+                let len = map::key_length(data)?;
 
-                data = &data[len..];
+                data = remaining(data, len)?;
                 total += len;
 
                 let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                data = remaining(data, len)?;
                 total += len;
             }
             Ok(total)
@@ -155,6 +264,83 @@ mod tests {
     fn test_parse_length() {
         let buf = b"*0\r\n";
         assert_eq!(parse_length(buf, "*"), Ok((2, 0)));
+        assert_eq!(parse_length(b"$12\r\n", "$"), Ok((3, 12)));
+    }
+
+    #[test]
+    fn test_parse_length_malformed() {
+        assert_eq!(
+            parse_length(b"*-5\r\n", "*"),
+            Err(RespError::InvalidFrameLength(-5))
+        );
+        for header in [
+            "*abc\r\n",
+            "*+3\r\n",
+            "* 3\r\n",
+            "*3 \r\n",
+            "*-\r\n",
+            "*\r\n\r\n",
+        ] {
+            assert!(
+                matches!(
+                    parse_length(header.as_bytes(), "*"),
+                    Err(RespError::InvalidLengthHeader(_))
+                ),
+                "{:?}",
+                header
+            );
+        }
+        assert!(matches!(
+            parse_length(b"$99999999999999999999999\r\n", "$"),
+            Err(RespError::InvalidLengthHeader(_))
+        ));
+        assert!(matches!(
+            parse_length(b"$9223372036854775807\r\n", "$"),
+            Err(RespError::FrameTooLarge(_, _))
+        ));
+    }
+
+    // 格式错误的长度在解码和计算长度时都返回错误，不会 panic
+    #[test]
+    fn test_decode_malformed_headers() {
+        for input in [
+            "$-2\r\n",
+            "*-2\r\n",
+            "%-1\r\n",
+            "~x\r\n",
+            ">+1\r\n:1\r\n",
+            "*1\r\n$-7\r\n",
+            "*2\r\n$18446744073709551616\r\n",
+        ] {
+            assert!(
+                RespFrame::expect_length(input.as_bytes()).is_err(),
+                "{:?}",
+                input
+            );
+            let mut buf = BytesMut::from(input);
+            assert!(RespFrame::decode(&mut buf).is_err(), "{:?}", input);
+        }
+
+        // 长度与数据不符
+        let mut buf = BytesMut::from("$3\r\nhelloo\r\n");
+        assert!(matches!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+
+        // null 仍然使用 -1
+        let mut buf = BytesMut::from("$-1\r\n*-1\r\n");
+        assert!(RespFrame::decode(&mut buf).unwrap().is_null());
+        assert!(RespFrame::decode(&mut buf).unwrap().is_null());
+    }
+
+    #[test]
+    fn test_encode_into_appends() {
+        let mut buf = BytesMut::from("+OK\r\n");
+        let frame: RespFrame = RespArray::new([BulkString::from("a").into(), 1.into()]).into();
+        frame.encode_into(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n*2\r\n$1\r\na\r\n:1\r\n");
+        assert_eq!(frame.encode(), &buf[5..]);
     }
 
     #[test]
@@ -4,7 +4,7 @@ use crate::{RespDecode, RespEncode, RespError};
 
 use super::extract_fixed_data;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 pub struct RespNull;
 
 impl RespDecode for RespNull {
@@ -21,11 +21,72 @@ impl RespDecode for RespNull {
 
 // - null: "_\r\n"
 impl RespEncode for RespNull {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encode_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
     }
 }
 
+// RESP2 没有独立的 null 类型，只能用长度为 -1 的 bulk string 或 array 表示。
+// 命令统一返回 RespNull，由网络层根据连接的协议版本转换
+const NULL_BULK_STRING: &[u8] = b"$-1\r\n";
+const NULL_ARRAY: &[u8] = b"*-1\r\n";
+
+// - null bulk string: "$-1\r\n"
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct RespNullBulkString;
+
+// - null array: "*-1\r\n"
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct RespNullArray;
+
+impl RespDecode for RespNullBulkString {
+    const PREFIX: &'static str = "$";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        extract_fixed_data(
+            buf,
+            std::str::from_utf8(NULL_BULK_STRING)?,
+            "NullBulkString",
+        )?;
+        Ok(RespNullBulkString)
+    }
+
+    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
+        Ok(NULL_BULK_STRING.len())
+    }
+}
+
+impl RespEncode for RespNullBulkString {
+    fn encode_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(NULL_BULK_STRING);
+    }
+}
+
+impl RespDecode for RespNullArray {
+    const PREFIX: &'static str = "*";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        extract_fixed_data(buf, std::str::from_utf8(NULL_ARRAY)?, "NullArray")?;
+        Ok(RespNullArray)
+    }
+
+    fn expect_length(_buf: &[u8]) -> Result<usize, RespError> {
+        Ok(NULL_ARRAY.len())
+    }
+}
+
+impl RespEncode for RespNullArray {
+    fn encode_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(NULL_ARRAY);
+    }
+}
+
+pub(super) fn is_null_bulk_string(buf: &[u8]) -> bool {
+    buf.starts_with(NULL_BULK_STRING)
+}
+
+pub(super) fn is_null_array(buf: &[u8]) -> bool {
+    buf.starts_with(NULL_ARRAY)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,4 +109,17 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_resp2_null_encode_decode() -> Result<()> {
+        let s: RespFrame = RespNullBulkString.into();
+        assert_eq!(s.encode(), b"$-1\r\n");
+        let s: RespFrame = RespNullArray.into();
+        assert_eq!(s.encode(), b"*-1\r\n");
+
+        let mut buf = BytesMut::from("$-1\r\n*-1\r\n");
+        assert_eq!(RespFrame::decode(&mut buf)?, RespNullBulkString.into());
+        assert_eq!(RespFrame::decode(&mut buf)?, RespNullArray.into());
+        Ok(())
+    }
 }
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+
+use bytes::BytesMut;
+
+use super::BUF_CAP;
+
+// 每个线程保留的空闲缓冲区个数，tokio 的每个 worker 线程各自一份，取用时不需要加锁
+const POOL_SIZE: usize = 4;
+// 编码过大 value 的缓冲区用完直接释放，避免池中长期占用大块内存
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+thread_local! {
+    static BUFFERS: RefCell<Vec<BytesMut>> = const { RefCell::new(Vec::new()) };
+}
+
+// 从当前线程的池中取出一个空的缓冲区交给 f，用完清空后放回。
+// 取出之后才调用 f，f 中再次编码（嵌套调用）时会拿到另一块缓冲区
+pub(crate) fn with_buffer<R>(f: impl FnOnce(&mut BytesMut) -> R) -> R {
+    let mut buf = BUFFERS
+        .with(|pool| pool.borrow_mut().pop())
+        .unwrap_or_else(|| BytesMut::with_capacity(BUF_CAP));
+    let result = f(&mut buf);
+
+    if buf.capacity() <= MAX_POOLED_CAPACITY {
+        buf.clear();
+        BUFFERS.with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < POOL_SIZE {
+                pool.push(buf);
+            }
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pooled() -> usize {
+        BUFFERS.with(|pool| pool.borrow().len())
+    }
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let first = with_buffer(|buf| {
+            buf.extend_from_slice(b"hello");
+            buf.as_ptr() as usize
+        });
+        let second = with_buffer(|buf| {
+            assert!(buf.is_empty());
+            buf.as_ptr() as usize
+        });
+        assert_eq!(first, second);
+        assert_eq!(pooled(), 1);
+    }
+
+    #[test]
+    fn test_nested_and_oversized_buffers() {
+        with_buffer(|outer| {
+            outer.extend_from_slice(b"outer");
+            with_buffer(|inner| inner.extend_from_slice(b"inner"));
+            assert_eq!(&outer[..], b"outer");
+        });
+        assert_eq!(pooled(), 2);
+
+        with_buffer(|buf| buf.reserve(MAX_POOLED_CAPACITY * 2));
+        assert_eq!(pooled(), 1);
+
+        for _ in 0..POOL_SIZE * 2 {
+            with_buffer(|outer| with_buffer(|inner| inner.len() + outer.len()));
+        }
+        assert!(pooled() <= POOL_SIZE);
+    }
+}
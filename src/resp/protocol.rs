@@ -0,0 +1,162 @@
+use crate::{BulkString, RespArray, RespFrame, RespNull, RespNullBulkString};
+
+use super::double::format_double;
+
+// 连接通过 HELLO 协商的协议版本，默认与 redis 一样使用 RESP2
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RespVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl RespVersion {
+    pub fn from_protover(protover: i64) -> Option<Self> {
+        match protover {
+            2 => Some(RespVersion::Resp2),
+            3 => Some(RespVersion::Resp3),
+            _ => None,
+        }
+    }
+
+    pub fn protover(&self) -> i64 {
+        match self {
+            RespVersion::Resp2 => 2,
+            RespVersion::Resp3 => 3,
+        }
+    }
+}
+
+impl RespFrame {
+    // 命令统一返回 RESP3 的类型，写回给 RESP2 客户端之前再降级为 RESP2 能表示的类型：
+    // - map 展开为 key/value 交替的数组
+    // - set 和 push 转为数组（RESP2 的 pub/sub 消息本来就是数组）
+    // - double 转为 bulk string
+    // - boolean 转为整数 1/0
+    // - attribute 被丢弃
+    // - null 转为 "$-1"（RESP2 客户端把 null bulk string 和 null array 都当作 nil）
+    // 反过来 RESP3 连接上的 "$-1" / "*-1" 统一转为 "_"
+    pub fn into_version(self, version: RespVersion) -> RespFrame {
+        match version {
+            RespVersion::Resp3 => self.into_resp3(),
+            RespVersion::Resp2 => self.into_resp2(),
+        }
+    }
+
+    fn into_resp3(self) -> RespFrame {
+        match self {
+            RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => RespNull.into(),
+            RespFrame::Array(array) => RespArray::new(
+                array
+                    .0
+                    .into_iter()
+                    .map(|v| v.into_resp3())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            frame => frame,
+        }
+    }
+
+    fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Map(map) => RespArray::new(
+                map.0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::from(k).into(), v.into_resp2()])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Set(set) => RespArray::new(
+                set.0
+                    .into_iter()
+                    .map(|v| v.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Push(push) => RespArray::new(
+                push.0
+                    .into_iter()
+                    .map(|v| v.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Array(array) => RespArray::new(
+                array
+                    .0
+                    .into_iter()
+                    .map(|v| v.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Double(d) => BulkString::from(format_double(d)).into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Null(_) => RespNullBulkString.into(),
+            // RESP2 没有 attribute，只保留真正的回复
+            RespFrame::Attribute(attr) => attr.frame.into_resp2(),
+            frame => frame,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespEncode, RespMap, RespPush, RespSet};
+
+    #[test]
+    fn test_resp3_frames_downgrade_to_resp2() {
+        let mut map = RespMap::new();
+        map.insert("a".to_string(), true.into());
+        map.insert("b".to_string(), 1.5.into());
+        let frame: RespFrame = map.into();
+
+        assert_eq!(
+            frame.clone().into_version(RespVersion::Resp2),
+            RespArray::new([
+                BulkString::from("a").into(),
+                RespFrame::Integer(1),
+                BulkString::from("b").into(),
+                BulkString::from("1.5").into(),
+            ])
+            .into()
+        );
+        assert_eq!(frame.clone().into_version(RespVersion::Resp3), frame);
+
+        let frame: RespFrame = RespSet::new([BulkString::from("x").into()]).into();
+        assert_eq!(
+            frame.into_version(RespVersion::Resp2),
+            RespArray::new([BulkString::from("x").into()]).into()
+        );
+
+        let frame: RespFrame = RespPush::new([BulkString::from("message").into()]).into();
+        assert_eq!(
+            frame.into_version(RespVersion::Resp2),
+            RespArray::new([BulkString::from("message").into()]).into()
+        );
+    }
+
+    #[test]
+    fn test_null_by_version() {
+        let frame: RespFrame =
+            RespArray::new([BulkString::from("v").into(), RespNull.into()]).into();
+        assert_eq!(
+            frame.clone().into_version(RespVersion::Resp2).encode(),
+            b"*2\r\n$1\r\nv\r\n$-1\r\n"
+        );
+        assert_eq!(
+            frame.into_version(RespVersion::Resp3).encode(),
+            b"*2\r\n$1\r\nv\r\n_\r\n"
+        );
+
+        let frame: RespFrame = crate::RespNullArray.into();
+        assert_eq!(frame.into_version(RespVersion::Resp3).encode(), b"_\r\n");
+    }
+
+    #[test]
+    fn test_protover() {
+        assert_eq!(RespVersion::from_protover(3), Some(RespVersion::Resp3));
+        assert_eq!(RespVersion::from_protover(4), None);
+        assert_eq!(RespVersion::default().protover(), 2);
+    }
+}
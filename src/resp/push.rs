@@ -0,0 +1,101 @@
+use std::ops::Deref;
+
+use bytes::{Buf, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError, RespFrame};
+
+use super::{calc_total_length, parse_length, write_formatted, CRLF_LEN};
+
+// - push: "><number-of-elements>\r\n<element-1>...<element-n>"
+// 服务器主动推送的带外消息，比如 pub/sub 的消息，第一个元素是消息类型
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+impl RespEncode for RespPush {
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!(">{}\r\n", self.len()));
+
+        for item in self.iter() {
+            item.encode_into(buf);
+        }
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+
+        buf.advance(end + CRLF_LEN);
+
+        let mut frames = Vec::with_capacity(len);
+        for _ in 0..len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+
+        Ok(RespPush::new(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl RespPush {
+    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use anyhow::Result;
+
+    #[test]
+    fn test_push_encode() {
+        let frame: RespFrame = RespPush::new([
+            BulkString::from("message").into(),
+            BulkString::from("news").into(),
+            BulkString::from("hello").into(),
+        ])
+        .into();
+
+        assert_eq!(
+            &frame.encode(),
+            b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> Result<()> {
+        let mut buf = BytesMut::from(">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nkey\r\n");
+
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespPush::new([
+                BulkString::from("invalidate").into(),
+                crate::RespArray::new([BulkString::from("key").into()]).into(),
+            ])
+            .into()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,635 @@
+// 基于 serde 在应用的数据结构和 RespFrame 之间转换，需要开启 serde feature
+//
+// - 整数 / 浮点数 / bool 对应 Integer / Double / Boolean
+// - 字符串和字节数组对应 BulkString
+// - None 和 unit 对应 Null
+// - 序列和元组对应 Array，map 和 struct 对应 Map
+// - enum 与 serde_json 一样使用外部标签：unit variant 是字符串，其余是只有一个 key 的 Map
+//
+// 反序列化时数字和 bool 也可以从 BulkString 中解析，因为 backend 中保存的都是字符串
+use std::fmt::{self, Display};
+
+use ::serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any, ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+use thiserror::Error;
+
+use crate::{BulkString, RespArray, RespFrame, RespMap, RespNull};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{0}")]
+pub struct SerdeError(String);
+
+impl ser::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerdeError(msg.to_string())
+    }
+}
+
+impl de::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerdeError(msg.to_string())
+    }
+}
+
+pub fn to_frame<T: Serialize + ?Sized>(value: &T) -> Result<RespFrame, SerdeError> {
+    value.serialize(FrameSerializer)
+}
+
+pub fn from_frame<T: DeserializeOwned>(frame: RespFrame) -> Result<T, SerdeError> {
+    T::deserialize(frame)
+}
+
+impl Serialize for RespFrame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RespFrame::SimpleString(s) => serializer.serialize_str(&s.0),
+            RespFrame::Error(e) => serializer.serialize_str(&e.0),
+            RespFrame::Integer(i) => serializer.serialize_i64(*i),
+            RespFrame::BulkString(s) => match std::str::from_utf8(s) {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => serializer.serialize_bytes(s),
+            },
+            RespFrame::Array(a) => serializer.collect_seq(a.iter()),
+            RespFrame::Set(s) => serializer.collect_seq(s.iter()),
+            RespFrame::Push(p) => serializer.collect_seq(p.iter()),
+            RespFrame::Map(m) => serializer.collect_map(m.iter()),
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+                serializer.serialize_none()
+            }
+            RespFrame::Boolean(b) => serializer.serialize_bool(*b),
+            RespFrame::Double(d) => serializer.serialize_f64(*d),
+            RespFrame::Attribute(a) => a.frame.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RespFrame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FrameVisitor)
+    }
+}
+
+struct FrameVisitor;
+
+impl<'de> Visitor<'de> for FrameVisitor {
+    type Value = RespFrame;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value representable as a RESP frame")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<RespFrame, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<RespFrame, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<RespFrame, E> {
+        i64::try_from(v)
+            .map(RespFrame::Integer)
+            .map_err(|_| E::custom(format!("integer {} out of range", v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<RespFrame, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<RespFrame, E> {
+        Ok(BulkString::from(v).into())
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<RespFrame, E> {
+        Ok(BulkString::from(v).into())
+    }
+
+    fn visit_none<E>(self) -> Result<RespFrame, E> {
+        Ok(RespNull.into())
+    }
+
+    fn visit_unit<E>(self) -> Result<RespFrame, E> {
+        Ok(RespNull.into())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<RespFrame, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<RespFrame, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(RespArray::new(items).into())
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<RespFrame, A::Error> {
+        let mut frames = RespMap::new();
+        while let Some((key, value)) = map.next_entry::<String, RespFrame>()? {
+            frames.insert(key, value);
+        }
+        Ok(frames.into())
+    }
+}
+
+// 把任意实现了 Serialize 的值转换为 RespFrame
+struct FrameSerializer;
+
+impl Serializer for FrameSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<RespFrame, SerdeError> {
+        Ok(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<RespFrame, SerdeError> {
+        Ok(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<RespFrame, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<RespFrame, SerdeError> {
+        i64::try_from(v)
+            .map(RespFrame::Integer)
+            .map_err(|_| SerdeError(format!("integer {} out of range", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<RespFrame, SerdeError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<RespFrame, SerdeError> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::from(v.to_string()).into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::from(v).into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::from(v).into())
+    }
+
+    fn serialize_none(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespNull.into())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<RespFrame, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespNull.into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<RespFrame, SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<RespFrame, SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, SerdeError> {
+        let mut map = RespMap::new();
+        map.insert(variant.to_string(), value.serialize(self)?);
+        Ok(map.into())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerdeError> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<SeqSerializer>, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, SerdeError> {
+        Ok(MapSerializer {
+            map: RespMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<MapSerializer>, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SeqSerializer(Vec<RespFrame>);
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.0.push(value.serialize(FrameSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespArray::new(self.0).into())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    map: RespMap,
+    key: Option<String>,
+}
+
+// RespMap 的 key 只能是字符串，数字等标量会被转成字符串
+fn map_key(frame: RespFrame) -> Result<String, SerdeError> {
+    match frame {
+        RespFrame::BulkString(s) => String::from_utf8(s.0.into())
+            .map_err(|_| SerdeError("map key must be valid utf8".to_string())),
+        RespFrame::SimpleString(s) => Ok(s.0.into()),
+        RespFrame::Integer(i) => Ok(i.to_string()),
+        RespFrame::Boolean(b) => Ok(b.to_string()),
+        _ => Err(SerdeError("map key must be a string".to_string())),
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(map_key(key.serialize(FrameSerializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerdeError("serialize_value called before serialize_key".to_string()))?;
+        self.map.insert(key, value.serialize(FrameSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(self.map.into())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.map
+            .insert(key.to_string(), value.serialize(FrameSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(self.map.into())
+    }
+}
+
+// tuple / struct variant 序列化为 { variant: [...] } 或 { variant: {...} }
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &'static str, frame: RespFrame) -> RespFrame {
+        let mut map = RespMap::new();
+        map.insert(variant.to_string(), frame);
+        map.into()
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        let frame = ser::SerializeSeq::end(self.inner)?;
+        Ok(Self::wrap(self.variant, frame))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        let frame = ser::SerializeStruct::end(self.inner)?;
+        Ok(Self::wrap(self.variant, frame))
+    }
+}
+
+// RespFrame 本身作为 Deserializer，把帧转换为应用的数据结构
+impl<'de> IntoDeserializer<'de, SerdeError> for RespFrame {
+    type Deserializer = RespFrame;
+
+    fn into_deserializer(self) -> RespFrame {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match self.as_str() {
+                    Some(s) => {
+                        let v: $ty = s.parse().map_err(|_| {
+                            SerdeError(format!("invalid {}: {:?}", stringify!($ty), s))
+                        })?;
+                        visitor.$visit(v)
+                    }
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for RespFrame {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            RespFrame::SimpleString(s) => visitor.visit_string(s.0.into()),
+            RespFrame::Error(e) => Err(SerdeError(e.0.into())),
+            RespFrame::Integer(i) => visitor.visit_i64(i),
+            RespFrame::BulkString(s) => match String::from_utf8(s.0.into()) {
+                Ok(s) => visitor.visit_string(s),
+                Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+            },
+            RespFrame::Array(a) => visitor.visit_seq(SeqDeserializer::new(a.0.into_iter())),
+            RespFrame::Set(s) => visitor.visit_seq(SeqDeserializer::new(s.0.into_iter())),
+            RespFrame::Push(p) => visitor.visit_seq(SeqDeserializer::new(p.0.into_iter())),
+            RespFrame::Map(m) => visitor.visit_map(MapDeserializer::new(m.0.into_iter())),
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+                visitor.visit_unit()
+            }
+            RespFrame::Boolean(b) => visitor.visit_bool(b),
+            RespFrame::Double(d) => visitor.visit_f64(d),
+            RespFrame::Attribute(a) => a.frame.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_bool => visit_bool: bool,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            RespFrame::Null(_) | RespFrame::NullBulkString(_) | RespFrame::NullArray(_) => {
+                visitor.visit_none()
+            }
+            frame => visitor.visit_some(frame),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            RespFrame::Map(m) if m.len() == 1 => visitor.visit_enum(MapAccessDeserializer::new(
+                MapDeserializer::new(m.0.into_iter()),
+            )),
+            frame => match frame.as_str() {
+                Some(s) => visitor.visit_enum(s.to_string().into_deserializer()),
+                None => Err(SerdeError(format!("invalid enum value: {:?}", frame))),
+            },
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Role {
+        Admin,
+        Guest { since: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        name: String,
+        age: u8,
+        score: f64,
+        active: bool,
+        tags: Vec<String>,
+        email: Option<String>,
+        role: Role,
+        extra: BTreeMap<String, i64>,
+    }
+
+    #[test]
+    fn test_struct_roundtrip() -> Result<(), SerdeError> {
+        let user = User {
+            name: "alice".to_string(),
+            age: 30,
+            score: 1.5,
+            active: true,
+            tags: vec!["a".to_string(), "b".to_string()],
+            email: None,
+            role: Role::Guest { since: 2020 },
+            extra: BTreeMap::from([("x".to_string(), 1)]),
+        };
+
+        let frame = to_frame(&user)?;
+        let RespFrame::Map(ref map) = frame else {
+            panic!("struct should serialize to a map");
+        };
+        assert_eq!(map.get("name"), Some(&BulkString::from("alice").into()));
+        assert_eq!(map.get("email"), Some(&RespNull.into()));
+
+        assert_eq!(from_frame::<User>(frame)?, user);
+        assert_eq!(to_frame(&Role::Admin)?, BulkString::from("Admin").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_numbers_from_bulk_strings() -> Result<(), SerdeError> {
+        let frame: RespFrame = RespArray::new([
+            BulkString::from("42").into(),
+            BulkString::from("1.25").into(),
+            BulkString::from("true").into(),
+        ])
+        .into();
+        let (a, b, c): (i64, f64, bool) = from_frame(frame)?;
+        assert_eq!((a, b, c), (42, 1.25, true));
+
+        assert!(from_frame::<i64>(BulkString::from("x").into()).is_err());
+        Ok(())
+    }
+}
@@ -1,29 +1,42 @@
-use std::ops::Deref;
+use std::{
+    cmp::Ordering,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Deref,
+};
 
 use bytes::{Buf, BytesMut};
+use indexmap::IndexSet;
 
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 
-use super::{calc_total_length, parse_length, BUF_CAP, CRLF_LEN};
+use super::{
+    calc_total_length, parse_length,
+    stream::{decode_streamed_aggregate, is_streamed, streamed_aggregate_length},
+    write_formatted, CRLF_LEN,
+};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub struct RespSet(pub(crate) Vec<RespFrame>);
+// 保持插入顺序并去重，编码时不会出现重复的成员
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RespSet(pub(crate) IndexSet<RespFrame>);
 
 impl RespEncode for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
+    fn encode_into(&self, buf: &mut BytesMut) {
+        write_formatted(buf, format_args!("~{}\r\n", self.len()));
 
-        for item in self.0 {
-            buf.extend_from_slice(&item.encode());
+        for item in self.iter() {
+            item.encode_into(buf);
         }
-        buf
     }
 }
 
 impl RespDecode for RespSet {
     const PREFIX: &'static str = "~";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        if is_streamed(buf, Self::PREFIX) {
+            let frames = decode_streamed_aggregate(buf, Self::PREFIX, false, RespFrame::decode)?;
+            return Ok(RespSet::new(frames));
+        }
+
         let (end, len) = parse_length(buf, Self::PREFIX)?;
 
         let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
@@ -34,28 +47,55 @@ impl RespDecode for RespSet {
 
         buf.advance(end + CRLF_LEN);
 
-        let mut frames = Vec::new();
+        let mut frames = RespSet::new([]);
         for _ in 0..len {
-            frames.push(RespFrame::decode(buf)?);
+            frames.insert(RespFrame::decode(buf)?);
         }
 
-        Ok(RespSet::new(frames))
+        Ok(frames)
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        if is_streamed(buf, Self::PREFIX) {
+            return streamed_aggregate_length(buf, Self::PREFIX, false);
+        }
         let (end, len) = parse_length(buf, Self::PREFIX)?;
         calc_total_length(buf, end, len, Self::PREFIX)
     }
 }
 
 impl RespSet {
-    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
-        RespSet(s.into())
+    pub fn new(s: impl IntoIterator<Item = RespFrame>) -> Self {
+        RespSet(s.into_iter().collect())
+    }
+
+    // 成员不存在时插入并返回 true
+    pub fn insert(&mut self, frame: RespFrame) -> bool {
+        self.0.insert(frame)
+    }
+}
+
+// 集合相等与成员顺序无关，因此 hash 也要与顺序无关
+impl Hash for RespSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let sum = self.0.iter().fold(0u64, |acc, item| {
+            let mut hasher = DefaultHasher::new();
+            item.hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        });
+        self.0.len().hash(state);
+        sum.hash(state);
+    }
+}
+
+impl PartialOrd for RespSet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.iter().partial_cmp(other.0.iter())
     }
 }
 
 impl Deref for RespSet {
-    type Target = Vec<RespFrame>;
+    type Target = IndexSet<RespFrame>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -95,4 +135,23 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_dedup() -> Result<()> {
+        let set = RespSet::new([1.into(), 2.into(), 1.into()]);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&RespFrame::Integer(2)));
+        assert_eq!(&RespFrame::from(set).encode(), b"~2\r\n:1\r\n:2\r\n");
+
+        let mut buf = BytesMut::from("~3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\na\r\n");
+        let frame = RespSet::decode(&mut buf)?;
+        assert_eq!(frame.len(), 2);
+
+        // 成员相同顺序不同的集合相等
+        assert_eq!(
+            RespSet::new([1.into(), 2.into()]),
+            RespSet::new([2.into(), 1.into()])
+        );
+        Ok(())
+    }
 }
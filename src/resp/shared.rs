@@ -0,0 +1,48 @@
+use super::RespFrame;
+
+// 最常见的回复预先编码好，写出时直接拷贝字节，不需要逐个字段编码。
+// 与 redis 的 shared.ok / shared.czero 等共享对象对应
+const SHARED_INTEGERS: [&[u8]; 10] = [
+    b":0\r\n", b":1\r\n", b":2\r\n", b":3\r\n", b":4\r\n", b":5\r\n", b":6\r\n", b":7\r\n",
+    b":8\r\n", b":9\r\n",
+];
+
+pub(super) fn shared_encoding(frame: &RespFrame) -> Option<&'static [u8]> {
+    match frame {
+        RespFrame::SimpleString(s) => match &**s {
+            "OK" => Some(b"+OK\r\n"),
+            "PONG" => Some(b"+PONG\r\n"),
+            _ => None,
+        },
+        RespFrame::Integer(n) => SHARED_INTEGERS.get(usize::try_from(*n).ok()?).copied(),
+        RespFrame::Null(_) => Some(b"_\r\n"),
+        RespFrame::NullBulkString(_) => Some(b"$-1\r\n"),
+        RespFrame::NullArray(_) => Some(b"*-1\r\n"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespEncode, RespNull, RespNullArray, RespNullBulkString, SimpleString};
+
+    #[test]
+    fn test_shared_encoding_matches_encode() {
+        let frames: Vec<RespFrame> = vec![
+            SimpleString::new("OK").into(),
+            SimpleString::new("PONG").into(),
+            RespNull.into(),
+            RespNullBulkString.into(),
+            RespNullArray.into(),
+        ];
+        let integers = (0..10).map(RespFrame::Integer);
+        for frame in frames.into_iter().chain(integers) {
+            assert_eq!(shared_encoding(&frame), Some(frame.encode().as_slice()));
+        }
+
+        assert_eq!(shared_encoding(&RespFrame::Integer(10)), None);
+        assert_eq!(shared_encoding(&RespFrame::Integer(-1)), None);
+        assert_eq!(shared_encoding(&SimpleString::new("ok").into()), None);
+    }
+}
@@ -1,16 +1,20 @@
 use std::ops::Deref;
 
+use bytes::BytesMut;
+
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, has_line_break, line_break_error, SmallString, CRLF_LEN};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
-pub struct SimpleError(pub(crate) String);
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct SimpleError(pub(crate) SmallString);
 
 // - error: "-Error message\r\n"
 impl RespEncode for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encode_into(&self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"-");
+        buf.extend_from_slice(self.0.as_bytes());
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
@@ -19,10 +23,13 @@ impl RespDecode for SimpleError {
 
     fn decode(buf: &mut bytes::BytesMut) -> Result<Self, RespError> {
         let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        if has_line_break(&buf[Self::PREFIX.len()..end]) {
+            return Err(line_break_error("simple error"));
+        }
         let data = buf.split_to(end + CRLF_LEN);
 
         let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(SimpleError::new(s.to_string()))
+        Ok(SimpleError::new(&*s))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -32,13 +39,28 @@ impl RespDecode for SimpleError {
 }
 
 impl SimpleError {
-    pub fn new(s: impl Into<String>) -> Self {
-        SimpleError(s.into())
+    // 错误没有可以替代的 bulk 类型，错误描述中又可能带有用户输入，
+    // 与 redis 回复错误时一样在构造时把 CR / LF 替换为空格，因此不会构造出无法编码的值
+    pub fn new(s: impl Into<SmallString>) -> Self {
+        let s = s.into();
+        match has_line_break(s.as_bytes()) {
+            true => SimpleError(s.replace(['\r', '\n'], " ").into()),
+            false => SimpleError(s),
+        }
+    }
+
+    // 包含 CR 或 LF 时返回错误
+    pub fn try_new(s: impl Into<SmallString>) -> Result<Self, RespError> {
+        let s = s.into();
+        if has_line_break(s.as_bytes()) {
+            return Err(line_break_error("simple error"));
+        }
+        Ok(SimpleError(s))
     }
 }
 
 impl Deref for SimpleError {
-    type Target = String;
+    type Target = str;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -47,7 +69,7 @@ impl Deref for SimpleError {
 
 impl From<&str> for SimpleError {
     fn from(value: &str) -> Self {
-        SimpleError(value.to_string())
+        SimpleError::new(value)
     }
 }
 
@@ -74,4 +96,17 @@ mod tests {
         assert_eq!(frame, SimpleError::new("Error message".to_string()));
         Ok(())
     }
+
+    #[test]
+    fn test_simple_error_line_breaks() {
+        let mut buf = BytesMut::from("-ERR a\nb\r\n");
+        assert!(SimpleError::decode(&mut buf).is_err());
+        assert!(SimpleError::try_new("ERR a\nb").is_err());
+
+        let err = SimpleError::new("ERR a\r\nb");
+        assert_eq!(&*err, "ERR a  b");
+        assert_eq!(SimpleError::from("ERR a\nb"), SimpleError::new("ERR a b"));
+        let frame: RespFrame = err.into();
+        assert_eq!(frame.encode(), b"-ERR a  b\r\n");
+    }
 }
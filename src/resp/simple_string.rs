@@ -1,16 +1,24 @@
 use std::ops::Deref;
 
-use crate::{RespDecode, RespEncode};
+use bytes::BytesMut;
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use crate::{BulkString, RespDecode, RespEncode, RespError, RespFrame};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
-pub struct SimpleString(pub(crate) String);
+use super::{extract_simple_frame_data, has_line_break, line_break_error, SmallString, CRLF_LEN};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+pub struct SimpleString(pub(crate) SmallString);
 
 // - simple string: "+OK\r\n"
 impl RespEncode for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
+    fn encode_into(&self, buf: &mut BytesMut) {
+        // 包含 CR 或 LF 的值无法作为 simple string 发送，改用 BulkString 编码，内容保持不变
+        if has_line_break(self.0.as_bytes()) {
+            return BulkString::from(&**self).encode_into(buf);
+        }
+        buf.extend_from_slice(b"+");
+        buf.extend_from_slice(self.0.as_bytes());
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
@@ -19,10 +27,13 @@ impl RespDecode for SimpleString {
 
     fn decode(buf: &mut bytes::BytesMut) -> Result<Self, crate::RespError> {
         let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        if has_line_break(&buf[Self::PREFIX.len()..end]) {
+            return Err(line_break_error("simple string"));
+        }
         let data = buf.split_to(end + CRLF_LEN);
 
         let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-        Ok(SimpleString::new(s.to_string()))
+        Ok(SimpleString::new(&*s))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, crate::RespError> {
@@ -32,7 +43,7 @@ impl RespDecode for SimpleString {
 }
 
 impl Deref for SimpleString {
-    type Target = String;
+    type Target = str;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -40,14 +51,31 @@ impl Deref for SimpleString {
 }
 
 impl SimpleString {
-    pub fn new(s: impl Into<String>) -> Self {
+    pub fn new(s: impl Into<SmallString>) -> Self {
         Self(s.into())
     }
+
+    // 内容来自用户输入时使用，包含 CR 或 LF 时返回错误
+    pub fn try_new(s: impl Into<SmallString>) -> Result<Self, RespError> {
+        let s = s.into();
+        if has_line_break(s.as_bytes()) {
+            return Err(line_break_error("simple string"));
+        }
+        Ok(Self(s))
+    }
+
+    // 包含 CR 或 LF 时改用 BulkString
+    pub fn new_or_bulk(s: &str) -> RespFrame {
+        match Self::try_new(s) {
+            Ok(s) => s.into(),
+            Err(_) => BulkString::from(s).into(),
+        }
+    }
 }
 
 impl From<&str> for SimpleString {
     fn from(value: &str) -> Self {
-        SimpleString(value.to_string())
+        SimpleString(value.into())
     }
 }
 
@@ -89,4 +117,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_simple_string_line_breaks() {
+        let mut buf = BytesMut::from("+he\nllo\r\n");
+        assert!(matches!(
+            SimpleString::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        let mut buf = BytesMut::from("+a\rb\r\n");
+        assert!(SimpleString::decode(&mut buf).is_err());
+
+        assert!(SimpleString::try_new("a\r\nb").is_err());
+        assert_eq!(
+            SimpleString::new_or_bulk("a\r\nb"),
+            BulkString::from("a\r\nb").into()
+        );
+        assert_eq!(
+            SimpleString::new_or_bulk("ok"),
+            SimpleString::new("ok").into()
+        );
+
+        // 通过 new 构造的值编码为 BulkString，解码后内容不变，也不会多出一个帧
+        let frame: RespFrame = SimpleString::new("a\r\n+b").into();
+        let mut buf = BytesMut::from(&frame.encode()[..]);
+        assert_eq!(&buf[..], b"$5\r\na\r\n+b\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap(),
+            BulkString::from("a\r\n+b").into()
+        );
+        assert!(buf.is_empty());
+    }
 }
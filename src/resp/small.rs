@@ -0,0 +1,190 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+// 与 String 同样占 24 字节，不超过该长度的字符串直接存放在结构体内部
+const INLINE_CAP: usize = 22;
+
+// SimpleString / SimpleError 使用的字符串。"OK"、"PONG" 以及大部分错误码这类短字符串不需要分配堆内存，
+// clone 也只是拷贝 24 个字节；更长的字符串仍然放在堆上
+#[derive(Clone)]
+pub struct SmallString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, buf: [u8; INLINE_CAP] },
+    Heap(Box<str>),
+}
+
+impl SmallString {
+    pub fn new(s: &str) -> Self {
+        match s.len() {
+            len if len <= INLINE_CAP => {
+                let mut buf = [0; INLINE_CAP];
+                buf[..len].copy_from_slice(s.as_bytes());
+                SmallString(Repr::Inline {
+                    len: len as u8,
+                    buf,
+                })
+            }
+            _ => SmallString(Repr::Heap(s.into())),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            // 内联的数据总是从合法的 &str 整体拷贝而来，校验不会失败，只是为了不使用 unsafe
+            Repr::Inline { len, buf } => std::str::from_utf8(&buf[..*len as usize]).unwrap_or(""),
+            Repr::Heap(s) => s,
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl Default for SmallString {
+    fn default() -> Self {
+        SmallString::new("")
+    }
+}
+
+impl Deref for SmallString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for SmallString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for SmallString {
+    fn from(s: &str) -> Self {
+        SmallString::new(s)
+    }
+}
+
+impl From<String> for SmallString {
+    fn from(s: String) -> Self {
+        match s.len() {
+            len if len <= INLINE_CAP => SmallString::new(&s),
+            _ => SmallString(Repr::Heap(s.into_boxed_str())),
+        }
+    }
+}
+
+impl From<SmallString> for String {
+    fn from(s: SmallString) -> Self {
+        match s.0 {
+            Repr::Heap(s) => s.into_string(),
+            Repr::Inline { .. } => s.as_str().to_string(),
+        }
+    }
+}
+
+impl PartialEq for SmallString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallString {}
+
+impl PartialEq<str> for SmallString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for SmallString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for SmallString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_string_inline_and_heap() {
+        assert_eq!(
+            std::mem::size_of::<SmallString>(),
+            std::mem::size_of::<String>()
+        );
+
+        let ok = SmallString::from("OK");
+        assert!(ok.is_inline());
+        assert_eq!(ok.as_str(), "OK");
+        assert_eq!(ok.clone(), ok);
+
+        let boundary = "x".repeat(INLINE_CAP);
+        assert!(SmallString::from(boundary.as_str()).is_inline());
+        let long = SmallString::from(format!("{}y", boundary));
+        assert!(!long.is_inline());
+        assert_eq!(long.len(), INLINE_CAP + 1);
+        assert_eq!(String::from(long), format!("{}y", boundary));
+
+        // 多字节字符按字节计算长度
+        let s = SmallString::from("错误：键不存在");
+        assert!(s.is_inline());
+        assert_eq!(&*s, "错误：键不存在");
+    }
+
+    #[test]
+    fn test_small_string_ordering_matches_str() {
+        let mut words = ["b", "a", &"z".repeat(30), "ab"]
+            .map(SmallString::from)
+            .to_vec();
+        words.sort();
+        let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+        assert_eq!(words, vec!["a", "ab", "b", &"z".repeat(30)]);
+        assert_eq!(SmallString::default(), "");
+    }
+}
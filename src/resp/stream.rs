@@ -0,0 +1,153 @@
+// RESP3 的流式类型：长度未知时用 "?" 代替长度
+// - streamed bulk string: "$?\r\n;<len>\r\n<data>\r\n...;0\r\n"
+// - streamed aggregate: "*?\r\n<element-1>...<element-n>.\r\n"，map / set 同理
+use bytes::{Buf, BytesMut};
+
+use crate::{RespDecode, RespError, RespFrame};
+
+use super::{map::key_length, parse_length, remaining, CRLF_LEN};
+
+const STREAM_MARKER: &[u8] = b"?\r\n";
+const CHUNK_PREFIX: &str = ";";
+const END_MARKER: &[u8] = b".\r\n";
+
+pub(super) fn is_streamed(buf: &[u8], prefix: &str) -> bool {
+    buf.starts_with(prefix.as_bytes()) && buf[prefix.len()..].starts_with(STREAM_MARKER)
+}
+
+fn header_len(prefix: &str) -> usize {
+    prefix.len() + STREAM_MARKER.len()
+}
+
+pub(super) fn streamed_string_length(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
+    let mut total = header_len(prefix);
+    loop {
+        let (end, len) = parse_length(remaining(buf, total)?, CHUNK_PREFIX)?;
+        total += end + CRLF_LEN;
+        if len == 0 {
+            return Ok(total);
+        }
+        total += len + CRLF_LEN;
+        if buf.len() < total {
+            return Err(RespError::NotComplete);
+        }
+    }
+}
+
+// 把所有 chunk 拼接成一个完整的字符串
+pub(super) fn decode_streamed_string(
+    buf: &mut BytesMut,
+    prefix: &str,
+) -> Result<Vec<u8>, RespError> {
+    streamed_string_length(buf, prefix)?;
+    buf.advance(header_len(prefix));
+
+    let mut data = Vec::new();
+    loop {
+        let (end, len) = parse_length(buf, CHUNK_PREFIX)?;
+        buf.advance(end + CRLF_LEN);
+        if len == 0 {
+            return Ok(data);
+        }
+        data.extend_from_slice(&buf[..len]);
+        buf.advance(len + CRLF_LEN);
+    }
+}
+
+// pairs 为 true 时每个元素是一对 key / value（map）
+pub(super) fn streamed_aggregate_length(
+    buf: &[u8],
+    prefix: &str,
+    pairs: bool,
+) -> Result<usize, RespError> {
+    let mut total = header_len(prefix);
+    loop {
+        let data = remaining(buf, total)?;
+        if data.starts_with(END_MARKER) {
+            return Ok(total + END_MARKER.len());
+        }
+        if pairs {
+            total += key_length(data)?;
+        }
+        total += RespFrame::expect_length(remaining(buf, total)?)?;
+        if buf.len() < total {
+            return Err(RespError::NotComplete);
+        }
+    }
+}
+
+pub(super) fn decode_streamed_aggregate<T>(
+    buf: &mut BytesMut,
+    prefix: &str,
+    pairs: bool,
+    mut decode: impl FnMut(&mut BytesMut) -> Result<T, RespError>,
+) -> Result<Vec<T>, RespError> {
+    streamed_aggregate_length(buf, prefix, pairs)?;
+    buf.advance(header_len(prefix));
+
+    let mut items = Vec::new();
+    while !buf.starts_with(END_MARKER) {
+        items.push(decode(buf)?);
+    }
+    buf.advance(END_MARKER.len());
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, RespArray, RespMap, RespSet};
+
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_streamed_bulk_string_decode() -> Result<()> {
+        let mut buf = BytesMut::from("$?\r\n;4\r\nHell\r\n;5\r\no wor\r\n;1\r\nd\r\n;0\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::from("Hello word").into());
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("$?\r\n;4\r\nHell\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap_err(),
+            RespError::NotComplete
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_streamed_aggregate_decode() -> Result<()> {
+        let mut buf = BytesMut::from("*?\r\n:1\r\n$?\r\n;2\r\nab\r\n;0\r\n.\r\n");
+        let frame = RespFrame::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespArray::new([1.into(), BulkString::from("ab").into()]).into()
+        );
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("%?\r\n+a\r\n:1\r\n+b\r\n:2\r\n.\r\n");
+        let mut map = RespMap::new();
+        map.insert("a".to_string(), 1.into());
+        map.insert("b".to_string(), 2.into());
+        assert_eq!(RespFrame::decode(&mut buf)?, map.into());
+
+        let mut buf = BytesMut::from("~?\r\n:1\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf).unwrap_err(),
+            RespError::NotComplete
+        );
+        buf.extend_from_slice(b".\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf)?,
+            RespSet::new([1.into()]).into()
+        );
+
+        // 流式数组作为普通数组的元素
+        let mut buf = BytesMut::from("*1\r\n*?\r\n:1\r\n.\r\n");
+        assert_eq!(
+            RespFrame::decode(&mut buf)?,
+            RespArray::new([RespArray::new([1.into()]).into()]).into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// proptest 的 Arbitrary 实现和生成策略，用于对编解码和命令解析做性质测试
+use proptest::{
+    collection::{btree_map, vec},
+    prelude::*,
+};
+
+use crate::{
+    BulkString, RespArray, RespAttribute, RespEncode, RespFrame, RespMap, RespNull, RespNullArray,
+    RespNullBulkString, RespPush, RespSet, SimpleError, SimpleString,
+};
+
+// simple string / error 以及 map 的 key 不能包含 CRLF
+fn arb_line() -> impl Strategy<Value = String> {
+    "[^\r\n]{0,16}"
+}
+
+fn arb_scalar() -> impl Strategy<Value = RespFrame> {
+    prop_oneof![
+        arb_line().prop_map(|s| SimpleString::new(s).into()),
+        arb_line().prop_map(|s| SimpleError::new(s).into()),
+        any::<i64>().prop_map(RespFrame::Integer),
+        vec(any::<u8>(), 0..32).prop_map(|v| BulkString::new(v).into()),
+        Just(RespNull.into()),
+        Just(RespNullBulkString.into()),
+        Just(RespNullArray.into()),
+        any::<bool>().prop_map(RespFrame::Boolean),
+        any::<f64>().prop_map(RespFrame::Double),
+    ]
+}
+
+// 任意的 RespFrame，编码后再解码应该得到相同的帧
+pub fn arb_frame() -> impl Strategy<Value = RespFrame> {
+    arb_scalar().prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(|v| RespArray::new(v).into()),
+            vec(inner.clone(), 0..8).prop_map(|v| RespSet::new(v).into()),
+            btree_map(arb_line(), inner.clone(), 0..8).prop_map(|m| RespMap::from(m).into()),
+            vec(inner.clone(), 0..8).prop_map(|v| RespPush::new(v).into()),
+            (btree_map(arb_line(), inner.clone(), 0..4), inner)
+                .prop_map(|(m, frame)| RespAttribute::new(RespMap::from(m), frame).into()),
+        ]
+    })
+}
+
+// 帧及其编码后的字节
+pub fn arb_encoded_frame() -> impl Strategy<Value = (RespFrame, Vec<u8>)> {
+    arb_frame().prop_map(|frame| {
+        let encoded = frame.encode();
+        (frame, encoded)
+    })
+}
+
+const COMMANDS: &[&str] = &[
+    "get",
+    "set",
+    "sadd",
+    "sismember",
+    "smembers",
+    "hget",
+    "hset",
+    "hgetall",
+    "hmget",
+    "echo",
+    "ping",
+    "hello",
+    "config",
+    "object",
+    "info",
+    "latency",
+    "keys",
+    "scan",
+    "hscan",
+    "sscan",
+    "mget",
+    "mset",
+    "del",
+    "unlink",
+    "exists",
+    "expire",
+    "pexpire",
+    "expireat",
+    "pexpireat",
+    "ttl",
+    "pttl",
+    "persist",
+    "bgsave",
+    "module",
+    "subscribe",
+    "unsubscribe",
+    "publish",
+    "role",
+    "replicaof",
+    "failover",
+];
+
+// 客户端发送的命令：已知的命令名（大小写随机）加上任意的参数
+pub fn arb_command_frame() -> impl Strategy<Value = RespArray> {
+    let name = (prop::sample::select(COMMANDS), any::<bool>()).prop_map(|(name, upper)| {
+        if upper {
+            name.to_ascii_uppercase()
+        } else {
+            name.to_string()
+        }
+    });
+    let arg = prop_oneof![
+        4 => vec(any::<u8>(), 0..8).prop_map(|v| RespFrame::from(BulkString::new(v))),
+        1 => arb_scalar(),
+    ];
+    (name, vec(arg, 0..5)).prop_map(|(name, args)| {
+        let mut frames = vec![BulkString::from(name).into()];
+        frames.extend(args);
+        RespArray::new(frames)
+    })
+}
+
+impl Arbitrary for RespFrame {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        arb_frame().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, RespDecode};
+    use bytes::BytesMut;
+
+    proptest! {
+        #[test]
+        fn test_frame_roundtrip((frame, encoded) in arb_encoded_frame()) {
+            let mut buf = BytesMut::from(&encoded[..]);
+            prop_assert_eq!(RespFrame::expect_length(&buf), Ok(encoded.len()));
+            prop_assert_eq!(RespFrame::decode(&mut buf), Ok(frame));
+            prop_assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn test_truncated_frame_not_complete((_, encoded) in arb_encoded_frame(), cut in any::<prop::sample::Index>()) {
+            let cut = cut.index(encoded.len());
+            let mut buf = BytesMut::from(&encoded[..cut]);
+            prop_assert!(RespFrame::decode(&mut buf).is_err());
+        }
+
+        #[test]
+        fn test_command_parse_never_panics(frame in arb_command_frame()) {
+            let _ = Command::try_from(frame);
+        }
+    }
+}
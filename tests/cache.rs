@@ -0,0 +1,76 @@
+// 缓存源站注册在全局，单独放在一个测试二进制中，避免影响其他集成测试
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use common::TestServer;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use simple_redis::{
+    cmd::{set_origin, CommandError, Origin},
+    BulkString, RespArray, RespFrame,
+};
+
+// 用 HashMap 模拟的源站，只处理 SET
+#[derive(Debug, Default, Clone)]
+struct MapOrigin(Arc<Mutex<HashMap<String, String>>>);
+
+fn arg(request: &RespArray, index: usize) -> Option<String> {
+    match request.get(index) {
+        Some(RespFrame::BulkString(value)) => Some(String::from_utf8_lossy(value).into_owned()),
+        _ => None,
+    }
+}
+
+impl Origin for MapOrigin {
+    fn fetch<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<RespFrame>, CommandError>> {
+        Box::pin(async move {
+            let value = self.0.lock().get(key).cloned();
+            Ok(value.map(|v| BulkString::from(v).into()))
+        })
+    }
+
+    fn write<'a>(&'a self, request: &'a RespArray) -> BoxFuture<'a, Result<(), CommandError>> {
+        Box::pin(async move {
+            match (arg(request, 0), arg(request, 1), arg(request, 2)) {
+                (Some(cmd), Some(key), Some(value)) if cmd.eq_ignore_ascii_case("set") => {
+                    self.0.lock().insert(key, value);
+                    Ok(())
+                }
+                _ => Err(CommandError::Upstream("read only origin".to_string())),
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_cache_origin_callback() -> Result<()> {
+    let origin = MapOrigin::default();
+    origin
+        .0
+        .lock()
+        .insert("warm".to_string(), "from-origin".to_string());
+    set_origin(origin.clone());
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    // 读穿：本地没有时从源站读取并回填
+    assert_eq!(client.get("warm").await?, Some("from-origin".to_string()));
+    assert_eq!(
+        server.backend.get_string("warm"),
+        Some("from-origin".to_string())
+    );
+    assert_eq!(client.get("missing").await?, None);
+
+    // 写穿：源站与本地都写入
+    client.set("k", "v").await?;
+    assert_eq!(origin.0.lock().get("k"), Some(&"v".to_string()));
+    assert_eq!(server.backend.get_string("k"), Some("v".to_string()));
+
+    // 源站拒绝的写入本地不执行
+    assert!(client.command(["DEL", "k"]).await.is_err());
+    assert_eq!(server.backend.get_string("k"), Some("v".to_string()));
+    Ok(())
+}
@@ -0,0 +1,70 @@
+// 集成测试共用的测试服务器：在临时端口上启动完整的服务器，测试结束时关闭并删除临时目录
+// 每个集成测试只用到其中的一部分
+#![allow(dead_code)]
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use simple_redis::{
+    client::Client,
+    network::{self, Shutdown},
+    Backend, BackendConfig, ServerConfig,
+};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub backend: Backend,
+    pub dir: PathBuf,
+    shutdown: Shutdown,
+}
+
+impl TestServer {
+    pub async fn start() -> Result<Self> {
+        Self::with_config(ServerConfig::default()).await
+    }
+
+    // 忽略 config 中的 port / bind / dir，总是监听 127.0.0.1 的临时端口并使用单独的临时目录
+    pub async fn with_config(mut config: ServerConfig) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "simple-redis-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)?;
+        config.port = 0;
+        config.bind = "127.0.0.1".to_string();
+        config.reuseport = false;
+        config.dir = dir.to_string_lossy().into_owned();
+
+        let listener = network::bind_listeners(&config)?.remove(0);
+        let addr = listener.local_addr()?;
+        let backend = Backend::with_config(BackendConfig {
+            server: config,
+            ..Default::default()
+        });
+        let shutdown = Shutdown::new();
+        tokio::spawn(network::serve(listener, backend.clone(), shutdown.clone()));
+        Ok(Self {
+            addr,
+            backend,
+            dir,
+            shutdown,
+        })
+    }
+
+    pub async fn client(&self) -> Result<Client> {
+        Client::connect(self.addr).await
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.trigger();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,163 @@
+// 把同样的命令序列分别发给本服务器和真实的 redis，逐条比较回复。
+// 只在设置了 SIMPLE_REDIS_DIFF_ADDR（例如 127.0.0.1:6379）时运行，所有 key 都带有本次运行独有的前缀，
+// 结束时只删除这些 key，不会影响 redis 中的其他数据
+mod common;
+
+use anyhow::Result;
+use common::TestServer;
+use simple_redis::{
+    client::Client, split_args, BulkString, RespArray, RespEncode, RespFrame, SimpleError,
+};
+
+const ADDR_ENV: &str = "SIMPLE_REDIS_DIFF_ADDR";
+
+// {k} 会被替换为 key 前缀
+const CASES: &[&str] = &[
+    "PING",
+    "PING hello",
+    "ECHO \"hello world\"",
+    "SET {k}str v1",
+    "GET {k}str",
+    "GET {k}missing",
+    "SET {k}str v2 NX",
+    "SET {k}str v3 XX GET",
+    "GET {k}str",
+    "SET {k}new v NX",
+    "HSET {k}hash f1 v1",
+    "HGET {k}hash f1",
+    "HGET {k}hash nope",
+    "HMGET {k}hash f1 nope",
+    "HGETALL {k}hash",
+    "HGETALL {k}missing",
+    "SADD {k}set a b c",
+    "SADD {k}set a d",
+    "SISMEMBER {k}set a",
+    "SISMEMBER {k}set z",
+    "SMEMBERS {k}set",
+    "SMEMBERS {k}missing",
+    "SET {k}hash v NX",
+    "SET {k}set v NX",
+    "HGET {k}hash f1",
+    "SISMEMBER {k}set a",
+    "HSET {k}xhash f v",
+    "SET {k}xhash v XX",
+    "GET {k}xhash",
+    "SADD {k}xset a",
+    "SET {k}xset v XX GET",
+    "SET {k}xset v XX",
+    "GET {k}xset",
+    "SSCAN {k}set 0 COUNT 100",
+    "GET {k}hash",
+    "SADD {k}str x",
+    "HGET {k}set f",
+    "MSET {k}m1 1 {k}m2 2",
+    "MGET {k}m1 {k}missing {k}hash {k}m2",
+    "EXISTS {k}m1 {k}m1 {k}set {k}missing",
+    "DEL {k}m1 {k}set {k}missing",
+    "UNLINK {k}m2",
+    "EXISTS {k}m1 {k}m2 {k}set",
+    "GET",
+    "SET {k}str",
+    "MSET {k}m1",
+    "DEL",
+];
+
+// 已知与 redis 不一致的命令，差异只输出不报错
+const KNOWN_DIFFERENCES: &[&str] = &[
+    // redis 回复新增的字段个数，这里回复 OK
+    "hset",
+];
+
+// 回复中元素顺序不固定的命令
+const UNORDERED: &[&str] = &["smembers", "hgetall", "sscan", "hscan", "scan", "keys"];
+
+#[tokio::test]
+async fn test_replies_match_redis() -> Result<()> {
+    let Ok(addr) = std::env::var(ADDR_ENV) else {
+        eprintln!("{} is not set, skipping differential tests", ADDR_ENV);
+        return Ok(());
+    };
+    let mut redis = Client::connect(addr).await?;
+    let server = TestServer::start().await?;
+    let mut ours = server.client().await?;
+
+    let prefix = format!("simple-redis-diff:{}:", std::process::id());
+    let mut mismatches = Vec::new();
+    for case in CASES {
+        let args = split_args(&case.replace("{k}", &prefix)).expect("valid test case");
+        let name = args[0].to_ascii_lowercase();
+        let expected = normalize(&name, redis.send(command(&args)).await?);
+        let actual = normalize(&name, ours.send(command(&args)).await?);
+        if expected == actual {
+            continue;
+        }
+        let report = format!("{}\n  redis: {:?}\n  ours:  {:?}", case, expected, actual);
+        if KNOWN_DIFFERENCES.contains(&name.as_str()) {
+            eprintln!("known difference: {}", report);
+        } else {
+            mismatches.push(report);
+        }
+    }
+
+    let keys = redis.command(["KEYS", &format!("{}*", prefix)]).await?;
+    if let RespFrame::Array(keys) = keys {
+        if !keys.is_empty() {
+            let mut del = vec![RespFrame::from(BulkString::from("DEL"))];
+            del.extend(keys.iter().cloned());
+            redis.send(RespArray::new(del).into()).await?;
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "replies differ from redis:\n{}",
+        mismatches.join("\n")
+    );
+    Ok(())
+}
+
+fn command(args: &[String]) -> RespFrame {
+    RespArray::new(
+        args.iter()
+            .map(|arg| BulkString::from(arg.as_str()).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+// 错误只比较错误码，错误描述不要求一致；顺序不固定的回复排序后再比较
+fn normalize(name: &str, frame: RespFrame) -> RespFrame {
+    match frame {
+        RespFrame::Error(e) => {
+            let code = e.split_whitespace().next().unwrap_or_default().to_string();
+            RespFrame::Error(SimpleError::new(code))
+        }
+        RespFrame::Array(array) if UNORDERED.contains(&name) => match name {
+            // HGETALL 在 RESP2 下是 field / value 交替的数组，按 field 排序
+            "hgetall" => {
+                let mut pairs = array
+                    .chunks(2)
+                    .map(|pair| pair.to_vec())
+                    .collect::<Vec<_>>();
+                pairs.sort_by_key(|pair| pair[0].encode());
+                RespArray::new(pairs.concat()).into()
+            }
+            // SCAN 系列的回复为 [游标, 元素数组]
+            "sscan" | "hscan" | "scan" => {
+                let mut items = array.to_vec();
+                if let Some(RespFrame::Array(inner)) = items.get_mut(1) {
+                    let mut sorted = inner.to_vec();
+                    sorted.sort_by_key(|frame| frame.encode());
+                    *inner = RespArray::new(sorted);
+                }
+                RespArray::new(items).into()
+            }
+            _ => {
+                let mut items = array.to_vec();
+                items.sort_by_key(|frame| frame.encode());
+                RespArray::new(items).into()
+            }
+        },
+        frame => frame,
+    }
+}
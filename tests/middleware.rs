@@ -0,0 +1,50 @@
+// 中间件注册在全局，单独放在一个测试二进制中，避免影响其他集成测试
+mod common;
+
+use anyhow::Result;
+use common::TestServer;
+use simple_redis::{
+    cmd::{register_middleware, CommandError, ConnectionContext, Middleware},
+    BulkString, RespArray, RespFrame,
+};
+
+// 按连接 id 给 key 加租户前缀，并拒绝 CONFIG 命令
+#[derive(Debug)]
+struct Tenant;
+
+impl Middleware for Tenant {
+    fn before(
+        &self,
+        request: &mut RespArray,
+        ctx: &mut ConnectionContext,
+    ) -> Result<Option<RespFrame>, CommandError> {
+        let Some(RespFrame::BulkString(name)) = request.first() else {
+            return Ok(None);
+        };
+        if name.eq_ignore_ascii_case(b"config") {
+            return Err(CommandError::NoPerm("config".to_string()));
+        }
+        if let Some(RespFrame::BulkString(key)) = request.get_mut(1) {
+            let prefixed = format!("tenant{}:{}", ctx.id, String::from_utf8_lossy(key));
+            *key = BulkString::from(prefixed);
+        }
+        Ok(None)
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_rewrites_and_vetoes() -> Result<()> {
+    register_middleware(Tenant);
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    client.set("k", "v").await?;
+    assert_eq!(client.get("k").await?, Some("v".to_string()));
+    assert_eq!(server.backend.get_string("k"), None);
+    let keys = server.backend.iter_keys().collect::<Vec<_>>();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0].starts_with("tenant") && keys[0].ends_with(":k"));
+
+    assert!(client.command(["CONFIG", "GET", "dir"]).await.is_err());
+    Ok(())
+}
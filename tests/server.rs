@@ -0,0 +1,222 @@
+// 通过真实的 TCP 连接覆盖 accept → decode → execute → encode 的完整流程
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use common::TestServer;
+use simple_redis::{client::ClientError, resp, BulkString, RespFrame, ServerConfig};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+#[tokio::test]
+async fn test_ping_and_echo() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+    assert_eq!(client.ping().await?, "PONG");
+    assert_eq!(
+        client.command(["ECHO", "hello"]).await?,
+        BulkString::from("hello").into()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_strings_hashes_and_sets() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    client.set("k", "v").await?;
+    assert_eq!(client.get("k").await?, Some("v".to_string()));
+    assert_eq!(client.get("missing").await?, None);
+
+    client.hset("h", "f", "1").await?;
+    assert_eq!(client.hget("h", "f").await?, Some("1".to_string()));
+    assert_eq!(client.hget("h", "missing").await?, None);
+
+    assert_eq!(
+        client.command(["SADD", "s", "a", "b"]).await?,
+        RespFrame::Integer(2)
+    );
+    assert_eq!(
+        client.command(["SISMEMBER", "s", "a"]).await?,
+        RespFrame::Integer(1)
+    );
+
+    // 同一个 backend 也可以直接读取通过网络写入的数据
+    assert_eq!(server.backend.get_string("k"), Some("v".to_string()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_errors_keep_connection_usable() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    client.set("k", "v").await?;
+    let err = client.command(["HGET", "k", "f"]).await.unwrap_err();
+    match err.downcast_ref::<ClientError>() {
+        Some(ClientError::Server(msg)) => assert!(msg.starts_with("WRONGTYPE")),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    assert!(client.command(["GET"]).await.is_err());
+    assert_eq!(client.ping().await?, "PONG");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipeline() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    let mut requests = Vec::new();
+    for i in 0..100 {
+        requests.push(resp!(["SET", format!("key:{}", i), i.to_string()]));
+    }
+    requests.push(resp!(["GET", "key:42"]));
+    let replies = client.pipeline(requests).await?;
+    assert_eq!(replies.len(), 101);
+    assert_eq!(replies[100], BulkString::from("42").into());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hello_switches_protocol() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut client = server.client().await?;
+
+    let RespFrame::Map(map) = client.command(["HELLO", "3"]).await? else {
+        panic!("HELLO 3 should reply with a map");
+    };
+    assert_eq!(map.get("proto"), Some(&RespFrame::Integer(3)));
+
+    // RESP3 下空值回复为 null
+    assert_eq!(client.command(["GET", "missing"]).await?, resp!(null));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_config_and_info() -> Result<()> {
+    let server = TestServer::with_config(ServerConfig {
+        set_max_intset_entries: 16,
+        ..Default::default()
+    })
+    .await?;
+    let mut client = server.client().await?;
+
+    // RESP2 下 CONFIG GET 回复 [name, value] 数组
+    assert_eq!(
+        client
+            .command(["CONFIG", "GET", "set-max-intset-entries"])
+            .await?,
+        resp!(["set-max-intset-entries", "16"])
+    );
+    let dir = server.dir.to_string_lossy().into_owned();
+    assert_eq!(
+        client.command(["CONFIG", "GET", "dir"]).await?,
+        resp!(["dir", dir])
+    );
+
+    client.get("k").await?;
+    let info = String::try_from(client.command(["INFO", "stats"]).await?)?;
+    assert!(info.contains("total_commands_processed:"));
+    assert!(info.contains("keyspace_misses:1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oplog_records_successful_writes() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut oplog = server.backend.subscribe_oplog();
+    let mut client = server.client().await?;
+
+    client.set("k", "v").await?;
+    client.get("k").await?;
+    // 执行失败的写命令不记录
+    assert!(client.command(["SADD", "k", "a"]).await.is_err());
+    client.command(["MSET", "a", "1", "b", "2"]).await?;
+
+    let entry = oplog.recv().await?;
+    assert_eq!((entry.seq, entry.keys.clone()), (1, vec!["k".to_string()]));
+    assert_eq!(
+        RespFrame::from(entry.command.clone()),
+        resp!(["SET", "k", "v"])
+    );
+    let entry = oplog.recv().await?;
+    assert_eq!(entry.seq, 2);
+    assert_eq!(entry.keys, vec!["a", "b"]);
+
+    // 相对的过期时间记录为时间戳
+    client.command(["EXPIRE", "a", "100"]).await?;
+    let entry = oplog.recv().await?;
+    assert_eq!(entry.command[0], BulkString::from("PEXPIREAT").into());
+    assert!(oplog.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_raw_protocol_error() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut stream = TcpStream::connect(server.addr).await?;
+
+    // 格式错误的帧被跳过，后面的请求正常处理
+    stream
+        .write_all(b"*1\r\n$x\r\n*1\r\n$4\r\nping\r\n")
+        .await?;
+    let mut buf = vec![0; 256];
+    let mut received = Vec::new();
+    while !received.ends_with(b"+PONG\r\n") {
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await??;
+        assert!(n > 0, "connection closed unexpectedly");
+        received.extend_from_slice(&buf[..n]);
+    }
+    assert!(received.starts_with(b"-ERR Protocol error"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_publish_to_subscriber() -> Result<()> {
+    let server = TestServer::start().await?;
+    let subscriber = server.client().await?;
+    let mut subscriber = subscriber.subscribe(&["news", "sports"]).await?;
+    let mut publisher = server.client().await?;
+
+    let reply = publisher.command(["PUBLISH", "sports", "goal"]).await?;
+    assert_eq!(reply, RespFrame::Integer(1));
+    let message = tokio::time::timeout(Duration::from_secs(5), subscriber.next_message())
+        .await??
+        .unwrap();
+    assert_eq!(
+        (message.channel.as_str(), message.payload.as_slice()),
+        ("sports", b"goal".as_slice())
+    );
+    assert_eq!(server.backend.pubsub_lag().len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_is_disconnected() -> Result<()> {
+    let config = ServerConfig {
+        pubsub_queue_limit: 4,
+        ..Default::default()
+    };
+    let server = TestServer::with_config(config).await?;
+    // 订阅之后不再读取，消息积压在服务器的队列和 socket 缓冲区中
+    let _subscriber = server.client().await?.subscribe(&["news"]).await?;
+    let mut publisher = server.client().await?;
+
+    let payload = "x".repeat(64 * 1024);
+    let mut delivered = 1;
+    for _ in 0..1000 {
+        let reply = publisher.command(["PUBLISH", "news", &payload]).await?;
+        delivered = i64::try_from(reply)?;
+        if delivered == 0 {
+            break;
+        }
+    }
+    assert_eq!(delivered, 0);
+    Ok(())
+}
@@ -0,0 +1,282 @@
+// 多线程同时读写 backend，检查并发下的不变量：计数不丢失、不会变为负数、统计与实际数据一致
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+use simple_redis::{Backend, BackendConfig, BulkString, KeyGuard, RespFrame, Value};
+
+const THREADS: usize = 8;
+const ROUNDS: usize = 2000;
+
+fn small_backend() -> Backend {
+    // shard 数少一些，让更多的写入落到同一个 shard 上互相竞争
+    Backend::with_config(BackendConfig {
+        shards: 4,
+        ..Default::default()
+    })
+}
+
+// 与 INCR / DECR 一样在 update 中完成读-改-写，结果小于 min 时不修改，返回是否修改
+fn incr_by(backend: &Backend, key: &str, delta: i64, min: i64) -> bool {
+    let mut applied = false;
+    backend.update(key, |old| {
+        let n = match old {
+            Some(RespFrame::Integer(n)) => n,
+            _ => 0,
+        };
+        if n + delta < min {
+            return Some(RespFrame::Integer(n));
+        }
+        applied = true;
+        Some(RespFrame::Integer(n + delta))
+    });
+    applied
+}
+
+fn integer(backend: &Backend, key: &str) -> Option<i64> {
+    match backend.get(key).as_deref() {
+        Some(RespFrame::Integer(n)) => Some(*n),
+        None => None,
+        other => panic!("unexpected value {:?}", other),
+    }
+}
+
+// 运行 writers 的同时不断执行 check，writers 全部结束后 check 再执行一次
+fn run_with_checker<W, C>(writers: W, check: C)
+where
+    W: Fn(usize) + Sync,
+    C: Fn() + Sync,
+{
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        let checker = s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                check();
+                thread::yield_now();
+            }
+        });
+        let handles = (0..THREADS)
+            .map(|t| {
+                let writers = &writers;
+                s.spawn(move || writers(t))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        checker.join().unwrap();
+    });
+    check();
+}
+
+#[test]
+fn test_concurrent_incr_has_no_lost_updates() {
+    let backend = small_backend();
+    run_with_checker(
+        |_| {
+            for i in 0..ROUNDS {
+                incr_by(&backend, "counter", 1, i64::MIN);
+                incr_by(&backend, &format!("counter:{}", i % 16), 1, i64::MIN);
+            }
+        },
+        || {
+            // 计数只会增加，读到的值不会超过总的写入次数
+            let n = integer(&backend, "counter").unwrap_or(0);
+            assert!((0..=(THREADS * ROUNDS) as i64).contains(&n));
+        },
+    );
+
+    assert_eq!(
+        integer(&backend, "counter"),
+        Some((THREADS * ROUNDS) as i64)
+    );
+    let total = (0..16)
+        .filter_map(|i| integer(&backend, &format!("counter:{}", i)))
+        .sum::<i64>();
+    assert_eq!(total, (THREADS * ROUNDS) as i64);
+}
+
+#[test]
+fn test_bounded_decr_never_goes_negative() {
+    let backend = small_backend();
+    let (increments, decrements) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    run_with_checker(
+        |t| {
+            // 一半的线程增加，另一半只在大于 0 时减少
+            for _ in 0..ROUNDS {
+                if t % 2 == 0 {
+                    incr_by(&backend, "stock", 1, 0);
+                    increments.fetch_add(1, Ordering::Relaxed);
+                } else if incr_by(&backend, "stock", -1, 0) {
+                    decrements.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        },
+        || assert!(integer(&backend, "stock").unwrap_or(0) >= 0),
+    );
+
+    let expected = increments.load(Ordering::Relaxed) - decrements.load(Ordering::Relaxed);
+    assert_eq!(integer(&backend, "stock"), Some(expected as i64));
+}
+
+#[test]
+fn test_concurrent_hset_and_sadd() {
+    let backend = small_backend();
+    let added = AtomicUsize::new(0);
+    run_with_checker(
+        |t| {
+            for i in 0..ROUNDS {
+                // 每个线程写入自己的字段，同时所有线程竞争同一批集合成员
+                backend.hset(
+                    "hash".to_string(),
+                    format!("{}:{}", t, i),
+                    BulkString::from(i.to_string()).into(),
+                );
+                if backend.set_of("set").add(format!("member:{}", i)) {
+                    added.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        },
+        || {
+            assert!(backend.hash("hash").len() <= THREADS * ROUNDS);
+            assert!(backend.set_of("set").len() <= ROUNDS);
+        },
+    );
+
+    assert_eq!(backend.hash("hash").len(), THREADS * ROUNDS);
+    // 每个成员只有一个线程的 SADD 返回新增
+    assert_eq!(added.load(Ordering::Relaxed), ROUNDS);
+    assert_eq!(backend.set_of("set").len(), ROUNDS);
+    let members = backend.set_of("set").iter().collect::<HashSet<_>>();
+    assert!((0..ROUNDS).all(|i| members.contains(&format!("member:{}", i))));
+}
+
+#[test]
+fn test_transfers_under_key_locks_conserve_total() {
+    let backend = small_backend();
+    let accounts = (0..8).map(|i| format!("account:{}", i)).collect::<Vec<_>>();
+    for account in &accounts {
+        backend.set(account.clone(), RespFrame::Integer(1000));
+    }
+    let balance = |guard: &KeyGuard<'_>, key: &str| match guard.get(key).as_deref() {
+        Some(RespFrame::Integer(n)) => *n,
+        other => panic!("unexpected value {:?}", other),
+    };
+
+    run_with_checker(
+        |t| {
+            for i in 0..ROUNDS {
+                let from = &accounts[(t + i) % accounts.len()];
+                let to = &accounts[(t * 3 + i * 7 + 1) % accounts.len()];
+                if from == to {
+                    continue;
+                }
+                let mut guard = backend.lock_keys(&[from, to]);
+                let (a, b) = (balance(&guard, from), balance(&guard, to));
+                if a > 0 {
+                    guard.set(from, RespFrame::Integer(a - 1));
+                    guard.set(to, RespFrame::Integer(b + 1));
+                }
+            }
+        },
+        || {
+            // 同时锁住所有账户读取，总额不变且没有负数
+            let keys = accounts.iter().map(String::as_str).collect::<Vec<_>>();
+            let guard = backend.lock_keys(&keys);
+            let balances = keys
+                .iter()
+                .map(|key| balance(&guard, key))
+                .collect::<Vec<_>>();
+            assert!(balances.iter().all(|&n| n >= 0));
+            assert_eq!(balances.iter().sum::<i64>(), 8000);
+        },
+    );
+}
+
+#[test]
+fn test_expiry_races_keep_accounting_consistent() {
+    let backend = small_backend();
+    run_with_checker(
+        |t| {
+            for i in 0..ROUNDS {
+                let key = format!("ttl:{}", i % 64);
+                match (t + i) % 4 {
+                    // 已经过期的写入和长 TTL 的写入交替出现，读写时触发惰性删除
+                    0 => backend.set_with_ttl(&key, "v", Duration::ZERO),
+                    1 => backend.set_with_ttl(&key, "v", Duration::from_secs(60)),
+                    2 => {
+                        let _ = backend.get_string(&key);
+                    }
+                    _ => {
+                        backend.remove(&key);
+                    }
+                }
+            }
+        },
+        || {
+            for key in backend.iter_keys() {
+                assert!(key.starts_with("ttl:"));
+            }
+        },
+    );
+
+    // 删除所有 key 之后，近似内存统计应该回到 0
+    for i in 0..64 {
+        backend.remove(&format!("ttl:{}", i));
+    }
+    assert_eq!(backend.iter_keys().count(), 0);
+    assert_eq!(backend.memory_stats().total(), 0);
+
+    let stats = backend.stats().fields();
+    let expired = stats
+        .iter()
+        .find(|(name, _)| *name == "expired_keys")
+        .map(|(_, n)| *n)
+        .unwrap();
+    assert!(expired as usize <= THREADS * ROUNDS / 4);
+}
+
+#[test]
+fn test_aggregate_reads_see_whole_updates() {
+    const FIELDS: usize = 16;
+    let backend = small_backend();
+    let round = |i: usize| RespFrame::from(BulkString::from(i.to_string()));
+
+    run_with_checker(
+        |t| {
+            // 每次在同一个 guard 内把 hash 的所有字段和 set 的所有成员换成同一轮的值
+            for i in 0..ROUNDS / 10 {
+                let i = t * ROUNDS + i;
+                let mut guard = backend.lock_keys(&["agg:hash", "agg:set"]);
+                for f in 0..FIELDS {
+                    guard.hset("agg:hash", &format!("f{}", f), round(i));
+                    if i > 0 {
+                        guard.srem("agg:set", &format!("{}:{}", i - 1, f));
+                    }
+                }
+                for f in 0..FIELDS {
+                    guard.sadd("agg:set", &format!("{}:{}", i, f));
+                }
+            }
+        },
+        || {
+            if let Some(Value::Hash(hash)) = backend.get_value("agg:hash") {
+                assert_eq!(hash.len(), FIELDS);
+                let first = hash.values().next().unwrap();
+                assert!(hash.values().all(|v| v == first), "torn hash {:?}", hash);
+            }
+            if let Some(Value::Set(set)) = backend.get_value("agg:set") {
+                // 上一轮的成员可能来自另一个线程，没有被删除，按轮次分组后每组都必须完整
+                let mut rounds = std::collections::HashMap::<&str, usize>::new();
+                for member in set.iter() {
+                    *rounds.entry(member.split(':').next().unwrap()).or_default() += 1;
+                }
+                assert!(rounds.values().all(|&n| n == FIELDS), "torn set {:?}", set);
+            }
+        },
+    );
+}